@@ -84,3 +84,9 @@ impl CameraUniform {
         self.view_proj = camera_view_proj.into();
     }
 }
+
+impl Default for CameraUniform {
+    fn default() -> Self {
+        Self::new()
+    }
+}
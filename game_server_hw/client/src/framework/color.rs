@@ -57,19 +57,19 @@ impl From<[f32; 3]> for Color {
     }
 }
 
-impl Into<[f32; 4]> for Color {
-    fn into(self) -> [f32; 4] {
-        [self.0, self.1, self.2, self.3]
+impl From<Color> for [f32; 4] {
+    fn from(color: Color) -> Self {
+        [color.0, color.1, color.2, color.3]
     }
 }
 
-impl Into<wgpu::Color> for Color {
-    fn into(self) -> wgpu::Color {
+impl From<Color> for wgpu::Color {
+    fn from(color: Color) -> Self {
         wgpu::Color {
-            r: self.0 as f64,
-            g: self.1 as f64,
-            b: self.2 as f64,
-            a: self.3 as f64,
+            r: color.0 as f64,
+            g: color.1 as f64,
+            b: color.2 as f64,
+            a: color.3 as f64,
         }
     }
 }
\ No newline at end of file
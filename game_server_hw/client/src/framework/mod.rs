@@ -15,6 +15,8 @@ use winit::{
     event::*,
     window::Window,
 };
+use std::net::SocketAddr;
+use network::{Message, PieceClass};
 use wgpu::util::DeviceExt;
 
 use texture::*;
@@ -54,17 +56,16 @@ pub struct State<'a> {
 
 impl<'a> State<'a> {
     // Creating some of the wgpu types requires async code
-    pub async fn new(window: &'a Window) -> Self {
+    /// `server`: 접속할 서버 주소 목록 (처음으로 연결된 주소 사용)
+    /// `piece`: 접속 후 고를 말 (없으면 서버가 정해준 말)
+    /// `login`: 접속하자마자 보낼 `Login` 또는 `Register` 메세지
+    pub async fn new(window: &'a Window, server: &[SocketAddr], piece: Option<PieceClass>, login: Option<Message>) -> Self {
         use winit::dpi::PhysicalSize;
 
         let size = window.request_inner_size(
             PhysicalSize::new(SCREEN_WIDTH, SCREEN_HEIGHT)
         );
-        let size = if size.is_none() {
-            window.inner_size()
-        } else {
-            size.unwrap()
-        };
+        let size = size.unwrap_or_else(|| window.inner_size());
         // let size = window.inner_size();
 
         // The instance is a handle to our GPU
@@ -246,7 +247,7 @@ impl<'a> State<'a> {
 
         let depth_texture = Texture::create_depth_texture(&device, &config, "depth_texture");
 
-        let mut scene = GameScene::new().await;
+        let mut scene = GameScene::new(server, piece, login).await;
         scene.init(&device);
 
 
@@ -273,7 +274,7 @@ impl<'a> State<'a> {
     }
 
     pub fn window(&self) -> &Window {
-        &self.window
+        self.window
     }
 
     pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
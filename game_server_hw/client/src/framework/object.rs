@@ -24,3 +24,9 @@ impl Object {
         self.model = model;
     }
 }
+
+impl Default for Object {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,63 @@
+use std::collections::VecDeque;
+use network::*;
+
+
+/// 받은 채팅을 최근 `capacity`줄까지 보관하고 터미널에 출력한다.
+pub struct ChatLog {
+    lines: VecDeque<String>,
+    capacity: usize,
+}
+
+impl ChatLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            lines: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn push(&mut self, line: String) {
+        println!("{}", line);
+
+        if self.lines.len() >= self.capacity {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line);
+    }
+
+    /// 마지막 `count`줄 (오래된 순)
+    pub fn recent(&self, count: usize) -> impl Iterator<Item = &String> {
+        self.lines.iter().skip(self.lines.len().saturating_sub(count))
+    }
+}
+
+
+/// `[All] Player 1#0: hello`
+pub fn format_chat(channel: ChatChannel, name: &str, text: &str) -> String {
+    let channel = match channel {
+        ChatChannel::Global => "All",
+        ChatChannel::Whisper => "Whisper",
+        ChatChannel::Proximity => "Near",
+    };
+
+    format!("[{}] {}: {}", channel, name, text)
+}
+
+
+/// 입력한 줄을 `ChatSend`로 바꾼다.
+/// `/w <대상> <내용>`: 귓속말 (대상은 `index#generation`), `/n <내용>`: 근처, 그 외: 전체
+pub fn parse_input(line: &str) -> Result<Message, String> {
+    let line = line.trim();
+
+    let (channel, target, text) = if let Some(rest) = line.strip_prefix("/w ") {
+        let (target, text) = rest.trim_start().split_once(' ')
+            .ok_or_else(|| "Usage: /w <player> <message>".to_string())?;
+        (ChatChannel::Whisper, target, text)
+    } else if let Some(text) = line.strip_prefix("/n ") {
+        (ChatChannel::Proximity, "", text)
+    } else {
+        (ChatChannel::Global, "", line)
+    };
+
+    Ok(Message::ChatSend { channel, target: target.to_string(), text: text.trim().to_string() })
+}
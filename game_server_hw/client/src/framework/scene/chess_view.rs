@@ -0,0 +1,181 @@
+use cgmath::Vector3;
+use std::{rc::Rc, cell::RefCell};
+use network::*;
+
+use super::super::{
+    object::Object,
+    model::Model,
+};
+
+
+/// 체스판의 왼쪽 위 칸의 위치. 월드 맵과 겹치지 않게 멀리 둔다.
+const ORIGIN: Vector3<f32> = Vector3::new(-100.0, 0.0, 0.0);
+
+
+/// 체스판을 그리는데 쓰는 모델의 인덱스
+pub struct ChessModels {
+    /// 밝은 칸, 어두운 칸
+    pub squares: [usize; 2],
+    pub cursor: usize,
+    pub selected: usize,
+    /// 색과 말 종류에 맞는 모델
+    pub piece: fn(ChessColor, PieceClass) -> usize,
+}
+
+
+/// 진행중인 대국의 화면. 자신의 색이 아래쪽에 오도록 판을 돌려서 그린다.
+pub struct ChessView {
+    color: ChessColor,
+    board: Option<BoardState>,
+
+    /// 화면 기준 커서 위치 (열, 행). 행 0이 위쪽
+    cursor: (i32, i32),
+    selected: Option<Square>,
+    /// 폰이 끝까지 갔을 때 승급할 말
+    promotion: PieceClass,
+
+    squares: Vec<Rc<RefCell<Object>>>,
+    pieces: Vec<Rc<RefCell<Object>>>,
+}
+
+impl ChessView {
+    pub fn new(color: ChessColor) -> Self {
+        Self {
+            color,
+            board: None,
+            cursor: (4, 6),
+            selected: None,
+            promotion: PieceClass::QUEEN,
+            squares: Vec::new(),
+            pieces: Vec::new(),
+        }
+    }
+
+    /// 카메라가 바라볼 판의 가운데
+    pub fn center(&self) -> Vector3<f32> {
+        ORIGIN + Vector3::new(3.5, 0.0, 3.5)
+    }
+
+    /// 화면 위치 -> 칸
+    fn square_at(&self, (column, row): (i32, i32)) -> Option<Square> {
+        match self.color {
+            ChessColor::White => Square::new(column, 7 - row),
+            ChessColor::Black => Square::new(7 - column, row),
+        }
+    }
+
+    /// 칸 -> 화면 위치
+    fn position_of(&self, square: Square) -> (i32, i32) {
+        match self.color {
+            ChessColor::White => (square.file(), 7 - square.rank()),
+            ChessColor::Black => (7 - square.file(), square.rank()),
+        }
+    }
+
+    pub fn set_board(&mut self, board: BoardState, models: &[Rc<RefCell<Model>>], ids: &ChessModels) {
+        self.board = Some(board);
+        self.rebuild(models, ids);
+    }
+
+    pub fn move_cursor(&mut self, x: i32, y: i32, models: &[Rc<RefCell<Model>>], ids: &ChessModels) {
+        self.cursor = ((self.cursor.0 + x).clamp(0, 7), (self.cursor.1 + y).clamp(0, 7));
+        self.rebuild(models, ids);
+    }
+
+    pub fn set_promotion(&mut self, class: PieceClass) {
+        self.promotion = class;
+    }
+
+    /// 첫번째는 자신의 말을 고르고, 두번째는 고른 말을 옮길 수를 돌려준다.
+    /// 규칙에 맞는지는 서버가 확인한다.
+    pub fn select(&mut self, models: &[Rc<RefCell<Model>>], ids: &ChessModels) -> Option<ChessMove> {
+        let board = self.board.as_ref()?;
+        let square = self.square_at(self.cursor)?;
+        let mine = board.piece_at(square).is_some_and(|piece| piece.color == self.color);
+
+        let mv = match self.selected {
+            // 다른 자신의 말을 누르면 다시 고름
+            Some(from) if from != square && !mine => {
+                let pawn = board.piece_at(from).is_some_and(|piece| piece.class == PieceClass::PAWN);
+                let last_rank = square.rank() == 0 || square.rank() == 7;
+
+                self.selected = None;
+                Some(if pawn && last_rank {
+                    ChessMove::with_promotion(from, square, self.promotion)
+                } else {
+                    ChessMove::new(from, square)
+                })
+            },
+            Some(from) if from == square => {
+                self.selected = None;
+                None
+            },
+            _ => {
+                self.selected = mine.then_some(square);
+                None
+            },
+        };
+
+        self.rebuild(models, ids);
+        mv
+    }
+
+    /// 판과 말을 모두 지우고 다시 만든다.
+    fn rebuild(&mut self, models: &[Rc<RefCell<Model>>], ids: &ChessModels) {
+        self.clear();
+
+        for row in 0..8 {
+            for column in 0..8 {
+                let square = self.square_at((column, row));
+                let model = if (column, row) == self.cursor {
+                    ids.cursor
+                } else if square.is_some() && square == self.selected {
+                    ids.selected
+                } else {
+                    ids.squares[((column + row) & 1) as usize]
+                };
+
+                let object = spawn(&models[model], column, row, -0.5);
+                self.squares.push(object);
+            }
+        }
+
+        let Some(board) = &self.board else {
+            return;
+        };
+
+        for square in Square::all() {
+            if let Some(ChessPiece { color, class }) = board.piece_at(square) {
+                let (column, row) = self.position_of(square);
+                let object = spawn(&models[(ids.piece)(color, class)], column, row, 0.0);
+                self.pieces.push(object);
+            }
+        }
+    }
+
+    /// 모델에 등록된 오브젝트를 모두 지운다.
+    pub fn clear(&mut self) {
+        for object in self.squares.drain(..).chain(self.pieces.drain(..)) {
+            if let Some(model) = object.borrow().model.upgrade() {
+                model.borrow_mut().remove_instance(object.clone());
+            }
+        }
+    }
+
+    pub fn objects(&self) -> impl Iterator<Item = &Rc<RefCell<Object>>> {
+        self.squares.iter().chain(self.pieces.iter())
+    }
+}
+
+
+fn spawn(model: &Rc<RefCell<Model>>, column: i32, row: i32, y: f32) -> Rc<RefCell<Object>> {
+    let object = Rc::new(RefCell::new(Object::new()));
+    model.borrow_mut().add_instance(object.clone());
+
+    let mut inner = object.borrow_mut();
+    inner.set_model(Rc::downgrade(model));
+    inner.transform.position = ORIGIN + Vector3::new(column as f32, y, row as f32);
+    drop(inner);
+
+    object
+}
@@ -8,11 +8,10 @@ use std::{
     rc::Rc, 
     cell::RefCell, 
     io::{Read, Write}, 
-    net::TcpStream,
+    net::{SocketAddr, TcpStream},
     collections::HashMap,
     iter::IntoIterator,
 };
-use get_addr::get_addr;
 use network::*;
 
 use super::super::{
@@ -22,7 +21,45 @@ use super::super::{
     color::Color,
     SCREEN_WIDTH, SCREEN_HEIGHT,
 };
-use super::Scene;
+use super::{
+    Scene,
+    chess_view::{ChessModels, ChessView},
+    chat::{format_chat, parse_input, ChatLog},
+};
+
+
+/// `models`의 순서: 바닥 2개, 벽, 말 종류마다 (자신, 다른 플레이어),
+/// 체스판 칸 2개, 커서, 고른 칸, 말 종류마다 NPC
+const FLOOR_MODELS: usize = 0;
+const WALL_MODEL: usize = 2;
+const PIECE_MODELS: usize = 3;
+const CHESS_SQUARE_MODELS: usize = PIECE_MODELS + PieceClass::ALL.len() * 2;
+const CURSOR_MODEL: usize = CHESS_SQUARE_MODELS + 2;
+const SELECTED_MODEL: usize = CURSOR_MODEL + 1;
+const NPC_MODELS: usize = SELECTED_MODEL + 1;
+
+fn piece_model(class: PieceClass, mine: bool) -> usize {
+    PIECE_MODELS + class.value() as usize * 2 + if mine { 0 } else { 1 }
+}
+
+fn npc_model(class: PieceClass) -> usize {
+    NPC_MODELS + class.value() as usize
+}
+
+/// 체스판에서는 백이 흰색, 흑이 검은색 모델
+const CHESS_MODELS: ChessModels = ChessModels {
+    squares: [CHESS_SQUARE_MODELS, CHESS_SQUARE_MODELS + 1],
+    cursor: CURSOR_MODEL,
+    selected: SELECTED_MODEL,
+    piece: |color, class| piece_model(class, color == ChessColor::White),
+};
+
+/// 보관하는 채팅 줄 수
+const CHAT_LOG_CAPACITY: usize = 100;
+
+fn piece_file(class: PieceClass) -> String {
+    format!("{}.obj", class.name())
+}
 
 
 pub struct GameScene {
@@ -33,16 +70,22 @@ pub struct GameScene {
 
     models: Vec<Rc<RefCell<Model>>>,
     objects: Vec<Rc<RefCell<Object>>>,
-    objects_from_server: HashMap<u32, Rc<RefCell<Object>>>,
+    objects_from_server: HashMap<PlayerId, Rc<RefCell<Object>>>,
+
+    player_id: PlayerId,
+    /// 진행중인 체스 대국
+    chess: Option<ChessView>,
 
-    player_id: u32,
+    chat_log: ChatLog,
+    /// 입력중인 채팅. 입력중에는 모든 키 입력을 채팅으로 받는다.
+    chat_input: Option<String>,
 
     stream: TcpStream,
     packet_parser: PacketParser,
 }
 
 impl GameScene {
-    pub async fn new() -> Self {
+    pub async fn new(server: &[SocketAddr], piece: Option<PieceClass>, login: Option<Message>) -> Self {
         let camera = DefaultCamera::from(CameraComponent {
             eye: Point3::new(0.0, 1.0, 2.0),
             target: Point3::new(0.0, 0.0, 0.0),
@@ -53,16 +96,17 @@ impl GameScene {
             zfar: 100.0,
         });
 
-        let (ip, port) = match get_addr() {
-            Ok((ip, port)) => (ip, port),
-            Err(e) => {
-                panic!("{}", e);
-            }
-        };
-        // let ip = "127.0.0.1".to_string();
-        // let port = 8080;
-        let addr = format!("{}:{}", ip, port);
-        let stream = TcpStream::connect(addr).unwrap();
+        let mut stream = TcpStream::connect(server).unwrap();
+
+        // 서버는 로그인, 입장한 뒤에 순서대로 처리하므로 미리 보내둬도 됨
+        if let Some(login) = login {
+            stream.write_all(&login.as_raw().as_bytes())
+                .expect("Failed to write to stream");
+        }
+        if let Some(class) = piece {
+            stream.write_all(&Message::SelectClass { class }.as_raw().as_bytes())
+                .expect("Failed to write to stream");
+        }
         stream.set_nonblocking(true).unwrap();
 
         Self {
@@ -75,7 +119,11 @@ impl GameScene {
             objects: Vec::new(),
             objects_from_server: HashMap::new(),
 
-            player_id: 0,
+            player_id: PlayerId::default(),
+            chess: None,
+
+            chat_log: ChatLog::new(CHAT_LOG_CAPACITY),
+            chat_input: None,
 
             // ip,
             // port,
@@ -86,31 +134,58 @@ impl GameScene {
 
     fn load_models(&mut self, device: &wgpu::Device) {
         block_on(async {
-            self.models = [
+            let mut models = vec![
                 Model::load("cube.obj", device, 0.5, Color::LIGHT_GRAY).await.unwrap(),
                 Model::load("cube.obj", device, 0.5, Color::DARK_GRAY).await.unwrap(),
-                Model::load("pawn.obj", device, 0.8, Color::WHITE).await.unwrap(),
-                Model::load("pawn.obj", device, 0.8, Color::BLACK).await.unwrap(),
-            ].into_iter()
-            .map(|model| Rc::new(RefCell::new(model)))
-            .collect();
+                Model::load("cube.obj", device, 0.5, Color::GRAY).await.unwrap(),
+            ];
+
+            // 자신은 흰색, 다른 플레이어는 검은색
+            for class in PieceClass::ALL {
+                let file = piece_file(class);
+                models.push(Model::load(&file, device, 0.8, Color::WHITE).await.unwrap());
+                models.push(Model::load(&file, device, 0.8, Color::BLACK).await.unwrap());
+            }
+
+            models.push(Model::load("cube.obj", device, 0.5, Color::from_rgb(0.93, 0.85, 0.7)).await.unwrap());
+            models.push(Model::load("cube.obj", device, 0.5, Color::from_rgb(0.55, 0.4, 0.3)).await.unwrap());
+            models.push(Model::load("cube.obj", device, 0.5, Color::YELLOW).await.unwrap());
+            models.push(Model::load("cube.obj", device, 0.5, Color::GREEN).await.unwrap());
+
+            // NPC는 빨간색
+            for class in PieceClass::ALL {
+                models.push(Model::load(&piece_file(class), device, 0.8, Color::RED).await.unwrap());
+            }
+
+            self.models = models.into_iter()
+                .map(|model| Rc::new(RefCell::new(model)))
+                .collect();
         });
     }
 
-    fn build_objects(&mut self) {
-        self.objects = (0..64)
-            .into_iter()
-            .map(|idx| {
+    /// 서버에서 받은 맵으로 타일 오브젝트를 다시 만든다.
+    fn build_tiles(&mut self, map: &TileMap) {
+        for object in self.objects.drain(..) {
+            if let Some(model) = object.borrow().model.upgrade() {
+                model.borrow_mut().remove_instance(object.clone());
+            }
+        }
+
+        self.objects = map.iter()
+            .map(|(x, z, tile)| {
                 let object = Rc::new(RefCell::new(Object::new()));
-                
-                let x = idx % 8;
-                let z = idx / 8;
-                let model = self.models[(x+z) & 1].clone();
+
+                // 바닥은 체크무늬, 벽은 바닥 위에 한칸 올림
+                let (idx, y) = match tile {
+                    Tile::Wall => (WALL_MODEL, 0.5),
+                    _ => (FLOOR_MODELS + ((x + z) & 1) as usize, -0.5),
+                };
+                let model = self.models[idx].clone();
                 model.borrow_mut().add_instance(object.clone());
                 object.borrow_mut().set_model(Rc::downgrade(&model));
                 object.borrow_mut().transform.position = Vector3::new(
                     x as f32, 
-                    -0.5, 
+                    y, 
                     z as f32
                 );
 
@@ -123,7 +198,34 @@ impl GameScene {
         self.objects_from_server.get(&self.player_id).cloned()
     }
 
+    /// 주변 8칸에 있는 오브젝트 중 가장 가까운 것 (같으면 id가 작은 것)
+    fn attack_target(&self) -> Option<PlayerId> {
+        let position = self.player()?.borrow().transform.position;
+
+        self.objects_from_server.iter()
+            .filter(|(&id, _)| id != self.player_id)
+            .map(|(&id, object)| {
+                let p = object.borrow().transform.position;
+                let dx = (p.x - position.x).round().abs() as i32;
+                let dz = (p.z - position.z).round().abs() as i32;
+                (id, dx, dz)
+            })
+            .filter(|&(_, dx, dz)| dx <= 1 && dz <= 1)
+            .min_by_key(|&(id, dx, dz)| (dx + dz, id))
+            .map(|(id, _, _)| id)
+    }
+
     fn update_camera(&mut self) {
+        // 대국중에는 체스판을 위에서 내려다봄
+        if let Some(chess) = &self.chess {
+            let center = chess.center();
+            let point = Point3::new(center.x, center.y, center.z);
+
+            self.camera.component.target = point;
+            self.camera.component.eye = point + Vector3::new(0.0, 8.0, 5.0);
+            return;
+        }
+
         if let Some(player) = self.player(){
             let p = player.borrow().transform.position;
     
@@ -154,75 +256,270 @@ impl GameScene {
         }
     }
     
-    fn process_message(&mut self, msg: &str) {
-        let msg = msg.trim().split_whitespace()
-            .map(|s| s.trim())
-            .collect::<Vec<&str>>();
-
+    fn process_message(&mut self, msg: Message) {
         // println!("Received: {:?}", msg);
 
-        if msg.len() == 0 {
-            return;
-        }
+        match msg {
+            Message::Init { id } => {
+                self.player_id = id;
+            }
 
-        match msg[0] {
-            "init" => {
-                if msg.len() < 2 {
-                    return;
-                }
+            Message::Map { map } => {
+                self.build_tiles(&map);
+            }
 
-                self.player_id = msg[1].parse::<u32>().unwrap();
+            // 시야 안에서 움직인 오브젝트 (모르는 id면 새로 생성)
+            Message::UpdateSnapshot { objects } |
+            Message::EnterView { objects } => {
+                for object in objects {
+                    self.update_object(object);
+                }
             }
 
-            "update" => {
-                if msg.len() < 2 {
-                    return;
+            Message::LeaveView { ids } => {
+                for id in ids {
+                    self.remove_object(id);
                 }
+            }
+
+            Message::MoveRejected { x, y, reason } => {
+                println!("Move ({} {}) rejected: {:?}", x, y, reason);
+            }
+
+            Message::QueuePosition { position } => {
+                println!("Waiting in queue: {}", position);
+            }
+
+            Message::ConnectRejected { reason } => {
+                eprintln!("Connection rejected: {:?}", reason);
+            }
+
+            Message::LoginRejected { reason } => {
+                eprintln!("Login rejected: {:?}", reason);
+            }
+
+            Message::Shutdown { seconds } => {
+                println!("Server shutting down in {} seconds", seconds);
+            }
+
+            Message::Notice { text } => {
+                println!("[Notice] {}", text);
+            }
 
-                let num_objects = msg[1].parse::<usize>().unwrap();
-                let mut valid_ids: Vec<u32> = Vec::new();
-
-                for i in 0..num_objects {
-                    let idx = 2 + i * 3;
-                    let id = msg[idx].parse::<u32>().unwrap();
-                    let x = msg[idx+1].parse::<i32>().unwrap();
-                    let z = msg[idx+2].parse::<i32>().unwrap();
-
-                    let object = self.objects_from_server.entry(id)
-                        .or_insert_with(|| {
-                            let object = Rc::new(RefCell::new(Object::new()));
-                            let idx = if id == self.player_id { 2 } else { 3 };
-                            let model = self.models[idx].clone();
-                            
-                            model.borrow_mut().add_instance(object.clone());
-                            object.borrow_mut().set_model(Rc::downgrade(&model));
-                            object
-                        });
-
-                    let mut object = object.borrow_mut();
-                    object.transform.position.x = x as f32;
-                    object.transform.position.z = z as f32;
-                    
-                    valid_ids.push(id);
+            Message::MatchStarted { color, opponent } => {
+                println!("Chess match against {} started, playing {:?}", opponent, color);
+                if let Some(mut chess) = self.chess.take() {
+                    chess.clear();
                 }
+                self.chess = Some(ChessView::new(color));
+            }
 
-                // 기존에 있던 id가 안보이면 삭제
-                self.objects_from_server.retain(|k, object| {
-                    let contains = valid_ids.contains(k);
-                    if !contains {
-                        if let Some(model) = object.borrow().model.upgrade() {
-                            let mut model = model.borrow_mut();
-                            model.remove_instance(object.clone());
-                        }
+            Message::ChessBoard { board } => {
+                if let Some(chess) = &mut self.chess {
+                    if board.check {
+                        println!("{:?} is in check", board.turn);
                     }
-                    contains
-                });
+                    chess.set_board(board, &self.models, &CHESS_MODELS);
+                }
+            }
+
+            Message::ChessRejected { reason } => {
+                println!("Chess request rejected: {:?}", reason);
+            }
+
+            Message::DrawOffer => {
+                println!("Opponent offers a draw (press F to accept)");
             }
+
+            // 결과를 보여준 뒤 월드 화면으로 돌아감
+            Message::GameOver { result, reason } => {
+                println!("Game over: {} ({:?})", result.pgn(), reason);
+                if let Some(mut chess) = self.chess.take() {
+                    chess.clear();
+                }
+            }
+
+            Message::Pgn { text } => {
+                println!("{}", text);
+            }
+
+            Message::Chat { channel, name, text, .. } => {
+                self.chat_log.push(format_chat(channel, &name, &text));
+            }
+
+            Message::ChatRejected { reason } => {
+                println!("Chat rejected: {:?}", reason);
+            }
+
+            Message::AttackRejected { target, reason } => {
+                println!("Attack on {} rejected: {:?}", target, reason);
+            }
+
+            Message::Damage { attacker, target, amount, hp } => {
+                println!("{} hit {} for {} (hp {})", attacker, target, amount, hp);
+            }
+
+            // 부활하면 `EnterView`로 다시 생성됨
+            Message::Death { id, killer } => {
+                if id == self.player_id {
+                    println!("Killed by {}, waiting for respawn", killer);
+                } else {
+                    println!("{} was killed by {}", id, killer);
+                }
+                self.remove_object(id);
+            }
+
+            Message::Respawn { id, x, y, hp } => {
+                println!("{} respawned at ({}, {}) with hp {}", id, x, y, hp);
+            }
+
             _ => {}
         }
     }
 
+    /// 말이 바뀌었으면 모델도 바꾼다.
+    fn update_object(&mut self, ObjectInfo { id, x, y: z, class, kind }: ObjectInfo) {
+        let model = match kind {
+            EntityKind::NPC => self.models[npc_model(class)].clone(),
+            _ => self.models[piece_model(class, id == self.player_id)].clone(),
+        };
+
+        let object = self.objects_from_server.entry(id)
+            .or_insert_with(|| Rc::new(RefCell::new(Object::new())))
+            .clone();
+
+        let current = object.borrow().model.upgrade();
+        if !current.as_ref().is_some_and(|current| Rc::ptr_eq(current, &model)) {
+            if let Some(current) = current {
+                current.borrow_mut().remove_instance(object.clone());
+            }
+            model.borrow_mut().add_instance(object.clone());
+            object.borrow_mut().set_model(Rc::downgrade(&model));
+        }
+
+        let mut object = object.borrow_mut();
+        object.transform.position.x = x as f32;
+        object.transform.position.z = z as f32;
+    }
+
+    fn remove_object(&mut self, id: PlayerId) {
+        if let Some(object) = self.objects_from_server.remove(&id) {
+            if let Some(model) = object.borrow().model.upgrade() {
+                let mut model = model.borrow_mut();
+                model.remove_instance(object.clone());
+            }
+        }
+    }
+
+    fn send(&mut self, message: Message) {
+        self.stream.write_all(&message.as_raw().as_bytes())
+            .expect("Failed to write to stream");
+    }
+
+    fn open_chat(&mut self) {
+        for line in self.chat_log.recent(5) {
+            println!("{}", line);
+        }
+        println!("Chat (Enter: send, Esc: cancel, /w <player> <message>: whisper, /n <message>: nearby)");
+
+        self.chat_input = Some(String::new());
+    }
+
+    /// 채팅 입력중의 키 입력. 모든 키 입력을 소비한다.
+    fn process_chat_input(&mut self, event: &KeyEvent) -> bool {
+        if event.state != ElementState::Pressed {
+            return true;
+        }
+        let Some(input) = &mut self.chat_input else {
+            return false;
+        };
+
+        match event.physical_key {
+            PhysicalKey::Code(KeyCode::Enter | KeyCode::NumpadEnter) => {
+                let line = self.chat_input.take().unwrap_or_default();
+                if line.trim().is_empty() {
+                    return true;
+                }
+
+                match parse_input(&line) {
+                    Ok(message) => {
+                        // 귓속말은 서버가 돌려주지 않으므로 직접 기록
+                        if let Message::ChatSend { channel: ChatChannel::Whisper, target, text } = &message {
+                            self.chat_log.push(format!("[To {}] {}", target, text));
+                        }
+                        self.send(message);
+                    },
+                    Err(e) => println!("{}", e),
+                }
+            },
+
+            PhysicalKey::Code(KeyCode::Escape) => {
+                self.chat_input = None;
+                println!("Chat cancelled");
+            },
+
+            PhysicalKey::Code(KeyCode::Backspace) => {
+                input.pop();
+            },
+
+            _ => {
+                if let Some(text) = &event.text {
+                    input.extend(text.chars().filter(|c| !c.is_control()));
+                }
+            },
+        }
+
+        true
+    }
+
+    /// 대국중의 키 입력.
+    /// WASD: 커서 이동, Space/Enter: 말 고르기 / 옮기기, 1~4: 승급할 말 (퀸, 룩, 비숍, 나이트),
+    /// R: 기권, F: 무승부 제안 / 수락
+    fn process_chess_input(&mut self, keycode: &KeyCode) -> bool {
+        let Some(chess) = &mut self.chess else {
+            return false;
+        };
+
+        let message = match keycode {
+            KeyCode::KeyW => { chess.move_cursor(0, -1, &self.models, &CHESS_MODELS); None },
+            KeyCode::KeyA => { chess.move_cursor(-1, 0, &self.models, &CHESS_MODELS); None },
+            KeyCode::KeyS => { chess.move_cursor(0, 1, &self.models, &CHESS_MODELS); None },
+            KeyCode::KeyD => { chess.move_cursor(1, 0, &self.models, &CHESS_MODELS); None },
+
+            KeyCode::Space | KeyCode::Enter => {
+                chess.select(&self.models, &CHESS_MODELS).map(|mv| Message::ChessMove { mv })
+            },
+
+            KeyCode::Digit1 | KeyCode::Digit2 | KeyCode::Digit3 | KeyCode::Digit4 => {
+                let class = match keycode {
+                    KeyCode::Digit1 => PieceClass::QUEEN,
+                    KeyCode::Digit2 => PieceClass::ROOK,
+                    KeyCode::Digit3 => PieceClass::BISHOP,
+                    _ => PieceClass::KNIGHT,
+                };
+                println!("Promote to {}", class);
+                chess.set_promotion(class);
+                None
+            },
+
+            KeyCode::KeyR => Some(Message::Resign),
+            KeyCode::KeyF => Some(Message::DrawOffer),
+
+            _ => return false,
+        };
+
+        if let Some(message) = message {
+            self.send(message);
+        }
+
+        true
+    }
+
     fn process_keyboard_input(&mut self, state: &ElementState, keycode: &KeyCode) -> bool {
+        if *state == ElementState::Pressed && self.process_chess_input(keycode) {
+            return true;
+        }
+
         match state {
             ElementState::Pressed => {
                 let mut direction = Vector2::new(0, 0);
@@ -232,14 +529,35 @@ impl GameScene {
                     KeyCode::KeyA => direction.x = -1,
                     KeyCode::KeyS => direction.y = 1,
                     KeyCode::KeyD => direction.x = 1,
+                    KeyCode::KeyT => {
+                        self.open_chat();
+                        return true;
+                    },
+                    // 옆에 있는 플레이어나 NPC 공격
+                    KeyCode::Space => {
+                        match self.attack_target() {
+                            Some(target) => self.send(Message::Attack { target }),
+                            None => println!("Nothing to attack"),
+                        }
+                        return true;
+                    },
+                    // 체스 대국 신청
+                    KeyCode::KeyM => {
+                        println!("Waiting for a chess opponent");
+                        self.send(Message::MatchRequest);
+                        return true;
+                    },
                     _ => return false,
                 }
                 
                 println!("Move ({} {})", direction.x, direction.y);
 
                 // println!("{}", self.stream.peer_addr().unwrap());
-                let msg = format!("move {} {} {}\n", self.player_id, direction.x, direction.y);
-                let packet = MessagePacket::new(0, &msg).as_raw();
+                let packet = Message::Move {
+                    id: self.player_id,
+                    x: direction.x,
+                    y: direction.y,
+                }.as_raw();
                 self.stream.write_all(&packet.as_bytes())
                     .expect("Failed to write to stream");
 
@@ -254,12 +572,15 @@ impl GameScene {
 
 impl Scene for GameScene {
     fn init(&mut self, device: &wgpu::Device) {
+        // 타일은 서버에서 맵을 받은 뒤에 만든다.
         self.load_models(device);
-        self.build_objects();
     }
 
     fn handle_event(&mut self, event: &WindowEvent) -> bool {
         match event {
+            WindowEvent::KeyboardInput { event, .. } if self.chat_input.is_some() => {
+                self.process_chat_input(event)
+            },
             WindowEvent::KeyboardInput {
                 event: KeyEvent {
                     state,
@@ -273,21 +594,27 @@ impl Scene for GameScene {
         }
     }
 
+    /// 오브젝트 정보는 서버가 tick마다 보내주므로 받은것만 처리
     fn update(&mut self) {
-        let packet = MessagePacket::new(0, "update").as_raw();
-        self.stream.write_all(&packet.as_bytes())
-            .expect("Failed to write to stream");
-
         self.pull_messages();
 
-        while let Some(packet) = self.packet_parser.pop() {
-            let packet = match MessagePacket::from_raw(packet) {
-                Ok(packet) => packet,
+        loop {
+            let packet = match self.packet_parser.pop() {
+                Ok(Some(packet)) => packet,
+                Ok(None) => break,
+                Err(e) => {
+                    eprintln!("Invalid packet from server; err = {}", e);
+                    self.packet_parser = PacketParser::new();
+                    break;
+                }
+            };
+
+            let msg = match Message::from_raw(packet) {
+                Ok(msg) => msg,
                 _ => continue,
             };
             
-            let msg = packet.msg;
-            self.process_message(&msg);
+            self.process_message(msg);
         }
 
         self.update_camera();
@@ -308,6 +635,8 @@ impl Scene for GameScene {
     }
 
     fn objects(&self) -> impl Iterator<Item = &Rc<RefCell<Object>>> {
-        self.objects.iter().chain(self.objects_from_server.values())
+        self.objects.iter()
+            .chain(self.objects_from_server.values())
+            .chain(self.chess.iter().flat_map(|chess| chess.objects()))
     }
 }
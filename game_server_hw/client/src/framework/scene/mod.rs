@@ -1,4 +1,6 @@
 pub mod game_scene;
+mod chess_view;
+mod chat;
 
 pub use game_scene::GameScene;
 
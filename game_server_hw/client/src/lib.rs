@@ -8,17 +8,19 @@ use winit::{
     window::WindowBuilder,
 };
 
+use std::net::SocketAddr;
+use network::{Message, PieceClass};
 use framework::*;
 
 
 /// 이벤트루프 시작 및 윈도우 생성
 #[tokio::main]
-pub async fn run() {
+pub async fn run(server: Vec<SocketAddr>, piece: Option<PieceClass>, login: Option<Message>) {
     env_logger::init();
     let event_loop = EventLoop::new().unwrap();
     let window = WindowBuilder::new().build(&event_loop).unwrap();
 
-    let mut state = State::new(&window).await;
+    let mut state = State::new(&window, &server, piece, login).await;
 
     println!("start");
 
@@ -27,7 +29,7 @@ pub async fn run() {
             Event::WindowEvent {
                 ref event,
                 window_id,
-            } if window_id == state.window().id() => if !state.handle_event(event) {
+            } if window_id == state.window().id() && !state.handle_event(event) => {
                 match event {
                     WindowEvent::CloseRequested => control_flow.exit(),
 
@@ -1,5 +1,49 @@
-use client;
+use std::process::ExitCode;
+use clap::Parser;
+use config::ClientArgs;
+use network::{Message, PieceClass};
 
-fn main() {
-    client::run();
-}
\ No newline at end of file
+
+fn main() -> ExitCode {
+    let config = match ClientArgs::parse().load() {
+        Ok(config) => config.client,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let server = match config.socket_addrs() {
+        Ok(server) => server,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let piece = match config.piece.as_deref().map(str::parse::<PieceClass>).transpose() {
+        Ok(piece) => piece,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // 이름이 있으면 접속하자마자 로그인 (또는 가입)
+    let login = config.name.map(|name| {
+        let password = config.password.unwrap_or_default();
+        match config.register {
+            true => Message::Register { name, password },
+            false => Message::Login { name, password },
+        }
+    });
+
+    // 잘린 이름이나 비밀번호로 로그인하지 않도록 패킷에 들어가지 않으면 거부
+    if let Some(Err(e)) = login.as_ref().map(Message::try_as_raw) {
+        eprintln!("Invalid login: {}", e);
+        return ExitCode::FAILURE;
+    }
+
+    client::run(server, piece, login);
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,89 @@
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use local_ip_address::local_ip;
+
+use super::ConfigError;
+
+
+/// 이 컴퓨터의 네트워크 주소를 뜻하는 호스트 이름
+pub const PUBLIC_HOST: &str = "public";
+
+
+/// `<host>:<port>` 형식의 주소를 나눈다.
+/// IPv6 주소는 `[::1]:7878`처럼 대괄호로 감싸야 한다.
+pub fn parse_addr(addr: &str) -> Result<(String, u16), ConfigError> {
+    let invalid = || ConfigError::InvalidAddr(addr.to_string());
+
+    let (host, port) = addr.rsplit_once(':').ok_or_else(invalid)?;
+    let port = port.parse::<u16>().map_err(|_| invalid())?;
+
+    let host = match host.strip_prefix('[') {
+        Some(host) => host.strip_suffix(']').ok_or_else(invalid)?,
+        // 대괄호 없는 IPv6 주소는 포트와 구분할 수 없음
+        None if host.contains(':') => return Err(invalid()),
+        None => host,
+    };
+
+    if host.is_empty() {
+        return Err(invalid());
+    }
+
+    Ok((host.to_string(), port))
+}
+
+/// 호스트 이름(`localhost`, `public`, IP 주소, 도메인)을 주소 목록으로 변환한다.
+pub fn resolve(host: &str, port: u16) -> Result<Vec<SocketAddr>, ConfigError> {
+    let resolve_error = |source| ConfigError::Resolve { host: host.to_string(), source };
+
+    if host == PUBLIC_HOST {
+        let ip = local_ip()
+            .map_err(|e| resolve_error(std::io::Error::new(std::io::ErrorKind::NotFound, e)))?;
+        return Ok(vec![SocketAddr::new(ip, port)]);
+    }
+
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        return Ok(vec![SocketAddr::new(ip, port)]);
+    }
+
+    let addrs = (host, port).to_socket_addrs()
+        .map_err(resolve_error)?
+        .collect::<Vec<_>>();
+
+    if addrs.is_empty() {
+        return Err(resolve_error(std::io::Error::new(std::io::ErrorKind::NotFound, "no addresses found")));
+    }
+
+    Ok(addrs)
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_addr() {
+        assert_eq!(parse_addr("localhost:7878").unwrap(), ("localhost".to_string(), 7878));
+        assert_eq!(parse_addr("127.0.0.1:80").unwrap(), ("127.0.0.1".to_string(), 80));
+        assert_eq!(parse_addr("[::1]:7878").unwrap(), ("::1".to_string(), 7878));
+        assert_eq!(parse_addr("example.com:1").unwrap(), ("example.com".to_string(), 1));
+
+        for addr in ["localhost", "localhost:", "localhost:abc", "localhost:70000", ":7878", "::1:7878", "[::1:7878", "[]:7878"] {
+            assert!(matches!(parse_addr(addr), Err(ConfigError::InvalidAddr(_))), "{}", addr);
+        }
+    }
+
+    #[test]
+    fn test_resolve() {
+        assert_eq!(resolve("127.0.0.1", 7878).unwrap(), vec!["127.0.0.1:7878".parse().unwrap()]);
+        assert_eq!(resolve("::1", 7878).unwrap(), vec!["[::1]:7878".parse().unwrap()]);
+        assert_eq!(resolve("[::1]", 7878).unwrap(), vec!["[::1]:7878".parse().unwrap()]);
+
+        let addrs = resolve("localhost", 7878).unwrap();
+        assert!(addrs.iter().all(|addr| addr.ip().is_loopback() && addr.port() == 7878));
+
+        assert!(matches!(resolve("invalid host name", 7878), Err(ConfigError::Resolve { .. })));
+    }
+}
@@ -0,0 +1,326 @@
+use std::{path::PathBuf, str::FromStr};
+use clap::{Args, Parser};
+
+use super::{parse_addr, Config, ConfigError};
+
+
+pub const ENV_CONFIG: &str = "GAME_CONFIG";
+pub const ENV_HOST: &str = "GAME_HOST";
+pub const ENV_PORT: &str = "GAME_PORT";
+pub const ENV_MAX_CLIENTS: &str = "GAME_MAX_CLIENTS";
+pub const ENV_MAX_WAITING: &str = "GAME_MAX_WAITING";
+pub const ENV_TICK_RATE: &str = "GAME_TICK_RATE";
+pub const ENV_MAP: &str = "GAME_MAP";
+pub const ENV_PIECE: &str = "GAME_PIECE";
+pub const ENV_PGN_DIR: &str = "GAME_PGN_DIR";
+pub const ENV_DATABASE: &str = "GAME_DATABASE";
+pub const ENV_SAVE_DIR: &str = "GAME_SAVE_DIR";
+pub const ENV_NAME: &str = "GAME_NAME";
+pub const ENV_PASSWORD: &str = "GAME_PASSWORD";
+
+
+/// 모든 프로그램이 공통으로 받는 인자
+#[derive(Debug, Clone, Default, Args)]
+pub struct CommonArgs {
+    // 이전 버전 호환용 (`<mode|ip>:<port>`)
+    #[arg(value_name = "ADDR", help = "Address as <host>:<port>, e.g. localhost:7878, public:7878, [::1]:7878")]
+    pub addr: Option<String>,
+
+    #[arg(short, long, value_name = "FILE", help = "TOML config file [env: GAME_CONFIG]")]
+    pub config: Option<PathBuf>,
+
+    #[arg(long, help = "Host: localhost, public, an ip address or a host name [env: GAME_HOST]")]
+    pub host: Option<String>,
+
+    #[arg(short, long, help = "Port [env: GAME_PORT]")]
+    pub port: Option<u16>,
+}
+
+impl CommonArgs {
+    /// 설정 파일 (없으면 기본값)
+    fn base(&self, env: &impl Fn(&str) -> Option<String>) -> Result<Config, ConfigError> {
+        match self.config.clone().or_else(|| env(ENV_CONFIG).map(PathBuf::from)) {
+            Some(path) => Config::from_file(&path),
+            None => Ok(Config::default()),
+        }
+    }
+
+    /// 환경변수, `ADDR`, `--host`/`--port` 순서로 덮어쓴다.
+    fn apply_endpoint(&self, host: &mut String, port: &mut u16, env: &impl Fn(&str) -> Option<String>) -> Result<(), ConfigError> {
+        override_with(host, None, ENV_HOST, env)?;
+        override_with(port, None, ENV_PORT, env)?;
+
+        if let Some(addr) = &self.addr {
+            (*host, *port) = parse_addr(addr)?;
+        }
+
+        if let Some(value) = &self.host {
+            *host = value.clone();
+        }
+        if let Some(value) = self.port {
+            *port = value;
+        }
+
+        Ok(())
+    }
+}
+
+
+#[derive(Debug, Clone, Default, Parser)]
+#[command(name = "server", about = "Game server")]
+pub struct ServerArgs {
+    #[command(flatten)]
+    pub common: CommonArgs,
+
+    #[arg(long, help = "Maximum number of connected clients [env: GAME_MAX_CLIENTS]")]
+    pub max_clients: Option<usize>,
+
+    #[arg(long, help = "Maximum number of connections waiting for a slot, 0 to disable [env: GAME_MAX_WAITING]")]
+    pub max_waiting: Option<usize>,
+
+    #[arg(long, help = "World snapshots per second [env: GAME_TICK_RATE]")]
+    pub tick_rate: Option<u32>,
+
+    #[arg(long, value_name = "FILE", help = "Tile map file [env: GAME_MAP]")]
+    pub map: Option<PathBuf>,
+
+    #[arg(long, value_name = "DIR", help = "Directory to save finished chess games as PGN [env: GAME_PGN_DIR]")]
+    pub pgn_dir: Option<PathBuf>,
+
+    #[arg(long, value_name = "FILE", help = "SQLite database for accounts, enables login [env: GAME_DATABASE]")]
+    pub database: Option<PathBuf>,
+
+    #[arg(long, value_name = "DIR", help = "Directory for world snapshots and the journal, enables crash recovery [env: GAME_SAVE_DIR]")]
+    pub save_dir: Option<PathBuf>,
+}
+
+impl ServerArgs {
+    pub fn load(&self) -> Result<Config, ConfigError> {
+        self.load_with(|name| std::env::var(name).ok())
+    }
+
+    /// `env`: 환경변수 조회
+    pub fn load_with(&self, env: impl Fn(&str) -> Option<String>) -> Result<Config, ConfigError> {
+        let mut config = self.common.base(&env)?;
+
+        let server = &mut config.server;
+        self.common.apply_endpoint(&mut server.host, &mut server.port, &env)?;
+        override_with(&mut server.max_clients, self.max_clients, ENV_MAX_CLIENTS, &env)?;
+        override_with(&mut server.max_waiting, self.max_waiting, ENV_MAX_WAITING, &env)?;
+        override_with(&mut config.world.tick_rate, self.tick_rate, ENV_TICK_RATE, &env)?;
+        if let Some(map) = self.map.clone().or_else(|| env(ENV_MAP).map(PathBuf::from)) {
+            config.world.map = Some(map);
+        }
+        if let Some(dir) = self.pgn_dir.clone().or_else(|| env(ENV_PGN_DIR).map(PathBuf::from)) {
+            config.chess.pgn_dir = Some(dir);
+        }
+        if let Some(database) = self.database.clone().or_else(|| env(ENV_DATABASE).map(PathBuf::from)) {
+            config.accounts.database = Some(database);
+        }
+        if let Some(dir) = self.save_dir.clone().or_else(|| env(ENV_SAVE_DIR).map(PathBuf::from)) {
+            config.world.save_dir = Some(dir);
+        }
+
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+
+#[derive(Debug, Clone, Default, Parser)]
+#[command(name = "client", about = "Game client")]
+pub struct ClientArgs {
+    #[command(flatten)]
+    pub common: CommonArgs,
+
+    #[arg(long, help = "Piece to play: pawn, knight, bishop, rook, queen or king [env: GAME_PIECE]")]
+    pub piece: Option<String>,
+
+    #[arg(long, help = "Account name to log in with [env: GAME_NAME]")]
+    pub name: Option<String>,
+
+    #[arg(long, help = "Account password [env: GAME_PASSWORD]")]
+    pub password: Option<String>,
+
+    #[arg(long, help = "Create the account instead of logging in")]
+    pub register: bool,
+}
+
+impl ClientArgs {
+    pub fn load(&self) -> Result<Config, ConfigError> {
+        self.load_with(|name| std::env::var(name).ok())
+    }
+
+    pub fn load_with(&self, env: impl Fn(&str) -> Option<String>) -> Result<Config, ConfigError> {
+        let mut config = self.common.base(&env)?;
+
+        let client = &mut config.client;
+        self.common.apply_endpoint(&mut client.host, &mut client.port, &env)?;
+        if let Some(piece) = self.piece.clone().or_else(|| env(ENV_PIECE)) {
+            client.piece = Some(piece);
+        }
+        if let Some(name) = self.name.clone().or_else(|| env(ENV_NAME)) {
+            client.name = Some(name);
+        }
+        if let Some(password) = self.password.clone().or_else(|| env(ENV_PASSWORD)) {
+            client.password = Some(password);
+        }
+        client.register |= self.register;
+
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+
+/// 환경변수 `name`, 명령줄 인자 `cli` 순서로 덮어쓴다.
+fn override_with<T: FromStr>(
+    value: &mut T,
+    cli: Option<T>,
+    name: &'static str,
+    env: &impl Fn(&str) -> Option<String>,
+) -> Result<(), ConfigError> {
+    if let Some(text) = env(name) {
+        *value = text.parse().map_err(|_| ConfigError::Env { name, value: text })?;
+    }
+
+    if let Some(cli) = cli {
+        *value = cli;
+    }
+
+    Ok(())
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars = vars.iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect::<HashMap<_, _>>();
+
+        move |name| vars.get(name).cloned()
+    }
+
+    fn write_config(name: &str, text: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("game_config_{}_{}.toml", std::process::id(), name));
+        std::fs::write(&path, text).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_cli() {
+        let args = ServerArgs::try_parse_from(["server", "localhost:9000", "--tick-rate", "30"]).unwrap();
+        assert_eq!(args.common.addr.as_deref(), Some("localhost:9000"));
+        assert_eq!(args.tick_rate, Some(30));
+
+        let args = ClientArgs::try_parse_from(["client", "--host", "::1", "-p", "1234"]).unwrap();
+        let config = args.load_with(env(&[])).unwrap();
+        assert_eq!((config.client.host.as_str(), config.client.port), ("::1", 1234));
+        assert_eq!(config.client.piece, None);
+
+        let args = ClientArgs::try_parse_from(["client", "--piece", "knight"]).unwrap();
+        let config = args.load_with(env(&[(ENV_PIECE, "rook")])).unwrap();
+        assert_eq!(config.client.piece.as_deref(), Some("knight"));
+
+        let args = ClientArgs::try_parse_from(["client", "--name", "alice", "--register"]).unwrap();
+        let config = args.load_with(env(&[(ENV_PASSWORD, "secret")])).unwrap();
+        assert_eq!(config.client.name.as_deref(), Some("alice"));
+        assert_eq!(config.client.password.as_deref(), Some("secret"));
+        assert!(config.client.register);
+
+        assert!(ServerArgs::try_parse_from(["server", "--port", "abc"]).is_err());
+        assert!(ServerArgs::try_parse_from(["server", "a:1", "b:2"]).is_err());
+    }
+
+    #[test]
+    fn test_precedence() {
+        let path = write_config("precedence", r#"
+            [server]
+            host = "0.0.0.0"
+            port = 1000
+            max_clients = 10
+
+            [world]
+            tick_rate = 5
+            map = "arena.txt"
+            save_dir = "save"
+
+            [chess]
+            pgn_dir = "games"
+
+            [accounts]
+            database = "game.db"
+        "#);
+        let path_str = path.to_str().unwrap();
+
+        // 파일
+        let config = ServerArgs::default().load_with(env(&[(ENV_CONFIG, path_str)])).unwrap();
+        assert_eq!((config.server.host.as_str(), config.server.port), ("0.0.0.0", 1000));
+        assert_eq!(config.server.max_clients, 10);
+        assert_eq!(config.world.tick_rate, 5);
+        // 맵 경로는 설정 파일 기준
+        assert_eq!(config.world.map, Some(path.parent().unwrap().join("arena.txt")));
+        assert_eq!(config.world.save_dir, Some(path.parent().unwrap().join("save")));
+        assert_eq!(config.chess.pgn_dir, Some(path.parent().unwrap().join("games")));
+        assert_eq!(config.accounts.database, Some(path.parent().unwrap().join("game.db")));
+
+        // 파일 < 환경변수
+        let vars = [(ENV_CONFIG, path_str), (ENV_PORT, "2000"), (ENV_MAX_CLIENTS, "20")];
+        let config = ServerArgs::default().load_with(env(&vars)).unwrap();
+        assert_eq!((config.server.host.as_str(), config.server.port), ("0.0.0.0", 2000));
+        assert_eq!(config.server.max_clients, 20);
+
+        // 환경변수 < 명령줄 인자
+        let args = ServerArgs::try_parse_from(["server", "[::1]:3000", "--max-clients", "30"]).unwrap();
+        let config = args.load_with(env(&vars)).unwrap();
+        assert_eq!((config.server.host.as_str(), config.server.port), ("::1", 3000));
+        assert_eq!(config.server.max_clients, 30);
+        assert_eq!(config.world.tick_rate, 5);
+
+        // ADDR < --port
+        let args = ServerArgs::try_parse_from(["server", "localhost:3000", "--port", "4000"]).unwrap();
+        let config = args.load_with(env(&vars)).unwrap();
+        assert_eq!((config.server.host.as_str(), config.server.port), ("localhost", 4000));
+
+        let args = ServerArgs::try_parse_from(["server", "--map", "maps/small.txt"]).unwrap();
+        let config = args.load_with(env(&[(ENV_CONFIG, path_str), (ENV_MAP, "env.txt")])).unwrap();
+        assert_eq!(config.world.map, Some(PathBuf::from("maps/small.txt")));
+        assert_eq!(config.chess.pgn_dir, Some(path.parent().unwrap().join("games")));
+
+        let config = args.load_with(env(&[(ENV_CONFIG, path_str), (ENV_PGN_DIR, "pgn")])).unwrap();
+        assert_eq!(config.chess.pgn_dir, Some(PathBuf::from("pgn")));
+
+        let config = args.load_with(env(&[(ENV_CONFIG, path_str), (ENV_SAVE_DIR, "state")])).unwrap();
+        assert_eq!(config.world.save_dir, Some(PathBuf::from("state")));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_errors() {
+        let args = ServerArgs::default();
+
+        let error = args.load_with(env(&[(ENV_PORT, "port")])).unwrap_err();
+        assert!(matches!(error, ConfigError::Env { name: ENV_PORT, .. }));
+
+        let error = args.load_with(env(&[(ENV_CONFIG, "/nonexistent/game.toml")])).unwrap_err();
+        assert!(matches!(error, ConfigError::Read { .. }));
+
+        let path = write_config("invalid", "[server]\nport = \"abc\"\n");
+        let error = args.load_with(env(&[(ENV_CONFIG, path.to_str().unwrap())])).unwrap_err();
+        assert!(matches!(error, ConfigError::Parse { .. }));
+        std::fs::remove_file(path).unwrap();
+
+        let error = args.load_with(env(&[(ENV_MAX_CLIENTS, "0")])).unwrap_err();
+        assert!(matches!(error, ConfigError::Invalid(_)));
+
+        let args = ClientArgs::try_parse_from(["client", "localhost"]).unwrap();
+        assert!(matches!(args.load_with(env(&[])), Err(ConfigError::InvalidAddr(_))));
+    }
+}
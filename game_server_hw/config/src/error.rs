@@ -0,0 +1,48 @@
+use std::{fmt, io, path::PathBuf};
+
+
+#[derive(Debug)]
+pub enum ConfigError {
+    /// 설정 파일을 읽을 수 없음
+    Read { path: PathBuf, source: io::Error },
+    /// 설정 파일의 형식이 잘못됨
+    Parse { path: PathBuf, source: toml::de::Error },
+    /// 환경변수의 값을 해석할 수 없음
+    Env { name: &'static str, value: String },
+    /// `<host>:<port>` 형식이 아님
+    InvalidAddr(String),
+    /// 호스트 이름으로 주소를 찾을 수 없음
+    Resolve { host: String, source: io::Error },
+    /// 설정 값이 허용 범위를 벗어남
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Read { path, source } => {
+                write!(f, "Failed to read config file {}: {}", path.display(), source)
+            },
+            Self::Parse { path, source } => {
+                write!(f, "Invalid config file {}: {}", path.display(), source)
+            },
+            Self::Env { name, value } => write!(f, "Invalid value {:?} for {}", value, name),
+            Self::InvalidAddr(addr) => {
+                write!(f, "Invalid address {:?}; expected <host>:<port> (e.g. localhost:7878, [::1]:7878)", addr)
+            },
+            Self::Resolve { host, source } => write!(f, "Failed to resolve host {:?}: {}", host, source),
+            Self::Invalid(message) => write!(f, "Invalid config: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Read { source, .. } => Some(source),
+            Self::Parse { source, .. } => Some(source),
+            Self::Resolve { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
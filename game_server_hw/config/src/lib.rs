@@ -0,0 +1,27 @@
+//! 서버, 클라이언트, 더미 클라이언트가 공유하는 설정.
+//!
+//! 우선순위: 기본값 < 설정 파일(TOML) < 환경변수 < 명령줄 인자
+//!
+//! ```toml
+//! [server]
+//! host = "0.0.0.0"
+//! port = 7878
+//!
+//! [world]
+//! width = 8
+//! height = 8
+//!
+//! [client]
+//! host = "localhost"
+//! port = 7878
+//! ```
+
+mod error;
+mod addr;
+mod settings;
+mod args;
+
+pub use error::*;
+pub use addr::*;
+pub use settings::*;
+pub use args::*;
@@ -0,0 +1,516 @@
+use std::{net::SocketAddr, path::{Path, PathBuf}};
+use serde::{Deserialize, Serialize};
+
+use super::{resolve, ConfigError, PUBLIC_HOST};
+
+
+pub const DEFAULT_PORT: u16 = 7878;
+
+
+/// 설정 파일 전체. 없는 항목은 기본값을 사용한다.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub world: WorldConfig,
+    pub chess: ChessConfig,
+    pub chat: ChatConfig,
+    pub accounts: AccountConfig,
+    pub client: ClientConfig,
+}
+
+impl Config {
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|source| ConfigError::Read { path: path.to_path_buf(), source })?;
+
+        let mut config: Self = toml::from_str(&text)
+            .map_err(|source| ConfigError::Parse { path: path.to_path_buf(), source })?;
+
+        // 맵 파일, 저장 폴더, PGN 폴더, 데이터베이스의 상대 경로는 설정 파일 기준
+        if let Some(dir) = path.parent() {
+            let paths = [
+                &mut config.world.map,
+                &mut config.world.save_dir,
+                &mut config.chess.pgn_dir,
+                &mut config.accounts.database,
+            ];
+            for path in paths.into_iter().flatten() {
+                if path.is_relative() {
+                    *path = dir.join(&*path);
+                }
+            }
+        }
+
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        self.server.validate()?;
+        self.world.validate()?;
+        self.chat.validate()?;
+        self.accounts.validate()
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    /// `localhost`, `public`(이 컴퓨터의 네트워크 주소), IP 주소, 호스트 이름
+    pub host: String,
+    pub port: u16,
+    /// 동시에 접속할 수 있는 클라이언트 수
+    pub max_clients: usize,
+    /// 슬롯이 가득 찼을 때 대기할 수 있는 연결 수 (0이면 대기열 사용 안함)
+    pub max_waiting: usize,
+    /// 종료 신호를 받은 뒤 클라이언트에게 종료를 알리며 기다리는 시간(초)
+    pub shutdown_countdown_secs: u16,
+    /// 종료를 알린 뒤 클라이언트의 연결이 끝나기를 기다리는 최대 시간(초)
+    pub shutdown_timeout_secs: u64,
+    /// 표준 입력으로 운영자 명령(`list`, `kick`, ...)을 받을지
+    pub admin_console: bool,
+}
+
+impl ServerConfig {
+    /// 플레이어 id의 슬롯 번호는 `u16`
+    pub const MAX_CLIENTS_LIMIT: usize = u16::MAX as usize + 1;
+
+    pub fn socket_addrs(&self) -> Result<Vec<SocketAddr>, ConfigError> {
+        resolve(&self.host, self.port)
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if !(1..=Self::MAX_CLIENTS_LIMIT).contains(&self.max_clients) {
+            return Err(ConfigError::Invalid(format!(
+                "server.max_clients must be between 1 and {}", Self::MAX_CLIENTS_LIMIT
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            host: PUBLIC_HOST.to_string(),
+            port: DEFAULT_PORT,
+            max_clients: 10000,
+            max_waiting: 1000,
+            shutdown_countdown_secs: 5,
+            shutdown_timeout_secs: 10,
+            admin_console: true,
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WorldConfig {
+    /// 맵 파일. 있으면 `width`, `height`, `spawn` 대신 맵을 사용한다.
+    pub map: Option<PathBuf>,
+    /// 맵 파일이 없을 때 월드의 가로, 세로 칸 수
+    pub width: i32,
+    pub height: i32,
+    /// 맵 파일이 없을 때 새 플레이어가 생성되는 칸
+    pub spawn: (i32, i32),
+    /// 초당 스냅샷 전송 횟수
+    pub tick_rate: u32,
+    /// 월드로 보내는 요청 채널의 크기
+    pub channel_capacity: usize,
+    /// 클라이언트에게 보내는 시야 반경 (칸). 이 안의 오브젝트만 전송
+    pub view_radius: i32,
+    /// 시작할 때 생성하는 NPC (`[[world.npcs]]`)
+    pub npcs: Vec<NpcConfig>,
+    pub combat: CombatConfig,
+    /// 월드 스냅샷과 저널을 저장하는 폴더. 있으면 시작할 때 마지막 상태로 복구한다.
+    pub save_dir: Option<PathBuf>,
+    /// 스냅샷을 저장하는 주기 (초). 그 사이의 요청은 저널에 기록한다.
+    pub save_interval_secs: u32,
+}
+
+impl WorldConfig {
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.map.is_none() {
+            if self.width < 1 || self.height < 1 {
+                return Err(ConfigError::Invalid("world.width and world.height must be at least 1".to_string()));
+            }
+
+            let (x, y) = self.spawn;
+            if !(0..self.width).contains(&x) || !(0..self.height).contains(&y) {
+                return Err(ConfigError::Invalid(format!("world.spawn {:?} is outside of the world", self.spawn)));
+            }
+        }
+
+        if self.tick_rate < 1 {
+            return Err(ConfigError::Invalid("world.tick_rate must be at least 1".to_string()));
+        }
+
+        if self.channel_capacity < 1 {
+            return Err(ConfigError::Invalid("world.channel_capacity must be at least 1".to_string()));
+        }
+
+        if self.view_radius < 0 {
+            return Err(ConfigError::Invalid("world.view_radius must not be negative".to_string()));
+        }
+
+        for npc in &self.npcs {
+            npc.validate()?;
+        }
+
+        self.combat.validate()?;
+
+        if self.save_interval_secs < 1 {
+            return Err(ConfigError::Invalid("world.save_interval_secs must be at least 1".to_string()));
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for WorldConfig {
+    fn default() -> Self {
+        Self {
+            map: None,
+            width: 8,
+            height: 8,
+            spawn: (3, 3),
+            tick_rate: 20,
+            channel_capacity: 128,
+            view_radius: 5,
+            npcs: Vec::new(),
+            combat: CombatConfig::default(),
+            save_dir: None,
+            save_interval_secs: 60,
+        }
+    }
+}
+
+
+/// NPC의 행동
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NpcBehavior {
+    /// 무작위로 돌아다님
+    #[default]
+    Wander,
+    /// `waypoints`를 순서대로 돌아다님
+    Patrol,
+    /// `chase_range`칸 안의 가장 가까운 플레이어를 쫓아감. 없으면 `Wander`
+    Chase,
+}
+
+
+/// 같은 설정으로 생성되는 NPC 무리
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NpcConfig {
+    pub behavior: NpcBehavior,
+    /// 표시할 말 (`pawn`, `knight`, ...). 이동은 말과 상관없이 상하좌우 한칸
+    pub piece: String,
+    pub count: usize,
+    /// 생성되는 칸. 차 있으면 가장 가까운 빈 칸, 없으면 플레이어처럼 스폰 타일
+    pub spawn: Option<(i32, i32)>,
+    /// `Patrol`이 순서대로 방문하는 칸
+    pub waypoints: Vec<(i32, i32)>,
+    pub chase_range: i32,
+    /// 몇 tick마다 한칸씩 이동하는지
+    pub move_interval: u32,
+}
+
+impl NpcConfig {
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.behavior == NpcBehavior::Patrol && self.waypoints.is_empty() {
+            return Err(ConfigError::Invalid("world.npcs with behavior \"patrol\" need waypoints".to_string()));
+        }
+
+        if self.chase_range < 0 {
+            return Err(ConfigError::Invalid("world.npcs.chase_range must not be negative".to_string()));
+        }
+
+        if self.move_interval < 1 {
+            return Err(ConfigError::Invalid("world.npcs.move_interval must be at least 1".to_string()));
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for NpcConfig {
+    fn default() -> Self {
+        Self {
+            behavior: NpcBehavior::default(),
+            piece: "pawn".to_string(),
+            count: 1,
+            spawn: None,
+            waypoints: Vec::new(),
+            chase_range: 5,
+            move_interval: 10,
+        }
+    }
+}
+
+
+/// 전투 규칙. 플레이어와 NPC 모두 같은 규칙을 따른다.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CombatConfig {
+    /// 생성, 부활할 때의 체력
+    pub max_hp: u16,
+    /// 한번 공격할 때의 피해
+    pub attack: u16,
+    /// 공격할 수 있는 거리 (가로, 세로 칸 수. 1이면 주변 8칸)
+    pub attack_range: i32,
+    /// 공격한 뒤 다시 공격할 수 있을 때까지의 tick
+    pub attack_cooldown: u32,
+    /// 죽은 뒤 부활할 때까지의 tick
+    pub respawn_ticks: u32,
+}
+
+impl CombatConfig {
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.max_hp < 1 {
+            return Err(ConfigError::Invalid("world.combat.max_hp must be at least 1".to_string()));
+        }
+
+        if self.attack_range < 1 {
+            return Err(ConfigError::Invalid("world.combat.attack_range must be at least 1".to_string()));
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for CombatConfig {
+    fn default() -> Self {
+        Self {
+            max_hp: 100,
+            attack: 10,
+            attack_range: 1,
+            attack_cooldown: 10,
+            respawn_ticks: 100,
+        }
+    }
+}
+
+
+/// 체스 대국
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChessConfig {
+    /// 끝난 대국의 PGN을 저장할 폴더. 없으면 저장하지 않는다.
+    pub pgn_dir: Option<PathBuf>,
+}
+
+
+/// 채팅
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChatConfig {
+    /// 메세지 하나의 최대 글자 수
+    pub max_len: usize,
+    /// 근처 채팅이 전달되는 거리 (가로, 세로 칸 수)
+    pub proximity_radius: i32,
+    /// `rate_window_ms`동안 보낼 수 있는 메세지 수
+    pub rate_limit: usize,
+    pub rate_window_ms: u64,
+    /// `*`로 가려지는 단어 (ASCII 대소문자 구분 없음)
+    pub blocklist: Vec<String>,
+}
+
+impl ChatConfig {
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.max_len < 1 {
+            return Err(ConfigError::Invalid("chat.max_len must be at least 1".to_string()));
+        }
+
+        if self.proximity_radius < 0 {
+            return Err(ConfigError::Invalid("chat.proximity_radius must not be negative".to_string()));
+        }
+
+        if self.rate_limit < 1 || self.rate_window_ms < 1 {
+            return Err(ConfigError::Invalid("chat.rate_limit and chat.rate_window_ms must be at least 1".to_string()));
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for ChatConfig {
+    fn default() -> Self {
+        Self {
+            max_len: 200,
+            proximity_radius: 5,
+            rate_limit: 5,
+            rate_window_ms: 5000,
+            blocklist: Vec::new(),
+        }
+    }
+}
+
+
+/// 계정. 데이터베이스가 없으면 로그인 없이 익명으로 접속한다.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AccountConfig {
+    /// 계정과 플레이어 기록을 저장하는 SQLite 파일. 없으면 만든다.
+    pub database: Option<PathBuf>,
+    /// 가입할 때 비밀번호의 최소 글자 수
+    pub min_password_len: usize,
+    /// 접속 중인 플레이어의 변경된 상태를 모아서 저장하는 주기
+    pub flush_interval_ms: u64,
+}
+
+impl AccountConfig {
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.min_password_len < 1 {
+            return Err(ConfigError::Invalid("accounts.min_password_len must be at least 1".to_string()));
+        }
+        if self.flush_interval_ms < 1 {
+            return Err(ConfigError::Invalid("accounts.flush_interval_ms must be at least 1".to_string()));
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for AccountConfig {
+    fn default() -> Self {
+        Self {
+            database: None,
+            min_password_len: 8,
+            flush_interval_ms: 1000,
+        }
+    }
+}
+
+
+/// 클라이언트가 접속할 서버
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClientConfig {
+    pub host: String,
+    pub port: u16,
+    /// 접속 후 고를 말 (`pawn`, `knight`, `bishop`, `rook`, `queen`, `king`).
+    /// 없으면 서버가 정해준 말을 사용한다.
+    pub piece: Option<String>,
+    /// 계정이 있는 서버에 로그인할 이름과 비밀번호
+    pub name: Option<String>,
+    pub password: Option<String>,
+    /// 로그인 대신 가입
+    pub register: bool,
+}
+
+impl ClientConfig {
+    pub fn socket_addrs(&self) -> Result<Vec<SocketAddr>, ConfigError> {
+        resolve(&self.host, self.port)
+    }
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            host: PUBLIC_HOST.to_string(),
+            port: DEFAULT_PORT,
+            piece: None,
+            name: None,
+            password: None,
+            register: false,
+        }
+    }
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partial_file() {
+        let config: Config = toml::from_str(r#"
+            [server]
+            port = 9000
+
+            [world]
+            width = 16
+            spawn = [10, 2]
+        "#).unwrap();
+
+        assert_eq!(config.server.port, 9000);
+        assert_eq!(config.server.host, PUBLIC_HOST);
+        assert_eq!(config.world.width, 16);
+        assert_eq!(config.world.height, 8);
+        assert_eq!(config.world.spawn, (10, 2));
+        assert_eq!(config.client, ClientConfig::default());
+        assert_eq!(config.chess.pgn_dir, None);
+        assert_eq!(config.chat, ChatConfig::default());
+        assert_eq!(config.accounts, AccountConfig::default());
+        assert_eq!(config.world.combat, CombatConfig::default());
+        config.validate().unwrap();
+
+        assert!(toml::from_str::<Config>("[server]\nprot = 1").is_err());
+        assert!(toml::from_str::<Config>("[[world.npcs]]\nbehavior = \"sleep\"").is_err());
+        assert!(toml::from_str::<Config>("[server]\nport = -1").is_err());
+    }
+
+    #[test]
+    fn test_npcs() {
+        let config: Config = toml::from_str(r#"
+            [[world.npcs]]
+            piece = "knight"
+            count = 3
+
+            [[world.npcs]]
+            behavior = "patrol"
+            waypoints = [[1, 1], [5, 1]]
+        "#).unwrap();
+
+        let npcs = &config.world.npcs;
+        assert_eq!(npcs.len(), 2);
+        assert_eq!((npcs[0].behavior, npcs[0].piece.as_str(), npcs[0].count), (NpcBehavior::Wander, "knight", 3));
+        assert_eq!(npcs[1].waypoints, [(1, 1), (5, 1)]);
+        config.validate().unwrap();
+
+        // 경유지 없는 순찰
+        let mut config = Config::default();
+        config.world.npcs.push(NpcConfig { behavior: NpcBehavior::Patrol, ..Default::default() });
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        Config::default().validate().unwrap();
+
+        let mut config = Config::default();
+        config.world.spawn = (8, 0);
+        assert!(matches!(config.validate(), Err(ConfigError::Invalid(_))));
+
+        // 맵 파일이 있으면 spawn은 사용하지 않음
+        config.world.map = Some(PathBuf::from("maps/arena.txt"));
+        config.validate().unwrap();
+
+        let mut config = Config::default();
+        config.world.tick_rate = 0;
+        assert!(config.validate().is_err());
+
+        let mut config = Config::default();
+        config.world.view_radius = -1;
+        assert!(config.validate().is_err());
+
+        let mut config = Config::default();
+        config.server.max_clients = ServerConfig::MAX_CLIENTS_LIMIT + 1;
+        assert!(config.validate().is_err());
+
+        let mut config = Config::default();
+        config.chat.rate_limit = 0;
+        assert!(config.validate().is_err());
+
+        let mut config = Config::default();
+        config.world.combat.attack_range = 0;
+        assert!(config.validate().is_err());
+    }
+}
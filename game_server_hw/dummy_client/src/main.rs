@@ -4,183 +4,227 @@ use std::{
     rc::Rc, 
     cell::RefCell, 
     collections::HashMap, 
-    time::{SystemTime, UNIX_EPOCH},
+    net::SocketAddr,
+    process::ExitCode,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 use tokio::{
     net::TcpStream,
-    io::{AsyncReadExt, AsyncWriteExt},
+    time::{interval, Interval},
 };
+use tokio_util::codec::Framed;
+use futures::{SinkExt, StreamExt};
 use rand::Rng;
-use get_addr::get_addr;
+use clap::Parser;
+use config::{ClientArgs, CommonArgs};
 use network::*;
 
 
 struct Player {
     x: i32,
     z: i32,
+    class: PieceClass,
 }
 
 struct Server {
-    players: HashMap<u32, Rc<RefCell<Player>>>,
+    players: HashMap<PlayerId, Rc<RefCell<Player>>>,
 
-    player_id: u32,
+    player_id: PlayerId,
 
     addr: String,
-    stream: TcpStream,
-    packet_parser: PacketParser,
+    stream: Framed<TcpStream, MessageCodec>,
 
-    timer: SystemTime,
+    /// ping, 이동 주기
+    timer: Interval,
 }
 
 impl Server {
-    pub async fn new() -> Self {
-        let (ip, port) = match get_addr() {
-            Ok((ip, port)) => (ip, port),
-            Err(e) => panic!("{}", e),
-        };
-        
-        let addr = format!("{}:{}", ip, port);
-        let stream = TcpStream::connect(addr.clone()).await.unwrap();
+    /// `piece`: 접속 후 고를 말
+    pub async fn new(addrs: &[SocketAddr], piece: PieceClass) -> Self {
+        let stream = TcpStream::connect(addrs).await.unwrap();
+        let addr = stream.peer_addr().unwrap().to_string();
 
-        Self {
+        let mut server = Self {
             players: HashMap::new(),
 
-            player_id: 0,
+            player_id: PlayerId::default(),
 
             addr,
-            stream,
-            packet_parser: PacketParser::new(),
+            stream: Framed::new(stream, MessageCodec::new()),
 
-            timer: SystemTime::now(),
-        }
+            timer: interval(Duration::from_secs(1)),
+        };
+
+        // 입장한 뒤에 처리됨
+        server.send(Message::SelectClass { class: piece }).await;
+        server
     }
 
     fn player(&self) -> Option<Rc<RefCell<Player>>> {
         self.players.get(&self.player_id).cloned()
     }
 
-    async fn pull_messages(&mut self) {
-        let mut buf = [0; 1024];
+    fn handle_read(&mut self, read: Option<Result<Message, std::io::Error>>) {
+        match read {
+            None => println!("Connection closed"),
 
-        match self.stream.read(&mut buf).await {
-            Ok(0) => println!("Connection closed"),
-            
-            Ok(n) => self.packet_parser.push(&buf[..n]),
+            Some(Ok(msg)) => self.process_message(msg),
 
-            Err(e) => eprintln!("Failed to read from socket; err = {:?}", e),
+            Some(Err(e)) => eprintln!("Failed to read from socket; err = {:?}", e),
         }
     }
     
-    fn process_message(&mut self, msg: &str) {
-        let msg = msg.trim().split_whitespace()
-            .map(|s| s.trim())
-            .collect::<Vec<&str>>();
-
+    fn process_message(&mut self, msg: Message) {
         // println!("Received: {:?}", msg);
 
-        if msg.len() == 0 {
-            return;
-        }
+        match msg {
+            Message::Init { id } => {
+                self.player_id = id;
+            }
 
-        match msg[0] {
-            "init" => {
-                if msg.len() < 2 {
-                    return;
-                }
+            Message::Pong { time } => {
+                let latency = now_millis().saturating_sub(time);
+                println!("server {} latency: {}ms", self.player_id, latency);
+            }
 
-                self.player_id = msg[1].parse::<u32>().unwrap();
+            Message::UpdateSnapshot { objects } |
+            Message::EnterView { objects } => {
+                for ObjectInfo { id, x, y: z, class, .. } in objects {
+                    let player = self.players.entry(id)
+                        .or_insert_with(|| {
+                            Rc::new(RefCell::new(Player { x, z, class }))
+                        });
+
+                    let mut player = player.borrow_mut();
+                    player.x = x;
+                    player.z = z;
+                    player.class = class;
+                }
             }
 
-            "update" => {
-                if msg.len() < 2 {
-                    return;
+            Message::LeaveView { ids } => {
+                for id in ids {
+                    self.players.remove(&id);
                 }
+            }
 
-                let num_objects = msg[1].parse::<usize>().unwrap();
-                let mut valid_ids: Vec<u32> = Vec::new();
+            Message::QueuePosition { position } => {
+                println!("waiting in queue: {}", position);
+            }
 
-                for i in 0..num_objects {
-                    let idx = 2 + i * 3;
-                    let id = msg[idx].parse::<u32>().unwrap();
-                    let x = msg[idx+1].parse::<i32>().unwrap();
-                    let z = msg[idx+2].parse::<i32>().unwrap();
+            Message::ConnectRejected { reason } => {
+                println!("connection rejected: {:?}", reason);
+            }
 
-                    self.players.entry(id)
-                        .or_insert_with(|| {
-                            Rc::new(RefCell::new(Player { x, z }))
-                        });
-                    
-                    valid_ids.push(id);
-                }
+            Message::Shutdown { seconds } => {
+                println!("server shutting down in {} seconds", seconds);
+            }
 
-                // 기존에 있던 id가 안보이면 삭제
-                self.players.retain(|k, _| valid_ids.contains(k));
+            Message::Notice { text } => {
+                println!("notice: {}", text);
             }
+
             _ => {}
         }
     }
 
-    async fn update(&mut self) {
-        let now = std::time::SystemTime::now()
-            .duration_since(UNIX_EPOCH).unwrap()
-            .as_millis();
-        let packet = MessagePacket::new(now, "update").as_raw();
-
-        self.stream.write_all(&packet.as_bytes()).await
+    async fn send(&mut self, msg: Message) {
+        self.stream.send(msg).await
             .expect("Failed to write to stream");
+    }
 
-        self.pull_messages().await;
-
-        while let Some(packet) = self.packet_parser.pop() {
-            let packet = match MessagePacket::from_raw(packet) {
-                Ok(packet) => packet,
-                Err(_) => continue,
-            };
-            
-            let msg = packet.msg;
-            self.process_message(&msg);
-
-            if self.timer.elapsed().unwrap().as_secs() >= 1 {
-                self.timer = SystemTime::now();
-                let latency = self.timer
-                    .duration_since(UNIX_EPOCH).unwrap()
-                    .as_millis() - packet.time;
-                println!("server {} latency: {}ms", self.player_id, latency);
-            }
-        }
+    /// 서버가 보내주는 스냅샷을 받으면서, 1초마다 ping과 랜덤 이동을 보낸다.
+    async fn update(&mut self) {
+        tokio::select! {
+            read = self.stream.next() => self.handle_read(read),
 
-        if self.timer.elapsed().unwrap().as_millis() >= 1000 {
-            let mut rng = rand::thread_rng();
-            let (x, z) = match rng.gen_range(0..4) {
-                0 => (1, 0),
-                1 => (0, 1),
-                2 => (-1, 0),
-                3 => (0, -1),
-                _ => (0, 0),
-            };
-
-            let move_msg = format!("move {} {x} {z}\n", self.player_id);
-            let packet = MessagePacket::new(now, &move_msg).as_raw();
-            self.stream.write_all(&packet.as_bytes()).await
-                .expect("Failed to write to stream");
+            _ = self.timer.tick() => self.act().await,
         }
     }
+
+    async fn act(&mut self) {
+        // 입장하기 전(대기중)에는 보내지 않음
+        let Some(player) = self.player() else {
+            return;
+        };
+        let class = player.borrow().class;
+
+        self.send(Message::Ping { time: now_millis() }).await;
+
+        // 말의 규칙에 맞는 방향으로 (여러칸 가는 말은 1~3칸)
+        let mut rng = rand::thread_rng();
+        let directions = class.directions();
+        let (x, z) = directions[rng.gen_range(0..directions.len())];
+        let distance = if class.slides() { rng.gen_range(1..=3) } else { 1 };
+        let (x, z) = (x * distance, z * distance);
+
+        self.send(Message::Move { id: self.player_id, x, y: z }).await;
+    }
+}
+
+
+fn now_millis() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH).unwrap()
+        .as_millis()
 }
 
 
 
 use futures::future::join_all;
 
+#[derive(Parser)]
+#[command(name = "dummy_client", about = "Connects many bots to the game server")]
+struct Cli {
+    #[command(flatten)]
+    common: CommonArgs,
+
+    #[arg(short, long, default_value_t = 100, help = "Number of bots")]
+    bots: usize,
+
+    #[arg(long, default_value = "random", help = "Piece for every bot, or random")]
+    piece: String,
+}
+
 #[tokio::main]
-async fn main() {
-    let servers = (0..100).map(|_| new_server());
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let piece = match cli.piece.as_str() {
+        "random" => None,
+        name => match name.parse::<PieceClass>() {
+            Ok(piece) => Some(piece),
+            Err(e) => {
+                eprintln!("{}", e);
+                return ExitCode::FAILURE;
+            }
+        },
+    };
+
+    let addrs = ClientArgs { common: cli.common, piece: None, name: None, password: None, register: false }.load()
+        .and_then(|config| config.client.socket_addrs());
+    let addrs = match addrs {
+        Ok(addrs) => addrs,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let servers = (0..cli.bots).map(|_| {
+        let piece = piece.unwrap_or_else(|| {
+            PieceClass::ALL[rand::thread_rng().gen_range(0..PieceClass::ALL.len())]
+        });
+        new_server(&addrs, piece)
+    });
     join_all(servers).await;
 
     println!("done");
+    ExitCode::SUCCESS
 }
 
-async fn new_server() {
-    let mut server = Server::new().await;
+async fn new_server(addrs: &[SocketAddr], piece: PieceClass) {
+    let mut server = Server::new(addrs, piece).await;
 
     loop {
         server.update().await;
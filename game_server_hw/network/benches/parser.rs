@@ -0,0 +1,160 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use network::*;
+
+
+/// 링버퍼 도입 이전의 `PacketParser` 구현 (비교용)
+mod legacy {
+    use std::collections::VecDeque;
+    use std::mem::size_of;
+    use network::*;
+
+    pub enum Packet {
+        Complete(RawPacket),
+        Incomplete(Vec<u8>),
+    }
+
+    use Packet::*;
+
+    pub struct PacketParser {
+        queue: VecDeque<Packet>,
+    }
+
+    impl PacketParser {
+        pub fn new() -> Self {
+            Self {
+                queue: VecDeque::new(),
+            }
+        }
+
+        pub fn push(&mut self, data: &[u8]) {
+            if data.is_empty() {
+                return;
+            }
+
+            let mut data = Vec::from(data);
+
+            if let Some(Incomplete(prev)) = self.queue.back() {
+                data.splice(0..0, prev.iter().cloned());
+                self.queue.pop_back().unwrap();
+            }
+
+            let mut len = data.len();
+            while len > 0 {
+                if len < 2 {
+                    self.queue.push_back(Incomplete(data));
+                    break;
+                }
+
+                let size = bytemuck::pod_read_unaligned::<PacketSize>(&data[0..size_of::<PacketSize>()]) as usize;
+
+                if len < size {
+                    self.queue.push_back(Incomplete(data));
+                    break;
+                }
+
+                let packet = data.drain(0..size).collect::<Vec<u8>>();
+
+                len = data.len();
+
+                let packet = match RawPacket::from_bytes(&packet) {
+                    Ok(packet) => Complete(packet),
+                    Err(_) => continue,
+                };
+
+                self.queue.push_back(packet);
+            }
+        }
+
+        pub fn pop(&mut self) -> Option<RawPacket> {
+            if self.queue.len() == 1 {
+                if let Some(Incomplete(_)) = self.queue.front() {
+                    return None;
+                }
+            }
+
+            match self.queue.pop_front() {
+                Some(Complete(some)) => Some(some),
+                _ => None,
+            }
+        }
+    }
+}
+
+
+/// 서버가 한번에 읽는 크기
+const READ_SIZE: usize = 1024;
+
+/// 봇 100개가 보내는 요청 (update / move / ping)
+fn client_stream() -> Vec<u8> {
+    (0..100u16)
+        .flat_map(|id| [
+            Message::UpdateRequest,
+            Message::Move { id: PlayerId::new(id, 0), x: 1, y: 0 },
+            Message::UpdateRequest,
+            Message::Ping { time: id as u128 },
+        ])
+        .flat_map(|msg| msg.as_raw().as_bytes())
+        .collect()
+}
+
+/// 오브젝트 100개짜리 스냅샷을 여러개 받는 경우
+fn server_stream() -> Vec<u8> {
+    let objects = (0..100u16)
+        .map(|id| ObjectInfo::new(PlayerId::new(id, 0), (id % 8) as i32, (id / 8) as i32, PieceClass::PAWN))
+        .collect::<Vec<_>>();
+
+    (0..20)
+        .flat_map(|_| Message::UpdateSnapshot { objects: objects.clone() }.as_raw().as_bytes())
+        .collect()
+}
+
+
+fn parse_legacy(stream: &[u8]) -> usize {
+    let mut parser = legacy::PacketParser::new();
+    let mut count = 0;
+
+    for chunk in stream.chunks(READ_SIZE) {
+        parser.push(chunk);
+        while let Some(packet) = parser.pop() {
+            count += packet.data().len();
+        }
+    }
+
+    count
+}
+
+fn parse_buffered(stream: &[u8]) -> usize {
+    let mut parser = PacketParser::new();
+    let mut count = 0;
+
+    for chunk in stream.chunks(READ_SIZE) {
+        parser.buffer_mut().extend_from_slice(chunk);
+        while let Ok(Some(packet)) = parser.pop() {
+            count += packet.data().len();
+        }
+    }
+
+    count
+}
+
+
+fn bench_parser(c: &mut Criterion) {
+    let mut group = c.benchmark_group("packet_parser");
+
+    for (name, stream) in [("client_stream", client_stream()), ("server_stream", server_stream())] {
+        assert_eq!(parse_legacy(&stream), parse_buffered(&stream));
+
+        group.throughput(Throughput::Bytes(stream.len() as u64));
+        group.bench_with_input(BenchmarkId::new("legacy", name), &stream, |b, stream| {
+            b.iter(|| parse_legacy(stream))
+        });
+        group.bench_with_input(BenchmarkId::new("buffered", name), &stream, |b, stream| {
+            b.iter(|| parse_buffered(stream))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_parser);
+criterion_main!(benches);
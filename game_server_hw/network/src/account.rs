@@ -0,0 +1,31 @@
+/// 서버가 로그인, 가입을 거부한 이유
+#[repr(u8)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum LoginRejectReason {
+    /// 없는 이름이거나 비밀번호가 틀림
+    InvalidCredentials = 1,
+    /// 가입: 이미 있는 이름
+    NameTaken = 2,
+    /// 가입: 사용할 수 없는 이름 (길이, 문자)
+    InvalidName = 3,
+    /// 가입: 비밀번호가 너무 짧거나 김
+    InvalidPassword = 4,
+    /// 같은 계정으로 이미 접속해있음
+    AlreadyOnline = 5,
+    /// 서버에서 계정을 읽거나 쓰지 못함
+    Unavailable = 6,
+}
+
+impl LoginRejectReason {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            1 => Some(Self::InvalidCredentials),
+            2 => Some(Self::NameTaken),
+            3 => Some(Self::InvalidName),
+            4 => Some(Self::InvalidPassword),
+            5 => Some(Self::AlreadyOnline),
+            6 => Some(Self::Unavailable),
+            _ => None,
+        }
+    }
+}
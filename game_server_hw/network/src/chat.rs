@@ -0,0 +1,52 @@
+/// 채팅을 받는 대상
+#[repr(u8)]
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum ChatChannel {
+    /// 접속한 모든 플레이어
+    Global = 1,
+    /// 한 플레이어에게만 (id 또는 이름으로 지정)
+    Whisper = 2,
+    /// 가까운 칸에 있는 플레이어
+    Proximity = 3,
+}
+
+impl ChatChannel {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            1 => Some(Self::Global),
+            2 => Some(Self::Whisper),
+            3 => Some(Self::Proximity),
+            _ => None,
+        }
+    }
+}
+
+
+/// 서버가 채팅을 거부한 이유
+#[repr(u8)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum ChatRejectReason {
+    /// 공백뿐인 메세지
+    Empty = 1,
+    /// 최대 길이를 넘음
+    TooLong = 2,
+    /// 너무 자주 보냄
+    RateLimited = 3,
+    /// 필터에 걸림
+    Blocked = 4,
+    /// 귓속말 대상이 접속해있지 않음
+    UnknownTarget = 5,
+}
+
+impl ChatRejectReason {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            1 => Some(Self::Empty),
+            2 => Some(Self::TooLong),
+            3 => Some(Self::RateLimited),
+            4 => Some(Self::Blocked),
+            5 => Some(Self::UnknownTarget),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,310 @@
+use std::fmt;
+use std::str::FromStr;
+
+use super::piece::PieceClass;
+
+
+/// 체스 말의 색. 백이 먼저 둔다.
+#[repr(u8)]
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum ChessColor {
+    White = 0,
+    Black = 1,
+}
+
+impl ChessColor {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::White),
+            1 => Some(Self::Black),
+            _ => None,
+        }
+    }
+
+    pub fn opponent(self) -> Self {
+        match self {
+            Self::White => Self::Black,
+            Self::Black => Self::White,
+        }
+    }
+}
+
+
+/// 체스판의 칸. `a1`이 0, `h1`이 7, `a8`이 56.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone, PartialOrd, Ord)]
+pub struct Square(u8);
+
+impl Square {
+    /// `file`: 0(a) ~ 7(h), `rank`: 0(1) ~ 7(8). 판 밖이면 `None`
+    pub fn new(file: i32, rank: i32) -> Option<Self> {
+        ((0..8).contains(&file) && (0..8).contains(&rank)).then(|| Self((rank * 8 + file) as u8))
+    }
+
+    pub fn from_index(index: u8) -> Option<Self> {
+        (index < 64).then_some(Self(index))
+    }
+
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    pub fn file(self) -> i32 {
+        (self.0 % 8) as i32
+    }
+
+    pub fn rank(self) -> i32 {
+        (self.0 / 8) as i32
+    }
+
+    /// `(file, rank)`만큼 떨어진 칸
+    pub fn offset(self, file: i32, rank: i32) -> Option<Self> {
+        Self::new(self.file() + file, self.rank() + rank)
+    }
+
+    pub fn all() -> impl Iterator<Item = Self> {
+        (0..64).map(Self)
+    }
+}
+
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let file = (b'a' + self.file() as u8) as char;
+        write!(f, "{}{}", file, self.rank() + 1)
+    }
+}
+
+impl FromStr for Square {
+    type Err = String;
+
+    /// `e4` 형식
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.as_bytes() {
+            &[file @ b'a'..=b'h', rank @ b'1'..=b'8'] => {
+                Ok(Self(((rank - b'1') * 8) + (file - b'a')))
+            },
+            _ => Err(format!("Invalid square '{}'", s)),
+        }
+    }
+}
+
+
+/// 체스 수 한개. 폰이 끝까지 가면 `promotion`이 있어야 한다.
+/// 캐슬링은 킹을 두칸 옮기는 수로 표현한다.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub struct ChessMove {
+    pub from: Square,
+    pub to: Square,
+    pub promotion: Option<PieceClass>,
+}
+
+impl ChessMove {
+    pub fn new(from: Square, to: Square) -> Self {
+        Self { from, to, promotion: None }
+    }
+
+    pub fn with_promotion(from: Square, to: Square, promotion: PieceClass) -> Self {
+        Self { from, to, promotion: Some(promotion) }
+    }
+}
+
+impl fmt::Display for ChessMove {
+    /// UCI 형식 (`e2e4`, `e7e8q`)
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.from, self.to)?;
+
+        match self.promotion {
+            Some(PieceClass::KNIGHT) => write!(f, "n"),
+            Some(PieceClass::BISHOP) => write!(f, "b"),
+            Some(PieceClass::ROOK) => write!(f, "r"),
+            Some(PieceClass::QUEEN) => write!(f, "q"),
+            _ => Ok(()),
+        }
+    }
+}
+
+
+/// 체스판 위의 말
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub struct ChessPiece {
+    pub color: ChessColor,
+    pub class: PieceClass,
+}
+
+impl ChessPiece {
+    pub fn new(color: ChessColor, class: PieceClass) -> Self {
+        Self { color, class }
+    }
+
+    /// 빈 칸은 0, 말은 `1 + class`에 흑이면 8을 더한다.
+    pub(crate) fn encode(piece: Option<Self>) -> u8 {
+        match piece {
+            None => 0,
+            Some(Self { color, class }) => 1 + class.value() + (color as u8) * 8,
+        }
+    }
+
+    /// 잘못된 값이면 `Err`
+    pub(crate) fn decode(value: u8) -> Result<Option<Self>, ()> {
+        if value == 0 {
+            return Ok(None);
+        }
+
+        let color = ChessColor::from_u8((value - 1) / 8).ok_or(())?;
+        let class = PieceClass::from_u8((value - 1) % 8).ok_or(())?;
+
+        Ok(Some(Self { color, class }))
+    }
+}
+
+
+/// 클라이언트에게 보내는 체스판 전체
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct BoardState {
+    /// `Square::index` 순서
+    pub squares: [Option<ChessPiece>; 64],
+    /// 다음에 둘 차례
+    pub turn: ChessColor,
+    /// 다음에 둘 쪽이 체크 상태인지
+    pub check: bool,
+}
+
+impl BoardState {
+    pub fn piece_at(&self, square: Square) -> Option<ChessPiece> {
+        self.squares[square.index()]
+    }
+}
+
+
+/// 대국 결과
+#[repr(u8)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum GameResult {
+    WhiteWins = 1,
+    BlackWins = 2,
+    Draw = 3,
+}
+
+impl GameResult {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            1 => Some(Self::WhiteWins),
+            2 => Some(Self::BlackWins),
+            3 => Some(Self::Draw),
+            _ => None,
+        }
+    }
+
+    pub fn win(color: ChessColor) -> Self {
+        match color {
+            ChessColor::White => Self::WhiteWins,
+            ChessColor::Black => Self::BlackWins,
+        }
+    }
+
+    /// PGN의 결과 표기
+    pub fn pgn(self) -> &'static str {
+        match self {
+            Self::WhiteWins => "1-0",
+            Self::BlackWins => "0-1",
+            Self::Draw => "1/2-1/2",
+        }
+    }
+}
+
+
+/// 대국이 끝난 이유
+#[repr(u8)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum GameOverReason {
+    Checkmate = 1,
+    Stalemate = 2,
+    Resignation = 3,
+    /// 무승부 제안을 상대가 수락
+    Agreement = 4,
+    /// 상대의 연결이 끊어짐
+    Abandoned = 5,
+    /// 50수 동안 폰 이동과 잡기가 없음
+    FiftyMoves = 6,
+    /// 양쪽 모두 체크메이트할 수 있는 말이 없음
+    InsufficientMaterial = 7,
+}
+
+impl GameOverReason {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            1 => Some(Self::Checkmate),
+            2 => Some(Self::Stalemate),
+            3 => Some(Self::Resignation),
+            4 => Some(Self::Agreement),
+            5 => Some(Self::Abandoned),
+            6 => Some(Self::FiftyMoves),
+            7 => Some(Self::InsufficientMaterial),
+            _ => None,
+        }
+    }
+}
+
+
+/// 서버가 체스 요청을 거부한 이유
+#[repr(u8)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum ChessRejectReason {
+    /// 진행중인 대국이 없음
+    NotInMatch = 1,
+    /// 상대의 차례
+    NotYourTurn = 2,
+    /// 규칙에 맞지 않는 수
+    IllegalMove = 3,
+    /// 이미 대국중이거나 상대를 기다리는 중
+    AlreadyInMatch = 4,
+}
+
+impl ChessRejectReason {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            1 => Some(Self::NotInMatch),
+            2 => Some(Self::NotYourTurn),
+            3 => Some(Self::IllegalMove),
+            4 => Some(Self::AlreadyInMatch),
+            _ => None,
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_square() {
+        let e4 = "e4".parse::<Square>().unwrap();
+        assert_eq!((e4.file(), e4.rank()), (4, 3));
+        assert_eq!(e4.to_string(), "e4");
+        assert_eq!(Square::new(0, 0).unwrap().index(), 0);
+        assert_eq!(Square::new(7, 7).unwrap().to_string(), "h8");
+        assert_eq!(e4.offset(4, 0), None);
+        assert_eq!(e4.offset(-4, 4).unwrap().to_string(), "a8");
+        assert!("i1".parse::<Square>().is_err());
+        assert!("a9".parse::<Square>().is_err());
+
+        let e7 = "e7".parse().unwrap();
+        let e8 = "e8".parse().unwrap();
+        assert_eq!(ChessMove::with_promotion(e7, e8, PieceClass::QUEEN).to_string(), "e7e8q");
+    }
+
+    #[test]
+    fn test_piece_encoding() {
+        for color in [ChessColor::White, ChessColor::Black] {
+            for class in PieceClass::ALL {
+                let piece = Some(ChessPiece::new(color, class));
+                assert_eq!(ChessPiece::decode(ChessPiece::encode(piece)), Ok(piece));
+            }
+        }
+
+        assert_eq!(ChessPiece::decode(0), Ok(None));
+        assert!(ChessPiece::decode(7).is_err());
+        assert!(ChessPiece::decode(17).is_err());
+    }
+}
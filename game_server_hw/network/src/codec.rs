@@ -0,0 +1,308 @@
+//! `tokio_util::codec`용 Decoder/Encoder. (`codec` feature)
+//!
+//! ```ignore
+//! let mut framed = Framed::new(stream, PacketCodec::new());
+//! while let Some(packet) = framed.next().await { ... }
+//! framed.send(Message::Init { id }).await?;
+//! ```
+
+use std::io;
+use bytes::BytesMut;
+use tokio_util::codec::{Decoder, Encoder};
+
+use super::{
+    packet::*,
+    protocol::*,
+    message::*,
+};
+
+
+/// `ParseError`는 `io::ErrorKind::InvalidData`로 감싸서 반환된다.
+/// (`error.get_ref()`에서 `ParseError`로 downcast 가능)
+fn parse_error(error: ParseError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+
+/// 헤더 단위로 패킷을 나누는 codec.
+/// 본문은 해석하지 않으므로, 본문이 잘못된 패킷도 그대로 넘겨준다.
+#[derive(Debug, Clone, Copy)]
+pub struct PacketCodec {
+    max_size: usize,
+}
+
+impl PacketCodec {
+    pub fn new() -> Self {
+        Self::with_max_size(MAX_PACKET_SIZE)
+    }
+
+    pub fn with_max_size(max_size: usize) -> Self {
+        Self {
+            max_size: max_size.clamp(MIN_PACKET_SIZE, MAX_PACKET_SIZE),
+        }
+    }
+
+    pub fn max_size(&self) -> usize {
+        self.max_size
+    }
+}
+
+impl Default for PacketCodec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decoder for PacketCodec {
+    type Item = RawPacket;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        let packet = RawPacket::split_from(src, self.max_size)
+            .map_err(parse_error)?;
+
+        if packet.is_none() {
+            src.reserve(MIN_PACKET_SIZE);
+        }
+
+        Ok(packet)
+    }
+}
+
+impl Encoder<RawPacket> for PacketCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, item: RawPacket, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        let header = item.header();
+        if header.size() > self.max_size {
+            return Err(parse_error(ParseError::TooLarge(header.size())));
+        }
+
+        dst.reserve(header.size());
+        dst.extend_from_slice(bytemuck::bytes_of(&header));
+        dst.extend_from_slice(item.data());
+
+        Ok(())
+    }
+}
+
+impl Encoder<Message> for PacketCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, item: Message, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        self.encode(item.try_as_raw()?, dst)
+    }
+}
+
+impl Encoder<MessagePacket> for PacketCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, item: MessagePacket, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        self.encode(item.as_raw(), dst)
+    }
+}
+
+
+/// `Message` 단위로 주고받는 codec.
+/// 헤더는 정상이라도 본문을 `Message`로 해석할 수 없으면 에러. (`MessageError`를 `InvalidData`로 감쌈)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MessageCodec {
+    inner: PacketCodec,
+}
+
+impl MessageCodec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_size(max_size: usize) -> Self {
+        Self {
+            inner: PacketCodec::with_max_size(max_size),
+        }
+    }
+}
+
+impl Decoder for MessageCodec {
+    type Item = Message;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        match self.inner.decode(src)? {
+            Some(packet) => Ok(Some(Message::from_raw(packet)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+impl Encoder<Message> for MessageCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, item: Message, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        self.inner.encode(item, dst)
+    }
+}
+
+
+/// `MessagePacket` 단위로 주고받는 codec.
+/// `PacketType::MESSAGE`가 아니거나 본문이 너무 짧은 패킷은 에러. (`InvalidData`)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MessagePacketCodec {
+    inner: PacketCodec,
+}
+
+impl MessagePacketCodec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_size(max_size: usize) -> Self {
+        Self {
+            inner: PacketCodec::with_max_size(max_size),
+        }
+    }
+}
+
+impl Decoder for MessagePacketCodec {
+    type Item = MessagePacket;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        match self.inner.decode(src)? {
+            Some(packet) if packet.packet_type() != PacketType::MESSAGE => {
+                Err(io::Error::new(io::ErrorKind::InvalidData, "Not a message packet"))
+            },
+            Some(packet) => Ok(Some(MessagePacket::from_raw(packet)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+impl Encoder<MessagePacket> for MessagePacketCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, item: MessagePacket, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        self.inner.encode(item, dst)
+    }
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{player_id::PlayerId, piece::PieceClass};
+
+    #[test]
+    fn test_packet_codec() {
+        let mut codec = PacketCodec::new();
+        let mut buf = BytesMut::new();
+
+        let packet1 = RawPacket::new(PacketType::MESSAGE, b"update");
+        let packet2 = Message::Move { id: PlayerId::new(1, 0), x: 0, y: -1 }.as_raw();
+        codec.encode(packet1.clone(), &mut buf).unwrap();
+        codec.encode(Message::Move { id: PlayerId::new(1, 0), x: 0, y: -1 }, &mut buf).unwrap();
+
+        // 잘린 패킷
+        let mut partial = buf.split_to(4);
+        assert_eq!(codec.decode(&mut partial).unwrap(), None);
+        partial.unsplit(buf);
+        let mut buf = partial;
+
+        assert_eq!(codec.decode(&mut buf).unwrap(), Some(packet1));
+        assert_eq!(codec.decode(&mut buf).unwrap(), Some(packet2));
+        assert_eq!(codec.decode(&mut buf).unwrap(), None);
+    }
+
+    #[test]
+    fn test_message_codec() {
+        let mut codec = MessageCodec::new();
+        let mut buf = BytesMut::new();
+
+        let messages = [
+            Message::Init { id: PlayerId::new(3, 0) },
+            Message::Ping { time: 100 },
+            Message::UpdateSnapshot { objects: vec![ObjectInfo::new(PlayerId::new(3, 0), 3, 3, PieceClass::PAWN)] },
+        ];
+        for msg in messages.iter() {
+            codec.encode(msg.clone(), &mut buf).unwrap();
+        }
+
+        for msg in messages {
+            assert_eq!(codec.decode(&mut buf).unwrap(), Some(msg));
+        }
+        assert_eq!(codec.decode(&mut buf).unwrap(), None);
+    }
+
+    #[test]
+    fn test_message_packet_codec() {
+        let mut codec = MessagePacketCodec::with_max_size(64);
+        let mut buf = BytesMut::new();
+
+        let packets = [
+            MessagePacket::new(1, "update"),
+            MessagePacket::new(u128::MAX, ""),
+            MessagePacket::new(300, "init 3 2 5 6"),
+        ];
+        for packet in packets.iter() {
+            codec.encode(MessagePacket::new(packet.time, &packet.msg), &mut buf).unwrap();
+        }
+
+        // 한 바이트씩 도착해도 완성된 패킷만 나옴
+        let mut partial = BytesMut::new();
+        let mut decoded = Vec::new();
+        for byte in buf.iter() {
+            partial.extend_from_slice(&[*byte]);
+            if let Some(packet) = codec.decode(&mut partial).unwrap() {
+                decoded.push(packet);
+            }
+        }
+        assert_eq!(decoded, packets);
+        assert!(partial.is_empty());
+
+        // 최대 크기를 넘는 패킷은 헤더만 보고 거부
+        let mut buf = BytesMut::new();
+        PacketCodec::new().encode(MessagePacket::new(1, &"a".repeat(64)), &mut buf).unwrap();
+        let mut header = buf.split_to(MIN_PACKET_SIZE);
+        let error = codec.decode(&mut header).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            error.get_ref().and_then(|e| e.downcast_ref::<ParseError>()),
+            Some(&ParseError::TooLarge(MIN_PACKET_SIZE + 16 + 64))
+        );
+
+        let error = codec.encode(MessagePacket::new(1, &"a".repeat(64)), &mut buf).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        // 다른 종류의 패킷
+        let mut buf = BytesMut::new();
+        codec.inner.encode(Message::Ping { time: 1 }, &mut buf).unwrap();
+        assert!(codec.decode(&mut buf).is_err());
+    }
+
+    #[test]
+    fn test_invalid_header() {
+        let mut codec = PacketCodec::with_max_size(16);
+        let mut buf = BytesMut::from(&[1u8, 0, 1][..]);
+
+        let error = codec.decode(&mut buf).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            error.get_ref().and_then(|e| e.downcast_ref::<ParseError>()),
+            Some(&ParseError::TooSmall(1))
+        );
+
+        let error = codec.encode(RawPacket::new(PacketType::MESSAGE, &[0; 14]), &mut buf).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_invalid_message_body() {
+        let mut buf = BytesMut::new();
+        PacketCodec::new().encode(RawPacket::new(PacketType::INIT, &[1]), &mut buf).unwrap();
+
+        let mut packets = buf.clone();
+        assert!(PacketCodec::new().decode(&mut packets).unwrap().is_some());
+        assert!(MessageCodec::new().decode(&mut buf).is_err());
+    }
+}
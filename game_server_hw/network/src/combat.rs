@@ -0,0 +1,28 @@
+/// 서버가 공격 요청을 거부한 이유
+#[repr(u8)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum AttackRejectReason {
+    /// 공격한 플레이어가 죽어있음 (부활 대기중)
+    Dead = 1,
+    /// 대상이 월드에 없거나 죽어있음
+    UnknownTarget = 2,
+    /// 대상이 공격 범위 밖에 있음
+    OutOfRange = 3,
+    /// 아직 다시 공격할 수 없음
+    Cooldown = 4,
+    /// 자기 자신을 공격
+    InvalidTarget = 5,
+}
+
+impl AttackRejectReason {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            1 => Some(Self::Dead),
+            2 => Some(Self::UnknownTarget),
+            3 => Some(Self::OutOfRange),
+            4 => Some(Self::Cooldown),
+            5 => Some(Self::InvalidTarget),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,25 @@
+/// 월드에 있는 오브젝트의 종류.
+/// `PieceClass`처럼 `ObjectInfo`에 `u8` 하나로 담긴다.
+#[repr(transparent)]
+#[derive(Debug, Default, PartialEq, Eq, Hash, Copy, Clone, bytemuck::Zeroable, bytemuck::Pod)]
+pub struct EntityKind(u8);
+
+impl EntityKind {
+    /// 접속한 클라이언트의 플레이어
+    pub const PLAYER: Self = Self(0);
+    /// 서버가 움직이는 NPC
+    pub const NPC: Self = Self(1);
+
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+
+    pub fn from_u8(value: u8) -> Option<Self> {
+        let kind = Self(value);
+        kind.is_known().then_some(kind)
+    }
+
+    pub fn is_known(&self) -> bool {
+        matches!(*self, Self::PLAYER | Self::NPC)
+    }
+}
@@ -1,5 +1,27 @@
 mod packet;
 mod protocol;
+mod message;
+mod player_id;
+mod map;
+mod piece;
+mod entity;
+mod chess;
+mod chat;
+mod combat;
+mod account;
+#[cfg(feature = "codec")]
+mod codec;
 
 pub use packet::*;
-pub use protocol::*;
\ No newline at end of file
+pub use protocol::*;
+pub use message::*;
+pub use player_id::*;
+pub use map::*;
+pub use piece::*;
+pub use entity::*;
+pub use chess::*;
+pub use chat::*;
+pub use combat::*;
+pub use account::*;
+#[cfg(feature = "codec")]
+pub use codec::*;
\ No newline at end of file
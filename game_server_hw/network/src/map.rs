@@ -0,0 +1,289 @@
+use std::fmt;
+use std::str::FromStr;
+use std::mem::size_of;
+
+use super::protocol::*;
+
+
+/// 맵 한칸의 종류
+#[repr(u8)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Tile {
+    Floor = 0,
+    Wall = 1,
+    /// 플레이어가 처음 생성될 수 있는 바닥
+    Spawn = 2,
+}
+
+impl Tile {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::Floor),
+            1 => Some(Self::Wall),
+            2 => Some(Self::Spawn),
+            _ => None,
+        }
+    }
+
+    /// 맵 파일에서 쓰는 문자
+    pub fn from_char(c: char) -> Option<Self> {
+        match c {
+            '.' => Some(Self::Floor),
+            '#' => Some(Self::Wall),
+            'S' => Some(Self::Spawn),
+            _ => None,
+        }
+    }
+
+    pub fn as_char(self) -> char {
+        match self {
+            Self::Floor => '.',
+            Self::Wall => '#',
+            Self::Spawn => 'S',
+        }
+    }
+
+    pub fn is_walkable(self) -> bool {
+        self != Self::Wall
+    }
+}
+
+
+/// 맵을 만들 수 없는 이유
+#[derive(Debug, PartialEq, Clone)]
+pub enum MapError {
+    /// 타일이 한칸도 없음
+    Empty,
+    /// `width` x `height`가 한 패킷에 담을 수 있는 크기(`TileMap::MAX_TILES`)를 넘음
+    TooLarge { width: usize, height: usize },
+    /// 타일 수가 `width` x `height`와 다름
+    TileCount { expected: usize, actual: usize },
+    /// 다른 줄과 길이가 다른 줄 (1부터)
+    RaggedRow { row: usize },
+    /// 알 수 없는 타일 문자 (1부터)
+    UnknownTile { row: usize, column: usize, tile: char },
+    /// 스폰 타일이 없음
+    NoSpawn,
+}
+
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "Map has no tiles"),
+            Self::TooLarge { width, height } => {
+                write!(f, "Map {}x{} exceeds {} tiles", width, height, TileMap::MAX_TILES)
+            },
+            Self::TileCount { expected, actual } => {
+                write!(f, "Map has {} tiles, expected {}", actual, expected)
+            },
+            Self::RaggedRow { row } => write!(f, "Row {} has a different width", row),
+            Self::UnknownTile { row, column, tile } => {
+                write!(f, "Unknown tile '{}' at row {}, column {}", tile, row, column)
+            },
+            Self::NoSpawn => write!(f, "Map has no spawn tile"),
+        }
+    }
+}
+
+impl std::error::Error for MapError {}
+
+
+/// 직사각형 타일 맵. 좌표는 `(x, y)`이고 `(0, 0)`이 왼쪽 위.
+///
+/// 맵 파일은 한 줄이 한 행인 텍스트이다. (`.` 바닥, `#` 벽, `S` 스폰)
+/// 줄 끝의 공백과 빈 줄은 무시한다.
+///
+/// ```text
+/// ######
+/// #S..S#
+/// #.##.#
+/// #S..S#
+/// ######
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TileMap {
+    width: u16,
+    height: u16,
+    tiles: Vec<Tile>,
+}
+
+impl TileMap {
+    /// 한 패킷(`Message::Map`)에 담을 수 있는 최대 타일 수
+    pub const MAX_TILES: usize = PacketSize::MAX as usize - size_of::<PacketHeader>() - 2 * size_of::<u16>();
+
+    /// `tiles`는 행 우선 순서
+    pub fn new(width: usize, height: usize, tiles: Vec<Tile>) -> Result<Self, MapError> {
+        if width == 0 || height == 0 {
+            return Err(MapError::Empty);
+        }
+        if width.saturating_mul(height) > Self::MAX_TILES {
+            return Err(MapError::TooLarge { width, height });
+        }
+        if tiles.len() != width * height {
+            return Err(MapError::TileCount { expected: width * height, actual: tiles.len() });
+        }
+        if !tiles.contains(&Tile::Spawn) {
+            return Err(MapError::NoSpawn);
+        }
+
+        Ok(Self {
+            width: width as u16,
+            height: height as u16,
+            tiles,
+        })
+    }
+
+    /// 벽이 없는 맵. `spawn`이 맵 밖이면 가장 가까운 칸을 스폰으로 쓴다.
+    pub fn open(width: usize, height: usize, spawn: (i32, i32)) -> Result<Self, MapError> {
+        if width == 0 || height == 0 {
+            return Err(MapError::Empty);
+        }
+        if width.saturating_mul(height) > Self::MAX_TILES {
+            return Err(MapError::TooLarge { width, height });
+        }
+
+        let mut tiles = vec![Tile::Floor; width * height];
+        let x = spawn.0.clamp(0, width as i32 - 1) as usize;
+        let y = spawn.1.clamp(0, height as i32 - 1) as usize;
+        tiles[y * width + x] = Tile::Spawn;
+
+        Self::new(width, height, tiles)
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width as i32
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height as i32
+    }
+
+    /// 맵 밖이면 `None`
+    pub fn get(&self, x: i32, y: i32) -> Option<Tile> {
+        if x < 0 || y < 0 || x >= self.width() || y >= self.height() {
+            return None;
+        }
+
+        Some(self.tiles[(y * self.width() + x) as usize])
+    }
+
+    /// 맵 밖은 벽으로 취급
+    pub fn is_walkable(&self, x: i32, y: i32) -> bool {
+        self.get(x, y).is_some_and(Tile::is_walkable)
+    }
+
+    /// 행 우선 순서의 모든 타일
+    pub fn tiles(&self) -> &[Tile] {
+        &self.tiles
+    }
+
+    /// `(x, y, tile)`
+    pub fn iter(&self) -> impl Iterator<Item = (i32, i32, Tile)> + '_ {
+        let width = self.width();
+        self.tiles.iter()
+            .enumerate()
+            .map(move |(i, &tile)| (i as i32 % width, i as i32 / width, tile))
+    }
+
+    /// 스폰 타일 좌표 (행 우선 순서)
+    pub fn spawns(&self) -> impl Iterator<Item = (i32, i32)> + '_ {
+        self.iter()
+            .filter(|&(_, _, tile)| tile == Tile::Spawn)
+            .map(|(x, y, _)| (x, y))
+    }
+}
+
+impl FromStr for TileMap {
+    type Err = MapError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rows = s.lines()
+            .map(str::trim_end)
+            .enumerate()
+            .filter(|(_, line)| !line.is_empty())
+            .collect::<Vec<_>>();
+
+        let width = rows.first().map_or(0, |(_, line)| line.chars().count());
+        let mut tiles = Vec::with_capacity(width * rows.len());
+
+        for &(i, line) in &rows {
+            let row = i + 1;
+            if line.chars().count() != width {
+                return Err(MapError::RaggedRow { row });
+            }
+
+            for (j, c) in line.chars().enumerate() {
+                let tile = Tile::from_char(c)
+                    .ok_or(MapError::UnknownTile { row, column: j + 1, tile: c })?;
+                tiles.push(tile);
+            }
+        }
+
+        Self::new(width, rows.len(), tiles)
+    }
+}
+
+impl fmt::Display for TileMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in self.tiles.chunks(self.width as usize) {
+            let line = row.iter().map(|tile| tile.as_char()).collect::<String>();
+            writeln!(f, "{}", line)?;
+        }
+
+        Ok(())
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAP: &str = "\
+#####
+#S..#
+#.#S#
+#####
+";
+
+    #[test]
+    fn test_parse() {
+        let map = MAP.parse::<TileMap>().unwrap();
+
+        assert_eq!((map.width(), map.height()), (5, 4));
+        assert_eq!(map.get(1, 1), Some(Tile::Spawn));
+        assert_eq!(map.get(2, 2), Some(Tile::Wall));
+        assert_eq!(map.get(5, 0), None);
+        assert!(map.is_walkable(2, 1));
+        assert!(!map.is_walkable(0, 0));
+        assert!(!map.is_walkable(-1, 1));
+        assert_eq!(map.spawns().collect::<Vec<_>>(), vec![(1, 1), (3, 2)]);
+
+        // 다시 텍스트로 바꿔도 같은 맵
+        assert_eq!(map.to_string(), MAP);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!("".parse::<TileMap>(), Err(MapError::Empty));
+        assert_eq!("S..\n..\n".parse::<TileMap>(), Err(MapError::RaggedRow { row: 2 }));
+        assert_eq!("S.\n.x\n".parse::<TileMap>(),
+            Err(MapError::UnknownTile { row: 2, column: 2, tile: 'x' }));
+        assert_eq!("..\n.#\n".parse::<TileMap>(), Err(MapError::NoSpawn));
+
+        let line = "S".repeat(300);
+        let text = format!("{}\n", line).repeat(300);
+        assert_eq!(text.parse::<TileMap>(), Err(MapError::TooLarge { width: 300, height: 300 }));
+
+        assert_eq!(TileMap::new(2, 2, vec![Tile::Spawn; 3]), Err(MapError::TileCount { expected: 4, actual: 3 }));
+    }
+
+    #[test]
+    fn test_open() {
+        let map = TileMap::open(4, 3, (10, -2)).unwrap();
+
+        assert!(map.tiles().iter().all(|tile| tile.is_walkable()));
+        assert_eq!(map.spawns().collect::<Vec<_>>(), vec![(3, 0)]);
+    }
+}
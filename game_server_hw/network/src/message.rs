@@ -0,0 +1,1129 @@
+use std::mem::size_of;
+use std::fmt;
+use bytemuck::Pod;
+
+use super::{
+    protocol::*,
+    player_id::PlayerId,
+    map::{Tile, TileMap},
+    piece::PieceClass,
+    entity::EntityKind,
+    chess::*,
+    chat::*,
+    combat::*,
+    account::*,
+};
+
+
+/// 업데이트 스냅샷에 담기는 오브젝트 한개의 정보
+#[repr(C, packed)]
+#[derive(Debug, PartialEq, Copy, Clone, bytemuck::Zeroable, bytemuck::Pod)]
+pub struct ObjectInfo {
+    pub id: PlayerId,
+    pub x: i32,
+    pub y: i32,
+    pub class: PieceClass,
+    pub kind: EntityKind,
+}
+
+impl ObjectInfo {
+    /// 플레이어
+    pub fn new(id: PlayerId, x: i32, y: i32, class: PieceClass) -> Self {
+        Self { id, x, y, class, kind: EntityKind::PLAYER }
+    }
+
+    pub fn npc(id: PlayerId, x: i32, y: i32, class: PieceClass) -> Self {
+        Self { id, x, y, class, kind: EntityKind::NPC }
+    }
+}
+
+
+#[repr(C, packed)]
+#[derive(Debug, PartialEq, Copy, Clone, bytemuck::Zeroable, bytemuck::Pod)]
+struct MoveBody {
+    id: PlayerId,
+    x: i32,
+    y: i32,
+}
+
+
+#[repr(C, packed)]
+#[derive(Debug, PartialEq, Copy, Clone, bytemuck::Zeroable, bytemuck::Pod)]
+struct MoveRejectedBody {
+    x: i32,
+    y: i32,
+    reason: u8,
+}
+
+
+#[repr(C, packed)]
+#[derive(Debug, PartialEq, Copy, Clone, bytemuck::Zeroable, bytemuck::Pod)]
+struct MatchStartedBody {
+    color: u8,
+    opponent: PlayerId,
+}
+
+
+#[repr(C, packed)]
+#[derive(Debug, PartialEq, Copy, Clone, bytemuck::Zeroable, bytemuck::Pod)]
+struct AttackRejectedBody {
+    target: PlayerId,
+    reason: u8,
+}
+
+
+#[repr(C, packed)]
+#[derive(Debug, PartialEq, Copy, Clone, bytemuck::Zeroable, bytemuck::Pod)]
+struct DamageBody {
+    attacker: PlayerId,
+    target: PlayerId,
+    amount: u16,
+    hp: u16,
+}
+
+
+#[repr(C, packed)]
+#[derive(Debug, PartialEq, Copy, Clone, bytemuck::Zeroable, bytemuck::Pod)]
+struct RespawnBody {
+    id: PlayerId,
+    x: i32,
+    y: i32,
+    hp: u16,
+}
+
+
+/// 승급이 없는 수의 `promotion` 값
+const NO_PROMOTION: u8 = u8::MAX;
+
+
+/// 패킷 하나에 담을 수 있는 본문의 최대 바이트 수
+const MAX_BODY_LEN: usize = PacketSize::MAX as usize - size_of::<PacketHeader>();
+
+
+/// 서버가 이동 요청을 거부한 이유
+#[repr(u8)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum MoveRejectReason {
+    /// 자신이 아닌 다른 플레이어의 id로 이동 요청
+    NotOwner = 1,
+    /// 상하좌우 한칸 이동이 아님
+    InvalidStep = 2,
+    /// 벽이나 맵 밖으로 이동
+    Blocked = 3,
+    /// 다른 플레이어가 있는 칸으로 이동
+    Occupied = 4,
+    /// 죽어서 부활을 기다리는 중
+    Dead = 5,
+}
+
+impl MoveRejectReason {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            1 => Some(Self::NotOwner),
+            2 => Some(Self::InvalidStep),
+            3 => Some(Self::Blocked),
+            4 => Some(Self::Occupied),
+            5 => Some(Self::Dead),
+            _ => None,
+        }
+    }
+}
+
+
+/// 서버가 접속을 거부한 이유
+#[repr(u8)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum ConnectRejectReason {
+    /// 빈 슬롯이 없고 대기열도 가득 참
+    ServerFull = 1,
+    /// 맵에 플레이어가 생성될 빈 칸이 없음
+    WorldFull = 2,
+    /// 서버가 종료하는 중
+    ShuttingDown = 3,
+    /// 운영자가 연결을 끊음
+    Kicked = 4,
+    /// 접속이 금지된 IP 주소
+    Banned = 5,
+}
+
+impl ConnectRejectReason {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            1 => Some(Self::ServerFull),
+            2 => Some(Self::WorldFull),
+            3 => Some(Self::ShuttingDown),
+            4 => Some(Self::Kicked),
+            5 => Some(Self::Banned),
+            _ => None,
+        }
+    }
+}
+
+
+/// 서버가 클라이언트의 요청을 처리하지 못한 이유
+#[repr(u8)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum ErrorCode {
+    /// 본문을 해석할 수 없는 패킷
+    MalformedPacket = 1,
+    /// 해석은 되지만 서버가 받을 수 없는 메세지 (서버 -> 클라 메세지 등)
+    UnexpectedMessage = 2,
+    /// 잘못된 패킷을 너무 많이 보내서 연결을 종료함
+    TooManyErrors = 3,
+    /// 로그인하기 전에는 보낼 수 없는 메세지
+    LoginRequired = 4,
+    /// 말은 입장한 뒤 이동, 공격하기 전에 한번만 고를 수 있음
+    ClassLocked = 5,
+}
+
+impl ErrorCode {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            1 => Some(Self::MalformedPacket),
+            2 => Some(Self::UnexpectedMessage),
+            3 => Some(Self::TooManyErrors),
+            4 => Some(Self::LoginRequired),
+            5 => Some(Self::ClassLocked),
+            _ => None,
+        }
+    }
+}
+
+
+/// 헤더는 정상이지만 본문을 `Message`로 해석할 수 없거나, `Message`를 자르지 않고는 패킷으로 만들 수 없는 경우
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum MessageError {
+    /// `Message`에 대응되지 않는 패킷 타입
+    UnsupportedType(PacketType),
+    /// 본문의 길이가 레이아웃과 맞지 않음
+    InvalidLength { packet_type: PacketType, len: usize },
+    /// 본문에 허용되지 않는 값이 있음
+    InvalidValue(PacketType),
+    /// 잘라야만 패킷에 들어가는 본문 (`Message::try_as_raw`)
+    TooLong(PacketType),
+}
+
+impl fmt::Display for MessageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedType(packet_type) => {
+                write!(f, "Packet type {} is not a message", packet_type.value())
+            },
+            Self::InvalidLength { packet_type, len } => {
+                write!(f, "Invalid body length {} for packet type {}", len, packet_type.value())
+            },
+            Self::InvalidValue(packet_type) => {
+                write!(f, "Invalid value in body of packet type {}", packet_type.value())
+            },
+            Self::TooLong(packet_type) => {
+                write!(f, "Body of packet type {} does not fit in a packet", packet_type.value())
+            },
+        }
+    }
+}
+
+impl std::error::Error for MessageError {}
+
+impl From<MessageError> for std::io::Error {
+    fn from(error: MessageError) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, error)
+    }
+}
+
+
+/// 가변 길이 목록의 길이
+type ListCount = u16;
+
+/// 패킷 하나에 담을 수 있는 `T` 목록의 최대 길이
+const fn max_list_len<T>() -> usize {
+    (PacketSize::MAX as usize - size_of::<PacketHeader>() - size_of::<ListCount>()) / size_of::<T>()
+}
+
+
+/// 서버와 클라이언트가 공유하는 메세지 스키마.
+/// 메세지마다 `PacketType`이 하나씩 대응되고, 본문은 고정된 바이너리 레이아웃을 가진다.
+/// (`PlayerId`는 `u32`, `ObjectInfo`는 `id: PlayerId, x: i32, y: i32, class: u8, kind: u8`)
+///
+/// | 메세지             | 본문                                  |
+/// |-------------------|---------------------------------------|
+/// | `Init`            | `id: PlayerId`                        |
+/// | `Ping`, `Pong`    | `time: u128`                          |
+/// | `Move`            | `id: PlayerId, x: i32, y: i32`        |
+/// | `UpdateRequest`   | (없음)                                 |
+/// | `UpdateSnapshot`  | `count: u16, [ObjectInfo; count]`     |
+/// | `EnterView`       | `count: u16, [ObjectInfo; count]`     |
+/// | `LeaveView`       | `count: u16, [id: PlayerId; count]`   |
+/// | `MoveRejected`    | `x: i32, y: i32, reason: u8`          |
+/// | `Error`           | `code: u8`                            |
+/// | `ConnectRejected` | `reason: u8`                          |
+/// | `QueuePosition`   | `position: u32`                       |
+/// | `Map`             | `width: u16, height: u16, [tile: u8; width * height]` |
+/// | `SelectClass`     | `class: u8`                           |
+/// | `MatchRequest`, `Resign`, `DrawOffer` | (없음)              |
+/// | `MatchStarted`    | `color: u8, opponent: PlayerId`       |
+/// | `ChessMove`       | `from: u8, to: u8, promotion: u8` (승급 없음은 `255`) |
+/// | `ChessBoard`      | `[piece: u8; 64], turn: u8, check: u8` |
+/// | `ChessRejected`   | `reason: u8`                          |
+/// | `GameOver`        | `result: u8, reason: u8`              |
+/// | `Pgn`             | `text: [u8]` (UTF-8)                  |
+/// | `ChatSend`        | `channel: u8, target_len: u8, [target: u8; target_len], text: [u8]` (UTF-8) |
+/// | `Chat`            | `channel: u8, from: PlayerId, name_len: u8, [name: u8; name_len], text: [u8]` (UTF-8) |
+/// | `ChatRejected`    | `reason: u8`                          |
+/// | `Attack`          | `target: PlayerId`                    |
+/// | `AttackRejected`  | `target: PlayerId, reason: u8`        |
+/// | `Damage`          | `attacker: PlayerId, target: PlayerId, amount: u16, hp: u16` |
+/// | `Death`           | `id: PlayerId, killer: PlayerId`      |
+/// | `Respawn`         | `id: PlayerId, x: i32, y: i32, hp: u16` |
+/// | `Login`, `Register` | `name_len: u8, [name: u8; name_len], password: [u8]` (UTF-8) |
+/// | `LoginRejected`   | `reason: u8`                          |
+/// | `Shutdown`        | `seconds: u16`                        |
+/// | `Notice`          | `text: [u8]` (UTF-8)                  |
+#[derive(Debug, PartialEq, Clone)]
+pub enum Message {
+    /// 서버 -> 클라: 접속한 클라이언트에게 부여된 id
+    Init { id: PlayerId },
+    Ping { time: u128 },
+    Pong { time: u128 },
+    /// 클라 -> 서버: 이동 방향
+    Move { id: PlayerId, x: i32, y: i32 },
+    /// 클라 -> 서버: 오브젝트 정보 요청
+    UpdateRequest,
+    /// 서버 -> 클라: 오브젝트 위치 정보
+    UpdateSnapshot { objects: Vec<ObjectInfo> },
+    /// 서버 -> 클라: 시야에 새로 들어온 오브젝트
+    EnterView { objects: Vec<ObjectInfo> },
+    /// 서버 -> 클라: 시야에서 벗어난 오브젝트
+    LeaveView { ids: Vec<PlayerId> },
+    /// 서버 -> 클라: 거부된 이동 요청의 방향과 이유
+    MoveRejected { x: i32, y: i32, reason: MoveRejectReason },
+    /// 서버 -> 클라: 요청을 처리하지 못함
+    Error { code: ErrorCode },
+    /// 서버 -> 클라: 접속 거부. 보낸 뒤 서버가 연결을 끊는다.
+    ConnectRejected { reason: ConnectRejectReason },
+    /// 서버 -> 클라: 대기열에서의 순서 (1부터). 자리가 나면 `Init`을 받는다.
+    QueuePosition { position: u32 },
+    /// 서버 -> 클라: 맵 전체. `Init` 바로 뒤에 보낸다.
+    Map { map: TileMap },
+    /// 클라 -> 서버: 자신의 말 종류 변경 (접속 직후에 보내면 그 말로 시작)
+    SelectClass { class: PieceClass },
+    /// 클라 -> 서버: 체스 대국 신청. 기다리는 상대가 있으면 바로 대국이 시작된다.
+    MatchRequest,
+    /// 서버 -> 클라: 대국 시작. 자신의 색과 상대의 id
+    MatchStarted { color: ChessColor, opponent: PlayerId },
+    /// 클라 -> 서버: 체스 수
+    ChessMove { mv: ChessMove },
+    /// 서버 -> 클라: 대국 시작과 매 수마다 보내는 체스판 전체
+    ChessBoard { board: BoardState },
+    /// 서버 -> 클라: 처리하지 못한 체스 요청
+    ChessRejected { reason: ChessRejectReason },
+    /// 클라 -> 서버: 기권
+    Resign,
+    /// 클라 -> 서버: 무승부 제안 (상대의 제안이 있으면 수락).
+    /// 서버 -> 클라: 상대가 무승부를 제안함
+    DrawOffer,
+    /// 서버 -> 클라: 대국 종료
+    GameOver { result: GameResult, reason: GameOverReason },
+    /// 서버 -> 클라: 끝난 대국의 PGN. `GameOver` 바로 뒤에 보낸다.
+    Pgn { text: String },
+    /// 클라 -> 서버: 채팅. `target`은 귓속말 대상의 id(`index#generation`)나 이름이고,
+    /// 다른 채널에서는 무시된다.
+    ChatSend { channel: ChatChannel, target: String, text: String },
+    /// 서버 -> 클라: 다른 플레이어(또는 자신)가 보낸 채팅
+    Chat { channel: ChatChannel, from: PlayerId, name: String, text: String },
+    /// 서버 -> 클라: 보내지 못한 채팅
+    ChatRejected { reason: ChatRejectReason },
+    /// 클라 -> 서버: 플레이어나 NPC 공격
+    Attack { target: PlayerId },
+    /// 서버 -> 클라: 처리하지 못한 공격 요청
+    AttackRejected { target: PlayerId, reason: AttackRejectReason },
+    /// 서버 -> 클라: 시야 안에서 일어난 공격. `hp`는 맞은 뒤 남은 체력
+    Damage { attacker: PlayerId, target: PlayerId, amount: u16, hp: u16 },
+    /// 서버 -> 클라: 체력이 0이 되어 죽음. 부활할 때까지 월드에서 사라진다.
+    Death { id: PlayerId, killer: PlayerId },
+    /// 서버 -> 클라: 스폰 위치에서 부활
+    Respawn { id: PlayerId, x: i32, y: i32, hp: u16 },
+    /// 클라 -> 서버: 계정이 있는 서버에 접속하면 가장 먼저 보낸다. 성공하면 `Init`을 받는다.
+    Login { name: String, password: String },
+    /// 클라 -> 서버: 계정을 만들고 로그인
+    Register { name: String, password: String },
+    /// 서버 -> 클라: 로그인, 가입 실패. 다시 시도할 수 있다.
+    LoginRejected { reason: LoginRejectReason },
+    /// 서버 -> 클라: `seconds`초 뒤에 서버가 종료됨. 0이면 보낸 뒤 서버가 연결을 끊는다.
+    Shutdown { seconds: u16 },
+    /// 서버 -> 클라: 운영자 공지
+    Notice { text: String },
+}
+
+impl Message {
+    /// 한 스냅샷(`UpdateSnapshot`, `EnterView`)에 담을 수 있는 최대 오브젝트 수
+    pub const MAX_SNAPSHOT_OBJECTS: usize = max_list_len::<ObjectInfo>();
+    /// `LeaveView`에 담을 수 있는 최대 id 수
+    pub const MAX_LEAVE_IDS: usize = max_list_len::<PlayerId>();
+    /// `Pgn`에 담을 수 있는 최대 바이트 수
+    pub const MAX_PGN_LEN: usize = MAX_BODY_LEN;
+    /// `Notice`에 담을 수 있는 최대 바이트 수
+    pub const MAX_NOTICE_LEN: usize = MAX_BODY_LEN;
+    /// 채팅의 `target`, `name`에 담을 수 있는 최대 바이트 수
+    pub const MAX_CHAT_NAME_LEN: usize = u8::MAX as usize;
+
+    pub fn packet_type(&self) -> PacketType {
+        match self {
+            Self::Init { .. } => PacketType::INIT,
+            Self::Ping { .. } => PacketType::PING,
+            Self::Pong { .. } => PacketType::PONG,
+            Self::Move { .. } => PacketType::MOVE,
+            Self::UpdateRequest => PacketType::UPDATE_REQUEST,
+            Self::UpdateSnapshot { .. } => PacketType::UPDATE_SNAPSHOT,
+            Self::EnterView { .. } => PacketType::ENTER_VIEW,
+            Self::LeaveView { .. } => PacketType::LEAVE_VIEW,
+            Self::MoveRejected { .. } => PacketType::MOVE_REJECTED,
+            Self::Error { .. } => PacketType::ERROR,
+            Self::ConnectRejected { .. } => PacketType::CONNECT_REJECTED,
+            Self::QueuePosition { .. } => PacketType::QUEUE_POSITION,
+            Self::Map { .. } => PacketType::MAP,
+            Self::SelectClass { .. } => PacketType::SELECT_CLASS,
+            Self::MatchRequest => PacketType::MATCH_REQUEST,
+            Self::MatchStarted { .. } => PacketType::MATCH_STARTED,
+            Self::ChessMove { .. } => PacketType::CHESS_MOVE,
+            Self::ChessBoard { .. } => PacketType::CHESS_BOARD,
+            Self::ChessRejected { .. } => PacketType::CHESS_REJECTED,
+            Self::Resign => PacketType::RESIGN,
+            Self::DrawOffer => PacketType::DRAW_OFFER,
+            Self::GameOver { .. } => PacketType::GAME_OVER,
+            Self::Pgn { .. } => PacketType::PGN,
+            Self::ChatSend { .. } => PacketType::CHAT_SEND,
+            Self::Chat { .. } => PacketType::CHAT,
+            Self::ChatRejected { .. } => PacketType::CHAT_REJECTED,
+            Self::Attack { .. } => PacketType::ATTACK,
+            Self::AttackRejected { .. } => PacketType::ATTACK_REJECTED,
+            Self::Damage { .. } => PacketType::DAMAGE,
+            Self::Death { .. } => PacketType::DEATH,
+            Self::Respawn { .. } => PacketType::RESPAWN,
+            Self::Login { .. } => PacketType::LOGIN,
+            Self::Register { .. } => PacketType::REGISTER,
+            Self::LoginRejected { .. } => PacketType::LOGIN_REJECTED,
+            Self::Shutdown { .. } => PacketType::SHUTDOWN,
+            Self::Notice { .. } => PacketType::NOTICE,
+        }
+    }
+
+    /// 목록의 길이가 최대 길이(`MAX_SNAPSHOT_OBJECTS` 등)를 넘으면 넘는 만큼은 잘린다.
+    /// (`Pgn`, `Notice`는 `MAX_PGN_LEN`, `MAX_NOTICE_LEN` 바이트까지, 채팅의 `target`, `name`과 로그인의 `name`은 `MAX_CHAT_NAME_LEN` 바이트까지,
+    /// 채팅의 `text`와 로그인의 `password`는 패킷에 들어가는 만큼. 로그인을 자르지 않으려면 `try_as_raw`)
+    pub fn as_raw(&self) -> RawPacket {
+        let packet_type = self.packet_type();
+
+        match self {
+            Self::Init { id } => RawPacket::new(packet_type, bytemuck::bytes_of(id)),
+
+            Self::Ping { time } | Self::Pong { time } => {
+                RawPacket::new(packet_type, bytemuck::bytes_of(time))
+            },
+
+            Self::Move { id, x, y } => {
+                let body = MoveBody { id: *id, x: *x, y: *y };
+                RawPacket::new(packet_type, bytemuck::bytes_of(&body))
+            },
+
+            Self::UpdateRequest => RawPacket::new(packet_type, &[]),
+
+            Self::UpdateSnapshot { objects } | Self::EnterView { objects } => {
+                RawPacket::new(packet_type, &write_list(objects))
+            },
+
+            Self::LeaveView { ids } => RawPacket::new(packet_type, &write_list(ids)),
+
+            Self::MoveRejected { x, y, reason } => {
+                let body = MoveRejectedBody { x: *x, y: *y, reason: *reason as u8 };
+                RawPacket::new(packet_type, bytemuck::bytes_of(&body))
+            },
+
+            Self::Error { code } => RawPacket::new(packet_type, &[*code as u8]),
+
+            Self::ConnectRejected { reason } => RawPacket::new(packet_type, &[*reason as u8]),
+
+            Self::QueuePosition { position } => RawPacket::new(packet_type, bytemuck::bytes_of(position)),
+
+            Self::Map { map } => RawPacket::new(packet_type, &write_map(map)),
+
+            Self::SelectClass { class } => RawPacket::new(packet_type, bytemuck::bytes_of(class)),
+
+            Self::MatchRequest | Self::Resign | Self::DrawOffer => RawPacket::new(packet_type, &[]),
+
+            Self::MatchStarted { color, opponent } => {
+                let body = MatchStartedBody { color: *color as u8, opponent: *opponent };
+                RawPacket::new(packet_type, bytemuck::bytes_of(&body))
+            },
+
+            Self::ChessMove { mv } => {
+                let promotion = mv.promotion.map_or(NO_PROMOTION, |class| class.value());
+                RawPacket::new(packet_type, &[mv.from.index() as u8, mv.to.index() as u8, promotion])
+            },
+
+            Self::ChessBoard { board } => RawPacket::new(packet_type, &write_board(board)),
+
+            Self::ChessRejected { reason } => RawPacket::new(packet_type, &[*reason as u8]),
+
+            Self::GameOver { result, reason } => RawPacket::new(packet_type, &[*result as u8, *reason as u8]),
+
+            Self::Pgn { text } => RawPacket::new(packet_type, truncate_str(text, Self::MAX_PGN_LEN).as_bytes()),
+
+            Self::ChatSend { channel, target, text } => {
+                RawPacket::new(packet_type, &write_chat(&[*channel as u8], target, text))
+            },
+
+            Self::Chat { channel, from, name, text } => {
+                let mut head = vec![*channel as u8];
+                head.extend_from_slice(bytemuck::bytes_of(from));
+                RawPacket::new(packet_type, &write_chat(&head, name, text))
+            },
+
+            Self::ChatRejected { reason } => RawPacket::new(packet_type, &[*reason as u8]),
+
+            Self::Attack { target } => RawPacket::new(packet_type, bytemuck::bytes_of(target)),
+
+            Self::AttackRejected { target, reason } => {
+                let body = AttackRejectedBody { target: *target, reason: *reason as u8 };
+                RawPacket::new(packet_type, bytemuck::bytes_of(&body))
+            },
+
+            Self::Damage { attacker, target, amount, hp } => {
+                let body = DamageBody { attacker: *attacker, target: *target, amount: *amount, hp: *hp };
+                RawPacket::new(packet_type, bytemuck::bytes_of(&body))
+            },
+
+            Self::Death { id, killer } => RawPacket::new(packet_type, bytemuck::cast_slice(&[*id, *killer])),
+
+            Self::Respawn { id, x, y, hp } => {
+                let body = RespawnBody { id: *id, x: *x, y: *y, hp: *hp };
+                RawPacket::new(packet_type, bytemuck::bytes_of(&body))
+            },
+
+            Self::Login { name, password } | Self::Register { name, password } => {
+                RawPacket::new(packet_type, &write_chat(&[], name, password))
+            },
+
+            Self::LoginRejected { reason } => RawPacket::new(packet_type, &[*reason as u8]),
+
+            Self::Shutdown { seconds } => RawPacket::new(packet_type, bytemuck::bytes_of(seconds)),
+
+            Self::Notice { text } => RawPacket::new(packet_type, truncate_str(text, Self::MAX_NOTICE_LEN).as_bytes()),
+        }
+    }
+
+    /// `as_raw`와 같지만, 로그인의 `name`, `password`를 잘라야 하면 `MessageError::TooLong`.
+    /// 잘린 비밀번호로 가입하거나 로그인하지 않도록 `Login`, `Register`는 이걸로 보낸다.
+    pub fn try_as_raw(&self) -> Result<RawPacket, MessageError> {
+        if let Self::Login { name, password } | Self::Register { name, password } = self {
+            if name.len() > Self::MAX_CHAT_NAME_LEN || 1 + name.len() + password.len() > MAX_BODY_LEN {
+                return Err(MessageError::TooLong(self.packet_type()));
+            }
+        }
+
+        Ok(self.as_raw())
+    }
+
+    pub fn from_raw(raw: RawPacket) -> Result<Self, MessageError> {
+        let packet_type = raw.packet_type();
+        let data = raw.data();
+
+        let message = match packet_type {
+            PacketType::INIT => Self::Init { id: read_body(packet_type, data)? },
+            PacketType::PING => Self::Ping { time: read_body(packet_type, data)? },
+            PacketType::PONG => Self::Pong { time: read_body(packet_type, data)? },
+
+            PacketType::MOVE => {
+                let MoveBody { id, x, y } = read_body(packet_type, data)?;
+                Self::Move { id, x, y }
+            },
+
+            PacketType::UPDATE_REQUEST => {
+                if !data.is_empty() {
+                    return Err(MessageError::InvalidLength { packet_type, len: data.len() });
+                }
+                Self::UpdateRequest
+            },
+
+            PacketType::UPDATE_SNAPSHOT => Self::UpdateSnapshot { objects: read_objects(packet_type, data)? },
+            PacketType::ENTER_VIEW => Self::EnterView { objects: read_objects(packet_type, data)? },
+            PacketType::LEAVE_VIEW => Self::LeaveView { ids: read_list(packet_type, data)? },
+
+            PacketType::MOVE_REJECTED => {
+                let MoveRejectedBody { x, y, reason } = read_body(packet_type, data)?;
+                let reason = MoveRejectReason::from_u8(reason)
+                    .ok_or(MessageError::InvalidValue(packet_type))?;
+                Self::MoveRejected { x, y, reason }
+            },
+
+            PacketType::ERROR => {
+                let code = ErrorCode::from_u8(read_body(packet_type, data)?)
+                    .ok_or(MessageError::InvalidValue(packet_type))?;
+                Self::Error { code }
+            },
+
+            PacketType::CONNECT_REJECTED => {
+                let reason = ConnectRejectReason::from_u8(read_body(packet_type, data)?)
+                    .ok_or(MessageError::InvalidValue(packet_type))?;
+                Self::ConnectRejected { reason }
+            },
+
+            PacketType::QUEUE_POSITION => Self::QueuePosition { position: read_body(packet_type, data)? },
+            PacketType::MAP => Self::Map { map: read_map(packet_type, data)? },
+
+            PacketType::SELECT_CLASS => {
+                let class = PieceClass::from_u8(read_body(packet_type, data)?)
+                    .ok_or(MessageError::InvalidValue(packet_type))?;
+                Self::SelectClass { class }
+            },
+
+            PacketType::MATCH_REQUEST | PacketType::RESIGN | PacketType::DRAW_OFFER => {
+                if !data.is_empty() {
+                    return Err(MessageError::InvalidLength { packet_type, len: data.len() });
+                }
+                match packet_type {
+                    PacketType::MATCH_REQUEST => Self::MatchRequest,
+                    PacketType::RESIGN => Self::Resign,
+                    _ => Self::DrawOffer,
+                }
+            },
+
+            PacketType::MATCH_STARTED => {
+                let MatchStartedBody { color, opponent } = read_body(packet_type, data)?;
+                let color = ChessColor::from_u8(color)
+                    .ok_or(MessageError::InvalidValue(packet_type))?;
+                Self::MatchStarted { color, opponent }
+            },
+
+            PacketType::CHESS_MOVE => {
+                let [from, to, promotion] = read_body::<[u8; 3]>(packet_type, data)?;
+                let invalid_value = MessageError::InvalidValue(packet_type);
+
+                let from = Square::from_index(from).ok_or(invalid_value)?;
+                let to = Square::from_index(to).ok_or(invalid_value)?;
+                let promotion = match promotion {
+                    NO_PROMOTION => None,
+                    class => Some(PieceClass::from_u8(class).ok_or(invalid_value)?),
+                };
+                Self::ChessMove { mv: ChessMove { from, to, promotion } }
+            },
+
+            PacketType::CHESS_BOARD => Self::ChessBoard { board: read_board(packet_type, data)? },
+
+            PacketType::CHESS_REJECTED => {
+                let reason = ChessRejectReason::from_u8(read_body(packet_type, data)?)
+                    .ok_or(MessageError::InvalidValue(packet_type))?;
+                Self::ChessRejected { reason }
+            },
+
+            PacketType::GAME_OVER => {
+                let [result, reason] = read_body::<[u8; 2]>(packet_type, data)?;
+                let result = GameResult::from_u8(result)
+                    .ok_or(MessageError::InvalidValue(packet_type))?;
+                let reason = GameOverReason::from_u8(reason)
+                    .ok_or(MessageError::InvalidValue(packet_type))?;
+                Self::GameOver { result, reason }
+            },
+
+            PacketType::PGN => {
+                let text = String::from_utf8(data.to_vec())
+                    .map_err(|_| MessageError::InvalidValue(packet_type))?;
+                Self::Pgn { text }
+            },
+
+            PacketType::CHAT_SEND => {
+                let (head, target, text) = read_chat::<1>(packet_type, data)?;
+                let channel = ChatChannel::from_u8(head[0])
+                    .ok_or(MessageError::InvalidValue(packet_type))?;
+                Self::ChatSend { channel, target, text }
+            },
+
+            PacketType::CHAT => {
+                let (head, name, text) = read_chat::<5>(packet_type, data)?;
+                let channel = ChatChannel::from_u8(head[0])
+                    .ok_or(MessageError::InvalidValue(packet_type))?;
+                let from = bytemuck::pod_read_unaligned(&head[1..]);
+                Self::Chat { channel, from, name, text }
+            },
+
+            PacketType::CHAT_REJECTED => {
+                let reason = ChatRejectReason::from_u8(read_body(packet_type, data)?)
+                    .ok_or(MessageError::InvalidValue(packet_type))?;
+                Self::ChatRejected { reason }
+            },
+
+            PacketType::ATTACK => Self::Attack { target: read_body(packet_type, data)? },
+
+            PacketType::ATTACK_REJECTED => {
+                let AttackRejectedBody { target, reason } = read_body(packet_type, data)?;
+                let reason = AttackRejectReason::from_u8(reason)
+                    .ok_or(MessageError::InvalidValue(packet_type))?;
+                Self::AttackRejected { target, reason }
+            },
+
+            PacketType::DAMAGE => {
+                let DamageBody { attacker, target, amount, hp } = read_body(packet_type, data)?;
+                Self::Damage { attacker, target, amount, hp }
+            },
+
+            PacketType::DEATH => {
+                let [id, killer] = read_body::<[PlayerId; 2]>(packet_type, data)?;
+                Self::Death { id, killer }
+            },
+
+            PacketType::RESPAWN => {
+                let RespawnBody { id, x, y, hp } = read_body(packet_type, data)?;
+                Self::Respawn { id, x, y, hp }
+            },
+
+            PacketType::LOGIN => {
+                let ([], name, password) = read_chat::<0>(packet_type, data)?;
+                Self::Login { name, password }
+            },
+
+            PacketType::REGISTER => {
+                let ([], name, password) = read_chat::<0>(packet_type, data)?;
+                Self::Register { name, password }
+            },
+
+            PacketType::LOGIN_REJECTED => {
+                let reason = LoginRejectReason::from_u8(read_body(packet_type, data)?)
+                    .ok_or(MessageError::InvalidValue(packet_type))?;
+                Self::LoginRejected { reason }
+            },
+
+            PacketType::SHUTDOWN => Self::Shutdown { seconds: read_body(packet_type, data)? },
+
+            PacketType::NOTICE => {
+                let text = String::from_utf8(data.to_vec())
+                    .map_err(|_| MessageError::InvalidValue(packet_type))?;
+                Self::Notice { text }
+            },
+
+            _ => return Err(MessageError::UnsupportedType(packet_type)),
+        };
+
+        Ok(message)
+    }
+}
+
+
+/// 본문 전체가 정확히 `T` 하나인 경우에만 읽는다.
+fn read_body<T: Pod>(packet_type: PacketType, data: &[u8]) -> Result<T, MessageError> {
+    if data.len() != size_of::<T>() {
+        return Err(MessageError::InvalidLength { packet_type, len: data.len() });
+    }
+
+    Ok(bytemuck::pod_read_unaligned(data))
+}
+
+/// `count: u16, [T; count]`
+fn write_list<T: Pod>(items: &[T]) -> Vec<u8> {
+    let items = &items[..items.len().min(max_list_len::<T>())];
+    let count = items.len() as ListCount;
+
+    let mut data = bytemuck::bytes_of(&count).to_vec();
+    data.extend_from_slice(bytemuck::cast_slice(items));
+
+    data
+}
+
+fn read_list<T: Pod>(packet_type: PacketType, data: &[u8]) -> Result<Vec<T>, MessageError> {
+    let invalid_length = MessageError::InvalidLength { packet_type, len: data.len() };
+
+    if data.len() < size_of::<ListCount>() {
+        return Err(invalid_length);
+    }
+
+    let (count, items) = data.split_at(size_of::<ListCount>());
+    let count = bytemuck::pod_read_unaligned::<ListCount>(count) as usize;
+
+    if items.len() != count * size_of::<T>() {
+        return Err(invalid_length);
+    }
+
+    let items = items.chunks_exact(size_of::<T>())
+        .map(bytemuck::pod_read_unaligned::<T>)
+        .collect();
+
+    Ok(items)
+}
+
+
+/// `read_list`에 더해 말 종류와 오브젝트 종류까지 확인한다.
+fn read_objects(packet_type: PacketType, data: &[u8]) -> Result<Vec<ObjectInfo>, MessageError> {
+    let objects = read_list::<ObjectInfo>(packet_type, data)?;
+
+    if objects.iter().any(|&ObjectInfo { class, kind, .. }| !class.is_known() || !kind.is_known()) {
+        return Err(MessageError::InvalidValue(packet_type));
+    }
+
+    Ok(objects)
+}
+
+/// `width: u16, height: u16, [tile: u8; width * height]`
+fn write_map(map: &TileMap) -> Vec<u8> {
+    let mut data = Vec::with_capacity(2 * size_of::<u16>() + map.tiles().len());
+    data.extend_from_slice(bytemuck::bytes_of(&(map.width() as u16)));
+    data.extend_from_slice(bytemuck::bytes_of(&(map.height() as u16)));
+    data.extend(map.tiles().iter().map(|&tile| tile as u8));
+
+    data
+}
+
+fn read_map(packet_type: PacketType, data: &[u8]) -> Result<TileMap, MessageError> {
+    let invalid_length = MessageError::InvalidLength { packet_type, len: data.len() };
+
+    if data.len() < 2 * size_of::<u16>() {
+        return Err(invalid_length);
+    }
+
+    let (size, tiles) = data.split_at(2 * size_of::<u16>());
+    let [width, height] = bytemuck::pod_read_unaligned::<[u16; 2]>(size);
+    let (width, height) = (width as usize, height as usize);
+
+    if tiles.len() != width * height {
+        return Err(invalid_length);
+    }
+
+    let tiles = tiles.iter()
+        .map(|&tile| Tile::from_u8(tile))
+        .collect::<Option<Vec<_>>>()
+        .ok_or(MessageError::InvalidValue(packet_type))?;
+
+    TileMap::new(width, height, tiles).map_err(|_| MessageError::InvalidValue(packet_type))
+}
+
+/// `[piece: u8; 64], turn: u8, check: u8`
+fn write_board(board: &BoardState) -> Vec<u8> {
+    let mut data = board.squares.iter()
+        .map(|&piece| ChessPiece::encode(piece))
+        .collect::<Vec<_>>();
+    data.push(board.turn as u8);
+    data.push(board.check as u8);
+
+    data
+}
+
+fn read_board(packet_type: PacketType, data: &[u8]) -> Result<BoardState, MessageError> {
+    let invalid_value = MessageError::InvalidValue(packet_type);
+
+    if data.len() != 66 {
+        return Err(MessageError::InvalidLength { packet_type, len: data.len() });
+    }
+    let (pieces, rest) = data.split_at(64);
+
+    let mut squares = [None; 64];
+    for (square, &piece) in squares.iter_mut().zip(pieces) {
+        *square = ChessPiece::decode(piece).map_err(|_| invalid_value)?;
+    }
+
+    let turn = ChessColor::from_u8(rest[0]).ok_or(invalid_value)?;
+    let check = match rest[1] {
+        0 => false,
+        1 => true,
+        _ => return Err(invalid_value),
+    };
+
+    Ok(BoardState { squares, turn, check })
+}
+
+/// `head, name_len: u8, [name: u8; name_len], text: [u8]`. (채팅, 로그인)
+/// `name`은 `MAX_CHAT_NAME_LEN` 바이트, `text`는 패킷에 들어가는 만큼 자른다.
+fn write_chat(head: &[u8], name: &str, text: &str) -> Vec<u8> {
+    let name = truncate_str(name, Message::MAX_CHAT_NAME_LEN);
+
+    let mut data = head.to_vec();
+    data.push(name.len() as u8);
+    data.extend_from_slice(name.as_bytes());
+
+    let text = truncate_str(text, MAX_BODY_LEN - data.len());
+    data.extend_from_slice(text.as_bytes());
+
+    data
+}
+
+/// `write_chat`의 반대. 길이가 `N`인 `head`와 `name`, `text`
+fn read_chat<const N: usize>(packet_type: PacketType, data: &[u8]) -> Result<([u8; N], String, String), MessageError> {
+    let invalid_length = MessageError::InvalidLength { packet_type, len: data.len() };
+
+    if data.len() < N + 1 {
+        return Err(invalid_length);
+    }
+    let (head, rest) = data.split_at(N);
+    let (name_len, rest) = (rest[0] as usize, &rest[1..]);
+
+    if rest.len() < name_len {
+        return Err(invalid_length);
+    }
+    let (name, text) = rest.split_at(name_len);
+
+    let to_string = |bytes: &[u8]| {
+        String::from_utf8(bytes.to_vec()).map_err(|_| MessageError::InvalidValue(packet_type))
+    };
+
+    Ok((head.try_into().unwrap(), to_string(name)?, to_string(text)?))
+}
+
+/// 문자 경계에 맞춰 `max_len` 바이트 이하로 자른다.
+fn truncate_str(text: &str, max_len: usize) -> &str {
+    if text.len() <= max_len {
+        return text;
+    }
+
+    let end = (0..=max_len).rev()
+        .find(|&end| text.is_char_boundary(end))
+        .unwrap_or(0);
+
+    &text[..end]
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(message: Message) {
+        let raw = message.as_raw();
+        assert_eq!(raw.packet_type(), message.packet_type());
+
+        let bytes = raw.as_bytes();
+        let raw = RawPacket::from_bytes(&bytes).unwrap();
+        assert_eq!(Message::from_raw(raw).unwrap(), message);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        round_trip(Message::Init { id: PlayerId::new(42, 0) });
+        round_trip(Message::Ping { time: 1_723_456_789_012 });
+        round_trip(Message::Pong { time: u128::MAX });
+        round_trip(Message::Move { id: PlayerId::new(7, 0), x: -1, y: 0 });
+        round_trip(Message::UpdateRequest);
+        round_trip(Message::UpdateSnapshot { objects: vec![] });
+        round_trip(Message::UpdateSnapshot {
+            objects: vec![
+                ObjectInfo::new(PlayerId::new(0, 0), 3, 3, PieceClass::PAWN),
+                ObjectInfo::new(PlayerId::new(2, 0), 0, 7, PieceClass::PAWN),
+                ObjectInfo::new(PlayerId::new(9999, 1), -4, 12, PieceClass::PAWN),
+            ],
+        });
+        round_trip(Message::EnterView { objects: vec![ObjectInfo::new(PlayerId::new(4, 0), 1, 1, PieceClass::PAWN)] });
+        round_trip(Message::LeaveView { ids: vec![] });
+        round_trip(Message::LeaveView { ids: vec![PlayerId::new(1, 0), PlayerId::new(5, 2), PlayerId::new(9, 7)] });
+        round_trip(Message::MoveRejected { x: 3, y: 0, reason: MoveRejectReason::InvalidStep });
+        round_trip(Message::MoveRejected { x: 0, y: -1, reason: MoveRejectReason::NotOwner });
+        round_trip(Message::MoveRejected { x: 1, y: 0, reason: MoveRejectReason::Occupied });
+        round_trip(Message::Error { code: ErrorCode::TooManyErrors });
+        round_trip(Message::ConnectRejected { reason: ConnectRejectReason::ServerFull });
+        round_trip(Message::ConnectRejected { reason: ConnectRejectReason::WorldFull });
+        round_trip(Message::QueuePosition { position: 12 });
+        round_trip(Message::Map { map: "#S.#\n#..#\n".parse().unwrap() });
+        round_trip(Message::Map { map: TileMap::open(8, 8, (3, 3)).unwrap() });
+        round_trip(Message::SelectClass { class: PieceClass::QUEEN });
+        round_trip(Message::EnterView { objects: vec![ObjectInfo::new(PlayerId::new(2, 0), 5, 1, PieceClass::KNIGHT)] });
+        round_trip(Message::UpdateSnapshot { objects: vec![ObjectInfo::npc(PlayerId::npc(0), 2, 2, PieceClass::ROOK)] });
+
+        let e7 = "e7".parse().unwrap();
+        let e8 = "e8".parse::<Square>().unwrap();
+        let mut squares = [None; 64];
+        squares[e8.index()] = Some(ChessPiece::new(ChessColor::White, PieceClass::QUEEN));
+        squares[0] = Some(ChessPiece::new(ChessColor::Black, PieceClass::KING));
+
+        round_trip(Message::MatchRequest);
+        round_trip(Message::MatchStarted { color: ChessColor::Black, opponent: PlayerId::new(3, 1) });
+        round_trip(Message::ChessMove { mv: ChessMove::new(e7, e8) });
+        round_trip(Message::ChessMove { mv: ChessMove::with_promotion(e7, e8, PieceClass::KNIGHT) });
+        round_trip(Message::ChessBoard { board: BoardState { squares, turn: ChessColor::Black, check: true } });
+        round_trip(Message::ChessRejected { reason: ChessRejectReason::NotYourTurn });
+        round_trip(Message::Resign);
+        round_trip(Message::DrawOffer);
+        round_trip(Message::GameOver { result: GameResult::Draw, reason: GameOverReason::Stalemate });
+        round_trip(Message::Pgn { text: "[Result \"1-0\"]\n\n1. e4 e5 1-0\n".to_string() });
+
+        round_trip(Message::ChatSend { channel: ChatChannel::Global, target: String::new(), text: "안녕하세요".to_string() });
+        round_trip(Message::ChatSend { channel: ChatChannel::Whisper, target: "3#0".to_string(), text: "hi".to_string() });
+        round_trip(Message::Chat {
+            channel: ChatChannel::Proximity,
+            from: PlayerId::new(3, 2),
+            name: "Player 3#2".to_string(),
+            text: String::new(),
+        });
+        round_trip(Message::ChatRejected { reason: ChatRejectReason::RateLimited });
+
+        round_trip(Message::Attack { target: PlayerId::npc(2) });
+        round_trip(Message::AttackRejected { target: PlayerId::new(4, 1), reason: AttackRejectReason::Cooldown });
+        round_trip(Message::Damage { attacker: PlayerId::new(1, 0), target: PlayerId::npc(0), amount: 10, hp: 90 });
+        round_trip(Message::Death { id: PlayerId::new(2, 3), killer: PlayerId::new(1, 0) });
+        round_trip(Message::Respawn { id: PlayerId::new(2, 3), x: -3, y: 7, hp: u16::MAX });
+
+        round_trip(Message::Login { name: "alice".to_string(), password: "correct horse".to_string() });
+        round_trip(Message::Register { name: "bob".to_string(), password: String::new() });
+        round_trip(Message::LoginRejected { reason: LoginRejectReason::AlreadyOnline });
+        round_trip(Message::Shutdown { seconds: 10 });
+        round_trip(Message::ConnectRejected { reason: ConnectRejectReason::ShuttingDown });
+        round_trip(Message::ConnectRejected { reason: ConnectRejectReason::Banned });
+        round_trip(Message::Notice { text: "서버 점검 예정".to_string() });
+        round_trip(Message::Error { code: ErrorCode::LoginRequired });
+        round_trip(Message::Error { code: ErrorCode::ClassLocked });
+    }
+
+    #[test]
+    fn test_layout() {
+        let raw = Message::Move { id: PlayerId::new(3, 0), x: 1, y: -1 }.as_raw();
+        let mut expected = Vec::new();
+        expected.extend_from_slice(&3u32.to_ne_bytes());
+        expected.extend_from_slice(&1i32.to_ne_bytes());
+        expected.extend_from_slice(&(-1i32).to_ne_bytes());
+        assert_eq!(raw.data(), expected.as_slice());
+
+        let raw = Message::UpdateSnapshot { objects: vec![ObjectInfo::new(PlayerId::new(1, 0), 2, 3, PieceClass::PAWN)] }.as_raw();
+        assert_eq!(raw.data().len(), size_of::<u16>() + 14);
+        assert_eq!(raw.as_bytes().len(), size_of::<PacketHeader>() + size_of::<u16>() + 14);
+    }
+
+    #[test]
+    fn test_snapshot_truncated() {
+        let objects = (0..Message::MAX_SNAPSHOT_OBJECTS as u32 + 10)
+            .map(|id| ObjectInfo::new(PlayerId::from_bits(id), 0, 0, PieceClass::PAWN))
+            .collect::<Vec<_>>();
+
+        let raw = Message::UpdateSnapshot { objects: objects.clone() }.as_raw();
+        let bytes = raw.as_bytes();
+        assert!(bytes.len() <= PacketSize::MAX as usize);
+
+        match Message::from_raw(RawPacket::from_bytes(&bytes).unwrap()).unwrap() {
+            Message::UpdateSnapshot { objects: decoded } => {
+                assert_eq!(decoded, objects[..Message::MAX_SNAPSHOT_OBJECTS]);
+            },
+            _ => panic!("unexpected message"),
+        }
+    }
+
+    #[test]
+    fn test_pgn_truncated() {
+        let text = "가".repeat(Message::MAX_PGN_LEN / 3 + 10);
+        let raw = Message::Pgn { text: text.clone() }.as_raw();
+        assert!(raw.as_bytes().len() <= PacketSize::MAX as usize);
+
+        match Message::from_raw(raw).unwrap() {
+            Message::Pgn { text: decoded } => assert!(text.starts_with(&decoded)),
+            _ => panic!("unexpected message"),
+        }
+    }
+
+    #[test]
+    fn test_chat_truncated() {
+        let raw = Message::Chat {
+            channel: ChatChannel::Global,
+            from: PlayerId::new(1, 0),
+            name: "n".repeat(300),
+            text: "가".repeat(MAX_BODY_LEN / 3),
+        }.as_raw();
+        assert!(raw.as_bytes().len() <= PacketSize::MAX as usize);
+
+        match Message::from_raw(raw).unwrap() {
+            Message::Chat { name, text, .. } => {
+                assert_eq!(name.len(), Message::MAX_CHAT_NAME_LEN);
+                assert!(text.chars().all(|c| c == '가'));
+            },
+            _ => panic!("unexpected message"),
+        }
+    }
+
+    #[test]
+    fn test_login_too_long() {
+        let login = Message::Login { name: "n".repeat(Message::MAX_CHAT_NAME_LEN), password: "p".repeat(MAX_BODY_LEN - 1 - Message::MAX_CHAT_NAME_LEN) };
+        assert_eq!(Message::from_raw(login.try_as_raw().unwrap()), Ok(login));
+
+        // 자르지 않고 거부
+        let name = Message::Register { name: "n".repeat(Message::MAX_CHAT_NAME_LEN + 1), password: String::new() };
+        assert_eq!(name.try_as_raw(), Err(MessageError::TooLong(PacketType::REGISTER)));
+        let password = Message::Login { name: "alice".to_string(), password: "p".repeat(MAX_BODY_LEN) };
+        assert_eq!(password.try_as_raw(), Err(MessageError::TooLong(PacketType::LOGIN)));
+
+        // 다른 메세지는 `as_raw`와 같음
+        let chat = Message::ChatSend { channel: ChatChannel::Global, target: String::new(), text: "hi".to_string() };
+        assert_eq!(chat.try_as_raw(), Ok(chat.as_raw()));
+    }
+
+    #[test]
+    fn test_invalid_body() {
+        let raw = RawPacket::new(PacketType::INIT, &[1, 2]);
+        assert_eq!(Message::from_raw(raw),
+            Err(MessageError::InvalidLength { packet_type: PacketType::INIT, len: 2 }));
+
+        let raw = RawPacket::new(PacketType::UPDATE_REQUEST, b"update");
+        assert_eq!(Message::from_raw(raw),
+            Err(MessageError::InvalidLength { packet_type: PacketType::UPDATE_REQUEST, len: 6 }));
+
+        // count는 2개인데 오브젝트는 1개만 있음
+        let mut data = 2u16.to_ne_bytes().to_vec();
+        data.extend_from_slice(bytemuck::bytes_of(&ObjectInfo::new(PlayerId::new(1, 0), 2, 3, PieceClass::PAWN)));
+        let raw = RawPacket::new(PacketType::UPDATE_SNAPSHOT, &data);
+        assert_eq!(Message::from_raw(raw),
+            Err(MessageError::InvalidLength { packet_type: PacketType::UPDATE_SNAPSHOT, len: 16 }));
+
+        // 알 수 없는 말 종류, 오브젝트 종류
+        let mut data = 1u16.to_ne_bytes().to_vec();
+        data.extend_from_slice(bytemuck::bytes_of(&ObjectInfo::new(PlayerId::new(1, 0), 2, 3, PieceClass::PAWN)));
+        let len = data.len();
+        for index in [len - 2, len - 1] {
+            let mut data = data.clone();
+            data[index] = 9;
+            let raw = RawPacket::new(PacketType::ENTER_VIEW, &data);
+            assert_eq!(Message::from_raw(raw), Err(MessageError::InvalidValue(PacketType::ENTER_VIEW)));
+        }
+        let raw = RawPacket::new(PacketType::SELECT_CLASS, &[6]);
+        assert_eq!(Message::from_raw(raw), Err(MessageError::InvalidValue(PacketType::SELECT_CLASS)));
+
+        // 알 수 없는 거부 이유
+        let raw = RawPacket::new(PacketType::MOVE_REJECTED, &[0, 0, 0, 0, 0, 0, 0, 0, 9]);
+        assert_eq!(Message::from_raw(raw), Err(MessageError::InvalidValue(PacketType::MOVE_REJECTED)));
+
+        let raw = RawPacket::new(PacketType::ERROR, &[0]);
+        assert_eq!(Message::from_raw(raw), Err(MessageError::InvalidValue(PacketType::ERROR)));
+
+        // 맵 크기와 타일 수가 다름
+        let raw = RawPacket::new(PacketType::MAP, &[2, 0, 1, 0, 2]);
+        assert_eq!(Message::from_raw(raw),
+            Err(MessageError::InvalidLength { packet_type: PacketType::MAP, len: 5 }));
+
+        // 알 수 없는 타일, 스폰이 없는 맵
+        let raw = RawPacket::new(PacketType::MAP, &[1, 0, 1, 0, 7]);
+        assert_eq!(Message::from_raw(raw), Err(MessageError::InvalidValue(PacketType::MAP)));
+        let raw = RawPacket::new(PacketType::MAP, &[1, 0, 1, 0, 0]);
+        assert_eq!(Message::from_raw(raw), Err(MessageError::InvalidValue(PacketType::MAP)));
+
+        // 판 밖의 칸, 알 수 없는 승급
+        let raw = RawPacket::new(PacketType::CHESS_MOVE, &[12, 64, NO_PROMOTION]);
+        assert_eq!(Message::from_raw(raw), Err(MessageError::InvalidValue(PacketType::CHESS_MOVE)));
+        let raw = RawPacket::new(PacketType::CHESS_MOVE, &[52, 60, 7]);
+        assert_eq!(Message::from_raw(raw), Err(MessageError::InvalidValue(PacketType::CHESS_MOVE)));
+
+        let mut data = [0u8; 66];
+        data[10] = 15;
+        let raw = RawPacket::new(PacketType::CHESS_BOARD, &data);
+        assert_eq!(Message::from_raw(raw), Err(MessageError::InvalidValue(PacketType::CHESS_BOARD)));
+
+        let raw = RawPacket::new(PacketType::PGN, &[0xff, 0xfe]);
+        assert_eq!(Message::from_raw(raw), Err(MessageError::InvalidValue(PacketType::PGN)));
+
+        // 알 수 없는 채널, 이름 길이가 본문보다 김, UTF-8이 아닌 이름
+        let raw = RawPacket::new(PacketType::CHAT_SEND, &[0, 0]);
+        assert_eq!(Message::from_raw(raw), Err(MessageError::InvalidValue(PacketType::CHAT_SEND)));
+        let raw = RawPacket::new(PacketType::CHAT_SEND, &[2, 4, b'a']);
+        assert_eq!(Message::from_raw(raw),
+            Err(MessageError::InvalidLength { packet_type: PacketType::CHAT_SEND, len: 3 }));
+        let raw = RawPacket::new(PacketType::CHAT, &[1, 0, 0, 0, 0, 1, 0xff]);
+        assert_eq!(Message::from_raw(raw), Err(MessageError::InvalidValue(PacketType::CHAT)));
+
+        let raw = RawPacket::new(PacketType::ATTACK_REJECTED, &[1, 0, 0, 0, 0]);
+        assert_eq!(Message::from_raw(raw), Err(MessageError::InvalidValue(PacketType::ATTACK_REJECTED)));
+        let raw = RawPacket::new(PacketType::DEATH, &[1, 0, 0, 0]);
+        assert_eq!(Message::from_raw(raw),
+            Err(MessageError::InvalidLength { packet_type: PacketType::DEATH, len: 4 }));
+
+        let raw = RawPacket::new(PacketType::MESSAGE, b"move 3 1 0");
+        assert_eq!(Message::from_raw(raw), Err(MessageError::UnsupportedType(PacketType::MESSAGE)));
+    }
+}
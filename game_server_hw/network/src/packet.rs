@@ -1,129 +1,165 @@
-use std::collections::VecDeque;
 use std::mem::size_of;
+use std::fmt;
+use bytes::BytesMut;
 
 use super::protocol::*;
 
 
-#[derive(Debug, PartialEq)]
-pub enum Packet {
-    Complete(RawPacket),
-    Incomplete(Vec<u8>),
+/// 헤더가 잘못되어 더이상 스트림을 해석할 수 없는 경우
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ParseError {
+    /// 헤더의 크기가 헤더 자체보다 작음
+    TooSmall(usize),
+    /// 헤더의 크기가 허용된 최대 크기보다 큼
+    TooLarge(usize),
+    UnknownType(u8),
 }
 
-
-use Packet::*;
-
-
-/// 뭉쳐온 패킷 분리 및 잘린 패킷 이어붙이기를 수행하는 큐 형태의 Parser
-pub struct PacketParser {
-    queue: VecDeque<Packet>,
-}
-
-impl PacketParser {
-    pub fn new() -> Self {
-        Self {
-            queue: VecDeque::new(),
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooSmall(size) => write!(f, "Packet size {} is smaller than header", size),
+            Self::TooLarge(size) => write!(f, "Packet size {} exceeds maximum", size),
+            Self::UnknownType(packet_type) => write!(f, "Unknown packet type {}", packet_type),
         }
     }
+}
 
-    pub fn push(&mut self, data: &[u8]) {
-        if data.len() == 0 {
-            return;
-        }
-
-        let mut data = Vec::from(data);
+impl std::error::Error for ParseError {}
 
-        if let Some(Incomplete(prev)) = self.queue.back() {
-            data.splice(0..0, prev.iter().cloned());
-            self.queue.pop_back().unwrap();
-        }
 
-        let mut len = data.len();
-        while len > 0 {
-            if len < 2 {
-                self.queue.push_back(Incomplete(data));
-                break;
-            }
+pub const MIN_PACKET_SIZE: usize = size_of::<PacketHeader>();
+pub const MAX_PACKET_SIZE: usize = PacketSize::MAX as usize;
 
-            let size = *bytemuck::from_bytes::<PacketSize>(&data[0..size_of::<PacketSize>()]) as usize;
-            
-            if len < size {
-                self.queue.push_back(Incomplete(data));
-                break;
-            }
 
-            let packet = data.drain(0..size).collect::<Vec<u8>>();
-            
-            len = data.len();
+impl RawPacket {
+    /// `buffer`의 맨 앞에서 완성된 패킷 한개를 복사 없이 잘라낸다.
+    /// 아직 패킷이 다 도착하지 않았으면 `Ok(None)`, 헤더가 잘못되었으면 `Err`.
+    /// 헤더는 도착하는 즉시 검증하므로 잘못된 크기의 패킷을 끝까지 기다리지 않는다.
+    pub fn split_from(buffer: &mut BytesMut, max_size: usize) -> Result<Option<Self>, ParseError> {
+        if buffer.len() < size_of::<PacketSize>() {
+            return Ok(None);
+        }
 
-            let packet = match RawPacket::from_bytes(&packet) {
-                Ok(packet) => Complete(packet),
-                Err(_) => continue,     // 알 수 없는 패킷은 버림
-            };
+        let size = bytemuck::pod_read_unaligned::<PacketSize>(&buffer[..size_of::<PacketSize>()]) as usize;
 
-            self.queue.push_back(packet);
+        if size < MIN_PACKET_SIZE {
+            return Err(ParseError::TooSmall(size));
         }
-    }
-
-    /// 한개 남았을 때 Incomplete이면 아직 완성 안된것이므로 pop하지 않음.  
-    /// 두개 이상 남았을때 제일 앞 패킷이 Incomplete이면 모종의 이유로 완성 안된것이므로 값을 버리기 위해 pop.  
-    pub fn pop(&mut self) -> Option<RawPacket> {
-        if self.queue.len() == 1 {
-            if let Some(Incomplete(_)) = self.queue.front() {
-                return None;
-            }
+        if size > max_size {
+            return Err(ParseError::TooLarge(size));
         }
 
-        match self.queue.pop_front() {
-            Some(Complete(some)) => Some(some),
-            _ => None,
+        if buffer.len() < size_of::<PacketHeader>() {
+            return Ok(None);
         }
-    }
-
-    pub fn is_empty(&self) -> bool {
-        self.queue.is_empty()
-    }
 
-    pub fn clear(&mut self) {
-        self.queue.clear();
-    }
+        let header = bytemuck::pod_read_unaligned::<PacketHeader>(&buffer[..size_of::<PacketHeader>()]);
+        if !header.packet_type().is_known() {
+            return Err(ParseError::UnknownType(header.packet_type().value()));
+        }
 
-    pub fn front(&self) -> Option<&Packet> {
-        self.queue.front()
-    }
+        if buffer.len() < size {
+            return Ok(None);
+        }
 
-    pub fn back(&self) -> Option<&Packet> {
-        self.queue.back()
-    }
+        let mut packet = buffer.split_to(size);
+        let data = packet.split_off(size_of::<PacketHeader>()).freeze();
 
-    pub fn len(&self) -> usize {
-        self.queue.len()
-    }
-
-    pub fn iter(&self) -> std::collections::vec_deque::Iter<Packet> {
-        self.queue.iter()
+        Ok(Some(Self::from_parts(header, data)))
     }
 }
 
 
+/// 뭉쳐온 패킷 분리 및 잘린 패킷 이어붙이기를 수행하는 Parser
+///
+/// 받은 데이터는 재사용되는 하나의 버퍼(`BytesMut`)에 쌓이고,
+/// `pop`으로 꺼낸 패킷은 그 버퍼를 복사 없이 나눠 가진다.
+/// 꺼낸 패킷들이 모두 drop되면 버퍼 공간은 다시 사용된다.
+///
+/// 헤더가 잘못된 데이터가 들어오면 `ParseError`를 반환하고,
+/// 이후로는 같은 에러만 반환한다(스트림의 경계를 더이상 알 수 없기 때문).
+pub struct PacketParser {
+    buffer: BytesMut,
+    max_size: usize,
+    error: Option<ParseError>,
+}
 
+impl PacketParser {
+    /// `buffer_mut`가 한번에 확보하는 여유 공간
+    pub const READ_CHUNK: usize = 4096;
 
+    pub fn new() -> Self {
+        Self::with_max_size(MAX_PACKET_SIZE)
+    }
 
+    /// `max_size`는 `MIN_PACKET_SIZE..=MAX_PACKET_SIZE` 범위로 제한된다.
+    pub fn with_max_size(max_size: usize) -> Self {
+        Self {
+            buffer: BytesMut::with_capacity(Self::READ_CHUNK),
+            max_size: max_size.clamp(MIN_PACKET_SIZE, MAX_PACKET_SIZE),
+            error: None,
+        }
+    }
 
+    pub fn max_size(&self) -> usize {
+        self.max_size
+    }
 
+    pub fn error(&self) -> Option<ParseError> {
+        self.error
+    }
 
+    pub fn push(&mut self, data: &[u8]) {
+        if self.error.is_some() {
+            return;
+        }
 
+        self.buffer.extend_from_slice(data);
+    }
 
+    /// 소켓에서 바로 읽어넣을 버퍼. (`AsyncReadExt::read_buf` 등)
+    /// 최소 `READ_CHUNK`만큼의 여유 공간을 확보해서 돌려준다.
+    pub fn buffer_mut(&mut self) -> &mut BytesMut {
+        self.buffer.reserve(Self::READ_CHUNK);
+        &mut self.buffer
+    }
 
+    /// 완성된 패킷이 없으면 `Ok(None)`.
+    pub fn pop(&mut self) -> Result<Option<RawPacket>, ParseError> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
 
+        match RawPacket::split_from(&mut self.buffer, self.max_size) {
+            Ok(packet) => Ok(packet),
+            Err(error) => {
+                self.error = Some(error);
+                self.buffer.clear();
+                Err(error)
+            }
+        }
+    }
 
+    /// 아직 패킷으로 꺼내지 않은 바이트 수
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
 
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
 
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+    }
+}
 
-
-
-
-
+impl Default for PacketParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 
 
@@ -138,15 +174,17 @@ mod tests {
 
         let packet = RawPacket::new(PacketType::MESSAGE, b"update");
         parser.push(&packet.as_bytes());
-        assert_eq!(parser.pop(), Some(packet));
+        assert_eq!(parser.pop(), Ok(Some(packet)));
 
         let packet = RawPacket::new(PacketType::MESSAGE, b"remove");
         parser.push(&packet.as_bytes());
-        assert_eq!(parser.pop(), Some(packet));
+        assert_eq!(parser.pop(), Ok(Some(packet)));
 
         let packet = RawPacket::new(PacketType::MESSAGE, b"init 3 2 5 6");
         parser.push(&packet.as_bytes());
-        assert_eq!(parser.pop(), Some(packet));
+        assert_eq!(parser.pop(), Ok(Some(packet)));
+
+        assert!(parser.is_empty());
     }
 
     #[test]
@@ -157,26 +195,24 @@ mod tests {
             let packet = RawPacket::new(PacketType::MESSAGE, b"update");
             let bytes = packet.as_bytes();
             parser.push(&bytes[..3]);
-            assert_eq!(parser.iter().last(), Some(&Incomplete(bytes[..3].to_vec())));
-            assert_eq!(parser.pop(), None);
+            assert_eq!(parser.len(), 3);
+            assert_eq!(parser.pop(), Ok(None));
 
             parser.push(&bytes[3..]);
-            assert_eq!(parser.iter().last(), Some(&Complete(packet)));
-            // assert_eq!(parser.pop(), Some(packet));
-            parser.pop();
+            assert_eq!(parser.pop(), Ok(Some(packet)));
+            assert!(parser.is_empty());
         }
 
         {
             let packet = RawPacket::new(PacketType::MESSAGE, b"remove");
             let bytes = packet.as_bytes();
             parser.push(&bytes[..6]);
-            assert_eq!(parser.iter().last(), Some(&Incomplete(bytes[..6].to_vec())));
-            assert_eq!(parser.pop(), None);
+            assert_eq!(parser.len(), 6);
+            assert_eq!(parser.pop(), Ok(None));
 
             parser.push(&bytes[6..]);
-            assert_eq!(parser.iter().last(), Some(&Complete(packet)));
-            // assert_eq!(parser.pop(), Some(packet));
-            parser.pop();
+            assert_eq!(parser.pop(), Ok(Some(packet)));
+            assert!(parser.is_empty());
         }
 
         {
@@ -198,25 +234,15 @@ mod tests {
             let cut = bytes1.len() + bytes2.len() + bytes3.len() + bytes4.len() / 2;
 
             parser.push(&chained[..cut]);
-            assert_eq!(parser.iter().last(), Some(&Incomplete(bytes4[..bytes4.len() / 2].to_vec())));
-
-            let mut quess = vec![
-                Complete(packet1),
-                Complete(packet2),
-                Complete(packet3),
-                Incomplete(bytes4[..bytes4.len() / 2].to_vec()),
-            ];
-
-            for it in parser.iter().zip(quess.iter()) {
-                assert_eq!(it.0, it.1);
-            }
+            assert_eq!(parser.pop(), Ok(Some(packet1)));
+            assert_eq!(parser.pop(), Ok(Some(packet2)));
+            assert_eq!(parser.pop(), Ok(Some(packet3)));
+            assert_eq!(parser.pop(), Ok(None));
+            assert_eq!(parser.len(), bytes4.len() / 2);
 
             parser.push(&chained[cut..]);
-            quess[3] = Complete(packet4);
-
-            for it in parser.iter().zip(quess.iter()) {
-                assert_eq!(it.0, it.1);
-            }
+            assert_eq!(parser.pop(), Ok(Some(packet4)));
+            assert_eq!(parser.pop(), Ok(None));
         }
     }
 
@@ -229,38 +255,195 @@ mod tests {
 
         parser.push(b"\n");
         assert_eq!(parser.len(), 1);
-        assert_eq!(parser.pop(), None);
+        assert_eq!(parser.pop(), Ok(None));
+    }
+
+    #[test]
+    fn test_buffer_reused() {
+        let mut parser = PacketParser::new();
+
+        let packet1 = RawPacket::new(PacketType::MESSAGE, b"update");
+        let packet2 = RawPacket::new(PacketType::MESSAGE, b"remove");
+
+        let buffer = parser.buffer_mut();
+        buffer.extend_from_slice(&packet1.as_bytes());
+        buffer.extend_from_slice(&packet2.as_bytes());
+        let range = buffer.as_ptr_range();
+
+        // 꺼낸 패킷의 데이터는 parser의 버퍼를 그대로 가리킴
+        let popped1 = parser.pop().unwrap().unwrap();
+        let popped2 = parser.pop().unwrap().unwrap();
+        assert!(range.contains(&popped1.data().as_ptr()));
+        assert!(range.contains(&popped2.data().as_ptr()));
+        assert_eq!(popped1, packet1);
+        assert_eq!(popped2, packet2);
+
+        // 패킷이 모두 drop되면 같은 공간을 다시 사용
+        drop(popped1);
+        drop(popped2);
+        let buffer = parser.buffer_mut();
+        assert_eq!(buffer.as_ptr(), range.start);
+    }
+
+    fn header(size: PacketSize, packet_type: u8) -> Vec<u8> {
+        let mut bytes = size.to_ne_bytes().to_vec();
+        bytes.push(packet_type);
+        bytes
+    }
+
+    #[test]
+    fn test_too_small_header() {
+        for size in 0..MIN_PACKET_SIZE as PacketSize {
+            let mut parser = PacketParser::new();
+            parser.push(&header(size, 1));
+            assert_eq!(parser.pop(), Err(ParseError::TooSmall(size as usize)));
+        }
+
+        // 크기만 들어와도 바로 거부
+        let mut parser = PacketParser::new();
+        parser.push(&0u16.to_ne_bytes());
+        assert_eq!(parser.pop(), Err(ParseError::TooSmall(0)));
+    }
+
+    #[test]
+    fn test_too_large_header() {
+        let mut parser = PacketParser::with_max_size(64);
+        assert_eq!(parser.max_size(), 64);
+
+        let packet = RawPacket::new(PacketType::MESSAGE, &[0; 61]);
+        parser.push(&packet.as_bytes());
+        assert_eq!(parser.pop(), Ok(Some(packet)));
+
+        parser.push(&header(65, 1));
+        assert_eq!(parser.pop(), Err(ParseError::TooLarge(65)));
+    }
+
+    #[test]
+    fn test_unknown_type() {
+        let mut parser = PacketParser::new();
+        parser.push(&header(3, 200));
+        assert_eq!(parser.pop(), Err(ParseError::UnknownType(200)));
+    }
+
+    #[test]
+    fn test_error_is_sticky() {
+        let mut parser = PacketParser::new();
+
+        let packet = RawPacket::new(PacketType::MESSAGE, b"update");
+        let mut bytes = packet.as_bytes();
+        bytes.extend_from_slice(&header(1, 1));
+        parser.push(&bytes);
+
+        // 에러 이전에 완성된 패킷은 꺼낼 수 있음
+        assert_eq!(parser.pop(), Ok(Some(packet)));
+        assert_eq!(parser.pop(), Err(ParseError::TooSmall(1)));
+        assert_eq!(parser.error(), Some(ParseError::TooSmall(1)));
+
+        let packet = RawPacket::new(PacketType::MESSAGE, b"update");
+        parser.push(&packet.as_bytes());
+        assert_eq!(parser.pop(), Err(ParseError::TooSmall(1)));
+        assert!(parser.is_empty());
+    }
+}
+
+
+#[cfg(test)]
+mod proptests {
+    use super::*;
+    use proptest::prelude::*;
+
+    const KNOWN_TYPES: [PacketType; 38] = [
+        PacketType::RAW, PacketType::MESSAGE,
+        PacketType::INIT, PacketType::PING, PacketType::PONG, PacketType::MOVE,
+        PacketType::UPDATE_REQUEST, PacketType::UPDATE_SNAPSHOT,
+        PacketType::ENTER_VIEW, PacketType::LEAVE_VIEW,
+        PacketType::MOVE_REJECTED, PacketType::ERROR,
+        PacketType::CONNECT_REJECTED, PacketType::QUEUE_POSITION,
+        PacketType::MAP, PacketType::SELECT_CLASS,
+        PacketType::MATCH_REQUEST, PacketType::MATCH_STARTED,
+        PacketType::CHESS_MOVE, PacketType::CHESS_BOARD, PacketType::CHESS_REJECTED,
+        PacketType::RESIGN, PacketType::DRAW_OFFER, PacketType::GAME_OVER, PacketType::PGN,
+        PacketType::CHAT_SEND, PacketType::CHAT, PacketType::CHAT_REJECTED,
+        PacketType::ATTACK, PacketType::ATTACK_REJECTED,
+        PacketType::DAMAGE, PacketType::DEATH, PacketType::RESPAWN,
+        PacketType::LOGIN, PacketType::REGISTER, PacketType::LOGIN_REJECTED,
+        PacketType::SHUTDOWN, PacketType::NOTICE,
+    ];
+
+    fn packet() -> impl Strategy<Value = RawPacket> {
+        (0..KNOWN_TYPES.len(), prop::collection::vec(any::<u8>(), 0..300))
+            .prop_map(|(t, data)| RawPacket::new(KNOWN_TYPES[t], &data))
+    }
+
+    /// `bytes`를 `cuts` 위치에서 잘라서 순서대로 push
+    fn push_split(parser: &mut PacketParser, bytes: &[u8], cuts: &[usize]) -> Result<Vec<RawPacket>, ParseError> {
+        let mut cuts = cuts.iter()
+            .map(|cut| cut % (bytes.len() + 1))
+            .collect::<Vec<_>>();
+        cuts.push(0);
+        cuts.push(bytes.len());
+        cuts.sort();
+
+        let mut popped = Vec::new();
+        for range in cuts.windows(2) {
+            parser.buffer_mut().extend_from_slice(&bytes[range[0]..range[1]]);
+            while let Some(packet) = parser.pop()? {
+                popped.push(packet);
+            }
+        }
+
+        Ok(popped)
     }
 
-    // #[test]
-    // fn test_incomplete_pop() {
-    //     let mut parser = PacketParser::new();
-
-    //     parser.push(b"upda");
-    //     assert_eq!(parser.pop(), None);
-
-    //     parser.push(b"te\n");
-    //     assert_eq!(parser.pop(), Some(b"update".as_slice().into()));
-    // }
-
-    // #[test]
-    // fn test_merged_and_sliced_packet() {
-    //     let mut parser = PacketParser::new();
-        
-    //     let packets1 = b"update\nupdate";
-    //     let packets2 = b"\nremove\ninit 3 2 5 ";
-    //     let packets3 = b"6\nhello!\n\n\n \nupdate\nstart localh";
-    //     let packets4 = b"ost:8080\nremove\n";
-    //     parser.push(packets1);
-    //     parser.push(packets2);
-    //     parser.push(packets3);
-    //     parser.push(packets4);
-    //     assert_eq!(
-    //         parser.len(), 
-    //         packets1.iter().filter(|&&x| x == b'\n').count() + 
-    //         packets2.iter().filter(|&&x| x == b'\n').count() + 
-    //         packets3.iter().filter(|&&x| x == b'\n').count() + 
-    //         packets4.iter().filter(|&&x| x == b'\n').count()
-    //     );
-    // }
+    proptest! {
+        #[test]
+        fn split_stream_yields_same_packets(
+            packets in prop::collection::vec(packet(), 0..20),
+            cuts in prop::collection::vec(any::<usize>(), 0..40),
+        ) {
+            let bytes = packets.iter()
+                .flat_map(|packet| packet.as_bytes())
+                .collect::<Vec<u8>>();
+
+            let mut parser = PacketParser::new();
+            let popped = push_split(&mut parser, &bytes, &cuts).unwrap();
+
+            prop_assert_eq!(popped, packets);
+            prop_assert!(parser.is_empty());
+        }
+
+        #[test]
+        fn random_bytes_never_panic(
+            bytes in prop::collection::vec(any::<u8>(), 0..2000),
+            cuts in prop::collection::vec(any::<usize>(), 0..40),
+            max_size in 0usize..2000,
+        ) {
+            let mut parser = PacketParser::with_max_size(max_size);
+
+            if let Ok(popped) = push_split(&mut parser, &bytes, &cuts) {
+                let consumed = popped.iter()
+                    .map(|packet| packet.as_bytes().len())
+                    .sum::<usize>();
+                prop_assert_eq!(consumed + parser.len(), bytes.len());
+                prop_assert!(popped.iter().all(|packet| packet.as_bytes().len() <= parser.max_size()));
+            }
+        }
+
+        #[test]
+        fn invalid_header_after_valid_packets(
+            packets in prop::collection::vec(packet(), 0..10),
+            bad_size in 0..MIN_PACKET_SIZE as PacketSize,
+            cuts in prop::collection::vec(any::<usize>(), 0..20),
+        ) {
+            let mut bytes = packets.iter()
+                .flat_map(|packet| packet.as_bytes())
+                .collect::<Vec<u8>>();
+            bytes.extend_from_slice(&bad_size.to_ne_bytes());
+
+            let mut parser = PacketParser::new();
+            let result = push_split(&mut parser, &bytes, &cuts);
+
+            prop_assert_eq!(result, Err(ParseError::TooSmall(bad_size as usize)));
+        }
+    }
 }
@@ -0,0 +1,176 @@
+use std::fmt;
+use std::str::FromStr;
+
+
+const ORTHOGONAL: [(i32, i32); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+const DIAGONAL: [(i32, i32); 4] = [(1, 1), (-1, 1), (-1, -1), (1, -1)];
+const ALL_DIRECTIONS: [(i32, i32); 8] = [
+    (1, 0), (0, 1), (-1, 0), (0, -1),
+    (1, 1), (-1, 1), (-1, -1), (1, -1),
+];
+const KNIGHT_JUMPS: [(i32, i32); 8] = [
+    (1, 2), (2, 1), (2, -1), (1, -2),
+    (-1, -2), (-2, -1), (-2, 1), (-1, 2),
+];
+
+
+/// 플레이어의 말 종류. 종류마다 이동 규칙이 다르다.
+/// `ObjectInfo`에 그대로 담기므로 `PacketType`처럼 `u8` 하나로 표현한다.
+///
+/// | 말       | 이동                                  |
+/// |----------|---------------------------------------|
+/// | `PAWN`   | 상하좌우 한칸                          |
+/// | `KNIGHT` | L자로 점프 (중간 칸은 막혀도 됨)        |
+/// | `BISHOP` | 대각선으로 여러칸 (중간 칸이 비어야 함)  |
+/// | `ROOK`   | 상하좌우로 여러칸 (중간 칸이 비어야 함)  |
+/// | `QUEEN`  | `BISHOP` + `ROOK`                     |
+/// | `KING`   | 8방향 한칸                             |
+#[repr(transparent)]
+#[derive(Debug, Default, PartialEq, Eq, Hash, Copy, Clone, bytemuck::Zeroable, bytemuck::Pod)]
+pub struct PieceClass(u8);
+
+impl PieceClass {
+    pub const PAWN: Self = Self(0);
+    pub const KNIGHT: Self = Self(1);
+    pub const BISHOP: Self = Self(2);
+    pub const ROOK: Self = Self(3);
+    pub const QUEEN: Self = Self(4);
+    pub const KING: Self = Self(5);
+
+    pub const ALL: [Self; 6] = [
+        Self::PAWN, Self::KNIGHT, Self::BISHOP,
+        Self::ROOK, Self::QUEEN, Self::KING,
+    ];
+
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+
+    pub fn from_u8(value: u8) -> Option<Self> {
+        let class = Self(value);
+        class.is_known().then_some(class)
+    }
+
+    pub fn is_known(&self) -> bool {
+        Self::ALL.contains(self)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Self::PAWN => "pawn",
+            Self::KNIGHT => "knight",
+            Self::BISHOP => "bishop",
+            Self::ROOK => "rook",
+            Self::QUEEN => "queen",
+            Self::KING => "king",
+            _ => "unknown",
+        }
+    }
+
+    /// 여러칸을 미끄러지듯 이동하는 말 (중간 칸이 비어있어야 함)
+    pub fn slides(&self) -> bool {
+        matches!(*self, Self::BISHOP | Self::ROOK | Self::QUEEN)
+    }
+
+    /// 한번에 이동할 수 있는 방향.
+    /// `slides`인 말은 이 방향으로 여러칸, 아니면 정확히 이 만큼 이동한다.
+    pub fn directions(&self) -> &'static [(i32, i32)] {
+        match *self {
+            Self::KNIGHT => &KNIGHT_JUMPS,
+            Self::BISHOP => &DIAGONAL,
+            Self::ROOK => &ORTHOGONAL,
+            Self::QUEEN | Self::KING => &ALL_DIRECTIONS,
+            Self::PAWN => &ORTHOGONAL,
+            _ => &[],
+        }
+    }
+
+    /// `(x, y)`만큼의 이동이 이 말의 규칙에 맞는지. 막힌 칸은 확인하지 않는다.
+    /// 맞으면 한칸씩의 방향과 칸 수를 돌려준다. (`slides`가 아니면 칸 수는 1)
+    pub fn step(&self, x: i32, y: i32) -> Option<((i32, i32), i32)> {
+        if self.slides() {
+            let distance = x.checked_abs()?.max(y.checked_abs()?);
+            if distance == 0 {
+                return None;
+            }
+
+            let direction = (x / distance, y / distance);
+            let exact = direction.0 * distance == x && direction.1 * distance == y;
+
+            (exact && self.directions().contains(&direction)).then_some((direction, distance))
+        } else {
+            self.directions().contains(&(x, y)).then_some(((x, y), 1))
+        }
+    }
+
+    pub fn is_valid_move(&self, x: i32, y: i32) -> bool {
+        self.step(x, y).is_some()
+    }
+}
+
+impl fmt::Display for PieceClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for PieceClass {
+    type Err = String;
+
+    /// 이름 (대소문자 구분 안함)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.into_iter()
+            .find(|class| class.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("Unknown piece '{}'", s))
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_moves() {
+        assert!(PieceClass::PAWN.is_valid_move(0, -1));
+        assert!(!PieceClass::PAWN.is_valid_move(1, 1));
+        assert!(!PieceClass::PAWN.is_valid_move(2, 0));
+
+        assert!(PieceClass::KNIGHT.is_valid_move(2, -1));
+        assert!(PieceClass::KNIGHT.is_valid_move(-1, 2));
+        assert!(!PieceClass::KNIGHT.is_valid_move(2, 2));
+        assert!(!PieceClass::KNIGHT.is_valid_move(1, 0));
+
+        assert_eq!(PieceClass::BISHOP.step(-3, 3), Some(((-1, 1), 3)));
+        assert_eq!(PieceClass::BISHOP.step(3, 2), None);
+        assert_eq!(PieceClass::BISHOP.step(1, 0), None);
+
+        assert_eq!(PieceClass::ROOK.step(0, -5), Some(((0, -1), 5)));
+        assert_eq!(PieceClass::ROOK.step(2, 2), None);
+
+        assert_eq!(PieceClass::QUEEN.step(4, 4), Some(((1, 1), 4)));
+        assert_eq!(PieceClass::QUEEN.step(0, 7), Some(((0, 1), 7)));
+        assert_eq!(PieceClass::QUEEN.step(1, 2), None);
+
+        assert!(PieceClass::KING.is_valid_move(-1, -1));
+        assert!(!PieceClass::KING.is_valid_move(0, 2));
+
+        for class in PieceClass::ALL {
+            assert!(!class.is_valid_move(0, 0));
+            assert!(!class.is_valid_move(i32::MIN, 0));
+        }
+    }
+
+    #[test]
+    fn test_names() {
+        for class in PieceClass::ALL {
+            assert_eq!(class.name().parse::<PieceClass>(), Ok(class));
+            assert_eq!(PieceClass::from_u8(class.value()), Some(class));
+        }
+
+        assert_eq!("Knight".parse::<PieceClass>(), Ok(PieceClass::KNIGHT));
+        assert!("dragon".parse::<PieceClass>().is_err());
+        assert_eq!(PieceClass::from_u8(6), None);
+    }
+}
@@ -0,0 +1,92 @@
+use std::fmt;
+use std::str::FromStr;
+
+
+/// 플레이어(슬롯) 식별자.
+/// 슬롯 번호(`index`)와 그 슬롯이 재사용된 횟수(`generation`)로 구성되어서,
+/// 슬롯이 재사용되더라도 이전 플레이어의 id와 구분된다.
+///
+/// 통신시에는 `u32` 하나로 전송된다. (하위 16비트: `index`, 상위 16비트: `generation`)
+#[repr(transparent)]
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone, bytemuck::Zeroable, bytemuck::Pod)]
+pub struct PlayerId(u32);
+
+impl PlayerId {
+    /// NPC의 id에만 사용하는 generation. 클라이언트의 슬롯은 이 generation을 건너뛴다.
+    pub const NPC_GENERATION: u16 = u16::MAX;
+
+    pub const fn new(index: u16, generation: u16) -> Self {
+        Self((generation as u32) << 16 | index as u32)
+    }
+
+    /// `index`번째 NPC의 id
+    pub const fn npc(index: u16) -> Self {
+        Self::new(index, Self::NPC_GENERATION)
+    }
+
+    pub const fn is_npc(self) -> bool {
+        self.generation() == Self::NPC_GENERATION
+    }
+
+    pub const fn index(self) -> u16 {
+        self.0 as u16
+    }
+
+    pub const fn generation(self) -> u16 {
+        (self.0 >> 16) as u16
+    }
+
+    pub const fn to_bits(self) -> u32 {
+        self.0
+    }
+
+    pub const fn from_bits(bits: u32) -> Self {
+        Self(bits)
+    }
+}
+
+impl fmt::Display for PlayerId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}#{}", self.index(), self.generation())
+    }
+}
+
+impl FromStr for PlayerId {
+    type Err = String;
+
+    /// `Display`와 같은 `index#generation` 형식
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid player id '{}'", s);
+
+        let (index, generation) = s.split_once('#').ok_or_else(invalid)?;
+        let index = index.parse().map_err(|_| invalid())?;
+        let generation = generation.parse().map_err(|_| invalid())?;
+
+        Ok(Self::new(index, generation))
+    }
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_generation() {
+        let id = PlayerId::new(9999, 3);
+        assert_eq!(id.index(), 9999);
+        assert_eq!(id.generation(), 3);
+        assert_eq!(PlayerId::from_bits(id.to_bits()), id);
+
+        assert_ne!(PlayerId::new(1, 0), PlayerId::new(1, 1));
+        assert_eq!(PlayerId::new(u16::MAX, u16::MAX).to_bits(), u32::MAX);
+        assert_eq!(id.to_string(), "9999#3");
+        assert_eq!("9999#3".parse(), Ok(id));
+        assert!(!id.is_npc());
+        assert!(PlayerId::npc(3).is_npc());
+        assert!("9999".parse::<PlayerId>().is_err());
+        assert!("1#70000".parse::<PlayerId>().is_err());
+    }
+}
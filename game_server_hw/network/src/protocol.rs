@@ -1,4 +1,5 @@
 use std::mem::size_of;
+use bytes::Bytes;
 
 
 #[repr(C, packed)]
@@ -7,6 +8,67 @@ pub struct PacketType(u8);
 impl PacketType {
     pub const RAW: Self = Self(0);
     pub const MESSAGE: Self = Self(1);
+
+    pub const INIT: Self = Self(2);
+    pub const PING: Self = Self(3);
+    pub const PONG: Self = Self(4);
+    pub const MOVE: Self = Self(5);
+    pub const UPDATE_REQUEST: Self = Self(6);
+    pub const UPDATE_SNAPSHOT: Self = Self(7);
+    pub const ENTER_VIEW: Self = Self(8);
+    pub const LEAVE_VIEW: Self = Self(9);
+    pub const MOVE_REJECTED: Self = Self(10);
+    pub const ERROR: Self = Self(11);
+    pub const CONNECT_REJECTED: Self = Self(12);
+    pub const QUEUE_POSITION: Self = Self(13);
+    pub const MAP: Self = Self(14);
+    pub const SELECT_CLASS: Self = Self(15);
+    pub const MATCH_REQUEST: Self = Self(16);
+    pub const MATCH_STARTED: Self = Self(17);
+    pub const CHESS_MOVE: Self = Self(18);
+    pub const CHESS_BOARD: Self = Self(19);
+    pub const CHESS_REJECTED: Self = Self(20);
+    pub const RESIGN: Self = Self(21);
+    pub const DRAW_OFFER: Self = Self(22);
+    pub const GAME_OVER: Self = Self(23);
+    pub const PGN: Self = Self(24);
+    pub const CHAT_SEND: Self = Self(25);
+    pub const CHAT: Self = Self(26);
+    pub const CHAT_REJECTED: Self = Self(27);
+    pub const ATTACK: Self = Self(28);
+    pub const ATTACK_REJECTED: Self = Self(29);
+    pub const DAMAGE: Self = Self(30);
+    pub const DEATH: Self = Self(31);
+    pub const RESPAWN: Self = Self(32);
+    pub const LOGIN: Self = Self(33);
+    pub const REGISTER: Self = Self(34);
+    pub const LOGIN_REJECTED: Self = Self(35);
+    pub const SHUTDOWN: Self = Self(36);
+    pub const NOTICE: Self = Self(37);
+
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+
+    pub fn is_known(&self) -> bool {
+        matches!(*self, 
+            Self::RAW | Self::MESSAGE |
+            Self::INIT | Self::PING | Self::PONG | Self::MOVE |
+            Self::UPDATE_REQUEST | Self::UPDATE_SNAPSHOT |
+            Self::ENTER_VIEW | Self::LEAVE_VIEW |
+            Self::MOVE_REJECTED | Self::ERROR |
+            Self::CONNECT_REJECTED | Self::QUEUE_POSITION |
+            Self::MAP | Self::SELECT_CLASS |
+            Self::MATCH_REQUEST | Self::MATCH_STARTED |
+            Self::CHESS_MOVE | Self::CHESS_BOARD | Self::CHESS_REJECTED |
+            Self::RESIGN | Self::DRAW_OFFER | Self::GAME_OVER | Self::PGN |
+            Self::CHAT_SEND | Self::CHAT | Self::CHAT_REJECTED |
+            Self::ATTACK | Self::ATTACK_REJECTED |
+            Self::DAMAGE | Self::DEATH | Self::RESPAWN |
+            Self::LOGIN | Self::REGISTER | Self::LOGIN_REJECTED |
+            Self::SHUTDOWN | Self::NOTICE
+        )
+    }
 }
 
 pub type PacketSize = u16;
@@ -19,11 +81,22 @@ pub struct PacketHeader {
     packet_type: PacketType,
 }
 
+impl PacketHeader {
+    pub fn size(&self) -> usize {
+        self.size as usize
+    }
+
+    pub fn packet_type(&self) -> PacketType {
+        self.packet_type
+    }
+}
+
 
-#[derive(Debug, PartialEq)]
+/// `data`는 `Bytes`이므로 clone해도 복사가 일어나지 않는다.
+#[derive(Debug, PartialEq, Clone)]
 pub struct RawPacket {
     header: PacketHeader,
-    data: Vec<u8>,
+    data: Bytes,
 }
 
 impl RawPacket {
@@ -35,10 +108,26 @@ impl RawPacket {
                 size,
                 packet_type,
             },
-            data: data.to_vec(),
+            data: Bytes::copy_from_slice(data),
         }
     }
 
+    /// 헤더 검증은 호출하는 쪽에서 해야 함
+    pub(crate) fn from_parts(header: PacketHeader, data: Bytes) -> Self {
+        Self {
+            header,
+            data,
+        }
+    }
+
+    pub fn header(&self) -> PacketHeader {
+        self.header
+    }
+
+    pub fn packet_type(&self) -> PacketType {
+        self.header.packet_type
+    }
+
     pub fn data(&self) -> &[u8] {
         &self.data
     }
@@ -60,7 +149,7 @@ impl RawPacket {
             return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "Invalid data"));
         }
 
-        let data = data[size_of::<PacketHeader>()..header.size as usize].to_vec();
+        let data = Bytes::copy_from_slice(&data[size_of::<PacketHeader>()..header.size as usize]);
 
         Ok(Self {
             header,
@@ -89,7 +178,7 @@ impl MessagePacket {
             return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "Invalid data"));
         }
         
-        let time = *bytemuck::from_bytes::<u128>(&raw.data()[0..size_of::<u128>()]);
+        let time = bytemuck::pod_read_unaligned::<u128>(&raw.data()[0..size_of::<u128>()]);
         let msg = String::from_utf8_lossy(&raw.data()[size_of::<u128>()..]);
 
         Ok(Self::new(time, &msg))
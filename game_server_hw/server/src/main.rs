@@ -1,16 +1,59 @@
+use std::process::ExitCode;
+use clap::Parser;
+use config::ServerArgs;
 use server::server;
-use get_addr::get_addr;
 
 
 #[tokio::main]
-async fn main() {
-    let (ip, port) = match get_addr() {
-        Ok((ip, port)) => (ip, port),
+async fn main() -> ExitCode {
+    let config = match ServerArgs::parse().load() {
+        Ok(config) => config,
         Err(e) => {
             eprintln!("{}", e);
-            return;
+            return ExitCode::FAILURE;
         }
     };
 
-    server::tcp_server::run_server(&ip, port).await;
+    let addrs = match config.server.socket_addrs() {
+        Ok(addrs) => addrs,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if let Err(e) = server::tcp_server::run_server(&addrs, config, shutdown_signal()).await {
+        eprintln!("Failed to run server: {}", e);
+        return ExitCode::FAILURE;
+    }
+
+    ExitCode::SUCCESS
+}
+
+
+/// Ctrl-C(SIGINT)나 SIGTERM을 받을 때까지 대기
+async fn shutdown_signal() {
+    // 신호를 받을 수 없으면 종료하지 않음
+    let interrupt = async {
+        if tokio::signal::ctrl_c().await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            },
+            Err(_) => std::future::pending::<()>().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = interrupt => {},
+        _ = terminate => {},
+    }
 }
@@ -0,0 +1,297 @@
+//! 계정 가입, 로그인과 로그아웃할 때의 플레이어 기록
+
+mod password;
+mod store;
+mod writer;
+
+pub use password::*;
+pub use store::*;
+pub use writer::*;
+
+
+use std::{
+    collections::HashSet,
+    fmt,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard},
+};
+use argon2::password_hash;
+use network::LoginRejectReason;
+use config::AccountConfig;
+
+use super::world::PlayerState;
+
+
+/// 이름의 최대 글자 수
+pub const MAX_NAME_LEN: usize = 16;
+/// 이름의 최소 글자 수
+pub const MIN_NAME_LEN: usize = 3;
+/// 비밀번호의 최대 글자 수. 너무 긴 비밀번호를 해시하지 않도록
+pub const MAX_PASSWORD_LEN: usize = 128;
+
+
+/// 가입, 로그인에 실패한 이유
+#[derive(Debug)]
+pub enum AccountError {
+    Open { path: PathBuf, source: rusqlite::Error },
+    Database(rusqlite::Error),
+    Hash(password_hash::Error),
+    /// 클라이언트의 요청이 잘못됨
+    Rejected(LoginRejectReason),
+}
+
+impl AccountError {
+    /// 클라이언트에게 보낼 거부 이유
+    pub fn reason(&self) -> LoginRejectReason {
+        match self {
+            Self::Rejected(reason) => *reason,
+            _ => LoginRejectReason::Unavailable,
+        }
+    }
+}
+
+impl fmt::Display for AccountError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Open { path, source } => write!(f, "Failed to open account database {}: {}", path.display(), source),
+            Self::Database(source) => write!(f, "Account database error: {}", source),
+            Self::Hash(source) => write!(f, "Failed to hash password: {}", source),
+            Self::Rejected(reason) => write!(f, "Login rejected: {:?}", reason),
+        }
+    }
+}
+
+impl std::error::Error for AccountError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Open { source, .. } | Self::Database(source) => Some(source),
+            Self::Hash(_) | Self::Rejected(_) => None,
+        }
+    }
+}
+
+impl From<rusqlite::Error> for AccountError {
+    fn from(source: rusqlite::Error) -> Self {
+        Self::Database(source)
+    }
+}
+
+impl From<password_hash::Error> for AccountError {
+    fn from(source: password_hash::Error) -> Self {
+        Self::Hash(source)
+    }
+}
+
+
+/// 가입할 수 있는 이름. 영문, 숫자, `_`만 사용
+pub fn is_valid_name(name: &str) -> bool {
+    (MIN_NAME_LEN..=MAX_NAME_LEN).contains(&name.len())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+
+/// 클라이언트 태스크들이 같이 쓰는 계정 저장소 핸들.
+/// 해시와 데이터베이스 작업은 blocking 스레드에서 한다.
+#[derive(Clone)]
+pub struct Accounts {
+    store: Arc<Mutex<AccountStore>>,
+    /// 접속 중인 계정 id
+    online: Arc<Mutex<HashSet<i64>>>,
+    min_password_len: usize,
+}
+
+impl Accounts {
+    pub fn open(path: &Path, config: &AccountConfig) -> Result<Self, AccountError> {
+        let store = AccountStore::open(path)
+            .map_err(|source| AccountError::Open { path: path.to_path_buf(), source })?;
+
+        Ok(Self::with_store(store, config))
+    }
+
+    pub fn with_store(store: AccountStore, config: &AccountConfig) -> Self {
+        Self {
+            store: Arc::new(Mutex::new(store)),
+            online: Arc::default(),
+            min_password_len: config.min_password_len,
+        }
+    }
+
+    /// 새 계정을 만들고 바로 로그인한다.
+    pub async fn register(&self, name: String, password: String) -> Result<Session, AccountError> {
+        if !is_valid_name(&name) {
+            return Err(AccountError::Rejected(LoginRejectReason::InvalidName));
+        }
+        if !(self.min_password_len..=MAX_PASSWORD_LEN).contains(&password.chars().count()) {
+            return Err(AccountError::Rejected(LoginRejectReason::InvalidPassword));
+        }
+
+        let store = self.store.clone();
+        let (id, name) = blocking(move || {
+            let hash = hash_password(&password)?;
+            lock(&store).create(&name, &hash)?
+                .map(|id| (id, name))
+                .ok_or(AccountError::Rejected(LoginRejectReason::NameTaken))
+        }).await?;
+
+        self.start_session(id, name, None)
+    }
+
+    /// 이름이 없거나 비밀번호가 틀리면 같은 이유로 거부한다.
+    pub async fn login(&self, name: String, password: String) -> Result<Session, AccountError> {
+        let rejected = AccountError::Rejected(LoginRejectReason::InvalidCredentials);
+        if !is_valid_name(&name) || password.chars().count() > MAX_PASSWORD_LEN {
+            return Err(rejected);
+        }
+
+        let store = self.store.clone();
+        let account = blocking(move || {
+            let account = lock(&store).find(&name)?;
+
+            // 응답 시간으로 이름이 있는지 알 수 없도록 없는 이름도 해시를 확인
+            let Some(account) = account else {
+                verify_dummy_password(&password);
+                return Ok(None);
+            };
+            Ok(Some(account).filter(|account| verify_password(&password, &account.password_hash)))
+        }).await?;
+
+        let account = account.ok_or(rejected)?;
+        self.start_session(account.id, account.name, account.state)
+    }
+
+    /// 로그아웃할 때의 플레이어 상태를 저장한다.
+    pub async fn save(&self, account: i64, state: PlayerState) -> Result<(), AccountError> {
+        let store = self.store.clone();
+
+        blocking(move || Ok(lock(&store).save_state(account, &state)?)).await
+    }
+
+    /// 여러 계정의 상태를 한 트랜잭션으로 저장한다. (`PlayerWriter`)
+    pub async fn save_all(&self, states: Vec<(i64, PlayerState)>) -> Result<(), AccountError> {
+        let store = self.store.clone();
+
+        blocking(move || Ok(lock(&store).save_states(&states)?)).await
+    }
+
+    fn start_session(&self, id: i64, name: String, state: Option<PlayerState>) -> Result<Session, AccountError> {
+        if !lock(&self.online).insert(id) {
+            return Err(AccountError::Rejected(LoginRejectReason::AlreadyOnline));
+        }
+
+        Ok(Session { id, name, state, online: self.online.clone() })
+    }
+}
+
+
+/// 로그인한 계정. 삭제되면 접속 중 표시를 지운다.
+#[derive(Debug)]
+pub struct Session {
+    id: i64,
+    name: String,
+    state: Option<PlayerState>,
+    online: Arc<Mutex<HashSet<i64>>>,
+}
+
+impl Session {
+    /// 계정 id
+    pub fn account(&self) -> i64 {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// 마지막으로 저장된 플레이어 상태. 새 계정이면 `None`
+    pub fn state(&self) -> Option<PlayerState> {
+        self.state
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        lock(&self.online).remove(&self.id);
+    }
+}
+
+
+async fn blocking<T, F>(f: F) -> Result<T, AccountError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, AccountError> + Send + 'static,
+{
+    tokio::task::spawn_blocking(f).await
+        .unwrap_or_else(|error| std::panic::resume_unwind(error.into_panic()))
+}
+
+/// 다른 태스크가 패닉해도 저장소는 계속 쓴다.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|error| error.into_inner())
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use network::PieceClass;
+
+    fn accounts() -> Accounts {
+        let config = AccountConfig { min_password_len: 4, ..Default::default() };
+        Accounts::with_store(AccountStore::open_in_memory().unwrap(), &config)
+    }
+
+    fn reason(result: Result<Session, AccountError>) -> LoginRejectReason {
+        result.unwrap_err().reason()
+    }
+
+    #[test]
+    fn test_valid_name() {
+        assert!(is_valid_name("alice_01"));
+        assert!(!is_valid_name("al"));
+        assert!(!is_valid_name("alice bob"));
+        assert!(!is_valid_name("앨리스앨리스"));
+        assert!(!is_valid_name(&"a".repeat(MAX_NAME_LEN + 1)));
+    }
+
+    #[tokio::test]
+    async fn test_register_and_login() {
+        let accounts = accounts();
+        let login = |name: &str, password: &str| accounts.login(name.to_string(), password.to_string());
+        let register = |name: &str, password: &str| accounts.register(name.to_string(), password.to_string());
+
+        assert_eq!(reason(register("a", "pass").await), LoginRejectReason::InvalidName);
+        assert_eq!(reason(register("alice", "abc").await), LoginRejectReason::InvalidPassword);
+        assert_eq!(reason(login("alice", "pass").await), LoginRejectReason::InvalidCredentials);
+
+        let session = register("Alice", "pass").await.unwrap();
+        assert_eq!((session.name(), session.state()), ("Alice", None));
+        assert_eq!(reason(register("alice", "word").await), LoginRejectReason::NameTaken);
+        assert_eq!(reason(login("alice", "pass").await), LoginRejectReason::AlreadyOnline);
+
+        let state = PlayerState { position: Some((2, 5)), class: PieceClass::KNIGHT, hp: 70 };
+        accounts.save(session.account(), state).await.unwrap();
+        drop(session);
+
+        assert_eq!(reason(login("alice", "word").await), LoginRejectReason::InvalidCredentials);
+        let session = login("ALICE", "pass").await.unwrap();
+        assert_eq!((session.name(), session.state()), ("Alice", Some(state)));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_name_verifies_dummy() {
+        let accounts = accounts();
+        accounts.register("alice".to_string(), "pass".to_string()).await.unwrap();
+
+        // 다른 테스트와 겹치지 않는 비밀번호로 구분
+        let verified = |password: &str| DUMMY_VERIFIED.lock().unwrap().iter().any(|p| p == password);
+
+        // 응답 시간이 같도록 없는 이름도 더미 해시로 비밀번호를 확인
+        assert_eq!(reason(accounts.login("bob".to_string(), "unknown name".to_string()).await), LoginRejectReason::InvalidCredentials);
+        assert!(verified("unknown name"));
+
+        // 있는 이름은 계정의 해시로 확인
+        assert_eq!(reason(accounts.login("alice".to_string(), "known name".to_string()).await), LoginRejectReason::InvalidCredentials);
+        assert!(!verified("known name"));
+    }
+}
@@ -0,0 +1,63 @@
+use argon2::{
+    password_hash::{self, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use std::sync::OnceLock;
+use rand::RngCore;
+
+
+/// 계정마다 다른 salt로 비밀번호를 해시한다. (PHC 문자열, argon2id)
+pub fn hash_password(password: &str) -> Result<String, password_hash::Error> {
+    let mut salt = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut salt);
+    let salt = SaltString::encode_b64(&salt)?;
+
+    let hash = Argon2::default().hash_password(password.as_bytes(), &salt)?;
+    Ok(hash.to_string())
+}
+
+/// 해시를 해석할 수 없으면 틀린 비밀번호로 취급한다.
+pub fn verify_password(password: &str, hash: &str) -> bool {
+    PasswordHash::new(hash)
+        .is_ok_and(|hash| Argon2::default().verify_password(password.as_bytes(), &hash).is_ok())
+}
+
+
+/// 테스트에서 `verify_dummy_password`로 확인한 비밀번호
+#[cfg(test)]
+pub(super) static DUMMY_VERIFIED: std::sync::Mutex<Vec<String>> = std::sync::Mutex::new(Vec::new());
+
+/// 없는 계정으로 로그인할 때도 같은 시간이 걸리도록 대신 확인하는 해시.
+/// 처음 사용할 때 `hash_password`와 같은 설정으로 만든다.
+pub fn verify_dummy_password(password: &str) {
+    static DUMMY_HASH: OnceLock<String> = OnceLock::new();
+
+    #[cfg(test)]
+    DUMMY_VERIFIED.lock().unwrap().push(password.to_string());
+
+    let hash = DUMMY_HASH.get_or_init(|| {
+        hash_password("dummy password").expect("hashing a fixed password never fails")
+    });
+    let _ = verify_password(password, hash);
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_and_verify() {
+        let hash = hash_password("correct horse").unwrap();
+        assert!(hash.starts_with("$argon2id$"));
+        assert!(!hash.contains("correct horse"));
+
+        assert!(verify_password("correct horse", &hash));
+        assert!(!verify_password("wrong horse", &hash));
+        assert!(!verify_password("correct horse", "not a hash"));
+
+        // 같은 비밀번호라도 salt가 다름
+        assert_ne!(hash_password("correct horse").unwrap(), hash);
+    }
+}
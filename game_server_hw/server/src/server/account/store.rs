@@ -0,0 +1,144 @@
+use std::path::Path;
+use rusqlite::{params, Connection, ErrorCode, OptionalExtension};
+use network::PieceClass;
+
+use crate::server::world::PlayerState;
+
+
+/// 데이터베이스에 저장된 계정
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Account {
+    pub id: i64,
+    /// 가입할 때 쓴 대소문자 그대로
+    pub name: String,
+    pub password_hash: String,
+    /// 한번도 로그아웃하지 않았으면 `None`
+    pub state: Option<PlayerState>,
+}
+
+
+/// 계정과 플레이어 기록을 저장하는 SQLite 데이터베이스.
+/// 이름은 대소문자를 구분하지 않는다.
+pub struct AccountStore {
+    connection: Connection,
+}
+
+impl AccountStore {
+    /// 파일이 없으면 만든다.
+    pub fn open(path: &Path) -> rusqlite::Result<Self> {
+        Self::init(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> rusqlite::Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(connection: Connection) -> rusqlite::Result<Self> {
+        connection.execute_batch("
+            CREATE TABLE IF NOT EXISTS accounts (
+                id            INTEGER PRIMARY KEY,
+                name          TEXT NOT NULL UNIQUE COLLATE NOCASE,
+                password_hash TEXT NOT NULL,
+                x             INTEGER,
+                y             INTEGER,
+                class         INTEGER,
+                hp            INTEGER
+            );
+        ")?;
+
+        Ok(Self { connection })
+    }
+
+    /// 새 계정의 id. 이미 있는 이름이면 `None`
+    pub fn create(&self, name: &str, password_hash: &str) -> rusqlite::Result<Option<i64>> {
+        let result = self.connection.execute(
+            "INSERT INTO accounts (name, password_hash) VALUES (?1, ?2)",
+            params![name, password_hash],
+        );
+
+        match result {
+            Ok(_) => Ok(Some(self.connection.last_insert_rowid())),
+            Err(rusqlite::Error::SqliteFailure(error, _)) if error.code == ErrorCode::ConstraintViolation => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    pub fn find(&self, name: &str) -> rusqlite::Result<Option<Account>> {
+        self.connection.query_row(
+            "SELECT id, name, password_hash, x, y, class, hp FROM accounts WHERE name = ?1",
+            params![name],
+            |row| {
+                let position = match (row.get::<_, Option<i32>>(3)?, row.get::<_, Option<i32>>(4)?) {
+                    (Some(x), Some(y)) => Some((x, y)),
+                    _ => None,
+                };
+                let class = row.get::<_, Option<u8>>(5)?.and_then(PieceClass::from_u8);
+                let hp = row.get::<_, Option<u16>>(6)?;
+                let state = class.zip(hp).map(|(class, hp)| PlayerState { position, class, hp });
+
+                Ok(Account { id: row.get(0)?, name: row.get(1)?, password_hash: row.get(2)?, state })
+            },
+        ).optional()
+    }
+
+    pub fn save_state(&self, id: i64, state: &PlayerState) -> rusqlite::Result<()> {
+        let (x, y) = state.position.unzip();
+        self.connection.execute(
+            "UPDATE accounts SET x = ?2, y = ?3, class = ?4, hp = ?5 WHERE id = ?1",
+            params![id, x, y, state.class.value(), state.hp],
+        )?;
+
+        Ok(())
+    }
+
+    /// 여러 계정의 상태를 한 트랜잭션으로 저장한다. 실패하면 하나도 저장되지 않는다.
+    pub fn save_states(&mut self, states: &[(i64, PlayerState)]) -> rusqlite::Result<()> {
+        let transaction = self.connection.transaction()?;
+        {
+            let mut statement = transaction.prepare_cached(
+                "UPDATE accounts SET x = ?2, y = ?3, class = ?4, hp = ?5 WHERE id = ?1",
+            )?;
+            for (id, state) in states {
+                let (x, y) = state.position.unzip();
+                statement.execute(params![id, x, y, state.class.value(), state.hp])?;
+            }
+        }
+
+        transaction.commit()
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accounts() {
+        let store = AccountStore::open_in_memory().unwrap();
+
+        let id = store.create("Alice", "hash").unwrap().unwrap();
+        assert_eq!(store.create("alice", "other").unwrap(), None);
+        assert_ne!(store.create("bob", "hash").unwrap(), Some(id));
+
+        let account = store.find("ALICE").unwrap().unwrap();
+        assert_eq!(account, Account { id, name: "Alice".to_string(), password_hash: "hash".to_string(), state: None });
+        assert_eq!(store.find("carol").unwrap(), None);
+
+        let state = PlayerState { position: Some((3, -1)), class: PieceClass::ROOK, hp: 40 };
+        store.save_state(id, &state).unwrap();
+        assert_eq!(store.find("alice").unwrap().unwrap().state, Some(state));
+
+        let state = PlayerState { position: None, ..state };
+        store.save_state(id, &state).unwrap();
+        assert_eq!(store.find("alice").unwrap().unwrap().state, Some(state));
+
+        let mut store = store;
+        let bob = store.find("bob").unwrap().unwrap().id;
+        let states = [(id, PlayerState { hp: 1, ..state }), (bob, PlayerState { position: Some((1, 1)), ..state })];
+        store.save_states(&states).unwrap();
+        assert_eq!(store.find("alice").unwrap().unwrap().state, Some(states[0].1));
+        assert_eq!(store.find("bob").unwrap().unwrap().state, Some(states[1].1));
+    }
+}
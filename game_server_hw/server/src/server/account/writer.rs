@@ -0,0 +1,295 @@
+use std::{collections::BTreeMap, time::Duration};
+use tokio::{
+    sync::{mpsc, oneshot},
+    time::{interval_at, sleep, Instant, MissedTickBehavior},
+};
+use config::AccountConfig;
+
+use super::{AccountError, Accounts};
+use super::super::world::PlayerState;
+
+
+/// 변경사항 채널의 크기. 가득 차면 월드는 다음 tick에 다시 보낸다.
+const WRITER_CHANNEL_CAPACITY: usize = 256;
+/// 종료할 때 저장에 실패하면 다시 시도하는 횟수
+const SHUTDOWN_RETRIES: u32 = 3;
+const SHUTDOWN_RETRY_DELAY: Duration = Duration::from_millis(200);
+
+
+/// 저장 결과
+pub type FlushResult = Result<(), AccountError>;
+
+
+/// `WriterInterface`가 채널을 통해 `PlayerWriter`로 보내는 요청.
+#[derive(Debug)]
+pub enum WriterCommand {
+    /// 월드에서 바뀐 플레이어들의 (계정 id, 상태). 다음 주기에 저장한다.
+    Update { changes: Vec<(i64, PlayerState)> },
+    /// 로그아웃한 플레이어의 상태. 밀린 변경사항과 함께 바로 저장한다.
+    Save { account: i64, state: PlayerState, reply: oneshot::Sender<FlushResult> },
+    Flush { reply: oneshot::Sender<FlushResult> },
+    Stats { reply: oneshot::Sender<WriterStats> },
+    /// 남은 변경사항을 저장하고 메세지 루프를 끝낸다.
+    Shutdown { reply: oneshot::Sender<FlushResult> },
+}
+
+
+/// `PlayerWriter`의 메세지 루프가 종료되어 요청을 처리할 수 없음
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriterClosed;
+
+impl std::fmt::Display for WriterClosed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Player writer is closed")
+    }
+}
+
+impl std::error::Error for WriterClosed {}
+
+
+/// 저장 대기열과 저장에 걸린 시간
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WriterStats {
+    /// 아직 저장하지 않은 계정 수
+    pub pending: usize,
+    /// 채널에서 처리를 기다리는 요청 수
+    pub queued: usize,
+    /// 성공한 저장(트랜잭션) 수
+    pub flushes: u64,
+    /// 실패한 저장 수. 실패한 변경사항은 다음 주기에 다시 저장한다.
+    pub failures: u64,
+    pub last_flush: Option<Duration>,
+    pub max_flush: Duration,
+}
+
+
+/// 접속 중인 플레이어의 상태를 월드 루프 밖에서 저장한다.
+/// 월드가 보낸 변경사항을 계정마다 마지막 상태만 남기고 모아 두었다가,
+/// `flush_interval_ms`마다 한 트랜잭션으로 저장한다.
+pub struct PlayerWriter {
+    accounts: Accounts,
+    /// 저장할 계정 id와 마지막 상태
+    pending: BTreeMap<i64, PlayerState>,
+    stats: WriterStats,
+
+    sender: mpsc::Sender<WriterCommand>,
+    receiver: mpsc::Receiver<WriterCommand>,
+
+    flush_interval: Duration,
+}
+
+impl PlayerWriter {
+    pub fn new(accounts: Accounts, config: &AccountConfig) -> Self {
+        let (sender, receiver) = mpsc::channel(WRITER_CHANNEL_CAPACITY);
+
+        Self {
+            accounts,
+            pending: BTreeMap::new(),
+            stats: WriterStats::default(),
+            sender,
+            receiver,
+            flush_interval: Duration::from_millis(config.flush_interval_ms.max(1)),
+        }
+    }
+
+    pub fn interface(&self) -> WriterInterface {
+        WriterInterface {
+            sender: self.sender.clone(),
+        }
+    }
+
+    /// `Shutdown`을 받을 때까지 리턴하지 않는다.
+    pub async fn run_message_loop(&mut self) {
+        let mut flush = interval_at(Instant::now() + self.flush_interval, self.flush_interval);
+        flush.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            tokio::select! {
+                command = self.receiver.recv() => match command {
+                    Some(WriterCommand::Shutdown { reply }) => {
+                        let _ = reply.send(self.shutdown().await);
+                        break;
+                    },
+                    Some(command) => self.process_command(command).await,
+                    None => break,
+                },
+
+                _ = flush.tick(), if !self.pending.is_empty() => {
+                    if let Err(error) = self.flush().await {
+                        eprintln!("Failed to save players: {}", error);
+                    }
+                },
+            }
+        }
+    }
+
+    async fn process_command(&mut self, command: WriterCommand) {
+        match command {
+            WriterCommand::Update { changes } => self.pending.extend(changes),
+
+            WriterCommand::Save { account, state, reply } => {
+                self.pending.insert(account, state);
+                let _ = reply.send(self.flush().await);
+            },
+
+            WriterCommand::Flush { reply } => {
+                let _ = reply.send(self.flush().await);
+            },
+
+            WriterCommand::Stats { reply } => {
+                let _ = reply.send(self.stats());
+            },
+
+            // 메세지 루프에서 먼저 처리하므로 종료하는 중에 또 받은 경우
+            WriterCommand::Shutdown { reply } => {
+                let _ = reply.send(self.flush().await);
+            },
+        }
+    }
+
+    fn stats(&self) -> WriterStats {
+        WriterStats {
+            pending: self.pending.len(),
+            queued: self.sender.max_capacity() - self.sender.capacity(),
+            ..self.stats
+        }
+    }
+
+    /// 모아 둔 변경사항을 한 트랜잭션으로 저장한다. 실패하면 다음에 다시 저장하도록 남겨 둔다.
+    async fn flush(&mut self) -> FlushResult {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+
+        let states = self.pending.iter().map(|(&account, &state)| (account, state)).collect();
+        let start = Instant::now();
+        let result = self.accounts.save_all(states).await;
+        let elapsed = start.elapsed();
+
+        match result {
+            Ok(()) => {
+                self.pending.clear();
+                self.stats.flushes += 1;
+                self.stats.last_flush = Some(elapsed);
+                self.stats.max_flush = self.stats.max_flush.max(elapsed);
+            },
+            Err(_) => self.stats.failures += 1,
+        }
+
+        result
+    }
+
+    /// 채널에 남은 변경사항까지 받아서 저장한다. 실패하면 잠시 후 다시 시도한다.
+    async fn shutdown(&mut self) -> FlushResult {
+        while let Ok(command) = self.receiver.try_recv() {
+            self.process_command(command).await;
+        }
+
+        let mut result = self.flush().await;
+        for _ in 0..SHUTDOWN_RETRIES {
+            if result.is_ok() {
+                break;
+            }
+            sleep(SHUTDOWN_RETRY_DELAY).await;
+            result = self.flush().await;
+        }
+
+        result
+    }
+}
+
+
+/// 다른 task에서 `PlayerWriter`에 접근하기 위한 핸들
+#[derive(Debug, Clone)]
+pub struct WriterInterface {
+    sender: mpsc::Sender<WriterCommand>,
+}
+
+impl WriterInterface {
+    /// 기다리지 않고 변경사항을 보낸다. 채널이 가득 찼거나 닫혔으면 `false`
+    pub fn update(&self, changes: Vec<(i64, PlayerState)>) -> bool {
+        self.sender.try_send(WriterCommand::Update { changes }).is_ok()
+    }
+
+    /// 앞서 보낸 변경사항과 함께 `state`가 저장될 때까지 기다린다.
+    pub async fn save(&self, account: i64, state: PlayerState) -> Result<FlushResult, WriterClosed> {
+        let (reply, response) = oneshot::channel();
+        self.sender.send(WriterCommand::Save { account, state, reply }).await.map_err(|_| WriterClosed)?;
+
+        response.await.map_err(|_| WriterClosed)
+    }
+
+    pub async fn flush(&self) -> Result<FlushResult, WriterClosed> {
+        let (reply, response) = oneshot::channel();
+        self.sender.send(WriterCommand::Flush { reply }).await.map_err(|_| WriterClosed)?;
+
+        response.await.map_err(|_| WriterClosed)
+    }
+
+    pub async fn stats(&self) -> Result<WriterStats, WriterClosed> {
+        let (reply, response) = oneshot::channel();
+        self.sender.send(WriterCommand::Stats { reply }).await.map_err(|_| WriterClosed)?;
+
+        response.await.map_err(|_| WriterClosed)
+    }
+
+    /// 남은 변경사항을 모두 저장하고 `PlayerWriter`를 종료한다.
+    pub async fn shutdown(&self) -> Result<FlushResult, WriterClosed> {
+        let (reply, response) = oneshot::channel();
+        self.sender.send(WriterCommand::Shutdown { reply }).await.map_err(|_| WriterClosed)?;
+
+        response.await.map_err(|_| WriterClosed)
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use network::PieceClass;
+    use super::super::AccountStore;
+
+    fn writer() -> (PlayerWriter, Accounts) {
+        let config = AccountConfig { min_password_len: 4, ..Default::default() };
+        let accounts = Accounts::with_store(AccountStore::open_in_memory().unwrap(), &config);
+        (PlayerWriter::new(accounts.clone(), &config), accounts)
+    }
+
+    fn state(x: i32, hp: u16) -> PlayerState {
+        PlayerState { position: Some((x, 0)), class: PieceClass::PAWN, hp }
+    }
+
+    #[tokio::test]
+    async fn test_batch_and_flush() {
+        let (mut writer, accounts) = writer();
+        let alice = accounts.register("alice".to_string(), "pass".to_string()).await.unwrap().account();
+        let bob = accounts.register("bob".to_string(), "pass".to_string()).await.unwrap().account();
+
+        // 같은 계정은 마지막 상태만 저장
+        writer.process_command(WriterCommand::Update { changes: vec![(alice, state(1, 100)), (bob, state(2, 100))] }).await;
+        writer.process_command(WriterCommand::Update { changes: vec![(alice, state(3, 50))] }).await;
+        assert_eq!(writer.stats().pending, 2);
+        assert_eq!(writer.stats().flushes, 0);
+
+        writer.flush().await.unwrap();
+        let stats = writer.stats();
+        assert_eq!((stats.pending, stats.flushes, stats.failures), (0, 1, 0));
+        assert!(stats.last_flush.is_some());
+
+        let session = accounts.login("alice".to_string(), "pass".to_string()).await.unwrap();
+        assert_eq!(session.state(), Some(state(3, 50)));
+        drop(session);
+
+        // 종료할 때 채널에 남은 변경사항까지 저장
+        let interface = writer.interface();
+        let task = tokio::spawn(async move { writer.run_message_loop().await });
+        assert!(interface.update(vec![(bob, state(5, 10))]));
+        interface.shutdown().await.unwrap().unwrap();
+        task.await.unwrap();
+        assert!(matches!(interface.flush().await, Err(WriterClosed)));
+
+        let session = accounts.login("bob".to_string(), "pass".to_string()).await.unwrap();
+        assert_eq!(session.state(), Some(state(5, 10)));
+    }
+}
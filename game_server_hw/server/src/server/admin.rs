@@ -0,0 +1,333 @@
+use std::{
+    io::BufRead,
+    fmt::Write,
+    net::IpAddr,
+    str::FromStr,
+    sync::Arc,
+};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use network::{Message, PlayerId};
+
+use super::{
+    account::WriterInterface,
+    world::{PlayerSummary, WorldInterface},
+    slot::SlotAllocator,
+};
+
+
+/// 표준 입력으로 받는 운영자 명령. 한 줄이 한 명령
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AdminCommand {
+    Help,
+    /// 접속한 연결과 플레이어 목록
+    List,
+    Kick { id: PlayerId },
+    /// 주소를 차단하고 그 주소의 연결을 끊음 (재시작하면 풀림)
+    Ban { ip: IpAddr },
+    Teleport { id: PlayerId, x: i32, y: i32 },
+    /// 모든 클라이언트에게 공지(`Notice`)
+    Broadcast { text: String },
+    Stats,
+    Shutdown,
+}
+
+impl AdminCommand {
+    pub const USAGE: &'static str = "\
+commands:
+  list                      list connections and players
+  kick <id>                 disconnect a player (id: index#generation)
+  ban <ip>                  disconnect and refuse connections from an address
+  teleport <id> <x> <y>     move a player to a cell
+  broadcast <message>       send a notice to every client
+  stats                     show server statistics
+  shutdown                  shut down the server gracefully
+  help                      show this message";
+}
+
+impl FromStr for AdminCommand {
+    type Err = String;
+
+    /// 명령 이름은 대소문자 구분 안함
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (name, rest) = s.split_once(char::is_whitespace).unwrap_or((s, ""));
+        let rest = rest.trim();
+        let args = rest.split_whitespace().collect::<Vec<_>>();
+
+        let expect_args = |count: usize, usage: &str| match args.len() == count {
+            true => Ok(()),
+            false => Err(format!("Usage: {}", usage)),
+        };
+
+        match name.to_ascii_lowercase().as_str() {
+            "help" => expect_args(0, "help").map(|_| Self::Help),
+            "list" => expect_args(0, "list").map(|_| Self::List),
+            "stats" => expect_args(0, "stats").map(|_| Self::Stats),
+            "shutdown" => expect_args(0, "shutdown").map(|_| Self::Shutdown),
+
+            "kick" => {
+                expect_args(1, "kick <id>")?;
+                Ok(Self::Kick { id: args[0].parse()? })
+            },
+            "ban" => {
+                expect_args(1, "ban <ip>")?;
+                let ip = args[0].parse().map_err(|_| format!("Invalid IP address '{}'", args[0]))?;
+                Ok(Self::Ban { ip })
+            },
+            "teleport" => {
+                expect_args(3, "teleport <id> <x> <y>")?;
+                let coordinate = |s: &str| s.parse().map_err(|_| format!("Invalid coordinate '{}'", s));
+                Ok(Self::Teleport { id: args[0].parse()?, x: coordinate(args[1])?, y: coordinate(args[2])? })
+            },
+            "broadcast" => match rest.is_empty() {
+                true => Err("Usage: broadcast <message>".to_string()),
+                false => Ok(Self::Broadcast { text: rest.to_string() }),
+            },
+
+            "" => Err("Empty command".to_string()),
+            name => Err(format!("Unknown command '{}' (try 'help')", name)),
+        }
+    }
+}
+
+
+/// 운영자 명령을 처리한다. 월드를 바꾸는 명령은 클라이언트의 요청처럼 월드의 채널로 보내므로,
+/// 접속 중인 클라이언트와 같은 순서로 처리된다.
+pub struct AdminConsole {
+    world: WorldInterface,
+    writer: Option<WriterInterface>,
+    slots: Arc<SlotAllocator>,
+    /// `shutdown` 명령을 받으면 취소
+    shutdown: CancellationToken,
+}
+
+impl AdminConsole {
+    pub fn new(world: WorldInterface, writer: Option<WriterInterface>, slots: Arc<SlotAllocator>, shutdown: CancellationToken) -> Self {
+        Self { world, writer, slots, shutdown }
+    }
+
+    /// `lines`가 닫히거나(표준 입력의 끝) `shutdown` 명령을 받을 때까지 명령을 처리하고 결과를 출력한다.
+    pub async fn run(&self, mut lines: mpsc::Receiver<String>) {
+        while let Some(line) = lines.recv().await {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            match line.parse::<AdminCommand>() {
+                Ok(command) => {
+                    let shutdown = command == AdminCommand::Shutdown;
+                    println!("{}", self.execute(command).await);
+                    if shutdown {
+                        break;
+                    }
+                },
+                Err(error) => println!("{}", error),
+            }
+        }
+    }
+
+    /// 명령을 실행하고, 출력할 결과를 돌려준다.
+    pub async fn execute(&self, command: AdminCommand) -> String {
+        const WORLD_CLOSED: &str = "World is closed";
+
+        match command {
+            AdminCommand::Help => AdminCommand::USAGE.to_string(),
+
+            AdminCommand::List => match self.world.list().await {
+                Ok(players) => format_list(&players),
+                Err(_) => WORLD_CLOSED.to_string(),
+            },
+
+            AdminCommand::Kick { id } => match self.world.kick(id).await {
+                Ok(true) => format!("Kicked {}", id),
+                Ok(false) => format!("No connection {}", id),
+                Err(_) => WORLD_CLOSED.to_string(),
+            },
+
+            AdminCommand::Ban { ip } => match self.world.ban(ip).await {
+                Ok(kicked) => format!("Banned {} ({} connections kicked)", ip, kicked),
+                Err(_) => WORLD_CLOSED.to_string(),
+            },
+
+            AdminCommand::Teleport { id, x, y } => match self.world.teleport(id, x, y).await {
+                Ok(Ok(_)) => format!("Teleported {} to ({}, {})", id, x, y),
+                Ok(Err(reason)) => format!("Cannot teleport {} to ({}, {}): {:?}", id, x, y, reason),
+                Err(_) => WORLD_CLOSED.to_string(),
+            },
+
+            AdminCommand::Broadcast { text } => {
+                self.world.notify(Message::Notice { text: text.clone() });
+                format!("Broadcast: {}", text)
+            },
+
+            AdminCommand::Stats => self.stats().await,
+
+            AdminCommand::Shutdown => {
+                self.shutdown.cancel();
+                "Shutting down".to_string()
+            },
+        }
+    }
+
+    async fn stats(&self) -> String {
+        let mut text = String::new();
+
+        match self.world.stats().await {
+            Ok(stats) => {
+                let _ = writeln!(text, "ticks: {}", stats.ticks);
+                let _ = writeln!(text, "players: {}, npcs: {}", stats.players, stats.npcs);
+                let _ = writeln!(text, "connections: {} (slots used: {}), banned: {}", stats.connections, self.slots.len(), stats.banned);
+            },
+            Err(_) => {
+                let _ = writeln!(text, "world: closed");
+            },
+        }
+
+        match &self.writer {
+            Some(writer) => match writer.stats().await {
+                Ok(stats) => {
+                    let last = stats.last_flush.map_or("-".to_string(), |last| format!("{:?}", last));
+                    let _ = write!(text,
+                        "writer: {} pending, {} queued, {} flushes, {} failures, last flush {}, max flush {:?}",
+                        stats.pending, stats.queued, stats.flushes, stats.failures, last, stats.max_flush,
+                    );
+                },
+                Err(_) => text.push_str("writer: closed"),
+            },
+            None => text.push_str("writer: disabled"),
+        }
+
+        text
+    }
+}
+
+
+/// 한 줄에 연결 하나
+fn format_list(players: &[PlayerSummary]) -> String {
+    let mut text = format!("{} connections", players.len());
+
+    for player in players {
+        let addr = player.addr.map_or("-".to_string(), |addr| addr.to_string());
+        let account = player.account.map_or("-".to_string(), |account| account.to_string());
+        let state = match player.state {
+            None => "not in world".to_string(),
+            Some(state) => match state.position {
+                Some((x, y)) => format!("{} at ({}, {}) hp {}", state.class, x, y, state.hp),
+                None => format!("{} dead", state.class),
+            },
+        };
+
+        let _ = write!(text, "\n  {:<10} {:<22} account {:<6} {}", player.id, addr, account, state);
+    }
+
+    text
+}
+
+
+/// 표준 입력을 한 줄씩 읽어서 보내는 스레드를 시작한다. 입력이 끝나면 채널이 닫힌다.
+/// 읽기는 블로킹이라서 tokio의 런타임이 종료되는 것을 막지 않도록 별도의 스레드에서 읽는다.
+pub fn stdin_lines() -> mpsc::Receiver<String> {
+    let (sender, receiver) = mpsc::channel(16);
+
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if sender.blocking_send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    receiver
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use network::PieceClass;
+    use super::super::world::World;
+    use crate::server::test_util::id;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("list".parse(), Ok(AdminCommand::List));
+        assert_eq!("  STATS ".parse(), Ok(AdminCommand::Stats));
+        assert_eq!("kick 3#1".parse(), Ok(AdminCommand::Kick { id: PlayerId::new(3, 1) }));
+        assert_eq!("ban 127.0.0.1".parse(), Ok(AdminCommand::Ban { ip: [127, 0, 0, 1].into() }));
+        assert_eq!("ban ::1".parse(), Ok(AdminCommand::Ban { ip: "::1".parse().unwrap() }));
+        assert_eq!("teleport 2#0 -1 4".parse(), Ok(AdminCommand::Teleport { id: id(2), x: -1, y: 4 }));
+        assert_eq!("broadcast  hello  world ".parse(), Ok(AdminCommand::Broadcast { text: "hello  world".to_string() }));
+
+        assert!("kick".parse::<AdminCommand>().is_err());
+        assert!("kick 3".parse::<AdminCommand>().is_err());
+        assert!("ban localhost".parse::<AdminCommand>().is_err());
+        assert!("teleport 2#0 1".parse::<AdminCommand>().is_err());
+        assert!("teleport 2#0 1 x".parse::<AdminCommand>().is_err());
+        assert!("broadcast".parse::<AdminCommand>().is_err());
+        assert!("list all".parse::<AdminCommand>().is_err());
+        assert!("restart".parse::<AdminCommand>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_execute() {
+        let mut world = World::with_tick_rate(100);
+        let interface = world.interface();
+        let world_task = tokio::spawn(async move { world.run_message_loop().await });
+
+        let shutdown = CancellationToken::new();
+        let console = AdminConsole::new(interface.clone(), None, Arc::new(SlotAllocator::new(4)), shutdown.clone());
+
+        let addr = "10.0.0.1:5000".parse().unwrap();
+        let kick = CancellationToken::new();
+        assert_eq!(interface.connect(id(1), Some(addr), kick.clone()).await, Ok(true));
+        let info = interface.add_player(id(1)).await.unwrap().unwrap();
+        let (x, y) = (info.x, info.y);
+
+        let list = console.execute(AdminCommand::List).await;
+        assert!(list.starts_with("1 connections"));
+        assert!(list.contains("10.0.0.1:5000"));
+        assert!(list.contains(&format!("{} at ({}, {})", PieceClass::PAWN, x, y)));
+
+        let teleport = AdminCommand::Teleport { id: id(1), x: x + 1, y };
+        assert!(console.execute(teleport).await.starts_with("Teleported"));
+        let teleport = AdminCommand::Teleport { id: id(1), x: -1, y: 0 };
+        assert!(console.execute(teleport).await.starts_with("Cannot teleport"));
+
+        assert_eq!(console.execute(AdminCommand::Kick { id: id(2) }).await, format!("No connection {}", id(2)));
+        assert_eq!(console.execute(AdminCommand::Kick { id: id(1) }).await, format!("Kicked {}", id(1)));
+        assert!(kick.is_cancelled());
+
+        // 차단한 주소의 연결은 끊기고, 다시 접속할 수 없음
+        let other = CancellationToken::new();
+        interface.connect(id(2), Some(addr), other.clone()).await.unwrap();
+        let ban = console.execute(AdminCommand::Ban { ip: addr.ip() }).await;
+        assert_eq!(ban, "Banned 10.0.0.1 (2 connections kicked)");
+        assert!(other.is_cancelled());
+        assert_eq!(interface.connect(id(3), Some(addr), CancellationToken::new()).await, Ok(false));
+        assert_eq!(interface.connect(id(3), Some("10.0.0.2:5000".parse().unwrap()), CancellationToken::new()).await, Ok(true));
+
+        let stats = console.execute(AdminCommand::Stats).await;
+        assert!(stats.contains("players: 1, npcs: 0"));
+        assert!(stats.contains("connections: 3"));
+        assert!(stats.contains("writer: disabled"));
+
+        // 연결이 끝나면 목록에서 빠짐
+        interface.remove_player(id(1)).await.unwrap();
+        interface.remove_player(id(2)).await.unwrap();
+        assert!(console.execute(AdminCommand::List).await.starts_with("1 connections"));
+
+        let mut notices = interface.subscribe_notices();
+        console.execute(AdminCommand::Broadcast { text: "hi".to_string() }).await;
+        assert_eq!(notices.recv().await.unwrap(), Message::Notice { text: "hi".to_string() });
+
+        console.execute(AdminCommand::Shutdown).await;
+        assert!(shutdown.is_cancelled());
+        world_task.abort();
+    }
+}
@@ -0,0 +1,80 @@
+use network::PlayerId;
+
+
+/// `ChatFilter`가 메세지를 검사한 결과
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum FilterResult {
+    /// 그대로 보냄
+    Pass,
+    /// 바꾼 내용으로 보냄
+    Replace(String),
+    /// 보내지 않음 (`ChatRejectReason::Blocked`)
+    Block,
+}
+
+
+/// 채팅을 보내기 전에 검사하는 훅. `ChatRouter::set_filter`로 바꿀 수 있다.
+pub trait ChatFilter: Send {
+    fn filter(&self, from: PlayerId, text: &str) -> FilterResult;
+}
+
+
+/// 목록에 있는 단어를 `*`로 가리는 기본 필터.
+/// ASCII 문자는 대소문자를 구분하지 않는다.
+#[derive(Debug, Clone, Default)]
+pub struct Blocklist {
+    words: Vec<String>,
+}
+
+impl Blocklist {
+    pub fn new(words: &[String]) -> Self {
+        Self {
+            words: words.iter()
+                .filter(|word| !word.is_empty())
+                .map(|word| word.to_ascii_lowercase())
+                .collect(),
+        }
+    }
+}
+
+impl ChatFilter for Blocklist {
+    fn filter(&self, _from: PlayerId, text: &str) -> FilterResult {
+        // ASCII만 바꾸므로 바이트 위치가 그대로 유지됨
+        let lowered = text.to_ascii_lowercase();
+        let mut masked = vec![false; text.len()];
+
+        for word in &self.words {
+            for (start, _) in lowered.match_indices(word.as_str()) {
+                masked[start..start + word.len()].fill(true);
+            }
+        }
+
+        if !masked.contains(&true) {
+            return FilterResult::Pass;
+        }
+
+        let text = text.char_indices()
+            .map(|(i, c)| if masked[i] { '*' } else { c })
+            .collect();
+
+        FilterResult::Replace(text)
+    }
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocklist() {
+        let blocklist = Blocklist::new(&["darn".to_string(), "바보".to_string(), String::new()]);
+        let filter = |text: &str| blocklist.filter(PlayerId::default(), text);
+
+        assert_eq!(filter("hello"), FilterResult::Pass);
+        assert_eq!(filter("DARN it, darnit"), FilterResult::Replace("**** it, ****it".to_string()));
+        assert_eq!(filter("이 바보야"), FilterResult::Replace("이 **야".to_string()));
+    }
+}
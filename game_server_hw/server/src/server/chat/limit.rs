@@ -0,0 +1,59 @@
+use std::{collections::VecDeque, time::{Duration, Instant}};
+
+
+/// 최근 `window`동안 `limit`번까지만 허용한다.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    limit: usize,
+    window: Duration,
+    /// 허용한 시각 (오래된 순)
+    history: VecDeque<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: usize, window: Duration) -> Self {
+        Self {
+            limit,
+            window,
+            history: VecDeque::with_capacity(limit),
+        }
+    }
+
+    /// 허용되면 `now`를 기록하고 `true`
+    pub fn try_acquire(&mut self, now: Instant) -> bool {
+        while self.history.front().is_some_and(|&time| now.saturating_duration_since(time) >= self.window) {
+            self.history.pop_front();
+        }
+
+        if self.history.len() >= self.limit {
+            return false;
+        }
+
+        self.history.push_back(now);
+        true
+    }
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter() {
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let mut limiter = RateLimiter::new(2, Duration::from_millis(100));
+
+        assert!(limiter.try_acquire(at(0)));
+        assert!(limiter.try_acquire(at(10)));
+        assert!(!limiter.try_acquire(at(50)));
+
+        // 첫번째 기록이 지나감
+        assert!(limiter.try_acquire(at(100)));
+        assert!(!limiter.try_acquire(at(109)));
+        assert!(limiter.try_acquire(at(110)));
+    }
+}
@@ -0,0 +1,9 @@
+//! 플레이어 사이의 채팅 (전체, 귓속말, 근처)
+
+mod filter;
+mod limit;
+mod router;
+
+pub use filter::*;
+pub use limit::*;
+pub use router::*;
@@ -0,0 +1,347 @@
+use std::{collections::HashMap, time::{Duration, Instant}};
+use tokio::sync::{mpsc, oneshot};
+use network::{ChatChannel, ChatRejectReason, Message, PlayerId};
+use config::ChatConfig;
+
+use super::{Blocklist, ChatFilter, FilterResult, RateLimiter};
+use super::super::{client::Outbox, world::WorldInterface};
+
+
+/// 채팅 요청 채널의 크기
+const CHAT_CHANNEL_CAPACITY: usize = 128;
+
+
+/// 채팅이 전달되었는지, 또는 거부된 이유
+pub type ChatResult = Result<(), ChatRejectReason>;
+
+
+/// `ChatInterface`가 채널을 통해 `ChatRouter`로 보내는 요청.
+#[derive(Debug)]
+pub enum ChatCommand {
+    /// 채팅 참가. 같은 id가 있으면 교체한다.
+    Join { id: PlayerId, name: String, outbox: Outbox },
+    Send { id: PlayerId, channel: ChatChannel, target: String, text: String, reply: oneshot::Sender<ChatResult> },
+    Leave { id: PlayerId },
+}
+
+
+/// `ChatRouter`의 메세지 루프가 종료되어 요청을 처리할 수 없음
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChatClosed;
+
+impl std::fmt::Display for ChatClosed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Chat router is closed")
+    }
+}
+
+impl std::error::Error for ChatClosed {}
+
+
+struct Member {
+    name: String,
+    outbox: Outbox,
+    limiter: RateLimiter,
+}
+
+
+/// 채팅을 채널에 맞는 플레이어들에게 전달한다.
+/// `MatchServer`처럼 별도의 task에서 채널로 요청을 받아 처리한다.
+/// 근처 채팅의 거리는 월드의 마지막 스냅샷 기준.
+pub struct ChatRouter {
+    members: HashMap<PlayerId, Member>,
+    world: WorldInterface,
+    filter: Box<dyn ChatFilter>,
+
+    sender: mpsc::Sender<ChatCommand>,
+    receiver: mpsc::Receiver<ChatCommand>,
+
+    config: ChatConfig,
+}
+
+impl ChatRouter {
+    pub fn new(world: WorldInterface) -> Self {
+        Self::with_config(ChatConfig::default(), world)
+    }
+
+    /// 필터는 `config.blocklist`의 `Blocklist`
+    pub fn with_config(config: ChatConfig, world: WorldInterface) -> Self {
+        let (sender, receiver) = mpsc::channel(CHAT_CHANNEL_CAPACITY);
+
+        Self {
+            members: HashMap::new(),
+            world,
+            filter: Box::new(Blocklist::new(&config.blocklist)),
+            sender,
+            receiver,
+            config,
+        }
+    }
+
+    pub fn set_filter(&mut self, filter: impl ChatFilter + 'static) {
+        self.filter = Box::new(filter);
+    }
+
+    pub fn interface(&self) -> ChatInterface {
+        ChatInterface {
+            sender: self.sender.clone(),
+        }
+    }
+
+    pub async fn run_message_loop(&mut self) {
+        while let Some(command) = self.receiver.recv().await {
+            self.process_command(command);
+        }
+    }
+
+    fn process_command(&mut self, command: ChatCommand) {
+        match command {
+            ChatCommand::Join { id, name, outbox } => {
+                let window = Duration::from_millis(self.config.rate_window_ms);
+                let limiter = RateLimiter::new(self.config.rate_limit, window);
+                self.members.insert(id, Member { name, outbox, limiter });
+            },
+
+            ChatCommand::Send { id, channel, target, text, reply } => {
+                let _ = reply.send(self.send(id, channel, &target, &text, Instant::now()));
+            },
+
+            ChatCommand::Leave { id } => {
+                self.members.remove(&id);
+            },
+        }
+    }
+
+    /// 길이, 횟수 제한과 필터를 거쳐 채널에 맞는 플레이어에게 보낸다.
+    /// 전체, 근처 채팅은 보낸 플레이어도 받는다.
+    /// 메세지가 밀렸거나(연결이 끊기는 중) 연결이 끝난 플레이어는 채팅에서 뺀다.
+    fn send(&mut self, id: PlayerId, channel: ChatChannel, target: &str, text: &str, now: Instant) -> ChatResult {
+        // 줄바꿈 등 제어 문자는 공백으로
+        let text = text.chars()
+            .map(|c| if c.is_control() { ' ' } else { c })
+            .collect::<String>();
+        let text = text.trim();
+
+        if text.is_empty() {
+            return Err(ChatRejectReason::Empty);
+        }
+        if text.chars().count() > self.config.max_len {
+            return Err(ChatRejectReason::TooLong);
+        }
+
+        let Some(member) = self.members.get_mut(&id) else {
+            return Err(ChatRejectReason::UnknownTarget);
+        };
+        if !member.limiter.try_acquire(now) {
+            return Err(ChatRejectReason::RateLimited);
+        }
+
+        let text = match self.filter.filter(id, text) {
+            FilterResult::Pass => text.to_string(),
+            FilterResult::Replace(text) => text,
+            FilterResult::Block => return Err(ChatRejectReason::Blocked),
+        };
+
+        let recipients = match channel {
+            ChatChannel::Global => self.members.keys().copied().collect(),
+            ChatChannel::Whisper => vec![self.find(target).ok_or(ChatRejectReason::UnknownTarget)?],
+            ChatChannel::Proximity => self.nearby(id),
+        };
+
+        let message = Message::Chat { channel, from: id, name: self.members[&id].name.clone(), text };
+        for recipient in recipients {
+            let Some(member) = self.members.get(&recipient) else {
+                continue;
+            };
+
+            // `OutboxError::Full`이면 `Outbox`가 연결을 끊으므로 `Closed`처럼 뺀다
+            if member.outbox.send(message.clone()).is_err() {
+                self.members.remove(&recipient);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `target`은 id(`index#generation`) 또는 이름 (ASCII 대소문자 구분 없음)
+    fn find(&self, target: &str) -> Option<PlayerId> {
+        let target = target.trim();
+
+        if let Ok(id) = target.parse() {
+            if self.members.contains_key(&id) {
+                return Some(id);
+            }
+        }
+
+        self.members.iter()
+            .find(|(_, member)| member.name.eq_ignore_ascii_case(target))
+            .map(|(&id, _)| id)
+    }
+
+    /// `id`의 `proximity_radius`칸 이내에 있는 참가자. 월드에 없으면 없음
+    fn nearby(&self, id: PlayerId) -> Vec<PlayerId> {
+        let snapshot = self.world.latest();
+        let Some(player) = snapshot.get(id) else {
+            return Vec::new();
+        };
+
+        snapshot.in_range(player.x, player.y, self.config.proximity_radius)
+            .map(|object| object.id)
+            .filter(|id| self.members.contains_key(id))
+            .collect()
+    }
+}
+
+
+/// 다른 task에서 `ChatRouter`에 접근하기 위한 핸들
+#[derive(Debug, Clone)]
+pub struct ChatInterface {
+    sender: mpsc::Sender<ChatCommand>,
+}
+
+impl ChatInterface {
+    /// 받는 채팅은 `outbox`로 `Chat`이 온다.
+    pub async fn join(&self, id: PlayerId, name: String, outbox: Outbox) -> Result<(), ChatClosed> {
+        self.sender.send(ChatCommand::Join { id, name, outbox }).await.map_err(|_| ChatClosed)
+    }
+
+    pub async fn send(&self, id: PlayerId, channel: ChatChannel, target: String, text: String) -> Result<ChatResult, ChatClosed> {
+        let (reply, response) = oneshot::channel();
+        self.sender.send(ChatCommand::Send { id, channel, target, text, reply }).await.map_err(|_| ChatClosed)?;
+
+        response.await.map_err(|_| ChatClosed)
+    }
+
+    pub async fn leave(&self, id: PlayerId) -> Result<(), ChatClosed> {
+        self.sender.send(ChatCommand::Leave { id }).await.map_err(|_| ChatClosed)
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::WorldConfig;
+    use super::super::super::{client::OUTBOX_CAPACITY, world::World};
+    use crate::server::test_util::id;
+
+    fn join(router: &mut ChatRouter, index: u16) -> mpsc::Receiver<Message> {
+        let (outbox, inbox) = Outbox::channel(OUTBOX_CAPACITY);
+        let name = format!("Player {}", id(index));
+        router.process_command(ChatCommand::Join { id: id(index), name, outbox });
+        inbox
+    }
+
+    fn received(inbox: &mut mpsc::Receiver<Message>) -> Vec<String> {
+        std::iter::from_fn(|| inbox.try_recv().ok())
+            .map(|message| match message {
+                Message::Chat { text, .. } => text,
+                message => panic!("unexpected message {:?}", message),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_global_and_whisper() {
+        let config = ChatConfig { blocklist: vec!["darn".to_string()], ..Default::default() };
+        let mut router = ChatRouter::with_config(config, World::new().interface());
+        let mut inboxes = [join(&mut router, 1), join(&mut router, 2), join(&mut router, 3)];
+        let now = Instant::now();
+
+        router.send(id(1), ChatChannel::Global, "", " hi\nall ", now).unwrap();
+        for inbox in &mut inboxes {
+            assert_eq!(received(inbox), ["hi all"]);
+        }
+
+        // id 또는 이름으로
+        router.send(id(1), ChatChannel::Whisper, "2#0", "psst", now).unwrap();
+        router.send(id(3), ChatChannel::Whisper, "player 2#0", "darn", now).unwrap();
+        assert_eq!(received(&mut inboxes[1]), ["psst", "****"]);
+        assert!(received(&mut inboxes[0]).is_empty());
+        assert!(received(&mut inboxes[2]).is_empty());
+
+        assert_eq!(router.send(id(1), ChatChannel::Whisper, "9#0", "hello?", now), Err(ChatRejectReason::UnknownTarget));
+        assert_eq!(router.send(id(1), ChatChannel::Global, "", " \t", now), Err(ChatRejectReason::Empty));
+        assert_eq!(router.send(id(1), ChatChannel::Global, "", &"a".repeat(201), now), Err(ChatRejectReason::TooLong));
+
+        // 나간 플레이어에게는 보낼 수 없음
+        router.process_command(ChatCommand::Leave { id: id(2) });
+        assert_eq!(router.send(id(3), ChatChannel::Whisper, "2#0", "bye", now), Err(ChatRejectReason::UnknownTarget));
+    }
+
+    #[test]
+    fn test_rate_limit_and_filter() {
+        struct NoShouting;
+        impl ChatFilter for NoShouting {
+            fn filter(&self, _from: PlayerId, text: &str) -> FilterResult {
+                if text.chars().any(|c| c.is_lowercase()) { FilterResult::Pass } else { FilterResult::Block }
+            }
+        }
+
+        let config = ChatConfig { rate_limit: 2, rate_window_ms: 1000, ..Default::default() };
+        let mut router = ChatRouter::with_config(config, World::new().interface());
+        router.set_filter(NoShouting);
+        let mut inbox = join(&mut router, 1);
+        let now = Instant::now();
+
+        // 거부된 메세지도 횟수에 포함
+        assert_eq!(router.send(id(1), ChatChannel::Global, "", "HEY", now), Err(ChatRejectReason::Blocked));
+        router.send(id(1), ChatChannel::Global, "", "hey", now).unwrap();
+        assert_eq!(router.send(id(1), ChatChannel::Global, "", "hey", now), Err(ChatRejectReason::RateLimited));
+        assert_eq!(received(&mut inbox), ["hey"]);
+
+        router.send(id(1), ChatChannel::Global, "", "hey again", now + Duration::from_secs(1)).unwrap();
+        assert_eq!(received(&mut inbox), ["hey again"]);
+    }
+
+    #[test]
+    fn test_stalled_and_closed_members() {
+        let mut router = ChatRouter::new(World::new().interface());
+        let mut inbox = join(&mut router, 1);
+        let now = Instant::now();
+
+        // 읽지 않는 플레이어
+        let (outbox, mut stalled) = Outbox::channel(1);
+        router.process_command(ChatCommand::Join { id: id(2), name: "stalled".to_string(), outbox });
+        // 연결이 끝난 플레이어
+        drop(join(&mut router, 3));
+
+        router.send(id(1), ChatChannel::Global, "", "one", now).unwrap();
+        router.send(id(1), ChatChannel::Global, "", "two", now).unwrap();
+        assert_eq!(received(&mut inbox), ["one", "two"]);
+        assert_eq!(received(&mut stalled), ["one"]);
+
+        // 가득 찬 쪽과 끊어진 쪽은 더이상 참가자가 아님
+        assert_eq!(router.send(id(1), ChatChannel::Whisper, "2#0", "hi", now), Err(ChatRejectReason::UnknownTarget));
+        assert_eq!(router.send(id(1), ChatChannel::Whisper, "3#0", "hi", now), Err(ChatRejectReason::UnknownTarget));
+        assert_eq!(router.members.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_proximity() {
+        // 스폰 순서대로 x = 0, 2, 7
+        let map = "S.S....S\n".parse().unwrap();
+        let mut world = World::with_map(WorldConfig { tick_rate: 100, ..Default::default() }, map);
+        let interface = world.interface();
+        let world_task = tokio::spawn(async move { world.run_message_loop().await });
+
+        for index in 1..=3 {
+            interface.add_player(id(index)).await.unwrap();
+        }
+        while interface.latest().len() < 3 {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+
+        let config = ChatConfig { proximity_radius: 2, ..Default::default() };
+        let mut router = ChatRouter::with_config(config, interface);
+        let mut inboxes = [join(&mut router, 1), join(&mut router, 2), join(&mut router, 3)];
+
+        router.send(id(1), ChatChannel::Proximity, "", "near", Instant::now()).unwrap();
+        assert_eq!(received(&mut inboxes[0]), ["near"]);
+        assert_eq!(received(&mut inboxes[1]), ["near"]);
+        assert!(received(&mut inboxes[2]).is_empty());
+
+        world_task.abort();
+    }
+}
@@ -0,0 +1,571 @@
+use network::{BoardState, ChessColor, ChessMove, ChessPiece, GameOverReason, GameResult, PieceClass, Square};
+
+
+/// 폰이 승급할 수 있는 말
+const PROMOTIONS: [PieceClass; 4] = [PieceClass::QUEEN, PieceClass::ROOK, PieceClass::BISHOP, PieceClass::KNIGHT];
+
+/// 캐슬링 권리의 인덱스
+const KINGSIDE: usize = 0;
+const QUEENSIDE: usize = 1;
+
+
+fn forward(color: ChessColor) -> i32 {
+    match color {
+        ChessColor::White => 1,
+        ChessColor::Black => -1,
+    }
+}
+
+fn home_rank(color: ChessColor) -> i32 {
+    match color {
+        ChessColor::White => 0,
+        ChessColor::Black => 7,
+    }
+}
+
+fn square(file: i32, rank: i32) -> Square {
+    Square::new(file, rank).expect("square out of board")
+}
+
+
+/// 체스판과 규칙.
+/// 합법적인 수는 말의 이동 규칙대로 만든 수 중에서, 둔 뒤에 자신의 킹이 공격받지 않는 수이다.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Board {
+    squares: [Option<ChessPiece>; 64],
+    turn: ChessColor,
+    /// `[색][KINGSIDE / QUEENSIDE]`
+    castling: [[bool; 2]; 2],
+    /// 바로 전에 폰이 두칸 전진했으면 그 폰이 지나간 칸
+    en_passant: Option<Square>,
+    /// 마지막 폰 이동이나 잡기 이후의 반수 (50수 규칙)
+    halfmove: u32,
+    /// 흑이 둘 때마다 1씩 증가 (1부터)
+    fullmove: u32,
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Board {
+    /// 시작 배치
+    pub fn new() -> Self {
+        Self::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")
+            .expect("invalid initial position")
+    }
+
+    /// FEN 문자열로 배치를 만든다. 뒤의 필드(캐슬링, 앙파상, 수)는 생략 가능
+    pub fn from_fen(fen: &str) -> Result<Self, String> {
+        let invalid = || format!("Invalid FEN '{}'", fen);
+        let mut fields = fen.split_whitespace();
+
+        let mut squares = [None; 64];
+        let ranks = fields.next().ok_or_else(invalid)?.split('/').collect::<Vec<_>>();
+        if ranks.len() != 8 {
+            return Err(invalid());
+        }
+
+        for (row, pieces) in ranks.iter().enumerate() {
+            let rank = 7 - row as i32;
+            let mut file = 0;
+
+            for c in pieces.chars() {
+                if let Some(skip) = c.to_digit(10) {
+                    file += skip as i32;
+                    continue;
+                }
+
+                let color = if c.is_ascii_uppercase() { ChessColor::White } else { ChessColor::Black };
+                let class = match c.to_ascii_lowercase() {
+                    'p' => PieceClass::PAWN,
+                    'n' => PieceClass::KNIGHT,
+                    'b' => PieceClass::BISHOP,
+                    'r' => PieceClass::ROOK,
+                    'q' => PieceClass::QUEEN,
+                    'k' => PieceClass::KING,
+                    _ => return Err(invalid()),
+                };
+
+                let square = Square::new(file, rank).ok_or_else(invalid)?;
+                squares[square.index()] = Some(ChessPiece::new(color, class));
+                file += 1;
+            }
+
+            if file != 8 {
+                return Err(invalid());
+            }
+        }
+
+        let turn = match fields.next().unwrap_or("w") {
+            "w" => ChessColor::White,
+            "b" => ChessColor::Black,
+            _ => return Err(invalid()),
+        };
+
+        let mut castling = [[false; 2]; 2];
+        for c in fields.next().unwrap_or("-").chars() {
+            match c {
+                'K' => castling[ChessColor::White as usize][KINGSIDE] = true,
+                'Q' => castling[ChessColor::White as usize][QUEENSIDE] = true,
+                'k' => castling[ChessColor::Black as usize][KINGSIDE] = true,
+                'q' => castling[ChessColor::Black as usize][QUEENSIDE] = true,
+                '-' => {},
+                _ => return Err(invalid()),
+            }
+        }
+
+        let en_passant = match fields.next().unwrap_or("-") {
+            "-" => None,
+            square => Some(square.parse().map_err(|_| invalid())?),
+        };
+
+        let halfmove = fields.next().unwrap_or("0").parse().map_err(|_| invalid())?;
+        let fullmove = fields.next().unwrap_or("1").parse().map_err(|_| invalid())?;
+
+        Ok(Self { squares, turn, castling, en_passant, halfmove, fullmove })
+    }
+
+    pub fn piece_at(&self, square: Square) -> Option<ChessPiece> {
+        self.squares[square.index()]
+    }
+
+    pub fn turn(&self) -> ChessColor {
+        self.turn
+    }
+
+    pub fn fullmove(&self) -> u32 {
+        self.fullmove
+    }
+
+    /// 클라이언트에게 보낼 상태
+    pub fn state(&self) -> BoardState {
+        BoardState {
+            squares: self.squares,
+            turn: self.turn,
+            check: self.in_check(self.turn),
+        }
+    }
+
+    fn king_square(&self, color: ChessColor) -> Option<Square> {
+        Square::all().find(|&square| self.piece_at(square) == Some(ChessPiece::new(color, PieceClass::KING)))
+    }
+
+    pub fn in_check(&self, color: ChessColor) -> bool {
+        self.king_square(color)
+            .is_some_and(|king| self.is_attacked(king, color.opponent()))
+    }
+
+    /// `by`의 말 중 하나가 `target`을 공격하는지
+    pub fn is_attacked(&self, target: Square, by: ChessColor) -> bool {
+        let is = |square: Option<Square>, classes: &[PieceClass]| {
+            square
+                .and_then(|square| self.piece_at(square))
+                .is_some_and(|piece| piece.color == by && classes.contains(&piece.class))
+        };
+
+        // 폰은 대각선 앞으로 공격하므로, 공격하는 폰은 대각선 뒤에 있다.
+        let behind = -forward(by);
+        if is(target.offset(-1, behind), &[PieceClass::PAWN]) || is(target.offset(1, behind), &[PieceClass::PAWN]) {
+            return true;
+        }
+
+        for class in [PieceClass::KNIGHT, PieceClass::KING] {
+            if class.directions().iter().any(|&(file, rank)| is(target.offset(file, rank), &[class])) {
+                return true;
+            }
+        }
+
+        for class in [PieceClass::BISHOP, PieceClass::ROOK] {
+            for &(file, rank) in class.directions() {
+                let mut next = target.offset(file, rank);
+                while let Some(square) = next {
+                    if self.piece_at(square).is_some() {
+                        if is(Some(square), &[class, PieceClass::QUEEN]) {
+                            return true;
+                        }
+                        break;
+                    }
+                    next = square.offset(file, rank);
+                }
+            }
+        }
+
+        false
+    }
+
+    /// 말의 이동 규칙만 확인한 수 (자신의 킹이 공격받는지는 확인하지 않음)
+    fn pseudo_moves(&self, from: Square, moves: &mut Vec<ChessMove>) {
+        let Some(piece) = self.piece_at(from) else {
+            return;
+        };
+        let color = piece.color;
+        let is_empty = |square: Square| self.piece_at(square).is_none();
+        let is_enemy = |square: Square| self.piece_at(square).is_some_and(|p| p.color != color);
+
+        match piece.class {
+            PieceClass::PAWN => {
+                let dir = forward(color);
+                let last_rank = home_rank(color.opponent());
+                let mut push = |to: Square| {
+                    if to.rank() == last_rank {
+                        moves.extend(PROMOTIONS.map(|class| ChessMove::with_promotion(from, to, class)));
+                    } else {
+                        moves.push(ChessMove::new(from, to));
+                    }
+                };
+
+                if let Some(one) = from.offset(0, dir).filter(|&to| is_empty(to)) {
+                    push(one);
+
+                    // 처음 위치에서는 두칸
+                    if from.rank() == home_rank(color) + dir {
+                        if let Some(two) = from.offset(0, 2 * dir).filter(|&to| is_empty(to)) {
+                            push(two);
+                        }
+                    }
+                }
+
+                for file in [-1, 1] {
+                    if let Some(to) = from.offset(file, dir) {
+                        if is_enemy(to) || self.en_passant == Some(to) {
+                            push(to);
+                        }
+                    }
+                }
+            },
+
+            class if class.slides() => {
+                for &(file, rank) in class.directions() {
+                    let mut next = from.offset(file, rank);
+                    while let Some(to) = next {
+                        if is_empty(to) {
+                            moves.push(ChessMove::new(from, to));
+                        } else {
+                            if is_enemy(to) {
+                                moves.push(ChessMove::new(from, to));
+                            }
+                            break;
+                        }
+                        next = to.offset(file, rank);
+                    }
+                }
+            },
+
+            class => {
+                for &(file, rank) in class.directions() {
+                    if let Some(to) = from.offset(file, rank).filter(|&to| is_empty(to) || is_enemy(to)) {
+                        moves.push(ChessMove::new(from, to));
+                    }
+                }
+
+                if class == PieceClass::KING {
+                    self.castling_moves(from, color, moves);
+                }
+            },
+        }
+    }
+
+    /// 킹과 룩이 움직인 적이 없고, 사이가 비어있고,
+    /// 킹이 체크 상태가 아니며 지나가는 칸과 도착하는 칸이 공격받지 않을 때
+    fn castling_moves(&self, from: Square, color: ChessColor, moves: &mut Vec<ChessMove>) {
+        let rank = home_rank(color);
+        if from != square(4, rank) || self.is_attacked(from, color.opponent()) {
+            return;
+        }
+
+        // (권리, 룩의 파일, 킹이 움직이는 방향)
+        for (side, rook_file, dir) in [(KINGSIDE, 7, 1), (QUEENSIDE, 0, -1)] {
+            if !self.castling[color as usize][side] {
+                continue;
+            }
+            if self.piece_at(square(rook_file, rank)) != Some(ChessPiece::new(color, PieceClass::ROOK)) {
+                continue;
+            }
+
+            let between = (rook_file.min(4) + 1)..rook_file.max(4);
+            if between.clone().any(|file| self.piece_at(square(file, rank)).is_some()) {
+                continue;
+            }
+
+            let passing = [square(4 + dir, rank), square(4 + 2 * dir, rank)];
+            if passing.iter().any(|&square| self.is_attacked(square, color.opponent())) {
+                continue;
+            }
+
+            moves.push(ChessMove::new(from, passing[1]));
+        }
+    }
+
+    /// 지금 차례인 쪽의 합법적인 수 전체
+    pub fn legal_moves(&self) -> Vec<ChessMove> {
+        let mut moves = Vec::new();
+        for from in Square::all() {
+            if self.piece_at(from).is_some_and(|piece| piece.color == self.turn) {
+                self.pseudo_moves(from, &mut moves);
+            }
+        }
+
+        moves.retain(|&mv| {
+            let mut next = self.clone();
+            next.apply(mv);
+            !next.in_check(self.turn)
+        });
+
+        moves
+    }
+
+    pub fn is_legal(&self, mv: ChessMove) -> bool {
+        // 출발 칸의 말만 확인하면 된다.
+        let mut moves = Vec::new();
+        if self.piece_at(mv.from).is_some_and(|piece| piece.color == self.turn) {
+            self.pseudo_moves(mv.from, &mut moves);
+        }
+
+        moves.contains(&mv) && {
+            let mut next = self.clone();
+            next.apply(mv);
+            !next.in_check(self.turn)
+        }
+    }
+
+    /// 합법적인 수면 두고 `true`
+    pub fn play(&mut self, mv: ChessMove) -> bool {
+        if !self.is_legal(mv) {
+            return false;
+        }
+
+        self.apply(mv);
+        true
+    }
+
+    /// 규칙 확인 없이 수를 둔다.
+    fn apply(&mut self, mv: ChessMove) {
+        let Some(mut piece) = self.piece_at(mv.from) else {
+            return;
+        };
+        let captured = self.piece_at(mv.to);
+        let color = piece.color;
+
+        if piece.class == PieceClass::PAWN {
+            // 앙파상: 대각선으로 빈 칸에 가면 옆의 폰을 잡는다.
+            if Some(mv.to) == self.en_passant && mv.from.file() != mv.to.file() {
+                self.squares[square(mv.to.file(), mv.from.rank()).index()] = None;
+            }
+            if let Some(class) = mv.promotion {
+                piece.class = class;
+            }
+        }
+
+        // 캐슬링: 룩도 킹을 넘어 옮긴다.
+        if piece.class == PieceClass::KING && (mv.to.file() - mv.from.file()).abs() == 2 {
+            let rank = mv.from.rank();
+            let (rook_from, rook_to) = if mv.to.file() > mv.from.file() { (7, 5) } else { (0, 3) };
+            self.squares[square(rook_to, rank).index()] = self.squares[square(rook_from, rank).index()].take();
+        }
+
+        self.squares[mv.to.index()] = Some(piece);
+        self.squares[mv.from.index()] = None;
+
+        // 킹이나 룩이 움직이거나 룩이 잡히면 캐슬링 권리가 사라진다.
+        if piece.class == PieceClass::KING {
+            self.castling[color as usize] = [false; 2];
+        }
+        for color in [ChessColor::White, ChessColor::Black] {
+            let rank = home_rank(color);
+            for (side, file) in [(KINGSIDE, 7), (QUEENSIDE, 0)] {
+                if mv.from == square(file, rank) || mv.to == square(file, rank) {
+                    self.castling[color as usize][side] = false;
+                }
+            }
+        }
+
+        let pawn_move = piece.class == PieceClass::PAWN || mv.promotion.is_some();
+        self.en_passant = (pawn_move && (mv.to.rank() - mv.from.rank()).abs() == 2)
+            .then(|| square(mv.from.file(), (mv.from.rank() + mv.to.rank()) / 2));
+
+        if pawn_move || captured.is_some() {
+            self.halfmove = 0;
+        } else {
+            self.halfmove += 1;
+        }
+
+        if color == ChessColor::Black {
+            self.fullmove += 1;
+        }
+        self.turn = color.opponent();
+    }
+
+    /// 대국이 규칙에 의해 끝났으면 결과와 이유
+    pub fn outcome(&self) -> Option<(GameResult, GameOverReason)> {
+        if self.legal_moves().is_empty() {
+            return Some(if self.in_check(self.turn) {
+                (GameResult::win(self.turn.opponent()), GameOverReason::Checkmate)
+            } else {
+                (GameResult::Draw, GameOverReason::Stalemate)
+            });
+        }
+
+        if self.halfmove >= 100 {
+            return Some((GameResult::Draw, GameOverReason::FiftyMoves));
+        }
+
+        if self.insufficient_material() {
+            return Some((GameResult::Draw, GameOverReason::InsufficientMaterial));
+        }
+
+        None
+    }
+
+    /// 킹만 남았거나, 킹 외에 나이트나 비숍 하나뿐이거나, 같은 색 칸의 비숍들만 남은 경우
+    fn insufficient_material(&self) -> bool {
+        let pieces = Square::all()
+            .filter_map(|square| self.piece_at(square).map(|piece| (square, piece.class)))
+            .filter(|&(_, class)| class != PieceClass::KING)
+            .collect::<Vec<_>>();
+
+        match pieces.as_slice() {
+            [] => true,
+            [(_, class)] => matches!(*class, PieceClass::KNIGHT | PieceClass::BISHOP),
+            _ => {
+                let square_color = |square: Square| (square.file() + square.rank()) % 2;
+                pieces.iter().all(|&(_, class)| class == PieceClass::BISHOP) &&
+                    pieces.iter().all(|&(square, _)| square_color(square) == square_color(pieces[0].0))
+            },
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mv(from: &str, to: &str) -> ChessMove {
+        ChessMove::new(from.parse().unwrap(), to.parse().unwrap())
+    }
+
+    fn sq(name: &str) -> Square {
+        name.parse().unwrap()
+    }
+
+    #[test]
+    fn test_initial_position() {
+        let board = Board::new();
+        assert_eq!(board.legal_moves().len(), 20);
+        assert!(!board.in_check(ChessColor::White));
+        assert_eq!(board.outcome(), None);
+
+        let mut board = board;
+        assert!(!board.play(mv("e2", "e5")));
+        assert!(!board.play(mv("e7", "e5")));
+        assert!(board.play(mv("e2", "e4")));
+        assert_eq!(board.turn(), ChessColor::Black);
+        assert_eq!(board.en_passant, Some(sq("e3")));
+    }
+
+    #[test]
+    fn test_castling() {
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+
+        let mut board = Board::from_fen(fen).unwrap();
+        assert!(board.play(mv("e1", "g1")));
+        assert_eq!(board.piece_at(sq("f1")), Some(ChessPiece::new(ChessColor::White, PieceClass::ROOK)));
+        assert_eq!(board.piece_at(sq("h1")), None);
+        assert!(board.play(mv("e8", "c8")));
+        assert_eq!(board.piece_at(sq("d8")), Some(ChessPiece::new(ChessColor::Black, PieceClass::ROOK)));
+
+        // 룩이 움직이면 그쪽 캐슬링 불가
+        let mut board = Board::from_fen(fen).unwrap();
+        assert!(board.play(mv("h1", "h2")));
+        assert!(board.play(mv("a8", "b8")));
+        assert!(board.play(mv("h2", "h1")));
+        assert!(board.play(mv("b8", "a8")));
+        assert!(!board.is_legal(mv("e1", "g1")));
+        assert!(board.is_legal(mv("e1", "c1")));
+
+        // 지나가는 칸이 공격받으면 불가
+        let board = Board::from_fen("4k3/8/8/8/8/8/5r2/R3K2R w KQ - 0 1").unwrap();
+        assert!(!board.is_legal(mv("e1", "g1")));
+        assert!(board.is_legal(mv("e1", "c1")));
+
+        // 체크 상태에서는 불가
+        let board = Board::from_fen("4k3/8/8/8/8/8/4r3/R3K2R w KQ - 0 1").unwrap();
+        assert!(!board.is_legal(mv("e1", "g1")));
+        assert!(!board.is_legal(mv("e1", "c1")));
+
+        // 사이가 막혀있으면 불가
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/RN2K2R w KQ - 0 1").unwrap();
+        assert!(!board.is_legal(mv("e1", "c1")));
+    }
+
+    #[test]
+    fn test_en_passant() {
+        let mut board = Board::from_fen("4k3/3p4/8/4P3/8/8/8/4K3 b - - 0 1").unwrap();
+        assert!(board.play(mv("d7", "d5")));
+        assert!(board.play(mv("e5", "d6")));
+        assert_eq!(board.piece_at(sq("d5")), None);
+        assert_eq!(board.piece_at(sq("d6")), Some(ChessPiece::new(ChessColor::White, PieceClass::PAWN)));
+
+        // 바로 다음 수에서만 가능
+        let mut board = Board::from_fen("4k3/3p4/8/4P3/8/8/8/4K3 b - - 0 1").unwrap();
+        assert!(board.play(mv("d7", "d5")));
+        assert!(board.play(mv("e1", "e2")));
+        assert!(board.play(mv("e8", "e7")));
+        assert!(!board.is_legal(mv("e5", "d6")));
+    }
+
+    #[test]
+    fn test_promotion() {
+        let mut board = Board::from_fen("8/4P3/8/8/8/8/8/k3K3 w - - 0 1").unwrap();
+        assert!(!board.is_legal(mv("e7", "e8")));
+        assert!(!board.is_legal(ChessMove::with_promotion(sq("e7"), sq("e8"), PieceClass::KING)));
+
+        assert!(board.play(ChessMove::with_promotion(sq("e7"), sq("e8"), PieceClass::KNIGHT)));
+        assert_eq!(board.piece_at(sq("e8")), Some(ChessPiece::new(ChessColor::White, PieceClass::KNIGHT)));
+    }
+
+    #[test]
+    fn test_pinned_piece() {
+        // e2의 비숍은 룩에 묶여서 움직일 수 없음
+        let board = Board::from_fen("4r1k1/8/8/8/8/8/4B3/4K3 w - - 0 1").unwrap();
+        assert!(!board.is_legal(mv("e2", "d3")));
+        assert!(board.is_legal(mv("e1", "d1")));
+    }
+
+    #[test]
+    fn test_outcome() {
+        // 바보의 메이트
+        let mut board = Board::new();
+        for (from, to) in [("f2", "f3"), ("e7", "e5"), ("g2", "g4"), ("d8", "h4")] {
+            assert!(board.play(mv(from, to)));
+        }
+        assert!(board.in_check(ChessColor::White));
+        assert_eq!(board.outcome(), Some((GameResult::BlackWins, GameOverReason::Checkmate)));
+
+        let board = Board::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(board.outcome(), Some((GameResult::Draw, GameOverReason::Stalemate)));
+
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 100 80").unwrap();
+        assert_eq!(board.outcome(), Some((GameResult::Draw, GameOverReason::FiftyMoves)));
+
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/2B1K3 w - - 0 1").unwrap();
+        assert_eq!(board.outcome(), Some((GameResult::Draw, GameOverReason::InsufficientMaterial)));
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/3NK3 w - - 0 1").unwrap();
+        assert_eq!(board.outcome(), Some((GameResult::Draw, GameOverReason::InsufficientMaterial)));
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/3NKN2 w - - 0 1").unwrap();
+        assert_eq!(board.outcome(), None);
+    }
+
+    #[test]
+    fn test_invalid_fen() {
+        assert!(Board::from_fen("").is_err());
+        assert!(Board::from_fen("8/8/8/8/8/8/8 w").is_err());
+        assert!(Board::from_fen("9/8/8/8/8/8/8/8 w").is_err());
+        assert!(Board::from_fen("8/8/8/8/8/8/8/7x w").is_err());
+        assert!(Board::from_fen("8/8/8/8/8/8/8/8 x").is_err());
+    }
+}
@@ -0,0 +1,199 @@
+use std::time::SystemTime;
+use network::{ChessColor, ChessMove, ChessRejectReason, GameOverReason, GameResult, PlayerId};
+
+use super::{san, Board, PgnGame};
+
+
+/// 무승부 제안의 결과
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DrawOffer {
+    /// 상대에게 제안함
+    Offered,
+    /// 상대의 제안을 수락해서 대국이 끝남
+    Accepted,
+}
+
+
+/// 두 플레이어의 대국 한판. 차례와 기보, 결과를 관리한다.
+pub struct ChessGame {
+    board: Board,
+    /// `[백, 흑]`
+    players: [PlayerId; 2],
+    /// SAN 표기의 기보
+    moves: Vec<String>,
+    /// 무승부를 제안한 쪽. 다음 수가 두어지면 취소된다.
+    draw_offer: Option<ChessColor>,
+    outcome: Option<(GameResult, GameOverReason)>,
+    started: SystemTime,
+}
+
+impl ChessGame {
+    pub fn new(white: PlayerId, black: PlayerId) -> Self {
+        Self::with_board(white, black, Board::new())
+    }
+
+    pub fn with_board(white: PlayerId, black: PlayerId, board: Board) -> Self {
+        Self {
+            board,
+            players: [white, black],
+            moves: Vec::new(),
+            draw_offer: None,
+            outcome: None,
+            started: SystemTime::now(),
+        }
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn player(&self, color: ChessColor) -> PlayerId {
+        self.players[color as usize]
+    }
+
+    pub fn players(&self) -> [PlayerId; 2] {
+        self.players
+    }
+
+    pub fn color_of(&self, id: PlayerId) -> Option<ChessColor> {
+        [ChessColor::White, ChessColor::Black].into_iter()
+            .find(|&color| self.player(color) == id)
+    }
+
+    pub fn moves(&self) -> &[String] {
+        &self.moves
+    }
+
+    /// 끝났으면 결과와 이유
+    pub fn outcome(&self) -> Option<(GameResult, GameOverReason)> {
+        self.outcome
+    }
+
+    /// 대국중인 플레이어의 색. 끝난 대국이면 `NotInMatch`
+    fn active_color(&self, id: PlayerId) -> Result<ChessColor, ChessRejectReason> {
+        match self.color_of(id) {
+            Some(color) if self.outcome.is_none() => Ok(color),
+            _ => Err(ChessRejectReason::NotInMatch),
+        }
+    }
+
+    /// `id`의 차례일 때 합법적인 수만 둔다. 두고 나서 규칙에 의해 끝났는지 확인한다.
+    pub fn play(&mut self, id: PlayerId, mv: ChessMove) -> Result<(), ChessRejectReason> {
+        let color = self.active_color(id)?;
+        if color != self.board.turn() {
+            return Err(ChessRejectReason::NotYourTurn);
+        }
+
+        if !self.board.is_legal(mv) {
+            return Err(ChessRejectReason::IllegalMove);
+        }
+
+        self.moves.push(san(&self.board, mv));
+        self.board.play(mv);
+        self.draw_offer = None;
+        self.outcome = self.board.outcome();
+
+        Ok(())
+    }
+
+    pub fn resign(&mut self, id: PlayerId) -> Result<(), ChessRejectReason> {
+        let color = self.active_color(id)?;
+        self.outcome = Some((GameResult::win(color.opponent()), GameOverReason::Resignation));
+
+        Ok(())
+    }
+
+    /// 상대가 먼저 제안했으면 수락한 것으로 처리한다.
+    pub fn offer_draw(&mut self, id: PlayerId) -> Result<DrawOffer, ChessRejectReason> {
+        let color = self.active_color(id)?;
+
+        if self.draw_offer == Some(color.opponent()) {
+            self.outcome = Some((GameResult::Draw, GameOverReason::Agreement));
+            return Ok(DrawOffer::Accepted);
+        }
+
+        self.draw_offer = Some(color);
+        Ok(DrawOffer::Offered)
+    }
+
+    /// `id`의 연결이 끊어져서 상대의 승리로 끝낸다.
+    pub fn abandon(&mut self, id: PlayerId) -> Result<(), ChessRejectReason> {
+        let color = self.active_color(id)?;
+        self.outcome = Some((GameResult::win(color.opponent()), GameOverReason::Abandoned));
+
+        Ok(())
+    }
+
+    pub fn pgn(&self) -> String {
+        let [white, black] = self.players.map(|id| format!("Player {}", id));
+
+        PgnGame {
+            white: &white,
+            black: &black,
+            started: self.started,
+            moves: &self.moves,
+            outcome: self.outcome,
+        }.to_pgn()
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::test_util::id;
+
+    fn mv(from: &str, to: &str) -> ChessMove {
+        ChessMove::new(from.parse().unwrap(), to.parse().unwrap())
+    }
+
+    #[test]
+    fn test_turns() {
+        let mut game = ChessGame::new(id(1), id(2));
+        assert_eq!(game.color_of(id(2)), Some(ChessColor::Black));
+        assert_eq!(game.color_of(id(3)), None);
+
+        assert_eq!(game.play(id(2), mv("e7", "e5")), Err(ChessRejectReason::NotYourTurn));
+        assert_eq!(game.play(id(3), mv("e2", "e4")), Err(ChessRejectReason::NotInMatch));
+        assert_eq!(game.play(id(1), mv("e2", "e5")), Err(ChessRejectReason::IllegalMove));
+        // 상대의 말은 움직일 수 없음
+        assert_eq!(game.play(id(1), mv("e7", "e5")), Err(ChessRejectReason::IllegalMove));
+
+        for (player, from, to) in [(1, "f2", "f3"), (2, "e7", "e5"), (1, "g2", "g4"), (2, "d8", "h4")] {
+            game.play(id(player), mv(from, to)).unwrap();
+        }
+        assert_eq!(game.moves(), ["f3", "e5", "g4", "Qh4#"]);
+        assert_eq!(game.outcome(), Some((GameResult::BlackWins, GameOverReason::Checkmate)));
+
+        // 끝난 대국
+        assert_eq!(game.play(id(1), mv("e2", "e4")), Err(ChessRejectReason::NotInMatch));
+        assert_eq!(game.resign(id(1)), Err(ChessRejectReason::NotInMatch));
+
+        let pgn = game.pgn();
+        assert!(pgn.contains("[White \"Player 1#0\"]"));
+        assert!(pgn.ends_with("1. f3 e5 2. g4 Qh4# 0-1\n"));
+    }
+
+    #[test]
+    fn test_resign_and_draw() {
+        let mut game = ChessGame::new(id(1), id(2));
+        game.resign(id(1)).unwrap();
+        assert_eq!(game.outcome(), Some((GameResult::BlackWins, GameOverReason::Resignation)));
+
+        let mut game = ChessGame::new(id(1), id(2));
+        assert_eq!(game.offer_draw(id(1)), Ok(DrawOffer::Offered));
+        // 수를 두면 제안은 취소됨
+        game.play(id(1), mv("e2", "e4")).unwrap();
+        assert_eq!(game.offer_draw(id(2)), Ok(DrawOffer::Offered));
+        assert_eq!(game.offer_draw(id(2)), Ok(DrawOffer::Offered));
+        assert_eq!(game.outcome(), None);
+        assert_eq!(game.offer_draw(id(1)), Ok(DrawOffer::Accepted));
+        assert_eq!(game.outcome(), Some((GameResult::Draw, GameOverReason::Agreement)));
+
+        let mut game = ChessGame::new(id(1), id(2));
+        game.abandon(id(2)).unwrap();
+        assert_eq!(game.outcome(), Some((GameResult::WhiteWins, GameOverReason::Abandoned)));
+        assert!(game.pgn().contains("[Termination \"abandoned\"]"));
+    }
+}
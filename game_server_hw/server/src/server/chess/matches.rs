@@ -0,0 +1,434 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::{mpsc, oneshot};
+use network::{ChessColor, ChessMove, ChessRejectReason, Message, PlayerId};
+use config::ChessConfig;
+
+use super::{ChessGame, DrawOffer};
+use super::super::client::Outbox;
+
+
+/// 대국 요청 채널의 크기
+const MATCH_CHANNEL_CAPACITY: usize = 128;
+
+
+/// 요청이 처리되었는지, 또는 거부된 이유
+pub type ChessResult = Result<(), ChessRejectReason>;
+
+
+/// `MatchInterface`가 채널을 통해 `MatchServer`로 보내는 요청.
+#[derive(Debug)]
+pub enum MatchCommand {
+    /// 대국 신청. 기다리는 플레이어가 있으면 대국을 시작한다.
+    Join { id: PlayerId, outbox: Outbox, reply: oneshot::Sender<ChessResult> },
+    Move { id: PlayerId, mv: ChessMove, reply: oneshot::Sender<ChessResult> },
+    Resign { id: PlayerId, reply: oneshot::Sender<ChessResult> },
+    OfferDraw { id: PlayerId, reply: oneshot::Sender<ChessResult> },
+    /// 연결 종료. 대국중이면 상대의 승리로 끝난다.
+    Leave { id: PlayerId },
+}
+
+
+/// `MatchServer`의 메세지 루프가 종료되어 요청을 처리할 수 없음
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MatchClosed;
+
+impl std::fmt::Display for MatchClosed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Match server is closed")
+    }
+}
+
+impl std::error::Error for MatchClosed {}
+
+
+struct Match {
+    game: ChessGame,
+    /// `[백, 흑]`
+    outboxes: [Outbox; 2],
+}
+
+impl Match {
+    /// 두 플레이어 모두에게 보냄. 연결이 끊어졌거나 메세지가 밀린 쪽은 무시 (밀린 쪽은 `Outbox`가 연결을 끊음)
+    fn broadcast(&self, message: Message) {
+        for outbox in &self.outboxes {
+            let _ = outbox.send(message.clone());
+        }
+    }
+
+    fn send_to(&self, color: ChessColor, message: Message) {
+        let _ = self.outboxes[color as usize].send(message);
+    }
+}
+
+
+/// 대국 신청을 받아 두명씩 짝지어 대국을 진행한다.
+/// `World`처럼 별도의 task에서 채널로 요청을 받아 처리한다.
+pub struct MatchServer {
+    /// 상대를 기다리는 플레이어
+    waiting: Option<(PlayerId, Outbox)>,
+    /// 대국중인 플레이어의 대국 번호
+    playing: HashMap<PlayerId, u64>,
+    matches: HashMap<u64, Match>,
+    next_match: u64,
+
+    sender: mpsc::Sender<MatchCommand>,
+    receiver: mpsc::Receiver<MatchCommand>,
+
+    config: ChessConfig,
+}
+
+impl Default for MatchServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MatchServer {
+    pub fn new() -> Self {
+        Self::with_config(ChessConfig::default())
+    }
+
+    pub fn with_config(config: ChessConfig) -> Self {
+        let (sender, receiver) = mpsc::channel(MATCH_CHANNEL_CAPACITY);
+
+        Self {
+            waiting: None,
+            playing: HashMap::new(),
+            matches: HashMap::new(),
+            next_match: 0,
+            sender,
+            receiver,
+            config,
+        }
+    }
+
+    pub fn interface(&self) -> MatchInterface {
+        MatchInterface {
+            sender: self.sender.clone(),
+        }
+    }
+
+    pub async fn run_message_loop(&mut self) {
+        while let Some(command) = self.receiver.recv().await {
+            self.process_command(command);
+        }
+    }
+
+    fn process_command(&mut self, command: MatchCommand) {
+        let id = match command {
+            MatchCommand::Join { id, .. } |
+            MatchCommand::Move { id, .. } |
+            MatchCommand::Resign { id, .. } |
+            MatchCommand::OfferDraw { id, .. } |
+            MatchCommand::Leave { id } => id,
+        };
+
+        match command {
+            MatchCommand::Join { id, outbox, reply } => {
+                let _ = reply.send(self.join(id, outbox));
+            },
+
+            MatchCommand::Move { id, mv, reply } => {
+                let result = self.with_match(id, |game| game.play(id, mv));
+                if result.is_ok() {
+                    let m = &self.matches[&self.playing[&id]];
+                    m.broadcast(Message::ChessBoard { board: m.game.board().state() });
+                }
+                let _ = reply.send(result);
+            },
+
+            MatchCommand::Resign { id, reply } => {
+                let _ = reply.send(self.with_match(id, |game| game.resign(id)));
+            },
+
+            MatchCommand::OfferDraw { id, reply } => {
+                let result = self.with_match(id, |game| game.offer_draw(id));
+                if let Ok(DrawOffer::Offered) = result {
+                    let m = &self.matches[&self.playing[&id]];
+                    if let Some(color) = m.game.color_of(id) {
+                        m.send_to(color.opponent(), Message::DrawOffer);
+                    }
+                }
+                let _ = reply.send(result.map(|_| ()));
+            },
+
+            MatchCommand::Leave { id } => self.leave(id),
+        }
+
+        self.finish_match(id);
+    }
+
+    fn join(&mut self, id: PlayerId, outbox: Outbox) -> ChessResult {
+        if self.playing.contains_key(&id) || self.waiting.as_ref().is_some_and(|(waiting, _)| *waiting == id) {
+            return Err(ChessRejectReason::AlreadyInMatch);
+        }
+
+        // 기다리던 플레이어의 연결이 끊어졌으면 대신 기다린다.
+        let opponent = self.waiting.take().filter(|(_, outbox)| !outbox.is_closed());
+        let Some((white, white_outbox)) = opponent else {
+            self.waiting = Some((id, outbox));
+            return Ok(());
+        };
+
+        // 먼저 기다린 쪽이 백
+        let game = ChessGame::new(white, id);
+        let m = Match { game, outboxes: [white_outbox, outbox] };
+
+        for color in [ChessColor::White, ChessColor::Black] {
+            let opponent = m.game.player(color.opponent());
+            m.send_to(color, Message::MatchStarted { color, opponent });
+        }
+        m.broadcast(Message::ChessBoard { board: m.game.board().state() });
+
+        let number = self.next_match;
+        self.next_match += 1;
+        self.playing.insert(white, number);
+        self.playing.insert(id, number);
+        self.matches.insert(number, m);
+
+        Ok(())
+    }
+
+    /// `id`가 대국중이면 그 대국에 `f`를 적용한다.
+    fn with_match<T>(&mut self, id: PlayerId, f: impl FnOnce(&mut ChessGame) -> Result<T, ChessRejectReason>) -> Result<T, ChessRejectReason> {
+        let m = self.playing.get(&id)
+            .and_then(|number| self.matches.get_mut(number))
+            .ok_or(ChessRejectReason::NotInMatch)?;
+
+        f(&mut m.game)
+    }
+
+    fn leave(&mut self, id: PlayerId) {
+        if self.waiting.as_ref().is_some_and(|(waiting, _)| *waiting == id) {
+            self.waiting = None;
+        }
+
+        // 이미 끝난 대국이면 무시
+        let _ = self.with_match(id, |game| game.abandon(id));
+    }
+
+    /// `id`의 대국이 끝났으면 결과와 PGN을 보내고 정리한다.
+    fn finish_match(&mut self, id: PlayerId) {
+        let Some(&number) = self.playing.get(&id) else {
+            return;
+        };
+        let Some((result, reason)) = self.matches.get(&number).and_then(|m| m.game.outcome()) else {
+            return;
+        };
+        let Some(m) = self.matches.remove(&number) else {
+            return;
+        };
+
+        for id in m.game.players() {
+            self.playing.remove(&id);
+        }
+
+        let pgn = m.game.pgn();
+        m.broadcast(Message::GameOver { result, reason });
+        m.broadcast(Message::Pgn { text: pgn.clone() });
+
+        if let Some(dir) = &self.config.pgn_dir {
+            save_pgn(dir.clone(), number, pgn);
+        }
+    }
+}
+
+
+/// `dir`에 `<시각>-<대국 번호>.pgn`으로 저장한다. 실패하면 로그만 남긴다.
+fn save_pgn(dir: PathBuf, number: u64, pgn: String) {
+    let time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let path = dir.join(format!("{}-{}.pgn", time, number));
+
+    tokio::spawn(async move {
+        let result = async {
+            tokio::fs::create_dir_all(&dir).await?;
+            tokio::fs::write(&path, pgn).await
+        }.await;
+
+        if let Err(e) = result {
+            eprintln!("Failed to save PGN to {}: {}", path.display(), e);
+        }
+    });
+}
+
+
+/// 다른 task에서 `MatchServer`에 접근하기 위한 핸들
+#[derive(Debug, Clone)]
+pub struct MatchInterface {
+    sender: mpsc::Sender<MatchCommand>,
+}
+
+impl MatchInterface {
+    async fn request(&self, command: impl FnOnce(oneshot::Sender<ChessResult>) -> MatchCommand) -> Result<ChessResult, MatchClosed> {
+        let (reply, response) = oneshot::channel();
+        self.sender.send(command(reply)).await.map_err(|_| MatchClosed)?;
+
+        response.await.map_err(|_| MatchClosed)
+    }
+
+    /// 대국 신청. 대국이 시작되면 `outbox`로 `MatchStarted`를 받는다.
+    pub async fn join(&self, id: PlayerId, outbox: Outbox) -> Result<ChessResult, MatchClosed> {
+        self.request(|reply| MatchCommand::Join { id, outbox, reply }).await
+    }
+
+    /// 둔 수는 양쪽 모두 `ChessBoard`로 받는다.
+    pub async fn play(&self, id: PlayerId, mv: ChessMove) -> Result<ChessResult, MatchClosed> {
+        self.request(|reply| MatchCommand::Move { id, mv, reply }).await
+    }
+
+    pub async fn resign(&self, id: PlayerId) -> Result<ChessResult, MatchClosed> {
+        self.request(|reply| MatchCommand::Resign { id, reply }).await
+    }
+
+    /// 상대는 `DrawOffer`를 받는다. 상대가 먼저 제안했으면 무승부로 끝난다.
+    pub async fn offer_draw(&self, id: PlayerId) -> Result<ChessResult, MatchClosed> {
+        self.request(|reply| MatchCommand::OfferDraw { id, reply }).await
+    }
+
+    pub async fn leave(&self, id: PlayerId) -> Result<(), MatchClosed> {
+        self.sender.send(MatchCommand::Leave { id }).await.map_err(|_| MatchClosed)
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use network::{ChessPiece, GameOverReason, GameResult, PieceClass};
+    use super::super::super::client::OUTBOX_CAPACITY;
+    use crate::server::test_util::id;
+
+    fn mv(from: &str, to: &str) -> ChessMove {
+        ChessMove::new(from.parse().unwrap(), to.parse().unwrap())
+    }
+
+    fn start() -> (MatchInterface, tokio::task::JoinHandle<()>) {
+        let mut server = MatchServer::new();
+        let interface = server.interface();
+        let task = tokio::spawn(async move { server.run_message_loop().await });
+        (interface, task)
+    }
+
+    async fn join(matches: &MatchInterface, index: u16) -> mpsc::Receiver<Message> {
+        let (outbox, inbox) = Outbox::channel(OUTBOX_CAPACITY);
+        assert_eq!(matches.join(id(index), outbox).await, Ok(Ok(())));
+        inbox
+    }
+
+    #[tokio::test]
+    async fn test_match() {
+        let (matches, task) = start();
+
+        assert_eq!(matches.play(id(1), mv("e2", "e4")).await, Ok(Err(ChessRejectReason::NotInMatch)));
+
+        let mut white = join(&matches, 1).await;
+        let (outbox, _) = Outbox::channel(OUTBOX_CAPACITY);
+        assert_eq!(matches.join(id(1), outbox).await, Ok(Err(ChessRejectReason::AlreadyInMatch)));
+        let mut black = join(&matches, 2).await;
+
+        assert_eq!(white.recv().await, Some(Message::MatchStarted { color: ChessColor::White, opponent: id(2) }));
+        assert_eq!(black.recv().await, Some(Message::MatchStarted { color: ChessColor::Black, opponent: id(1) }));
+        for inbox in [&mut white, &mut black] {
+            assert!(matches!(inbox.recv().await, Some(Message::ChessBoard { .. })));
+        }
+
+        assert_eq!(matches.play(id(2), mv("e7", "e5")).await, Ok(Err(ChessRejectReason::NotYourTurn)));
+        assert_eq!(matches.play(id(1), mv("e2", "e5")).await, Ok(Err(ChessRejectReason::IllegalMove)));
+        assert_eq!(matches.play(id(1), mv("e2", "e4")).await, Ok(Ok(())));
+
+        for inbox in [&mut white, &mut black] {
+            let Some(Message::ChessBoard { board }) = inbox.recv().await else {
+                panic!("expected board");
+            };
+            assert_eq!(board.turn, ChessColor::Black);
+            assert_eq!(board.piece_at("e4".parse().unwrap()), Some(ChessPiece::new(ChessColor::White, PieceClass::PAWN)));
+        }
+
+        // 무승부 제안은 상대에게 전달됨
+        assert_eq!(matches.offer_draw(id(2)).await, Ok(Ok(())));
+        assert_eq!(white.recv().await, Some(Message::DrawOffer));
+
+        assert_eq!(matches.resign(id(2)).await, Ok(Ok(())));
+        for inbox in [&mut white, &mut black] {
+            assert_eq!(inbox.recv().await,
+                Some(Message::GameOver { result: GameResult::WhiteWins, reason: GameOverReason::Resignation }));
+            let Some(Message::Pgn { text }) = inbox.recv().await else {
+                panic!("expected pgn");
+            };
+            assert!(text.ends_with("1. e4 1-0\n"));
+        }
+
+        // 끝난 뒤에는 새 대국을 신청할 수 있음
+        assert_eq!(matches.resign(id(1)).await, Ok(Err(ChessRejectReason::NotInMatch)));
+        let _white = join(&matches, 1).await;
+
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_leave() {
+        let (matches, task) = start();
+
+        // 기다리다 나간 플레이어와는 짝지어지지 않음
+        let _first = join(&matches, 1).await;
+        matches.leave(id(1)).await.unwrap();
+        let mut white = join(&matches, 2).await;
+        let mut black = join(&matches, 3).await;
+        assert_eq!(white.recv().await, Some(Message::MatchStarted { color: ChessColor::White, opponent: id(3) }));
+
+        // 대국중에 나가면 상대의 승리
+        matches.leave(id(3)).await.unwrap();
+        loop {
+            match white.recv().await {
+                Some(Message::GameOver { result, reason }) => {
+                    assert_eq!((result, reason), (GameResult::WhiteWins, GameOverReason::Abandoned));
+                    break;
+                },
+                Some(_) => continue,
+                None => panic!("game over was not sent"),
+            }
+        }
+        while let Ok(message) = black.try_recv() {
+            assert!(!matches!(message, Message::GameOver { result: GameResult::BlackWins, .. }));
+        }
+
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_save_pgn() {
+        let dir = std::env::temp_dir().join(format!("game_pgn_{}", std::process::id()));
+        let mut server = MatchServer::with_config(ChessConfig { pgn_dir: Some(dir.clone()) });
+        let matches = server.interface();
+        let task = tokio::spawn(async move { server.run_message_loop().await });
+
+        let _white = join(&matches, 1).await;
+        let _black = join(&matches, 2).await;
+        matches.offer_draw(id(1)).await.unwrap().unwrap();
+        matches.offer_draw(id(2)).await.unwrap().unwrap();
+
+        let saved = tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            loop {
+                if let Ok(mut entries) = std::fs::read_dir(&dir) {
+                    if let Some(Ok(entry)) = entries.next() {
+                        if let Ok(text) = std::fs::read_to_string(entry.path()) {
+                            if text.ends_with("1/2-1/2\n") {
+                                return text;
+                            }
+                        }
+                    }
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+            }
+        }).await.expect("pgn was not saved");
+        assert!(saved.contains("[Result \"1/2-1/2\"]"));
+
+        std::fs::remove_dir_all(dir).unwrap();
+        task.abort();
+    }
+}
@@ -0,0 +1,11 @@
+//! 두 플레이어를 짝지어 진행하는 체스 대국 (8x8 판, 표준 규칙)
+
+mod board;
+mod game;
+mod matches;
+mod pgn;
+
+pub use board::*;
+pub use game::*;
+pub use matches::*;
+pub use pgn::*;
@@ -0,0 +1,241 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+use network::{ChessMove, GameOverReason, GameResult, PieceClass};
+
+use super::Board;
+
+
+/// PGN 한 줄의 최대 길이
+const LINE_WIDTH: usize = 80;
+
+
+fn piece_letter(class: PieceClass) -> &'static str {
+    match class {
+        PieceClass::KNIGHT => "N",
+        PieceClass::BISHOP => "B",
+        PieceClass::ROOK => "R",
+        PieceClass::QUEEN => "Q",
+        PieceClass::KING => "K",
+        _ => "",
+    }
+}
+
+
+/// `board`에서 `mv`를 둘 때의 SAN 표기 (`Nbd7`, `exd5`, `O-O`, `e8=Q#` 등).
+/// `mv`는 합법적인 수여야 한다.
+pub fn san(board: &Board, mv: ChessMove) -> String {
+    let Some(piece) = board.piece_at(mv.from) else {
+        return mv.to_string();
+    };
+
+    let mut text = String::new();
+    let file_diff = mv.to.file() - mv.from.file();
+
+    if piece.class == PieceClass::KING && file_diff.abs() == 2 {
+        text.push_str(if file_diff > 0 { "O-O" } else { "O-O-O" });
+    } else if piece.class == PieceClass::PAWN {
+        // 대각선 이동은 항상 잡기 (앙파상 포함)
+        if file_diff != 0 {
+            text.push_str(&mv.from.to_string()[..1]);
+            text.push('x');
+        }
+        text.push_str(&mv.to.to_string());
+
+        if let Some(class) = mv.promotion {
+            text.push('=');
+            text.push_str(piece_letter(class));
+        }
+    } else {
+        text.push_str(piece_letter(piece.class));
+
+        // 같은 칸으로 갈 수 있는 같은 종류의 다른 말이 있으면 출발 칸으로 구분
+        let others = board.legal_moves().into_iter()
+            .filter(|other| other.to == mv.to && other.from != mv.from)
+            .filter(|other| board.piece_at(other.from) == Some(piece))
+            .map(|other| other.from)
+            .collect::<Vec<_>>();
+
+        if !others.is_empty() {
+            let from = mv.from.to_string();
+            if others.iter().all(|other| other.file() != mv.from.file()) {
+                text.push_str(&from[..1]);
+            } else if others.iter().all(|other| other.rank() != mv.from.rank()) {
+                text.push_str(&from[1..]);
+            } else {
+                text.push_str(&from);
+            }
+        }
+
+        if board.piece_at(mv.to).is_some() {
+            text.push('x');
+        }
+        text.push_str(&mv.to.to_string());
+    }
+
+    let mut next = board.clone();
+    if next.play(mv) && next.in_check(next.turn()) {
+        text.push(if next.legal_moves().is_empty() { '#' } else { '+' });
+    }
+
+    text
+}
+
+
+/// PGN에 기록할 대국 정보
+pub struct PgnGame<'a> {
+    pub white: &'a str,
+    pub black: &'a str,
+    /// 대국이 시작된 시각
+    pub started: SystemTime,
+    /// SAN 표기의 수 목록 (백부터)
+    pub moves: &'a [String],
+    /// 끝나지 않았으면 `None`
+    pub outcome: Option<(GameResult, GameOverReason)>,
+}
+
+impl PgnGame<'_> {
+    /// 태그와 수 목록. 수 목록은 `LINE_WIDTH`에 맞춰 줄바꿈한다.
+    pub fn to_pgn(&self) -> String {
+        let result = self.outcome.map_or("*", |(result, _)| result.pgn());
+        let termination = match self.outcome {
+            None => "unterminated",
+            Some((_, GameOverReason::Abandoned)) => "abandoned",
+            Some(_) => "normal",
+        };
+
+        let mut text = String::new();
+        for (name, value) in [
+            ("Event", "Casual game"),
+            ("Site", "game_server_hw"),
+            ("Date", &pgn_date(self.started)),
+            ("Round", "-"),
+            ("White", self.white),
+            ("Black", self.black),
+            ("Result", result),
+            ("Termination", termination),
+        ] {
+            text.push_str(&format!("[{} \"{}\"]\n", name, value.replace('\\', "\\\\").replace('"', "\\\"")));
+        }
+        text.push('\n');
+
+        let tokens = self.moves.iter().enumerate()
+            .flat_map(|(ply, san)| {
+                let number = (ply % 2 == 0).then(|| format!("{}.", ply / 2 + 1));
+                number.into_iter().chain(Some(san.clone()))
+            })
+            .chain(Some(result.to_string()));
+
+        let mut line_len = 0;
+        for token in tokens {
+            if line_len > 0 && line_len + 1 + token.len() > LINE_WIDTH {
+                text.push('\n');
+                line_len = 0;
+            } else if line_len > 0 {
+                text.push(' ');
+                line_len += 1;
+            }
+
+            text.push_str(&token);
+            line_len += token.len();
+        }
+        text.push('\n');
+
+        text
+    }
+}
+
+
+/// `YYYY.MM.DD` (UTC)
+fn pgn_date(time: SystemTime) -> String {
+    let Ok(elapsed) = time.duration_since(UNIX_EPOCH) else {
+        return "????.??.??".to_string();
+    };
+
+    // 1970-01-01부터의 일수를 그레고리력 날짜로 변환
+    let days = (elapsed.as_secs() / 86400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+
+    format!("{:04}.{:02}.{:02}", year, month, day)
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use network::Square;
+
+    fn mv(from: &str, to: &str) -> ChessMove {
+        ChessMove::new(from.parse().unwrap(), to.parse().unwrap())
+    }
+
+    #[test]
+    fn test_san() {
+        let board = Board::new();
+        assert_eq!(san(&board, mv("e2", "e4")), "e4");
+        assert_eq!(san(&board, mv("g1", "f3")), "Nf3");
+
+        // 파일, 랭크로 구분
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/RN2K2R w KQ - 0 1").unwrap();
+        assert_eq!(san(&board, mv("h1", "f1")), "Rf1");
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/R4RK1 w - - 0 1").unwrap();
+        assert_eq!(san(&board, mv("a1", "d1")), "Rad1");
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1").unwrap();
+        assert_eq!(san(&board, mv("e1", "g1")), "O-O");
+        assert_eq!(san(&board, mv("e1", "c1")), "O-O-O");
+        let board = Board::from_fen("4k3/8/8/8/R7/8/8/R3K3 w - - 0 1").unwrap();
+        assert_eq!(san(&board, mv("a1", "a2")), "R1a2");
+
+        // 잡기, 앙파상, 승급, 체크
+        let board = Board::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+        assert_eq!(san(&board, mv("e5", "d6")), "exd6");
+        let board = Board::from_fen("3rk3/4P3/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let e7: Square = "e7".parse().unwrap();
+        assert_eq!(san(&board, ChessMove::with_promotion(e7, "d8".parse().unwrap(), PieceClass::QUEEN)), "exd8=Q+");
+        assert_eq!(san(&board, mv("e1", "e2")), "Ke2");
+
+        // 체크메이트
+        let board = Board::from_fen("rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq g3 0 2").unwrap();
+        assert_eq!(san(&board, mv("d8", "h4")), "Qh4#");
+    }
+
+    #[test]
+    fn test_pgn() {
+        let moves = ["f3", "e5", "g4", "Qh4#"].map(String::from);
+        let game = PgnGame {
+            white: "Player 1#0",
+            black: "Player \"2\"",
+            started: UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+            moves: &moves,
+            outcome: Some((GameResult::BlackWins, GameOverReason::Checkmate)),
+        };
+
+        let pgn = game.to_pgn();
+        assert!(pgn.contains("[Date \"2023.11.14\"]\n"));
+        assert!(pgn.contains("[Black \"Player \\\"2\\\"\"]\n"));
+        assert!(pgn.contains("[Result \"0-1\"]\n"));
+        assert!(pgn.ends_with("\n\n1. f3 e5 2. g4 Qh4# 0-1\n"));
+
+        // 긴 대국은 줄바꿈
+        let moves = vec!["Nf3".to_string(); 100];
+        let game = PgnGame { moves: &moves, outcome: None, ..game };
+        let pgn = game.to_pgn();
+        assert!(pgn.lines().all(|line| line.len() <= LINE_WIDTH));
+        assert!(pgn.trim_end().ends_with("50. Nf3 Nf3 *"));
+    }
+
+    #[test]
+    fn test_date() {
+        assert_eq!(pgn_date(UNIX_EPOCH), "1970.01.01");
+        assert_eq!(pgn_date(UNIX_EPOCH + Duration::from_secs(951_782_400)), "2000.02.29");
+        assert_eq!(pgn_date(UNIX_EPOCH + Duration::from_secs(1_798_675_200)), "2026.12.31");
+    }
+}
@@ -0,0 +1,139 @@
+use std::fmt;
+use network::*;
+
+
+/// 클라이언트가 서버로 보낼 수 있는 요청
+#[derive(Debug, PartialEq, Clone)]
+pub enum ClientCommand {
+    Ping { time: u128 },
+    Move { id: PlayerId, x: i32, y: i32 },
+    /// 전체 오브젝트 정보 요청 (이전 버전 클라이언트 호환용)
+    UpdateRequest,
+    SelectClass { class: PieceClass },
+    /// 체스 대국 신청
+    MatchRequest,
+    ChessMove { mv: ChessMove },
+    Resign,
+    /// 무승부 제안 또는 수락
+    DrawOffer,
+    Chat { channel: ChatChannel, target: String, text: String },
+    Attack { target: PlayerId },
+}
+
+impl ClientCommand {
+    pub fn parse(packet: RawPacket) -> Result<Self, CommandError> {
+        let message = Message::from_raw(packet).map_err(CommandError::Malformed)?;
+        Self::try_from(message)
+    }
+}
+
+impl TryFrom<Message> for ClientCommand {
+    type Error = CommandError;
+
+    fn try_from(message: Message) -> Result<Self, Self::Error> {
+        match message {
+            Message::Ping { time } => Ok(Self::Ping { time }),
+            Message::Move { id, x, y } => Ok(Self::Move { id, x, y }),
+            Message::UpdateRequest => Ok(Self::UpdateRequest),
+            Message::SelectClass { class } => Ok(Self::SelectClass { class }),
+            Message::MatchRequest => Ok(Self::MatchRequest),
+            Message::ChessMove { mv } => Ok(Self::ChessMove { mv }),
+            Message::Resign => Ok(Self::Resign),
+            Message::DrawOffer => Ok(Self::DrawOffer),
+            Message::ChatSend { channel, target, text } => Ok(Self::Chat { channel, target, text }),
+            Message::Attack { target } => Ok(Self::Attack { target }),
+            message => Err(CommandError::Unexpected(message.packet_type())),
+        }
+    }
+}
+
+
+/// 클라이언트가 보낸 패킷을 요청으로 해석할 수 없는 경우
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum CommandError {
+    /// 본문을 `Message`로 해석할 수 없음
+    Malformed(MessageError),
+    /// 정상적인 메세지지만 클라이언트가 보낼 수 없는 메세지
+    Unexpected(PacketType),
+    /// 로그인하기 전에 보낸 메세지
+    NotLoggedIn(PacketType),
+}
+
+impl CommandError {
+    /// 클라이언트에게 보낼 에러 코드
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::Malformed(_) => ErrorCode::MalformedPacket,
+            Self::Unexpected(_) => ErrorCode::UnexpectedMessage,
+            Self::NotLoggedIn(_) => ErrorCode::LoginRequired,
+        }
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Malformed(error) => write!(f, "Malformed packet: {}", error),
+            Self::Unexpected(packet_type) => {
+                write!(f, "Unexpected message type {} from client", packet_type.value())
+            },
+            Self::NotLoggedIn(packet_type) => {
+                write!(f, "Message type {} sent before login", packet_type.value())
+            },
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::test_util::id;
+
+    #[test]
+    fn test_parse() {
+        let parse = |message: Message| ClientCommand::parse(message.as_raw());
+
+        assert_eq!(parse(Message::Ping { time: 7 }), Ok(ClientCommand::Ping { time: 7 }));
+        assert_eq!(parse(Message::Move { id: id(1), x: 0, y: 1 }), Ok(ClientCommand::Move { id: id(1), x: 0, y: 1 }));
+        assert_eq!(parse(Message::UpdateRequest), Ok(ClientCommand::UpdateRequest));
+        assert_eq!(parse(Message::SelectClass { class: PieceClass::ROOK }),
+            Ok(ClientCommand::SelectClass { class: PieceClass::ROOK }));
+        assert_eq!(parse(Message::MatchRequest), Ok(ClientCommand::MatchRequest));
+        assert_eq!(parse(Message::DrawOffer), Ok(ClientCommand::DrawOffer));
+        assert_eq!(parse(Message::Attack { target: id(2) }), Ok(ClientCommand::Attack { target: id(2) }));
+
+        let chat = Message::ChatSend { channel: ChatChannel::Whisper, target: "2#0".to_string(), text: "hi".to_string() };
+        assert_eq!(parse(chat), Ok(ClientCommand::Chat {
+            channel: ChatChannel::Whisper,
+            target: "2#0".to_string(),
+            text: "hi".to_string(),
+        }));
+
+        let mv = ChessMove::new("e2".parse().unwrap(), "e4".parse().unwrap());
+        assert_eq!(parse(Message::ChessMove { mv }), Ok(ClientCommand::ChessMove { mv }));
+
+        // 서버 -> 클라 메세지
+        let error = parse(Message::Init { id: id(1) }).unwrap_err();
+        assert_eq!(error, CommandError::Unexpected(PacketType::INIT));
+        assert_eq!(error.code(), ErrorCode::UnexpectedMessage);
+        assert_eq!(parse(Message::GameOver { result: GameResult::Draw, reason: GameOverReason::Agreement }),
+            Err(CommandError::Unexpected(PacketType::GAME_OVER)));
+
+        // 로그인은 연결할 때만
+        assert_eq!(parse(Message::Login { name: "alice".to_string(), password: "pass".to_string() }),
+            Err(CommandError::Unexpected(PacketType::LOGIN)));
+        assert_eq!(CommandError::NotLoggedIn(PacketType::MOVE).code(), ErrorCode::LoginRequired);
+
+        let error = ClientCommand::parse(RawPacket::new(PacketType::MOVE, &[1, 2, 3])).unwrap_err();
+        assert_eq!(error, CommandError::Malformed(MessageError::InvalidLength { packet_type: PacketType::MOVE, len: 3 }));
+        assert_eq!(error.code(), ErrorCode::MalformedPacket);
+
+        let error = ClientCommand::parse(RawPacket::new(PacketType::MESSAGE, b"move 1 0 1")).unwrap_err();
+        assert_eq!(error, CommandError::Malformed(MessageError::UnsupportedType(PacketType::MESSAGE)));
+    }
+}
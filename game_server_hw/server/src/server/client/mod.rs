@@ -1,39 +1,200 @@
+mod command;
+
+pub use command::*;
+
+
+use std::{net::SocketAddr, time::Duration};
 use tokio::{
     net::TcpStream,
-    io::{AsyncReadExt, AsyncWriteExt},
+    sync::{broadcast::{self, error::RecvError}, mpsc::{self, error::TrySendError}},
+};
+use tokio_util::{codec::Framed, sync::CancellationToken};
+use futures::{SinkExt, StreamExt};
+use super::{
+    account::{Accounts, Session, WriterInterface},
+    world::{View, WorldInterface, WorldSnapshot},
+    chess::{ChessResult, MatchClosed, MatchInterface},
+    chat::{ChatInterface, ChatResult, ChatClosed},
 };
-use super::world::WorldInterface;
 use network::*;
 
 
+/// 잘못된 패킷을 이만큼 받으면 연결 종료
+pub const MAX_MALFORMED_PACKETS: u32 = 8;
+
+/// 계정을 사용하는 서버에서 접속한 뒤 이 시간 안에 로그인하지 않으면 연결 종료
+pub const LOGIN_TIMEOUT: Duration = Duration::from_secs(30);
+/// 로그인, 가입에 이만큼 실패하면 연결 종료
+pub const MAX_LOGIN_ATTEMPTS: u32 = 3;
+/// 클라이언트가 읽지 않아 `Outbox`에 이만큼 밀리면 연결 종료
+pub const OUTBOX_CAPACITY: usize = 256;
+
+
+/// `Outbox`로 보내지 못한 이유
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutboxError {
+    /// 클라이언트가 밀린 메세지를 읽지 않음. 메세지는 버리고 연결을 끊는다.
+    Full,
+    /// 연결이 끝남
+    Closed,
+}
+
+
+/// 다른 task(`MatchServer`, `ChatRouter` 등)가 클라이언트에게 보내는 메세지를 받는 채널.
+/// 클라이언트 task가 받아서 소켓으로 보낸다.
+/// 크기가 제한되어 있으므로 보내는 쪽은 기다리지 않고, 가득 차면 메세지를 버린다.
+#[derive(Debug, Clone)]
+pub struct Outbox {
+    sender: mpsc::Sender<Message>,
+    /// 가득 차면 취소되고, 클라이언트 task가 연결을 끊는다.
+    overflow: CancellationToken,
+}
+
+impl Outbox {
+    pub fn channel(capacity: usize) -> (Self, mpsc::Receiver<Message>) {
+        let (sender, receiver) = mpsc::channel(capacity);
+        (Self { sender, overflow: CancellationToken::new() }, receiver)
+    }
+
+    pub fn send(&self, message: Message) -> Result<(), OutboxError> {
+        match self.sender.try_send(message) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => {
+                self.overflow.cancel();
+                Err(OutboxError::Full)
+            },
+            Err(TrySendError::Closed(_)) => Err(OutboxError::Closed),
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.sender.is_closed()
+    }
+}
+
+
 pub struct Client {
-    id: u32,
-    
-    stream: TcpStream,
-    packet_parser: PacketParser,
+    id: PlayerId,
+
+    stream: Framed<TcpStream, PacketCodec>,
+    /// 차단할 때 사용. 주소를 알 수 없으면 `None`
+    addr: Option<SocketAddr>,
+    /// 운영자가 내보내면 취소됨
+    kick: CancellationToken,
 
     world: WorldInterface,
+    view: View,
+
+    matches: MatchInterface,
+    chat: ChatInterface,
+    /// 대국 상대의 수, 채팅 등 다른 task가 보내는 메세지
+    outbox: Outbox,
+    inbox: mpsc::Receiver<Message>,
+    /// 서버 종료 등 모든 클라이언트에게 보내는 알림
+    notices: broadcast::Receiver<Message>,
+
+    /// 없으면 로그인 없이 입장
+    accounts: Option<Accounts>,
+    /// 로그아웃할 때의 상태를 저장
+    writer: Option<WriterInterface>,
+    session: Option<Session>,
 
+    /// 말을 골랐거나 이동, 공격한 뒤에는 말을 바꿀 수 없음
+    class_locked: bool,
+    /// 지금까지 받은 잘못된 패킷 수
+    malformed: u32,
     running: bool,
 }
 
 impl Client {
-    pub fn new(id: u32, stream: TcpStream, world: WorldInterface) -> Self {
+    pub fn new(id: PlayerId, stream: TcpStream, world: WorldInterface, matches: MatchInterface, chat: ChatInterface) -> Self {
+        let (outbox, inbox) = Outbox::channel(OUTBOX_CAPACITY);
+        // 로그인하는 중에도 종료 알림을 받도록 먼저 구독
+        let notices = world.subscribe_notices();
+        let addr = stream.peer_addr().ok();
+        let view = View::new(world.view_radius());
+
         Self {
             id,
-            stream,
-            packet_parser: PacketParser::new(),
+            stream: Framed::new(stream, PacketCodec::new()),
+            addr,
+            kick: CancellationToken::new(),
             world,
+            view,
+            matches,
+            chat,
+            outbox,
+            inbox,
+            notices,
+            accounts: None,
+            writer: None,
+            session: None,
+            class_locked: false,
+            malformed: 0,
             running: true,
         }
     }
 
+    /// 입장하기 전에 로그인이나 가입을 요구한다. 로그아웃할 때의 상태는 `writer`로 저장한다.
+    pub fn set_accounts(&mut self, accounts: Accounts, writer: WriterInterface) {
+        self.accounts = Some(accounts);
+        self.writer = Some(writer);
+    }
+
     pub async fn handle_connection(&mut self) {
-        self.world.add_player(self.id).await;
+        // 차단된 주소는 로그인하기 전에 거부
+        match self.world.connect(self.id, self.addr, self.kick.clone()).await {
+            Ok(true) => {},
+            Ok(false) => {
+                let reason = ConnectRejectReason::Banned;
+                let _ = self.stream.send(Message::ConnectRejected { reason }).await;
+                self.running = false;
+                return;
+            },
+            Err(_) => {
+                self.running = false;
+                return;
+            },
+        }
+
+        if let Some(accounts) = self.accounts.clone() {
+            match self.login(&accounts).await {
+                Some(session) => self.session = Some(session),
+                None => {
+                    self.running = false;
+                    return;
+                },
+            }
+        }
 
-        let packet = MessagePacket::new(0, format!("init {}", self.id).as_str());
+        // add되면서 보내지는 스냅샷을 놓치지 않도록 먼저 구독
+        let mut snapshots = self.world.subscribe();
 
-        match self.stream_write(packet.as_raw()).await {
+        // 로그인했으면 마지막으로 로그아웃한 위치와 상태로 입장
+        let added = match &self.session {
+            Some(session) => self.world.login_player(self.id, session.account(), session.state()).await,
+            None => self.world.add_player(self.id).await,
+        };
+
+        match added {
+            Ok(Some(_)) => {},
+            Ok(None) => {
+                // 빈 칸이 없으면 접속 거부
+                let reason = ConnectRejectReason::WorldFull;
+                let _ = self.stream.send(Message::ConnectRejected { reason }).await;
+                self.running = false;
+                return;
+            },
+            Err(_) => {
+                self.running = false;
+                return;
+            },
+        }
+
+        let init = Message::Init { id: self.id };
+        let map = Message::Map { map: self.world.map().clone() };
+
+        match self.stream.send(init).await.and(self.stream.send(map).await) {
             Ok(_) => {
                 // println!("Client {} connected", self.id);
             },
@@ -44,86 +205,865 @@ impl Client {
             }
         }
 
-        let mut buf = [0; 1024];
-    
+        let name = match &self.session {
+            Some(session) => session.name().to_string(),
+            None => format!("Player {}", self.id),
+        };
+        if self.chat.join(self.id, name, self.outbox.clone()).await.is_err() {
+            self.running = false;
+            return;
+        }
+
         while self.running {
-            let read = self.stream.read(&mut buf).await;
-    
-            match read {
-                Ok(0) => {
-                    // println!("Connection closed");
-                    break;
+            tokio::select! {
+                packet = self.stream.next() => match packet {
+                    None => {
+                        // println!("Connection closed");
+                        break;
+                    },
+
+                    Some(Ok(packet)) => {
+                        self.process_packet(packet).await;
+                    },
+
+                    Some(Err(_)) => {
+                        // 헤더가 잘못된 패킷을 보내는 클라이언트는 연결 종료
+                        // eprintln!("Failed to read from socket; err = {:?}", e);
+                        break;
+                    },
+                },
+
+                snapshot = snapshots.recv() => match snapshot {
+                    Ok(snapshot) => {
+                        if self.send_view(&snapshot).await.is_err() {
+                            break;
+                        }
+                    },
+
+                    // 밀린 스냅샷은 버리고 다음 스냅샷을 기다림
+                    Err(RecvError::Lagged(_)) => {},
+
+                    Err(RecvError::Closed) => break,
                 },
-    
-                Ok(n) => {
-                    self.process_packets(&buf[..n]).await;
+
+                // `outbox`를 갖고 있으므로 닫히지 않음
+                Some(message) = self.inbox.recv() => {
+                    if self.stream.send(message).await.is_err() {
+                        break;
+                    }
                 },
-    
-                Err(_) => {
-                    // eprintln!("Failed to read from socket; err = {:?}", e);
+
+                // 밀린 메세지를 읽지 않는 클라이언트는 연결 종료
+                _ = self.outbox.overflow.cancelled() => break,
+
+                notice = self.notices.recv() => {
+                    if !self.send_notice(notice).await {
+                        break;
+                    }
+                },
+
+                _ = self.kick.cancelled() => {
+                    self.send_kicked().await;
                     break;
                 },
-            };
+            }
+        }
+
+        self.logout().await;
+    }
+
+    /// 알림을 보낸다. 서버가 지금 종료하는 알림(`Shutdown { seconds: 0 }`)이었거나 보내지 못했으면 `false`
+    async fn send_notice(&mut self, notice: Result<Message, RecvError>) -> bool {
+        match notice {
+            Ok(message) => {
+                let last = message == Message::Shutdown { seconds: 0 };
+                self.stream.send(message).await.is_ok() && !last
+            },
+
+            // 밀린 알림은 버림
+            Err(RecvError::Lagged(_)) => true,
+
+            Err(RecvError::Closed) => false,
         }
+    }
 
-        self.world.remove_player(self.id).await;
+    /// 운영자가 내보냈음을 알린다. (보내지 못해도 연결은 끊음)
+    async fn send_kicked(&mut self) {
+        let reason = ConnectRejectReason::Kicked;
+        let _ = self.stream.send(Message::ConnectRejected { reason }).await;
     }
 
-    
-    async fn process_packets(&mut self, data: &[u8]) {
-        self.packet_parser.push(data);
 
-        while let Some(packet) = self.packet_parser.pop() {
-            let packet = match MessagePacket::from_raw(packet) {
-                Ok(packet) => packet,
-                Err(_) => continue,
+    /// 로그인이나 가입에 성공할 때까지 다른 요청은 받지 않는다. (ping 제외)
+    /// 시간 안에 로그인하지 못했거나, 너무 많이 실패했거나, 연결이 끊기면 `None`
+    async fn login(&mut self, accounts: &Accounts) -> Option<Session> {
+        let deadline = tokio::time::sleep(LOGIN_TIMEOUT);
+        tokio::pin!(deadline);
+        let mut attempts = 0;
+
+        while self.running {
+            let packet = tokio::select! {
+                packet = self.stream.next() => packet,
+                _ = &mut deadline => return None,
+                notice = self.notices.recv() => {
+                    if !self.send_notice(notice).await {
+                        return None;
+                    }
+                    continue;
+                },
+                _ = self.kick.cancelled() => {
+                    self.send_kicked().await;
+                    return None;
+                },
+            };
+            let Some(Ok(packet)) = packet else {
+                return None;
+            };
+
+            let result = match Message::from_raw(packet) {
+                Ok(Message::Login { name, password }) => accounts.login(name, password).await,
+                Ok(Message::Register { name, password }) => accounts.register(name, password).await,
+
+                Ok(message) => {
+                    let response = match message {
+                        Message::Ping { time } => Message::Pong { time },
+                        message => self.reject(CommandError::NotLoggedIn(message.packet_type())),
+                    };
+                    self.stream.send(response).await.ok()?;
+                    continue;
+                },
+
+                Err(error) => {
+                    let response = self.reject(CommandError::Malformed(error));
+                    self.stream.send(response).await.ok()?;
+                    continue;
+                },
+            };
+
+            let error = match result {
+                Ok(session) => return Some(session),
+                Err(error) => error,
             };
-            
-            let msg = packet.msg;
+            let reason = error.reason();
+            if reason == LoginRejectReason::Unavailable {
+                eprintln!("Client {}: {}", self.id, error);
+            }
+
+            attempts += 1;
+            self.stream.send(Message::LoginRejected { reason }).await.ok()?;
+            if attempts >= MAX_LOGIN_ATTEMPTS {
+                return None;
+            }
+        }
+
+        None
+    }
+
+    /// 로그인한 플레이어를 월드에서 삭제하고 상태를 저장한다.
+    /// 저장이 끝난 다음에 세션을 닫으므로, 다시 로그인하면 저장된 상태로 입장한다.
+    async fn logout(&mut self) {
+        let (Some(writer), Some(session)) = (&self.writer, self.session.take()) else {
+            return;
+        };
+
+        if let Ok(Some(state)) = self.world.take_player(self.id).await {
+            match writer.save(session.account(), state).await {
+                Ok(Ok(())) => {},
+                Ok(Err(error)) => eprintln!("Client {}: {}", self.id, error),
+                Err(error) => eprintln!("Client {}: {}", self.id, error),
+            }
+        }
+    }
+
+
+    /// 스냅샷에서 시야 안의 변경사항만 보냄
+    async fn send_view(&mut self, snapshot: &WorldSnapshot) -> Result<(), std::io::Error> {
+        let messages = self.view.update(self.id, snapshot);
+        self.send_messages(messages).await
+    }
+
+    async fn send_messages(&mut self, messages: Vec<Message>) -> Result<(), std::io::Error> {
+        if messages.is_empty() {
+            return Ok(());
+        }
+
+        for msg in messages {
+            self.stream.feed(msg).await?;
+        }
+        SinkExt::<Message>::flush(&mut self.stream).await
+    }
+
+    async fn process_packet(&mut self, packet: RawPacket) {
+        let response = match ClientCommand::parse(packet) {
+            Ok(command) => self.process_command(command).await,
+            Err(error) => Some(self.reject(error)),
+        };
+
+        if let Some(response) = response {
+            match self.stream.send(response).await {
+                Ok(_) => {},
+                Err(_) => {
+                    self.running = false;
+                    // eprintln!("Failed to write to socket; err = {:?}", e);
+                }
+            }
+        }
+    }
+
+    /// 잘못된 패킷에 대한 응답. 너무 많이 받으면 응답을 보낸 뒤 연결 종료
+    fn reject(&mut self, error: CommandError) -> Message {
+        // eprintln!("Client {}: {}", self.id, error);
+        self.malformed += 1;
+
+        if self.malformed >= MAX_MALFORMED_PACKETS {
+            self.running = false;
+            return Message::Error { code: ErrorCode::TooManyErrors };
+        }
 
-            if let Some(response) = self.process_message(&msg).await {
-                let packet = MessagePacket::new(packet.time, &response);
+        Message::Error { code: error.code() }
+    }
+
+    async fn process_command(&mut self, command: ClientCommand) -> Option<Message> {
+        match command {
+            ClientCommand::Ping { time } => Some(Message::Pong { time }),
 
-                match self.stream_write(packet.as_raw()).await {
-                    Ok(_) => {},
+            ClientCommand::Move { id, x, y } => {
+                if let Err(reason) = validate_move(self.id, id, x, y) {
+                    return Some(Message::MoveRejected { x, y, reason });
+                }
+
+                // 이동시킬 플레이어는 항상 이 연결의 플레이어.
+                // 이동한 결과는 스냅샷으로 전달되고, 이동하지 못했으면 이유를 알려준다.
+                match self.world.move_player(self.id, x, y).await {
+                    Ok(Ok(_)) => {
+                        self.class_locked = true;
+                        None
+                    },
+                    Ok(Err(reason)) => Some(Message::MoveRejected { x, y, reason }),
                     Err(_) => {
                         self.running = false;
-                        // eprintln!("Failed to write to socket; err = {:?}", e);
-                        break;
-                    }
+                        None
+                    },
                 }
-            }
+            },
+
+            // 전체 월드가 아니라 시야 안의 오브젝트만 (이전 버전 클라이언트 호환용)
+            ClientCommand::UpdateRequest => {
+                let messages = self.view.snapshot_messages();
+                if self.send_messages(messages).await.is_err() {
+                    self.running = false;
+                }
+                None
+            },
+
+            // 바뀐 말은 스냅샷으로 전달됨
+            ClientCommand::SelectClass { class } => {
+                if self.class_locked {
+                    return Some(Message::Error { code: ErrorCode::ClassLocked });
+                }
+
+                if self.world.set_class(self.id, class).await.is_err() {
+                    self.running = false;
+                }
+                self.class_locked = true;
+
+                None
+            },
+
+            ClientCommand::MatchRequest => {
+                let result = self.matches.join(self.id, self.outbox.clone()).await;
+                self.chess_response(result)
+            },
+
+            // 둔 수는 `ChessBoard`로 전달됨
+            ClientCommand::ChessMove { mv } => {
+                let result = self.matches.play(self.id, mv).await;
+                self.chess_response(result)
+            },
+
+            ClientCommand::Resign => {
+                let result = self.matches.resign(self.id).await;
+                self.chess_response(result)
+            },
+
+            ClientCommand::DrawOffer => {
+                let result = self.matches.offer_draw(self.id).await;
+                self.chess_response(result)
+            },
+
+            // 피해와 죽음은 스냅샷과 함께 전달됨
+            ClientCommand::Attack { target } => {
+                match self.world.attack(self.id, target).await {
+                    Ok(Ok(())) => {
+                        self.class_locked = true;
+                        None
+                    },
+                    Ok(Err(reason)) => Some(Message::AttackRejected { target, reason }),
+                    Err(_) => {
+                        self.running = false;
+                        None
+                    },
+                }
+            },
+
+            // 전달된 채팅은 `outbox`로 받음
+            ClientCommand::Chat { channel, target, text } => {
+                let result = self.chat.send(self.id, channel, target, text).await;
+                self.chat_response(result)
+            },
+        }
+    }
+
+    /// 채팅이 거부되었을 때만 응답한다.
+    fn chat_response(&mut self, result: Result<ChatResult, ChatClosed>) -> Option<Message> {
+        match result {
+            Ok(Ok(())) => None,
+            Ok(Err(reason)) => Some(Message::ChatRejected { reason }),
+            Err(_) => {
+                self.running = false;
+                None
+            },
         }
     }
 
-    async fn process_message(&mut self, msg: &str) -> Option<String> {
-        let msg = msg.trim().split_whitespace()
-            .collect::<Vec<&str>>();
-    
-        if msg.len() == 0 {
-            return None;
-        }
-    
-        match msg[0] {
-            "ping" => Some("pong".to_string()),
-    
-            "move" if msg.len() == 4 => {
-                let id = msg[1].parse::<u32>().unwrap();
-                let x = msg[2].parse::<i32>().unwrap();
-                let y = msg[3].parse::<i32>().unwrap();
-    
-                self.world.move_player(id, x, y).await;
-    
+    /// 체스 요청이 거부되었을 때만 응답한다.
+    fn chess_response(&mut self, result: Result<ChessResult, MatchClosed>) -> Option<Message> {
+        match result {
+            Ok(Ok(())) => None,
+            Ok(Err(reason)) => Some(Message::ChessRejected { reason }),
+            Err(_) => {
+                self.running = false;
                 None
             },
-    
-            "update" => Some(self.world.update_message()),
-    
-            _ => None
         }
     }
+}
+
+
+/// `player_id`의 연결에서 들어온 이동 요청 검증.
+/// 자신의 플레이어만 이동할 수 있고, 어떤 말로도 할 수 없는 이동은 `World`에 보내지 않는다.
+/// (플레이어의 말에 맞는 이동인지는 `World`에서 확인)
+pub fn validate_move(player_id: PlayerId, id: PlayerId, x: i32, y: i32) -> Result<(), MoveRejectReason> {
+    if id != player_id {
+        return Err(MoveRejectReason::NotOwner);
+    }
+
+    if !PieceClass::ALL.iter().any(|class| class.is_valid_move(x, y)) {
+        return Err(MoveRejectReason::InvalidStep);
+    }
+
+    Ok(())
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::{io::AsyncWriteExt, net::TcpListener, task::JoinHandle};
+    use config::WorldConfig;
+    use config::AccountConfig;
+    use super::super::{
+        account::{AccountStore, PlayerWriter},
+        world::World,
+        chess::MatchServer,
+        chat::ChatRouter,
+    };
+    use crate::server::test_util::id;
+
+    #[test]
+    fn test_outbox() {
+        let (outbox, mut inbox) = Outbox::channel(2);
+        outbox.send(Message::Ping { time: 1 }).unwrap();
+        outbox.send(Message::Ping { time: 2 }).unwrap();
+        assert!(!outbox.overflow.is_cancelled());
+
+        // 가득 차면 기다리지 않고 버린 뒤 연결을 끊도록 알림
+        assert_eq!(outbox.send(Message::Ping { time: 3 }), Err(OutboxError::Full));
+        assert!(outbox.overflow.is_cancelled());
+        assert_eq!(inbox.try_recv(), Ok(Message::Ping { time: 1 }));
+        assert_eq!(inbox.try_recv(), Ok(Message::Ping { time: 2 }));
+        assert!(inbox.try_recv().is_err());
+
+        drop(inbox);
+        assert!(outbox.is_closed());
+        assert_eq!(outbox.send(Message::Ping { time: 4 }), Err(OutboxError::Closed));
+    }
+
+    #[test]
+    fn test_validate_move() {
+        assert_eq!(validate_move(id(1), id(1), 1, 0), Ok(()));
+        assert_eq!(validate_move(id(1), id(1), 0, -1), Ok(()));
+
+        assert_eq!(validate_move(id(1), id(2), 1, 0), Err(MoveRejectReason::NotOwner));
+        assert_eq!(validate_move(id(1), id(2), 5, 5), Err(MoveRejectReason::NotOwner));
+
+        // 말에 따라 가능한 이동
+        assert_eq!(validate_move(id(1), id(1), 1, 1), Ok(()));
+        assert_eq!(validate_move(id(1), id(1), 0, -7), Ok(()));
+        assert_eq!(validate_move(id(1), id(1), -2, 1), Ok(()));
+
+        assert_eq!(validate_move(id(1), id(1), 0, 0), Err(MoveRejectReason::InvalidStep));
+        assert_eq!(validate_move(id(1), id(1), 2, 3), Err(MoveRejectReason::InvalidStep));
+        assert_eq!(validate_move(id(1), id(1), i32::MIN, 0), Err(MoveRejectReason::InvalidStep));
+    }
+
+    /// 별도의 task에서 실행되는 월드, 대국 서버, 채팅과 연결을 받는 서버.
+    /// 드랍하면 모든 task를 멈춘다.
+    struct TestServer {
+        addr: SocketAddr,
+        world: WorldInterface,
+        /// 모든 연결이 끝나면 완료
+        clients: JoinHandle<()>,
+        tasks: Vec<JoinHandle<()>>,
+    }
+
+    impl TestServer {
+        /// `map`이 없으면 `config`의 빈 맵을 사용한다. `accounts`가 있으면 메모리의 계정 저장소로 로그인을 요구한다.
+        /// 연결을 `count`개 받고 순서대로 id 1부터 붙인다. 각 연결은 별도의 task에서 처리하고,
+        /// 끝나면 `run_with_cleanup`처럼 월드에서 삭제한다.
+        async fn start(config: WorldConfig, map: Option<&str>, accounts: Option<AccountConfig>, count: u16) -> Self {
+            let config = WorldConfig { tick_rate: 100, ..config };
+            let mut world = match map {
+                Some(map) => World::with_map(config, map.parse().unwrap()),
+                None => World::with_config(config).unwrap(),
+            };
+            let interface = world.interface();
+            let mut tasks = vec![tokio::spawn(async move { world.run_message_loop().await })];
+
+            let mut match_server = MatchServer::new();
+            let matches = match_server.interface();
+            tasks.push(tokio::spawn(async move { match_server.run_message_loop().await }));
+
+            let mut router = ChatRouter::new(interface.clone());
+            let chat = router.interface();
+            tasks.push(tokio::spawn(async move { router.run_message_loop().await }));
+
+            let accounts = accounts.map(|config| {
+                let accounts = Accounts::with_store(AccountStore::open_in_memory().unwrap(), &config);
+                let mut writer = PlayerWriter::new(accounts.clone(), &config);
+                let writer_interface = writer.interface();
+                tasks.push(tokio::spawn(async move { writer.run_message_loop().await }));
+                (accounts, writer_interface)
+            });
+
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+
+            let world = interface.clone();
+            let clients = tokio::spawn(async move {
+                let mut connections = Vec::new();
+                for index in 1..=count {
+                    let (stream, _) = listener.accept().await.unwrap();
+                    let mut client = Client::new(id(index), stream, world.clone(), matches.clone(), chat.clone());
+                    if let Some((accounts, writer)) = &accounts {
+                        client.set_accounts(accounts.clone(), writer.clone());
+                    }
+
+                    let world = world.clone();
+                    connections.push(tokio::spawn(async move {
+                        client.handle_connection().await;
+                        world.remove_player(id(index)).await.unwrap();
+                    }));
+                }
+                for connection in connections {
+                    connection.await.unwrap();
+                }
+            });
+
+            Self { addr, world: interface, clients, tasks }
+        }
+
+        async fn connect(&self) -> Framed<TcpStream, MessageCodec> {
+            Framed::new(TcpStream::connect(self.addr).await.unwrap(), MessageCodec::new())
+        }
+
+        /// 모든 연결이 끝날 때까지 기다림
+        async fn finish(&mut self) {
+            tokio::time::timeout(Duration::from_secs(5), &mut self.clients).await
+                .expect("connection was not closed")
+                .unwrap();
+        }
+    }
+
+    impl Drop for TestServer {
+        fn drop(&mut self) {
+            self.clients.abort();
+            for task in &self.tasks {
+                task.abort();
+            }
+        }
+    }
+
+    async fn next_message(stream: &mut Framed<TcpStream, MessageCodec>) -> Message {
+        tokio::time::timeout(Duration::from_secs(5), stream.next()).await
+            .expect("timed out")
+            .expect("connection closed")
+            .expect("invalid message")
+    }
+
+    /// 시야 갱신 메세지를 건너뛴 다음 메세지
+    async fn next_reply(stream: &mut Framed<TcpStream, MessageCodec>) -> Message {
+        loop {
+            match next_message(stream).await {
+                Message::UpdateSnapshot { .. } |
+                Message::EnterView { .. } |
+                Message::LeaveView { .. } => continue,
+                message => return message,
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rejected_moves() {
+        let mut server = TestServer::start(WorldConfig::default(), None, None, 1).await;
+
+        // 다른 플레이어 (id 2)
+        server.world.add_player(id(2)).await.unwrap();
+
+        let mut stream = server.connect().await;
+        assert_eq!(next_message(&mut stream).await, Message::Init { id: id(1) });
+        assert_eq!(next_message(&mut stream).await, Message::Map { map: TileMap::open(8, 8, (3, 3)).unwrap() });
+
+        stream.send(Message::Move { id: id(2), x: 1, y: 0 }).await.unwrap();
+        assert_eq!(next_reply(&mut stream).await,
+            Message::MoveRejected { x: 1, y: 0, reason: MoveRejectReason::NotOwner });
+
+        stream.send(Message::Move { id: id(1), x: 3, y: 0 }).await.unwrap();
+        assert_eq!(next_reply(&mut stream).await,
+            Message::MoveRejected { x: 3, y: 0, reason: MoveRejectReason::InvalidStep });
+
+        // 다른 플레이어가 있는 칸 (id 2는 (3, 3), id 1은 그 옆 (4, 3)에 생성됨)
+        stream.send(Message::Move { id: id(1), x: -1, y: 0 }).await.unwrap();
+        assert_eq!(next_reply(&mut stream).await,
+            Message::MoveRejected { x: -1, y: 0, reason: MoveRejectReason::Occupied });
+
+        // 맵 밖 ((4, 0)에서 한칸 더 위)
+        for _ in 0..4 {
+            stream.send(Message::Move { id: id(1), x: 0, y: -1 }).await.unwrap();
+        }
+        assert_eq!(next_reply(&mut stream).await,
+            Message::MoveRejected { x: 0, y: -1, reason: MoveRejectReason::Blocked });
+
+        // 올바른 이동은 응답 없이 반영됨. 시야 갱신으로 확인
+        stream.send(Message::Move { id: id(1), x: 0, y: 1 }).await.unwrap();
+        loop {
+            match next_message(&mut stream).await {
+                Message::UpdateSnapshot { objects } | Message::EnterView { objects } => {
+                    let positions = objects.iter()
+                        .map(|&ObjectInfo { id, x, y, .. }| (id, x, y))
+                        .collect::<Vec<_>>();
+
+                    // 다른 플레이어는 움직이지 않음
+                    assert!(positions.iter().all(|&(other, x, y)| other != id(2) || (x, y) == (3, 3)));
+                    if positions.contains(&(id(1), 4, 1)) {
+                        break;
+                    }
+                },
+                _ => {},
+            }
+        }
+
+        drop(stream);
+        server.finish().await;
+    }
+
+    #[tokio::test]
+    async fn test_select_class_once() {
+        let server = TestServer::start(WorldConfig::default(), None, None, 2).await;
+
+        // 입장한 뒤 한번만 고를 수 있음
+        let mut first = server.connect().await;
+        first.send(Message::SelectClass { class: PieceClass::ROOK }).await.unwrap();
+        wait_for_object(&mut first, ObjectInfo::new(id(1), 3, 3, PieceClass::ROOK)).await;
+        first.send(Message::SelectClass { class: PieceClass::QUEEN }).await.unwrap();
+        assert_eq!(next_reply(&mut first).await, Message::Error { code: ErrorCode::ClassLocked });
+
+        // 이동한 뒤에는 바꿀 수 없음 (id 2는 (3, 3) 옆 (4, 3)에 생성됨)
+        let mut second = server.connect().await;
+        second.send(Message::Move { id: id(2), x: 0, y: 1 }).await.unwrap();
+        wait_for_object(&mut second, ObjectInfo::new(id(2), 4, 4, PieceClass::PAWN)).await;
+        second.send(Message::SelectClass { class: PieceClass::QUEEN }).await.unwrap();
+        assert_eq!(next_reply(&mut second).await, Message::Error { code: ErrorCode::ClassLocked });
+        wait_for_object(&mut second, ObjectInfo::new(id(2), 4, 4, PieceClass::PAWN)).await;
+    }
+
+    #[tokio::test]
+    async fn test_world_full() {
+        let mut server = TestServer::start(WorldConfig::default(), Some("S#\n"), None, 1).await;
+
+        // 하나뿐인 칸을 차지
+        server.world.add_player(id(2)).await.unwrap();
+
+        let mut stream = server.connect().await;
+        assert_eq!(next_message(&mut stream).await,
+            Message::ConnectRejected { reason: ConnectRejectReason::WorldFull });
+
+        server.finish().await;
+        assert!(stream.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_view_radius() {
+        let config = WorldConfig { view_radius: 1, ..Default::default() };
+        let mut server = TestServer::start(config, Some("S.S\n"), None, 1).await;
+
+        // (0, 0)에 먼저 생성되므로 접속하는 플레이어는 2칸 떨어진 (2, 0)
+        let far = server.world.add_player(id(2)).await.unwrap().unwrap();
+        assert_eq!((far.x, far.y), (0, 0));
+
+        let mut stream = server.connect().await;
+        let me = ObjectInfo::new(id(1), 2, 0, PieceClass::PAWN);
+        wait_for_object(&mut stream, me).await;
+
+        // 시야 밖의 플레이어는 `update` 요청에도 보내지 않음
+        stream.send(Message::UpdateRequest).await.unwrap();
+        loop {
+            match next_message(&mut stream).await {
+                Message::UpdateSnapshot { objects } => {
+                    assert_eq!(objects, vec![me]);
+                    break;
+                },
+                Message::EnterView { objects } => assert_eq!(objects, vec![me]),
+                message => panic!("unexpected message {:?}", message),
+            }
+        }
+
+        drop(stream);
+        server.finish().await;
+    }
+
+    #[tokio::test]
+    async fn test_kick_and_ban() {
+        let mut server = TestServer::start(WorldConfig::default(), None, None, 2).await;
+
+        let mut stream = server.connect().await;
+        assert_eq!(next_message(&mut stream).await, Message::Init { id: id(1) });
+        assert!(matches!(next_message(&mut stream).await, Message::Map { .. }));
+
+        assert_eq!(server.world.kick(id(1)).await, Ok(true));
+        assert_eq!(next_reply(&mut stream).await, Message::ConnectRejected { reason: ConnectRejectReason::Kicked });
+        assert!(stream.next().await.is_none());
+
+        // 차단된 주소는 입장하기 전에 거부
+        assert_eq!(server.world.ban(server.addr.ip()).await, Ok(0));
+        let mut stream = server.connect().await;
+        assert_eq!(next_message(&mut stream).await, Message::ConnectRejected { reason: ConnectRejectReason::Banned });
+        assert!(stream.next().await.is_none());
+
+        server.finish().await;
+        assert!(server.world.list().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_malformed_packets_disconnect() {
+        let mut server = TestServer::start(WorldConfig::default(), None, None, 1).await;
+
+        let mut stream = server.connect().await;
+        assert_eq!(next_message(&mut stream).await, Message::Init { id: id(1) });
+        assert_eq!(next_message(&mut stream).await, Message::Map { map: TileMap::open(8, 8, (3, 3)).unwrap() });
+
+        // 서버 -> 클라 메세지
+        stream.send(Message::Init { id: id(5) }).await.unwrap();
+        assert_eq!(next_reply(&mut stream).await, Message::Error { code: ErrorCode::UnexpectedMessage });
+
+        // 정상적인 요청은 계속 처리됨
+        stream.send(Message::Ping { time: 1 }).await.unwrap();
+        assert_eq!(next_reply(&mut stream).await, Message::Pong { time: 1 });
+
+        // 본문이 잘못된 패킷
+        let malformed = RawPacket::new(PacketType::MOVE, &[1, 2, 3]).as_bytes();
+        for _ in 1..MAX_MALFORMED_PACKETS - 1 {
+            stream.get_mut().write_all(&malformed).await.unwrap();
+            assert_eq!(next_reply(&mut stream).await, Message::Error { code: ErrorCode::MalformedPacket });
+        }
+
+        stream.get_mut().write_all(&malformed).await.unwrap();
+        assert_eq!(next_reply(&mut stream).await, Message::Error { code: ErrorCode::TooManyErrors });
+
+        // 서버가 연결을 종료함
+        server.finish().await;
+        while let Some(Ok(_)) = stream.next().await {}
+    }
+
+    #[tokio::test]
+    async fn test_packets_before_invalid_header() {
+        let mut server = TestServer::start(WorldConfig::default(), None, None, 1).await;
+
+        let mut stream = server.connect().await;
+        assert_eq!(next_message(&mut stream).await, Message::Init { id: id(1) });
+        assert!(matches!(next_message(&mut stream).await, Message::Map { .. }));
+
+        // 헤더가 잘못된 패킷과 한번에 도착해도, 그 이전에 완성된 패킷은 연결을 끊기 전에 처리
+        let mut bytes = Message::Ping { time: 1 }.as_raw().as_bytes();
+        bytes.extend_from_slice(&1u16.to_ne_bytes());
+        stream.get_mut().write_all(&bytes).await.unwrap();
+        assert_eq!(next_reply(&mut stream).await, Message::Pong { time: 1 });
+
+        server.finish().await;
+        assert!(stream.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_chess_match() {
+        let server = TestServer::start(WorldConfig::default(), None, None, 2).await;
+
+        let mut white = server.connect().await;
+        let mut black = server.connect().await;
+        for (stream, index) in [(&mut white, 1), (&mut black, 2)] {
+            assert_eq!(next_message(stream).await, Message::Init { id: id(index) });
+            assert!(matches!(next_message(stream).await, Message::Map { .. }));
+        }
+
+        // 대국 전에는 둘 수 없음
+        let mv = |from: &str, to: &str| Message::ChessMove {
+            mv: ChessMove::new(from.parse().unwrap(), to.parse().unwrap()),
+        };
+        white.send(mv("e2", "e4")).await.unwrap();
+        assert_eq!(next_reply(&mut white).await, Message::ChessRejected { reason: ChessRejectReason::NotInMatch });
+
+        white.send(Message::MatchRequest).await.unwrap();
+        // 응답이 없으므로 ping으로 처리 순서를 맞춤
+        white.send(Message::Ping { time: 1 }).await.unwrap();
+        assert_eq!(next_reply(&mut white).await, Message::Pong { time: 1 });
+
+        black.send(Message::MatchRequest).await.unwrap();
+        assert_eq!(next_reply(&mut black).await, Message::MatchStarted { color: ChessColor::Black, opponent: id(1) });
+        assert_eq!(next_reply(&mut white).await, Message::MatchStarted { color: ChessColor::White, opponent: id(2) });
+
+        for stream in [&mut white, &mut black] {
+            assert!(matches!(next_reply(stream).await, Message::ChessBoard { .. }));
+        }
+
+        // 상대의 차례
+        black.send(mv("e7", "e5")).await.unwrap();
+        assert_eq!(next_reply(&mut black).await, Message::ChessRejected { reason: ChessRejectReason::NotYourTurn });
+
+        // 바보의 메이트. 매 수마다 양쪽 모두 체스판을 받음
+        for (color, from, to) in [(0, "f2", "f3"), (1, "e7", "e5"), (0, "g2", "g4"), (1, "d8", "h4")] {
+            let mover = if color == 0 { &mut white } else { &mut black };
+            mover.send(mv(from, to)).await.unwrap();
+
+            for stream in [&mut white, &mut black] {
+                assert!(matches!(next_reply(stream).await, Message::ChessBoard { .. }));
+            }
+        }
+
+        for stream in [&mut white, &mut black] {
+            assert_eq!(next_reply(stream).await, Message::GameOver { result: GameResult::BlackWins, reason: GameOverReason::Checkmate });
+
+            let Message::Pgn { text } = next_reply(stream).await else {
+                panic!("expected pgn");
+            };
+            assert!(text.ends_with("1. f3 e5 2. g4 Qh4# 0-1\n"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_chat() {
+        let server = TestServer::start(WorldConfig::default(), None, None, 2).await;
+
+        let mut first = server.connect().await;
+        let mut second = server.connect().await;
+
+        // 채팅 참가는 `Map` 다음이므로 ping으로 기다림
+        for stream in [&mut first, &mut second] {
+            stream.send(Message::Ping { time: 1 }).await.unwrap();
+            assert!(matches!(next_reply(stream).await, Message::Init { .. }));
+            assert!(matches!(next_reply(stream).await, Message::Map { .. }));
+            assert_eq!(next_reply(stream).await, Message::Pong { time: 1 });
+        }
+
+        let chat = |channel, target: &str, text: &str| Message::ChatSend {
+            channel,
+            target: target.to_string(),
+            text: text.to_string(),
+        };
+
+        first.send(chat(ChatChannel::Global, "", "hello")).await.unwrap();
+        for stream in [&mut first, &mut second] {
+            assert_eq!(next_reply(stream).await, Message::Chat {
+                channel: ChatChannel::Global,
+                from: id(1),
+                name: "Player 1#0".to_string(),
+                text: "hello".to_string(),
+            });
+        }
+
+        second.send(chat(ChatChannel::Whisper, "1#0", "hi")).await.unwrap();
+        second.send(chat(ChatChannel::Whisper, "Player 7#0", "anyone?")).await.unwrap();
+        assert_eq!(next_reply(&mut second).await, Message::ChatRejected { reason: ChatRejectReason::UnknownTarget });
+        assert!(matches!(next_reply(&mut first).await, Message::Chat { channel: ChatChannel::Whisper, text, .. } if text == "hi"));
+    }
+
+    /// 전체 오브젝트 정보에 `info`가 있을 때까지 요청
+    async fn wait_for_object(stream: &mut Framed<TcpStream, MessageCodec>, info: ObjectInfo) {
+        stream.send(Message::UpdateRequest).await.unwrap();
+        loop {
+            if let Message::UpdateSnapshot { objects } = next_message(stream).await {
+                if objects.contains(&info) {
+                    break;
+                }
+                stream.send(Message::UpdateRequest).await.unwrap();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_login() {
+        let config = AccountConfig { min_password_len: 4, ..Default::default() };
+        let mut server = TestServer::start(WorldConfig::default(), None, Some(config), 3).await;
+
+        let login = |name: &str, password: &str| Message::Login { name: name.to_string(), password: password.to_string() };
+        let register = |name: &str, password: &str| Message::Register { name: name.to_string(), password: password.to_string() };
 
-    async fn stream_write(&mut self, packet: RawPacket) -> Result<(), std::io::Error> {
-        self.stream.write_all(&packet.as_bytes()).await
+        // 로그인 전에는 ping만 응답
+        let mut stream = server.connect().await;
+        stream.send(Message::Move { id: id(1), x: 1, y: 0 }).await.unwrap();
+        assert_eq!(next_message(&mut stream).await, Message::Error { code: ErrorCode::LoginRequired });
+        stream.send(Message::Ping { time: 1 }).await.unwrap();
+        assert_eq!(next_message(&mut stream).await, Message::Pong { time: 1 });
+
+        stream.send(register("alice", "abc")).await.unwrap();
+        assert_eq!(next_message(&mut stream).await, Message::LoginRejected { reason: LoginRejectReason::InvalidPassword });
+        stream.send(register("alice", "pass")).await.unwrap();
+        assert_eq!(next_message(&mut stream).await, Message::Init { id: id(1) });
+        assert!(matches!(next_message(&mut stream).await, Message::Map { .. }));
+
+        // 로그인한 뒤에는 보낼 수 없음
+        stream.send(login("alice", "pass")).await.unwrap();
+        assert_eq!(next_reply(&mut stream).await, Message::Error { code: ErrorCode::UnexpectedMessage });
+
+        // 스폰 타일 (3, 3)에서 (3, 4)로 이동한 뒤 로그아웃
+        stream.send(Message::Move { id: id(1), x: 0, y: 1 }).await.unwrap();
+        wait_for_object(&mut stream, ObjectInfo::new(id(1), 3, 4, PieceClass::PAWN)).await;
+        drop(stream);
+
+        // 로그아웃하면서 저장하고 월드에서 삭제될 때까지 기다림
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !server.world.list().await.unwrap().is_empty() {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        }).await.expect("player was not logged out");
+
+        // 로그인에 너무 많이 실패하면 연결 종료
+        let mut stream = server.connect().await;
+        for _ in 0..MAX_LOGIN_ATTEMPTS {
+            stream.send(login("alice", "word")).await.unwrap();
+            assert_eq!(next_message(&mut stream).await, Message::LoginRejected { reason: LoginRejectReason::InvalidCredentials });
+        }
+        assert!(stream.next().await.is_none());
+
+        // 저장된 위치에서 다시 시작
+        let mut stream = server.connect().await;
+        stream.send(login("ALICE", "pass")).await.unwrap();
+        assert_eq!(next_message(&mut stream).await, Message::Init { id: id(3) });
+        assert!(matches!(next_message(&mut stream).await, Message::Map { .. }));
+        wait_for_object(&mut stream, ObjectInfo::new(id(3), 3, 4, PieceClass::PAWN)).await;
+
+        drop(stream);
+        server.finish().await;
     }
-}
\ No newline at end of file
+}
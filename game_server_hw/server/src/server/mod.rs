@@ -1,3 +1,11 @@
 pub mod tcp_server;
 pub mod world;
-pub mod client;
\ No newline at end of file
+pub mod client;
+pub mod chess;
+pub mod chat;
+pub mod slot;
+pub mod queue;
+pub mod account;
+pub mod admin;
+#[cfg(test)]
+pub mod test_util;
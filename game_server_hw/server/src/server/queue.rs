@@ -0,0 +1,138 @@
+use std::{collections::VecDeque, time::Duration};
+use tokio::{net::TcpStream, time::timeout};
+use tokio_util::codec::Framed;
+use futures::{future::join_all, SinkExt};
+use network::*;
+
+
+/// 대기중인 연결에 보내는 최대 시간. 대기중에는 읽지 않으므로, 받지 않는 클라이언트 때문에
+/// 접속을 받는 루프가 멈추지 않도록 이 시간 안에 보내지 못하면 연결을 끊는다.
+pub const QUEUE_SEND_TIMEOUT: Duration = Duration::from_millis(200);
+
+
+/// 빈 슬롯이 없을 때 접속한 연결들의 대기열.
+/// 대기중인 클라이언트에게는 `QueuePosition`으로 자신의 순서를 알려준다.
+pub struct WaitingQueue {
+    capacity: usize,
+    waiting: VecDeque<Framed<TcpStream, PacketCodec>>,
+}
+
+impl WaitingQueue {
+    /// `capacity`가 0이면 대기열을 사용하지 않음
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            waiting: VecDeque::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.waiting.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.waiting.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.waiting.len() >= self.capacity
+    }
+
+    /// 대기열 맨 뒤에 추가하고 순서를 알려준다.
+    /// 대기열이 가득 찼으면 `ConnectRejected`를 보내고 연결을 끊는다.
+    pub async fn push(&mut self, stream: TcpStream) {
+        let mut stream = Framed::new(stream, PacketCodec::new());
+
+        if self.is_full() {
+            let reason = ConnectRejectReason::ServerFull;
+            send(&mut stream, Message::ConnectRejected { reason }).await;
+            return;
+        }
+
+        let position = self.waiting.len() as u32 + 1;
+        if send(&mut stream, Message::QueuePosition { position }).await {
+            self.waiting.push_back(stream);
+        }
+    }
+
+    /// 가장 오래 기다린 연결
+    pub fn pop(&mut self) -> Option<TcpStream> {
+        // 대기중에는 읽지 않으므로 읽기 버퍼에 남은 데이터는 없음
+        self.waiting.pop_front().map(Framed::into_inner)
+    }
+
+    /// 모든 대기중인 연결에게 `ConnectRejected`를 보내고 연결을 끊는다.
+    pub async fn reject_all(&mut self, reason: ConnectRejectReason) {
+        let sends = self.waiting.drain(..).map(|mut stream| async move {
+            send(&mut stream, Message::ConnectRejected { reason }).await;
+        });
+        join_all(sends).await;
+    }
+
+    /// 모든 대기중인 연결에게 현재 순서를 동시에 보내고, 보내지 못한 연결은 대기열에서 뺀다.
+    /// 뺀 연결 뒤의 순서는 다음에 알려줄 때 앞당겨진다.
+    pub async fn report_positions(&mut self) {
+        let sends = self.waiting.drain(..).enumerate().map(|(index, mut stream)| async move {
+            let position = index as u32 + 1;
+            send(&mut stream, Message::QueuePosition { position }).await.then_some(stream)
+        });
+
+        self.waiting = join_all(sends).await.into_iter().flatten().collect();
+    }
+}
+
+
+/// `QUEUE_SEND_TIMEOUT` 안에 보냈는지
+async fn send(stream: &mut Framed<TcpStream, PacketCodec>, message: Message) -> bool {
+    matches!(timeout(QUEUE_SEND_TIMEOUT, stream.send(message)).await, Ok(Ok(())))
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    /// 보내는 버퍼가 가득 찰 때까지 써서, 읽지 않는 클라이언트에 연결된 스트림을 만든다.
+    async fn stalled_stream(listener: &TcpListener) -> (TcpStream, TcpStream) {
+        let peer = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+        let (stream, _) = listener.accept().await.unwrap();
+
+        // 받는 쪽의 버퍼로 옮겨져서 다시 쓸 수 있게 되면 더 씀
+        let chunk = [0u8; 64 * 1024];
+        loop {
+            while stream.try_write(&chunk).is_ok() {}
+            if timeout(Duration::from_millis(100), stream.writable()).await.is_err() {
+                return (stream, peer);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stalled_peer() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut queue = WaitingQueue::new(4);
+
+        // 받지 않는 연결은 대기열에 넣지 않음
+        let (stream, _peer) = stalled_stream(&listener).await;
+        tokio::time::timeout(QUEUE_SEND_TIMEOUT * 5, queue.push(stream)).await.expect("push blocked");
+        assert!(queue.is_empty());
+
+        // 대기중에 받지 않게 된 연결은 순서를 알려줄 때 뺌
+        let peer = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+        let (stream, _) = listener.accept().await.unwrap();
+        queue.push(stream).await;
+        let (stalled, _stalled_peer) = stalled_stream(&listener).await;
+        queue.waiting.push_back(Framed::new(stalled, PacketCodec::new()));
+        assert_eq!(queue.len(), 2);
+
+        tokio::time::timeout(QUEUE_SEND_TIMEOUT * 5, queue.report_positions()).await.expect("report blocked");
+        assert_eq!(queue.len(), 1);
+
+        tokio::time::timeout(QUEUE_SEND_TIMEOUT * 5, queue.reject_all(ConnectRejectReason::ShuttingDown)).await.expect("reject blocked");
+        assert!(queue.is_empty());
+        drop(peer);
+    }
+}
@@ -0,0 +1,202 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+use tokio::sync::Notify;
+use network::PlayerId;
+
+
+/// 접속한 클라이언트에게 슬롯(`PlayerId`)을 할당한다.
+/// World를 읽어서 클라이언트 수를 세면 병목이 생길 수 있으므로 따로 관리.
+///
+/// 빈 슬롯은 큐로 관리해서 할당, 해제 모두 O(1)이다.
+/// 해제된 슬롯은 generation을 올리고 최대한 늦게 재사용하므로, 이전 플레이어의 id와 겹치지 않는다.
+pub struct SlotAllocator {
+    capacity: usize,
+    slots: Mutex<Slots>,
+    /// 사용중인 슬롯 수. lock 없이 읽을 수 있다.
+    live: AtomicUsize,
+    released: Notify,
+}
+
+#[derive(Default)]
+struct Slots {
+    /// 슬롯별 현재(또는 다음에 할당될) generation. 한번도 할당되지 않은 슬롯은 아직 없음
+    generations: Vec<u16>,
+    occupied: Vec<bool>,
+    /// 해제된 슬롯 (먼저 해제된 슬롯부터 재사용)
+    free: VecDeque<u16>,
+}
+
+impl SlotAllocator {
+    /// 슬롯 번호는 `u16`이므로 `capacity`는 최대 `u16::MAX + 1`
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.min(u16::MAX as usize + 1),
+            slots: Mutex::new(Slots::default()),
+            live: AtomicUsize::new(0),
+            released: Notify::new(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// 사용중인 슬롯 수
+    pub fn len(&self) -> usize {
+        self.live.load(Ordering::Relaxed)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 빈 슬롯이 없으면 `None`
+    pub fn allocate(&self) -> Option<PlayerId> {
+        let mut slots = self.slots.lock().unwrap();
+
+        // 한번도 사용하지 않은 슬롯부터 사용
+        let index = if slots.generations.len() < self.capacity {
+            slots.generations.push(0);
+            slots.occupied.push(false);
+            (slots.generations.len() - 1) as u16
+        } else {
+            slots.free.pop_front()?
+        };
+
+        slots.occupied[index as usize] = true;
+        self.live.fetch_add(1, Ordering::Relaxed);
+
+        Some(PlayerId::new(index, slots.generations[index as usize]))
+    }
+
+    /// 사용중인 슬롯의 현재 id가 아니면(이미 해제됨 등) 무시하고 `false`
+    pub fn release(&self, id: PlayerId) -> bool {
+        let mut slots = self.slots.lock().unwrap();
+        let index = id.index() as usize;
+
+        let valid = slots.occupied.get(index) == Some(&true)
+            && slots.generations[index] == id.generation();
+        if !valid {
+            return false;
+        }
+
+        slots.occupied[index] = false;
+        // NPC의 generation은 건너뜀
+        let generation = match id.generation().wrapping_add(1) {
+            PlayerId::NPC_GENERATION => 0,
+            generation => generation,
+        };
+        slots.generations[index] = generation;
+        slots.free.push_back(id.index());
+        self.live.fetch_sub(1, Ordering::Relaxed);
+        drop(slots);
+
+        self.released.notify_one();
+
+        true
+    }
+
+    /// 슬롯이 해제될 때까지 대기.
+    /// 기다리는 쪽이 없을 때 해제되었으면 바로 리턴하므로, 깨어난 뒤 `allocate`가 실패할 수도 있다.
+    pub async fn released(&self) {
+        self.released.notified().await
+    }
+
+    /// 모든 슬롯이 해제될 때까지 대기. `released`를 기다리는 다른 task가 없어야 한다.
+    pub async fn drained(&self) {
+        while !self.is_empty() {
+            self.released().await;
+        }
+    }
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exhaustion() {
+        let slots = SlotAllocator::new(3);
+
+        let ids = (0..3).map(|_| slots.allocate().unwrap()).collect::<Vec<_>>();
+        assert_eq!(ids, vec![PlayerId::new(0, 0), PlayerId::new(1, 0), PlayerId::new(2, 0)]);
+        assert_eq!(slots.len(), 3);
+        assert_eq!(slots.allocate(), None);
+
+        assert!(slots.release(ids[1]));
+        assert_eq!(slots.len(), 2);
+        assert_eq!(slots.allocate(), Some(PlayerId::new(1, 1)));
+        assert_eq!(slots.allocate(), None);
+
+        assert!(SlotAllocator::new(0).allocate().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_released() {
+        let slots = std::sync::Arc::new(SlotAllocator::new(1));
+        let id = slots.allocate().unwrap();
+
+        let waiter = {
+            let slots = slots.clone();
+            tokio::spawn(async move {
+                slots.released().await;
+                slots.allocate()
+            })
+        };
+
+        tokio::task::yield_now().await;
+        assert!(slots.release(id));
+        assert_eq!(waiter.await.unwrap(), Some(PlayerId::new(0, 1)));
+    }
+
+    #[test]
+    fn test_reuse() {
+        let slots = SlotAllocator::new(4);
+
+        let a = slots.allocate().unwrap();
+        let b = slots.allocate().unwrap();
+        assert!(slots.release(a));
+        assert!(slots.release(b));
+        assert!(slots.is_empty());
+
+        // 해제된 id는 다시 해제할 수 없음
+        assert!(!slots.release(a));
+        assert!(!slots.release(PlayerId::new(3, 0)));
+
+        // 한번도 사용하지 않은 슬롯을 먼저 사용하고, 그 다음 먼저 해제된 슬롯부터 재사용
+        assert_eq!(slots.allocate(), Some(PlayerId::new(2, 0)));
+        assert_eq!(slots.allocate(), Some(PlayerId::new(3, 0)));
+        let a2 = slots.allocate().unwrap();
+        assert_eq!(a2, PlayerId::new(a.index(), 1));
+        assert_ne!(a2, a);
+
+        // 이전 generation으로는 새 플레이어의 슬롯을 해제할 수 없음
+        assert!(!slots.release(a));
+        assert_eq!(slots.len(), 3);
+        assert!(slots.release(a2));
+    }
+
+    #[test]
+    fn test_skip_npc_generation() {
+        // 슬롯 0이 여러번 재사용되어 NPC의 generation 직전까지 옴
+        let slots = SlotAllocator::new(1);
+        *slots.slots.lock().unwrap() = Slots {
+            generations: vec![PlayerId::NPC_GENERATION - 1],
+            occupied: vec![false],
+            free: VecDeque::from([0]),
+        };
+
+        let id = slots.allocate().unwrap();
+        assert_eq!(id.generation(), PlayerId::NPC_GENERATION - 1);
+        assert!(slots.release(id));
+        assert_eq!(slots.allocate(), Some(PlayerId::new(0, 0)));
+    }
+}
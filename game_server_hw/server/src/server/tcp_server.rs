@@ -1,83 +1,426 @@
-use tokio::net::{TcpListener, TcpStream};
-use std::sync::Mutex;
+use std::{future::Future, net::SocketAddr, sync::Arc, time::Duration};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    time::{interval, sleep, timeout, MissedTickBehavior},
+};
+use tokio_util::sync::CancellationToken;
+use network::{ConnectRejectReason, Message, PlayerId};
+use config::{Config, ServerConfig};
 
 use super::{
+    admin::{stdin_lines, AdminConsole},
+    account::{Accounts, PlayerWriter, WriterInterface},
     world::*,
+    chess::{MatchInterface, MatchServer},
+    chat::{ChatInterface, ChatRouter},
     client::Client,
+    slot::SlotAllocator,
+    queue::WaitingQueue,
 };
 
 
 
-pub async fn run_server(ip: &str, port: u16) {
-    let addr = format!("{}:{}", ip, port);
-    let tcp_listener = TcpListener::bind(addr.clone()).await
-        .expect("Failed to bind tcp listener");
+/// `addrs` 중 처음으로 bind에 성공한 주소에서 접속을 받는다.
+/// `shutdown`이 끝나거나 운영자 콘솔에서 `shutdown`을 입력하면 접속을 그만 받고, 클라이언트들에게 종료를 알린 뒤
+/// 연결이 끝나기를 기다렸다가 월드와 플레이어 상태를 저장하고 리턴한다.
+/// 맵 파일을 읽을 수 없거나 NPC를 생성할 수 없으면 `InvalidData` 에러.
+/// 계정 데이터베이스를 열 수 없거나, 저장된 월드를 복구할 수 없거나, 종료할 때 저장하지 못하면 `Other` 에러.
+pub async fn run_server<S>(addrs: &[SocketAddr], config: Config, shutdown: S) -> std::io::Result<()>
+where
+    S: Future<Output = ()>,
+{
+    let map = load_map(&config.world)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+
+    let accounts = config.accounts.database.as_ref()
+        .map(|path| Accounts::open(path, &config.accounts))
+        .transpose()
+        .map_err(std::io::Error::other)?;
+
+    let tcp_listener = TcpListener::bind(addrs).await?;
 
-    println!("Tcp server - listening on: {}", tcp_listener.local_addr().unwrap());
+    println!("Tcp server - listening on: {}", tcp_listener.local_addr()?);
+    println!("Map: {}x{}", map.width(), map.height());
 
-    let mut world = World::new();
+    let save_dir = config.world.save_dir.clone();
+    let mut world = World::with_map(config.world, map);
 
-    {
-        tokio::spawn(wait_for_players(tcp_listener, (&world).into()));
+    let recovered = match &save_dir {
+        Some(dir) => world.recover(dir).map_err(std::io::Error::other)?,
+        None => false,
+    };
+    if recovered {
+        // 연결은 모두 끊겼으므로 플레이어는 꺼내서 계정에 저장
+        let players = world.take_all_players();
+        if let Some(accounts) = &accounts {
+            for (account, state) in &players {
+                if let Err(e) = accounts.save(*account, *state).await {
+                    eprintln!("Failed to save account {}: {}", account, e);
+                }
+            }
+        }
+        println!("World: recovered ({} players saved)", players.len());
+    } else {
+        world.spawn_npcs()
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
     }
+    world.save().await.map_err(std::io::Error::other)?;
+    println!("NPCs: {}", world.npc_count());
+    match &config.accounts.database {
+        Some(path) => println!("Accounts: {}", path.display()),
+        None => println!("Accounts: disabled"),
+    }
+    match &save_dir {
+        Some(dir) => println!("Saves: {}", dir.display()),
+        None => println!("Saves: disabled"),
+    }
+    let slots = Arc::new(SlotAllocator::new(config.server.max_clients));
+    let queue = WaitingQueue::new(config.server.max_waiting);
+
+    let mut match_server = MatchServer::with_config(config.chess);
+    let matches = match_server.interface();
+    tokio::spawn(async move { match_server.run_message_loop().await });
 
-    world.run_message_loop().await; 
+    let mut chat_router = ChatRouter::with_config(config.chat, world.interface());
+    let chat = chat_router.interface();
+    tokio::spawn(async move { chat_router.run_message_loop().await });
+
+    // 로그인한 플레이어의 상태는 월드 루프 밖에서 모아서 저장
+    let accounts = accounts.map(|accounts| {
+        let mut writer = PlayerWriter::new(accounts.clone(), &config.accounts);
+        let interface = writer.interface();
+        world.set_writer(interface.clone());
+        tokio::spawn(async move { writer.run_message_loop().await });
+        (accounts, interface)
+    });
+    let writer = accounts.as_ref().map(|(_, writer)| writer.clone());
+
+    // 운영자 콘솔의 명령도 클라이언트의 요청처럼 월드의 채널로 처리
+    let console_shutdown = CancellationToken::new();
+    if config.server.admin_console {
+        let console = AdminConsole::new(world.interface(), writer.clone(), slots.clone(), console_shutdown.clone());
+        tokio::spawn(async move { console.run(stdin_lines()).await });
+        println!("Admin console: type 'help' for commands");
+    }
+
+    let stop = CancellationToken::new();
+    let listener = tokio::spawn(wait_for_players(tcp_listener, world.interface(), matches, chat, accounts, slots.clone(), queue, stop.clone()));
+
+    // 클라이언트들이 로그아웃할 수 있도록 연결이 모두 끝날 때까지 월드는 계속 실행
+    let interface = world.interface();
+    let drain = async {
+        tokio::select! {
+            _ = shutdown => {},
+            _ = console_shutdown.cancelled() => {},
+        }
+        println!("Shutting down...");
+
+        stop.cancel();
+        let _ = listener.await;
+        drain_clients(&interface, &slots, &config.server).await;
+    };
+    tokio::select! {
+        _ = world.run_message_loop() => {},
+        _ = drain => {},
+    }
+
+    if persist_on_shutdown(&mut world, writer.as_ref()).await {
+        println!("Server stopped");
+        Ok(())
+    } else {
+        Err(std::io::Error::other("Failed to save state on shutdown"))
+    }
 }
 
 
-const MAX_CLIENTS: usize =  10000;
-/// World를 직접 읽으면 최신 데이터가 아닐 가능성이 있다.  
-/// World에 Mutex, RwLock등을 걸면 클라이언트가 읽는데 병목이 생길 수 있다.  
-/// 따라서 클라이언트 개수만 세기 위해 따로 분리.  
-static CLIENT_SLOTS: Mutex<[Option<()>; MAX_CLIENTS]> = Mutex::new([None; MAX_CLIENTS]);
+/// 종료까지 남은 시간을 1초마다 알리고, 클라이언트들의 연결이 끝나기를 기다린다.
+async fn drain_clients(world: &WorldInterface, slots: &SlotAllocator, config: &ServerConfig) {
+    for seconds in (1..=config.shutdown_countdown_secs).rev() {
+        world.notify(Message::Shutdown { seconds });
+        sleep(Duration::from_secs(1)).await;
+    }
+    world.notify(Message::Shutdown { seconds: 0 });
+
+    let wait = Duration::from_secs(config.shutdown_timeout_secs);
+    if timeout(wait, slots.drained()).await.is_err() {
+        eprintln!("{} clients did not disconnect in time", slots.len());
+    }
+}
 
 
+/// 연결이 끝나지 않은 플레이어의 상태와 월드를 저장하고, 밀린 저장을 마친다.
+/// 하나라도 실패하면 `false`
+async fn persist_on_shutdown(world: &mut World, writer: Option<&WriterInterface>) -> bool {
+    let mut saved = true;
+
+    let players = world.take_all_players();
+    if let Some(writer) = writer {
+        for (account, state) in players {
+            if !matches!(writer.save(account, state).await, Ok(Ok(()))) {
+                eprintln!("Failed to save account {}", account);
+                saved = false;
+            }
+        }
+
+        match writer.shutdown().await {
+            Ok(Ok(())) => {},
+            Ok(Err(e)) => {
+                eprintln!("Failed to save players: {}", e);
+                saved = false;
+            },
+            Err(e) => {
+                eprintln!("Failed to save players: {}", e);
+                saved = false;
+            },
+        }
+    }
+
+    if let Err(e) = world.save().await {
+        eprintln!("Failed to save world: {}", e);
+        saved = false;
+    }
+
+    saved
+}
+
+
+/// 대기중인 클라이언트에게 순서를 알려주는 주기
+const QUEUE_REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+
+
+/// Listens for incoming connections.
+/// 빈 슬롯이 없으면 대기열에 넣고, 슬롯이 해제되면 먼저 기다린 연결부터 입장시킨다.
+/// `stop`이 취소되면 대기중인 연결을 거부하고 리턴한다.
+#[allow(clippy::too_many_arguments)]
+async fn wait_for_players(
+    listener: TcpListener,
+    world: WorldInterface,
+    matches: MatchInterface,
+    chat: ChatInterface,
+    accounts: Option<(Accounts, WriterInterface)>,
+    slots: Arc<SlotAllocator>,
+    mut queue: WaitingQueue,
+    stop: CancellationToken,
+) {
+    let mut report = interval(QUEUE_REPORT_INTERVAL);
+    report.set_missed_tick_behavior(MissedTickBehavior::Skip);
 
-/// Listens for incoming connections
-async fn wait_for_players(listener: TcpListener, world: WorldPointer) {
     loop {
-        // println!("Waiting for connection...");
-        match listener.accept().await {
-            Ok((stream, _addr)) => {
-                let mut slots = CLIENT_SLOTS.lock().unwrap();
-                let mut accepted = false;
-
-                for id in 0..MAX_CLIENTS {
-                    if slots[id].is_none() {
-                        slots[id] = Some(());
-                        // println!("Accepted connection from: {}", addr);
-                        accepted = true;
-                        tokio::spawn(handle_connection(id as u32, stream, world));
+        tokio::select! {
+            // 실패한 연결은 무시하고 다음 연결을 기다림
+            Ok((stream, _)) = listener.accept() => {
+                // 대기중인 연결이 있으면 먼저 입장해야 함
+                let slot = if queue.is_empty() { slots.allocate() } else { None };
+
+                match slot {
+                    Some(id) => {
+                        tokio::spawn(handle_connection(id, stream, world.clone(), matches.clone(), chat.clone(), accounts.clone(), slots.clone()));
+                    },
+                    None => queue.push(stream).await,
+                }
+            },
+
+            _ = slots.released(), if !queue.is_empty() => {
+                while !queue.is_empty() {
+                    let Some(id) = slots.allocate() else {
                         break;
+                    };
+
+                    if let Some(stream) = queue.pop() {
+                        tokio::spawn(handle_connection(id, stream, world.clone(), matches.clone(), chat.clone(), accounts.clone(), slots.clone()));
                     }
                 }
-                if !accepted {
-                    // println!("Connection from {} refused; server full", addr);
-                }
             },
-            Err(_) => {
-                // eprintln!("Failed to accept connection; err = {:?}", e);
-            }
+
+            _ = report.tick(), if !queue.is_empty() => queue.report_positions().await,
+
+            _ = stop.cancelled() => break,
         }
     }
+
+    queue.reject_all(ConnectRejectReason::ShuttingDown).await;
 }
 
 
-async fn handle_connection(id: u32, stream: TcpStream, world: WorldPointer) {
-    let mut client = Client::new(id, stream, WorldInterface::new(world));
+async fn handle_connection(
+    id: PlayerId,
+    stream: TcpStream,
+    world: WorldInterface,
+    matches: MatchInterface,
+    chat: ChatInterface,
+    accounts: Option<(Accounts, WriterInterface)>,
+    slots: Arc<SlotAllocator>,
+) {
+    let mut client = Client::new(id, stream, world.clone(), matches.clone(), chat.clone());
+    if let Some((accounts, writer)) = accounts {
+        client.set_accounts(accounts, writer);
+    }
+
+    // 접속한 클라이언트 수는 운영자 콘솔의 `list`, `stats`로 확인
+    run_with_cleanup(id, &world, &matches, &chat, async move { client.handle_connection().await }).await;
+
+    slots.release(id);
+}
+
 
-    {
-        let slots = CLIENT_SLOTS.lock().unwrap();
-        println!("num clients: {}", slots.iter().filter(|x| x.is_some()).count());
+/// `task`를 별도의 task에서 실행하고, 끝나면 월드에서 플레이어를 삭제하고 대국, 채팅에서 내보낸다.
+/// `task`에서 panic이 나도 삭제는 항상 실행된다.
+async fn run_with_cleanup<F>(id: PlayerId, world: &WorldInterface, matches: &MatchInterface, chat: &ChatInterface, task: F)
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    if let Err(e) = tokio::spawn(task).await {
+        if e.is_panic() {
+            eprintln!("Client {} panicked", id);
+        }
     }
-    
-    client.handle_connection().await;
 
-    {
-        let mut slots = CLIENT_SLOTS.lock().unwrap();
-        slots[id as usize] = None;
-        println!("Connection {} closed", id);
+    // 서버가 종료중이면 실패하지만 무시
+    let _ = world.remove_player(id).await;
+    let _ = matches.leave(id).await;
+    let _ = chat.leave(id).await;
+}
 
-        println!("num clients: {}", slots.iter().filter(|x| x.is_some()).count());
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use tokio_util::codec::Framed;
+    use tokio::sync::oneshot;
+    use network::MessageCodec;
+    use crate::server::test_util::id;
+
+    /// 시야 갱신과 대기 순서 메세지를 제외한 다음 메세지
+    async fn next_message(stream: &mut Framed<TcpStream, MessageCodec>) -> Option<Message> {
+        loop {
+            let message = tokio::time::timeout(Duration::from_secs(5), stream.next()).await
+                .expect("timed out")?
+                .expect("invalid message");
+
+            match message {
+                Message::UpdateSnapshot { .. } |
+                Message::EnterView { .. } |
+                Message::QueuePosition { .. } => continue,
+                message => return Some(message),
+            }
+        }
     }
+
+    #[tokio::test]
+    async fn test_cleanup_on_panic() {
+        let mut world = World::with_tick_rate(100);
+        let interface = world.interface();
+        let world_task = tokio::spawn(async move { world.run_message_loop().await });
+
+        interface.add_player(id(1)).await.unwrap();
+        interface.add_player(id(2)).await.unwrap();
+
+        let matches = MatchServer::new().interface();
+        let chat = ChatRouter::new(interface.clone()).interface();
+        run_with_cleanup(id(1), &interface, &matches, &chat, async { panic!("client task panicked") }).await;
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let snapshot = interface.latest();
+                if snapshot.get(id(1)).is_none() && snapshot.get(id(2)).is_some() {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        }).await.expect("panicked player was not removed");
+
+        world_task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_waiting_queue() {
+        let mut world = World::with_tick_rate(100);
+        let interface = world.interface();
+        let world_task = tokio::spawn(async move { world.run_message_loop().await });
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let slots = Arc::new(SlotAllocator::new(1));
+        let matches = MatchServer::new().interface();
+        let mut chat_router = ChatRouter::new(interface.clone());
+        let chat = chat_router.interface();
+        let chat_task = tokio::spawn(async move { chat_router.run_message_loop().await });
+        let stop = CancellationToken::new();
+        let server = tokio::spawn(wait_for_players(listener, interface, matches, chat, None, slots.clone(), WaitingQueue::new(1), stop.clone()));
+
+        let connect = || async {
+            Framed::new(TcpStream::connect(addr).await.unwrap(), MessageCodec::new())
+        };
+
+        let mut first = connect().await;
+        assert_eq!(next_message(&mut first).await, Some(Message::Init { id: PlayerId::new(0, 0) }));
+
+        // 슬롯이 없으므로 대기
+        let mut second = connect().await;
+        let position = tokio::time::timeout(Duration::from_secs(5), second.next()).await.unwrap();
+        assert_eq!(position.unwrap().unwrap(), Message::QueuePosition { position: 1 });
+
+        // 대기열도 가득 참
+        let mut third = connect().await;
+        assert_eq!(next_message(&mut third).await,
+            Some(Message::ConnectRejected { reason: ConnectRejectReason::ServerFull }));
+        assert_eq!(next_message(&mut third).await, None);
+
+        // 첫번째 연결이 끊기면 대기중이던 연결이 입장
+        drop(first);
+        assert_eq!(next_message(&mut second).await, Some(Message::Init { id: PlayerId::new(0, 1) }));
+        assert_eq!(slots.len(), 1);
+
+        // 종료하면 대기중인 연결은 거부
+        let mut fourth = connect().await;
+        let position = tokio::time::timeout(Duration::from_secs(5), fourth.next()).await.unwrap();
+        assert_eq!(position.unwrap().unwrap(), Message::QueuePosition { position: 1 });
+        stop.cancel();
+        assert_eq!(next_message(&mut fourth).await,
+            Some(Message::ConnectRejected { reason: ConnectRejectReason::ShuttingDown }));
+        server.await.unwrap();
+        chat_task.abort();
+        world_task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_graceful_shutdown() {
+        let addr = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let mut config = Config::default();
+        config.server.shutdown_countdown_secs = 1;
+        config.server.admin_console = false;
+        config.world.tick_rate = 100;
+
+        let (stop, stopped) = oneshot::channel::<()>();
+        let server = tokio::spawn(async move {
+            run_server(&[addr], config, async { let _ = stopped.await; }).await
+        });
+
+        let mut stream = loop {
+            match TcpStream::connect(addr).await {
+                Ok(stream) => break Framed::new(stream, MessageCodec::new()),
+                Err(_) => sleep(Duration::from_millis(10)).await,
+            }
+        };
+        assert_eq!(next_message(&mut stream).await, Some(Message::Init { id: id(0) }));
+        assert!(matches!(next_message(&mut stream).await, Some(Message::Map { .. })));
+
+        // 남은 시간을 알린 다음 연결을 끊고, 클라이언트가 끝나면 리턴
+        stop.send(()).unwrap();
+        assert_eq!(next_message(&mut stream).await, Some(Message::Shutdown { seconds: 1 }));
+        assert_eq!(next_message(&mut stream).await, Some(Message::Shutdown { seconds: 0 }));
+        assert_eq!(next_message(&mut stream).await, None);
+
+        tokio::time::timeout(Duration::from_secs(5), server).await
+            .expect("server did not stop")
+            .unwrap()
+            .unwrap();
+        assert!(TcpStream::connect(addr).await.is_err());
+    }
+
 }
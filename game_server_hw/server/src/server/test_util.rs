@@ -0,0 +1,8 @@
+//! 여러 모듈의 테스트가 같이 쓰는 도우미
+
+use network::PlayerId;
+
+/// 세대가 0인 플레이어 id
+pub fn id(index: u16) -> PlayerId {
+    PlayerId::new(index, 0)
+}
@@ -0,0 +1,300 @@
+use serde::{Deserialize, Serialize};
+use network::{AttackRejectReason, Message, PlayerId};
+
+use super::{AttackResult, World};
+
+
+/// 플레이어와 NPC의 체력, 공격 대기시간, 부활 시각
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(super) struct Vitals {
+    hp: u16,
+    /// 다시 공격할 수 있는 tick
+    next_attack: u64,
+    /// 죽어있으면 부활할 tick
+    respawn_at: Option<u64>,
+}
+
+impl Vitals {
+    pub(super) fn new(hp: u16) -> Self {
+        Self {
+            hp,
+            next_attack: 0,
+            respawn_at: None,
+        }
+    }
+
+    pub(super) fn hp(&self) -> u16 {
+        self.hp
+    }
+
+    pub(super) fn is_alive(&self) -> bool {
+        self.respawn_at.is_none()
+    }
+}
+
+
+/// tick 동안 일어난 전투. 스냅샷과 함께 발행되고,
+/// 클라이언트에게는 관련된 오브젝트가 시야 안에 있을 때만 보낸다.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CombatEvent {
+    Damage { attacker: PlayerId, target: PlayerId, amount: u16, hp: u16 },
+    Death { id: PlayerId, killer: PlayerId },
+    Respawn { id: PlayerId, x: i32, y: i32, hp: u16 },
+}
+
+impl CombatEvent {
+    /// 관련된 오브젝트 (하나뿐이면 같은 id 두개)
+    pub fn ids(&self) -> [PlayerId; 2] {
+        match *self {
+            Self::Damage { attacker, target, .. } => [attacker, target],
+            Self::Death { id, killer } => [id, killer],
+            Self::Respawn { id, .. } => [id, id],
+        }
+    }
+
+    pub fn message(&self) -> Message {
+        match *self {
+            Self::Damage { attacker, target, amount, hp } => Message::Damage { attacker, target, amount, hp },
+            Self::Death { id, killer } => Message::Death { id, killer },
+            Self::Respawn { id, x, y, hp } => Message::Respawn { id, x, y, hp },
+        }
+    }
+}
+
+
+impl World {
+    /// `id`가 `target`을 공격한다. 둘 다 살아있어야 하고,
+    /// `target`이 `attack_range`칸 안에 있고 공격 대기시간이 지났을 때만 공격한다.
+    /// 체력이 0이 된 대상은 월드에서 사라지고 `respawn_ticks` 뒤에 부활한다.
+    pub fn attack(&mut self, id: PlayerId, target: PlayerId) -> AttackResult {
+        let combat = self.config.combat.clone();
+        let now = self.ticks;
+
+        let from = self.alive_position(id).ok_or(AttackRejectReason::Dead)?;
+        if id == target {
+            return Err(AttackRejectReason::InvalidTarget);
+        }
+        let to = self.alive_position(target).ok_or(AttackRejectReason::UnknownTarget)?;
+
+        if (to.0 - from.0).abs() > combat.attack_range || (to.1 - from.1).abs() > combat.attack_range {
+            return Err(AttackRejectReason::OutOfRange);
+        }
+
+        let (_, _, vitals) = self.combatant_mut(id).ok_or(AttackRejectReason::Dead)?;
+        if now < vitals.next_attack {
+            return Err(AttackRejectReason::Cooldown);
+        }
+        vitals.next_attack = now + combat.attack_cooldown as u64;
+
+        let (_, _, vitals) = self.combatant_mut(target).ok_or(AttackRejectReason::UnknownTarget)?;
+        let amount = combat.attack.min(vitals.hp);
+        vitals.hp -= amount;
+        let hp = vitals.hp;
+
+        self.events.push(CombatEvent::Damage { attacker: id, target, amount, hp });
+        if hp == 0 {
+            self.kill(target, id);
+        }
+        self.dirty = true;
+
+        Ok(())
+    }
+
+    /// 부활할 때까지 칸과 스냅샷에서 뺀다.
+    fn kill(&mut self, id: PlayerId, killer: PlayerId) {
+        let respawn_at = self.ticks + self.config.combat.respawn_ticks as u64;
+        let Some((x, y)) = self.alive_position(id) else {
+            return;
+        };
+        if let Some((_, _, vitals)) = self.combatant_mut(id) {
+            vitals.respawn_at = Some(respawn_at);
+        }
+
+        self.occupied.remove(&(x, y));
+        self.sectors.remove(id, x, y);
+        self.events.push(CombatEvent::Death { id, killer });
+        self.dirty = true;
+    }
+
+    /// tick마다 호출. 부활할 때가 된 오브젝트를 id 순서대로 부활시킨다.
+    pub(super) fn update_respawns(&mut self) {
+        let now = self.ticks;
+        let mut due = self.players.iter()
+            .map(|(id, player)| (*id, player.vitals))
+            .chain(self.npcs.iter().map(|(id, npc)| (*id, npc.vitals)))
+            .filter(|(_, vitals)| vitals.respawn_at.is_some_and(|at| at <= now))
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>();
+        due.sort();
+
+        for id in due {
+            self.respawn(id);
+        }
+    }
+
+    /// 플레이어는 스폰 타일, NPC는 처음 생성된 칸(없으면 스폰 타일)에서 체력을 채워 부활한다.
+    /// 빈 칸이 없으면 다음 tick에 다시 시도한다.
+    fn respawn(&mut self, id: PlayerId) {
+        let position = match self.npcs.get(&id).and_then(|npc| npc.home) {
+            Some(home) if !self.occupied.contains_key(&home) => Some(home),
+            Some(home) => self.nearest_free_cell(&[home]),
+            None => self.spawn_point(),
+        };
+        let Some((x, y)) = position else {
+            return;
+        };
+
+        let hp = self.config.combat.max_hp;
+        let Some((px, py, vitals)) = self.combatant_mut(id) else {
+            return;
+        };
+        (*px, *py) = (x, y);
+        *vitals = Vitals::new(hp);
+
+        self.occupied.insert((x, y), id);
+        self.sectors.insert(id, x, y);
+        self.events.push(CombatEvent::Respawn { id, x, y, hp });
+        self.dirty = true;
+    }
+
+    /// 살아있는 플레이어나 NPC의 위치
+    fn alive_position(&self, id: PlayerId) -> Option<(i32, i32)> {
+        if let Some(player) = self.players.get(&id) {
+            return player.vitals.is_alive().then_some((player.x, player.y));
+        }
+
+        self.npcs.get(&id)
+            .filter(|npc| npc.vitals.is_alive())
+            .map(|npc| (npc.x, npc.y))
+    }
+
+    fn combatant_mut(&mut self, id: PlayerId) -> Option<(&mut i32, &mut i32, &mut Vitals)> {
+        if let Some(player) = self.players.get_mut(&id) {
+            return Some((&mut player.x, &mut player.y, &mut player.vitals));
+        }
+
+        self.npcs.get_mut(&id).map(|npc| (&mut npc.x, &mut npc.y, &mut npc.vitals))
+    }
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::{CombatConfig, NpcConfig, WorldConfig};
+    use network::{MoveRejectReason, PieceClass};
+    use crate::server::test_util::id;
+
+    /// 공격 3번에 죽고, 2 tick마다 공격, 3 tick 뒤 부활
+    fn world(map: &str) -> World {
+        let combat = CombatConfig { max_hp: 30, attack: 10, attack_range: 1, attack_cooldown: 2, respawn_ticks: 3 };
+        let config = WorldConfig { combat, ..Default::default() };
+        World::with_map(config, map.parse().unwrap())
+    }
+
+    fn position(world: &World, id: PlayerId) -> Option<(i32, i32)> {
+        world.snapshot().get(id).map(|info| (info.x, info.y))
+    }
+
+    #[test]
+    fn test_attack_rules() {
+        let mut world = world("S.S.S\n");
+        for index in 1..=3 {
+            world.add_player(id(index));
+        }
+        // 1: (0, 0), 2: (1, 0), 3: (4, 0)
+        world.move_player(id(2), -1, 0).unwrap();
+
+        assert_eq!(world.attack(id(1), id(1)), Err(AttackRejectReason::InvalidTarget));
+        assert_eq!(world.attack(id(1), id(9)), Err(AttackRejectReason::UnknownTarget));
+        assert_eq!(world.attack(id(9), id(1)), Err(AttackRejectReason::Dead));
+        assert_eq!(world.attack(id(1), id(3)), Err(AttackRejectReason::OutOfRange));
+
+        assert_eq!(world.attack(id(1), id(2)), Ok(()));
+        assert_eq!(world.attack(id(1), id(2)), Err(AttackRejectReason::Cooldown));
+        // 맞은 쪽은 바로 반격 가능
+        assert_eq!(world.attack(id(2), id(1)), Ok(()));
+
+        world.tick();
+        assert_eq!(world.attack(id(1), id(2)), Err(AttackRejectReason::Cooldown));
+        world.tick();
+        assert_eq!(world.attack(id(1), id(2)), Ok(()));
+
+        assert_eq!(world.snapshot().events(), &[
+            CombatEvent::Damage { attacker: id(1), target: id(2), amount: 10, hp: 10 },
+        ]);
+    }
+
+    #[test]
+    fn test_death_and_respawn() {
+        let mut world = world("S.S\n");
+        world.add_player(id(1));
+        world.add_player(id(2));
+        world.move_player(id(2), -1, 0).unwrap();
+
+        for _ in 0..3 {
+            world.attack(id(1), id(2)).unwrap();
+            world.tick();
+            world.tick();
+        }
+        assert_eq!(world.snapshot().get(id(2)), None);
+
+        // 죽은 플레이어는 움직이거나 공격할 수 없고, 공격받지도 않음
+        assert_eq!(world.move_player(id(2), 1, 0), Err(MoveRejectReason::Dead));
+        assert_eq!(world.attack(id(2), id(1)), Err(AttackRejectReason::Dead));
+        assert_eq!(world.attack(id(1), id(2)), Err(AttackRejectReason::UnknownTarget));
+
+        // 죽은 칸은 비어있음
+        world.move_player(id(1), 1, 0).unwrap();
+
+        // 마지막 공격 뒤 tick 2번 지남, 한번 더 지나야 비어있는 스폰 타일에서 부활
+        world.tick();
+        assert_eq!(position(&world, id(2)), Some((0, 0)));
+        // 발행된 스냅샷에 포함
+        assert_eq!(world.interface().latest().events(), &[CombatEvent::Respawn { id: id(2), x: 0, y: 0, hp: 30 }]);
+        assert!(world.snapshot().events().is_empty());
+    }
+
+    #[test]
+    fn test_npc_death() {
+        let mut world = world("S...\n");
+        world.add_npc(&NpcConfig { spawn: Some((1, 0)), move_interval: 1000, ..Default::default() }).unwrap();
+        world.add_player(id(1));
+        let npc = PlayerId::npc(0);
+
+        for _ in 0..3 {
+            world.attack(id(1), npc).unwrap();
+            world.tick();
+            world.tick();
+        }
+        assert_eq!(position(&world, npc), None);
+
+        // 생성된 칸이 차 있으면 가장 가까운 빈 칸에서 부활
+        world.move_player(id(1), 1, 0).unwrap();
+        world.tick();
+        let info = *world.snapshot().get(npc).unwrap();
+        assert_eq!((info.x, info.y, info.class), (2, 0, PieceClass::PAWN));
+    }
+
+    #[test]
+    fn test_remove_dead_player() {
+        let mut world = world("S.\n");
+        world.add_player(id(1));
+        world.add_player(id(2));
+        for _ in 0..3 {
+            world.attack(id(1), id(2)).unwrap();
+            world.tick();
+            world.tick();
+        }
+
+        // 죽은 칸에 들어간 플레이어는 지워지지 않음
+        world.move_player(id(1), 1, 0).unwrap();
+        world.remove_player(id(2));
+        world.tick();
+        world.tick();
+        assert_eq!(position(&world, id(1)), Some((1, 0)));
+        assert_eq!(world.move_player(id(1), -1, 0).map(|info| info.x), Ok(0));
+    }
+}
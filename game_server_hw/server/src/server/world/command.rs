@@ -0,0 +1,79 @@
+use std::net::{IpAddr, SocketAddr};
+use tokio::sync::oneshot;
+use tokio_util::sync::CancellationToken;
+use network::{AttackRejectReason, MoveRejectReason, ObjectInfo, PieceClass, PlayerId};
+
+use super::PlayerState;
+
+
+/// `WorldInterface`가 채널을 통해 `World`로 보내는 요청.
+/// 결과가 필요한 요청은 `reply`로 응답을 받는다.
+#[derive(Debug)]
+pub enum WorldCommand {
+    /// 플레이어 추가. 추가된 플레이어의 정보로 응답 (빈 칸이 없으면 `None`)
+    Add { id: PlayerId, reply: oneshot::Sender<Option<ObjectInfo>> },
+    /// 로그인한 플레이어를 저장된 상태로 추가. 응답은 `Add`와 같음
+    Login { id: PlayerId, account: i64, state: Option<PlayerState>, reply: oneshot::Sender<Option<ObjectInfo>> },
+    /// 플레이어 이동 (`x`, `y`: 이동 방향). 이동한 결과로 응답
+    Move { id: PlayerId, x: i32, y: i32, reply: oneshot::Sender<MoveResult> },
+    /// 플레이어의 말 종류 변경
+    SetClass { id: PlayerId, class: PieceClass },
+    /// `id`가 `target`을 공격. 공격한 결과로 응답
+    Attack { id: PlayerId, target: PlayerId, reply: oneshot::Sender<AttackResult> },
+    Remove { id: PlayerId },
+    /// 플레이어를 삭제하고 삭제하기 전의 상태로 응답 (없으면 `None`)
+    Take { id: PlayerId, reply: oneshot::Sender<Option<PlayerState>> },
+    /// 연결을 등록. `kick`은 운영자가 내보낼 때 취소된다. 차단된 주소면 `false`로 응답
+    Connect { id: PlayerId, addr: Option<SocketAddr>, kick: CancellationToken, reply: oneshot::Sender<bool> },
+    /// 연결과 플레이어 목록으로 응답 (id 순)
+    List { reply: oneshot::Sender<Vec<PlayerSummary>> },
+    /// 연결을 끊음. 연결이 있었는지로 응답
+    Kick { id: PlayerId, reply: oneshot::Sender<bool> },
+    /// 주소를 차단하고 그 주소의 연결을 끊음. 끊은 연결 수로 응답
+    Ban { ip: IpAddr, reply: oneshot::Sender<usize> },
+    /// 플레이어를 `(x, y)` 칸으로 옮김. 옮긴 결과로 응답
+    Teleport { id: PlayerId, x: i32, y: i32, reply: oneshot::Sender<MoveResult> },
+    Stats { reply: oneshot::Sender<WorldStats> },
+}
+
+
+/// 운영자 콘솔의 `list`에 보여줄 연결 하나
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlayerSummary {
+    pub id: PlayerId,
+    pub addr: Option<SocketAddr>,
+    pub account: Option<i64>,
+    /// 아직 입장하지 않았으면(로그인 중) `None`
+    pub state: Option<PlayerState>,
+}
+
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WorldStats {
+    pub ticks: u64,
+    pub players: usize,
+    pub npcs: usize,
+    pub connections: usize,
+    pub banned: usize,
+}
+
+
+/// 이동 후 플레이어의 정보, 또는 이동하지 못한 이유
+pub type MoveResult = Result<ObjectInfo, MoveRejectReason>;
+
+
+/// 공격했는지, 또는 공격하지 못한 이유
+pub type AttackResult = Result<(), AttackRejectReason>;
+
+
+/// `World`의 메세지 루프가 종료되어 요청을 처리할 수 없음
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorldClosed;
+
+impl std::fmt::Display for WorldClosed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "World is closed")
+    }
+}
+
+impl std::error::Error for WorldClosed {}
@@ -0,0 +1,293 @@
+use std::{
+    fmt,
+    fs::{self, File, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+use serde::Deserialize;
+use tokio::sync::{mpsc, oneshot};
+
+use super::{JournalRecord, WorldSave, SAVE_VERSION};
+
+
+const SNAPSHOT_FILE: &str = "world.json";
+const SNAPSHOT_TEMP_FILE: &str = "world.json.tmp";
+
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io { path: PathBuf, source: io::Error },
+    Parse { path: PathBuf, source: serde_json::Error },
+    /// 이 서버가 읽을 수 없는 버전의 스냅샷
+    Version { path: PathBuf, version: u32 },
+    /// 저장 스레드가 종료됨
+    Stopped,
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { path, source } => write!(f, "Failed to access {}: {}", path.display(), source),
+            Self::Parse { path, source } => write!(f, "Invalid save file {}: {}", path.display(), source),
+            Self::Version { path, version } => {
+                write!(f, "Unsupported save version {} in {} (expected {})", version, path.display(), SAVE_VERSION)
+            },
+            Self::Stopped => write!(f, "World save writer has stopped"),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            Self::Parse { source, .. } => Some(source),
+            Self::Version { .. } | Self::Stopped => None,
+        }
+    }
+}
+
+
+/// 스냅샷(`world.json`)과 그 뒤의 요청을 기록하는 저널(`journal-{generation}.jsonl`)이 있는 폴더
+pub(super) struct SaveDir {
+    dir: PathBuf,
+    generation: u64,
+    /// 스냅샷을 쓴 다음부터 열림
+    journal: Option<BufWriter<File>>,
+}
+
+impl SaveDir {
+    pub(super) fn new(dir: &Path) -> Self {
+        Self { dir: dir.to_path_buf(), generation: 0, journal: None }
+    }
+
+    /// 마지막 스냅샷과 그 뒤의 저널을 읽는다. 스냅샷이 없으면 `None`
+    /// 저장 도중 종료되어 잘린 마지막 줄은 무시한다.
+    pub(super) fn load(&mut self) -> Result<Option<(WorldSave, Vec<JournalRecord>)>, SaveError> {
+        let path = self.dir.join(SNAPSHOT_FILE);
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(source) => return Err(SaveError::Io { path, source }),
+        };
+
+        // 형식이 다르면 파싱 에러보다 버전 에러가 알기 쉬우므로 먼저 확인
+        #[derive(Deserialize)]
+        struct Version { version: u32 }
+
+        let Version { version } = serde_json::from_str(&text)
+            .map_err(|source| SaveError::Parse { path: path.clone(), source })?;
+        if version != SAVE_VERSION {
+            return Err(SaveError::Version { path, version });
+        }
+
+        let save: WorldSave = serde_json::from_str(&text)
+            .map_err(|source| SaveError::Parse { path, source })?;
+        self.generation = save.generation;
+
+        let path = journal_path(&self.dir, save.generation);
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(source) => return Err(SaveError::Io { path, source }),
+        };
+
+        let mut journal = Vec::new();
+        let mut lines = text.split_inclusive('\n').peekable();
+        while let Some(line) = lines.next() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            match serde_json::from_str(line) {
+                Ok(record) => journal.push(record),
+                Err(_) if lines.peek().is_none() && !line.ends_with('\n') => break,
+                Err(source) => return Err(SaveError::Parse { path, source }),
+            }
+        }
+
+        Ok(Some((save, journal)))
+    }
+
+    /// `json`을 `generation`세대의 스냅샷으로 임시 파일에 쓴 다음 바꿔치기하고, 그 세대의 저널을 시작한다.
+    /// 실패하면 이전 스냅샷과 저널이 그대로 남는다.
+    pub(super) fn write_snapshot(&mut self, generation: u64, json: &[u8]) -> Result<(), SaveError> {
+        let io_error = |path: &Path| {
+            let path = path.to_path_buf();
+            move |source| SaveError::Io { path, source }
+        };
+
+        fs::create_dir_all(&self.dir).map_err(io_error(&self.dir))?;
+
+        let temp = self.dir.join(SNAPSHOT_TEMP_FILE);
+        let mut file = File::create(&temp).map_err(io_error(&temp))?;
+        file.write_all(json).map_err(io_error(&temp))?;
+        file.sync_all().map_err(io_error(&temp))?;
+
+        // 새 저널이 스냅샷보다 먼저 있어야 스냅샷을 바꾼 직후 종료돼도 기록이 이어진다.
+        let journal_file = journal_path(&self.dir, generation);
+        let journal = OpenOptions::new().create(true).write(true).truncate(true).open(&journal_file)
+            .map_err(io_error(&journal_file))?;
+
+        let path = self.dir.join(SNAPSHOT_FILE);
+        if let Err(source) = fs::rename(&temp, &path) {
+            let _ = fs::remove_file(&journal_file);
+            return Err(SaveError::Io { path, source });
+        }
+
+        // 지우지 못해도 다음 스냅샷에는 영향이 없음
+        let _ = fs::remove_file(journal_path(&self.dir, self.generation));
+
+        self.generation = generation;
+        self.journal = Some(BufWriter::new(journal));
+        Ok(())
+    }
+
+    /// 저널에 줄들을 추가한다. `flush`를 호출해야 파일에 쓰인다.
+    pub(super) fn append(&mut self, lines: &[u8]) -> Result<(), SaveError> {
+        let Some(journal) = &mut self.journal else {
+            return Ok(());
+        };
+
+        journal.write_all(lines)
+            .map_err(|source| SaveError::Io { path: journal_path(&self.dir, self.generation), source })
+    }
+
+    pub(super) fn flush(&mut self) -> Result<(), SaveError> {
+        match &mut self.journal {
+            Some(journal) => journal.flush()
+                .map_err(|source| SaveError::Io { path: journal_path(&self.dir, self.generation), source }),
+            None => Ok(()),
+        }
+    }
+
+    fn process(&mut self, command: SaveCommand) {
+        match command {
+            SaveCommand::Journal { lines } => {
+                if let Err(error) = self.append(&lines).and_then(|_| self.flush()) {
+                    eprintln!("Failed to write world journal: {}", error);
+                }
+            },
+
+            SaveCommand::Snapshot { lines, generation, json, reply } => {
+                // 앞의 요청은 스냅샷에 실패해도 이어서 쓸 지금 저널에 남김
+                let result = self.append(&lines)
+                    .and_then(|_| self.flush())
+                    .and_then(|_| self.write_snapshot(generation, &json));
+
+                match reply {
+                    Some(reply) => {
+                        let _ = reply.send(result);
+                    },
+                    None => {
+                        if let Err(error) = result {
+                            eprintln!("Failed to save world: {}", error);
+                        }
+                    },
+                }
+            },
+
+            #[cfg(test)]
+            SaveCommand::Sync { reply } => {
+                let _ = reply.send(());
+            },
+        }
+    }
+}
+
+
+/// `SaveWriter`로 보내는 요청. 파일에는 보낸 순서대로 쓴다.
+pub(super) enum SaveCommand {
+    /// 저널에 이어서 쓰고 파일로 내보냄
+    Journal { lines: Vec<u8> },
+    /// 지금 저널에 `lines`를 쓴 다음, `json`을 `generation`세대의 스냅샷으로 쓰고 그 세대의 저널을 시작
+    Snapshot { lines: Vec<u8>, generation: u64, json: Vec<u8>, reply: Option<oneshot::Sender<Result<(), SaveError>>> },
+    /// 앞의 요청을 모두 처리하면 응답
+    #[cfg(test)]
+    Sync { reply: oneshot::Sender<()> },
+}
+
+
+/// 스냅샷과 저널을 쓰는 스레드의 핸들.
+/// 파일 쓰기와 `sync_all`은 블로킹이라서 월드 루프가 멈추지 않도록 별도의 스레드에서 처리한다.
+/// 핸들이 drop되면 남은 요청을 처리하고 스레드가 종료된다.
+pub(super) struct SaveWriter {
+    sender: mpsc::UnboundedSender<SaveCommand>,
+}
+
+impl SaveWriter {
+    pub(super) fn spawn(mut saves: SaveDir) -> Self {
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+
+        std::thread::spawn(move || {
+            while let Some(command) = receiver.blocking_recv() {
+                saves.process(command);
+            }
+        });
+
+        Self { sender }
+    }
+
+    /// 스레드가 종료됐으면 `false` (응답 채널도 닫힘)
+    pub(super) fn send(&self, command: SaveCommand) -> bool {
+        self.sender.send(command).is_ok()
+    }
+}
+
+fn journal_path(dir: &Path, generation: u64) -> PathBuf {
+    dir.join(format!("journal-{}.jsonl", generation))
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::JournalEntry;
+
+    #[test]
+    fn test_journal_lines() {
+        let dir = std::env::temp_dir().join(format!("world-journal-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let mut saves = SaveDir::new(&dir);
+        assert!(saves.load().unwrap().is_none());
+
+        let save = WorldSave {
+            version: SAVE_VERSION,
+            generation: 0,
+            ticks: 3,
+            seed: 1,
+            next_spawn: 0,
+            next_npc: 0,
+            players: Vec::new(),
+            npcs: Vec::new(),
+        };
+        let json = |generation| serde_json::to_vec(&WorldSave { generation, ..save.clone() }).unwrap();
+        saves.write_snapshot(1, &json(1)).unwrap();
+        for record in [JournalRecord { tick: 3, entry: JournalEntry::Add { id: 1 } }, JournalRecord { tick: 4, entry: JournalEntry::Tick }] {
+            saves.append(&record.to_line()).unwrap();
+        }
+        saves.flush().unwrap();
+
+        // 쓰다가 잘린 마지막 줄은 무시
+        let path = dir.join("journal-1.jsonl");
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"tick\":5,\"comm").unwrap();
+
+        let (loaded, journal) = SaveDir::new(&dir).load().unwrap().unwrap();
+        assert_eq!(loaded, WorldSave { generation: 1, ..save.clone() });
+        assert_eq!(journal.len(), 2);
+        assert_eq!(journal[1], JournalRecord { tick: 4, entry: JournalEntry::Tick });
+
+        // 다음 세대로 넘어가면 이전 저널은 삭제
+        saves.write_snapshot(3, &json(3)).unwrap();
+        assert!(!path.exists());
+        assert!(dir.join("journal-3.jsonl").exists());
+
+        fs::write(dir.join(SNAPSHOT_FILE), "{\"version\":99}").unwrap();
+        assert!(matches!(SaveDir::new(&dir).load(), Err(SaveError::Version { version: 99, .. })));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,81 @@
+use std::{fmt, io, path::PathBuf};
+use network::{MapError, TileMap};
+use config::WorldConfig;
+
+
+/// 맵 파일을 읽지 못한 이유
+#[derive(Debug)]
+pub enum MapLoadError {
+    Read { path: PathBuf, source: io::Error },
+    Parse { path: PathBuf, source: MapError },
+    /// 맵 파일 없이 `width`, `height`로 만들 수 없는 크기
+    Invalid(MapError),
+}
+
+impl fmt::Display for MapLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Read { path, source } => write!(f, "Failed to read map {}: {}", path.display(), source),
+            Self::Parse { path, source } => write!(f, "Invalid map {}: {}", path.display(), source),
+            Self::Invalid(source) => write!(f, "Invalid world size: {}", source),
+        }
+    }
+}
+
+impl std::error::Error for MapLoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Read { source, .. } => Some(source),
+            Self::Parse { source, .. } | Self::Invalid(source) => Some(source),
+        }
+    }
+}
+
+
+/// 설정의 맵 파일을 읽는다.
+/// 맵 파일이 없으면 `width` x `height` 크기에 `spawn` 한칸만 스폰인 빈 맵을 만든다.
+pub fn load_map(config: &WorldConfig) -> Result<TileMap, MapLoadError> {
+    let Some(path) = &config.map else {
+        return open_map(config).map_err(MapLoadError::Invalid);
+    };
+
+    let text = std::fs::read_to_string(path)
+        .map_err(|source| MapLoadError::Read { path: path.clone(), source })?;
+
+    text.parse()
+        .map_err(|source| MapLoadError::Parse { path: path.clone(), source })
+}
+
+pub(super) fn open_map(config: &WorldConfig) -> Result<TileMap, MapError> {
+    let width = config.width.max(1) as usize;
+    let height = config.height.max(1) as usize;
+
+    TileMap::open(width, height, config.spawn)
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use network::Tile;
+
+    #[test]
+    fn test_load_map() {
+        let map = load_map(&WorldConfig::default()).unwrap();
+        assert_eq!((map.width(), map.height()), (8, 8));
+        assert_eq!(map.spawns().collect::<Vec<_>>(), vec![(3, 3)]);
+
+        let path = std::env::temp_dir().join(format!("game_map_{}.txt", std::process::id()));
+        std::fs::write(&path, "###\n#S#\n###\n").unwrap();
+        let config = WorldConfig { map: Some(path.clone()), ..Default::default() };
+        let map = load_map(&config).unwrap();
+        assert_eq!(map.get(1, 1), Some(Tile::Spawn));
+
+        std::fs::write(&path, "###\n#.#\n###\n").unwrap();
+        assert!(matches!(load_map(&config), Err(MapLoadError::Parse { source: MapError::NoSpawn, .. })));
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(load_map(&config), Err(MapLoadError::Read { .. })));
+    }
+}
@@ -1,149 +1,1235 @@
-use std::collections::HashMap;
-use tokio::sync::mpsc;
+mod combat;
+mod command;
+mod journal;
+mod map;
+mod npc;
+mod save;
+mod sector;
+mod snapshot;
+mod view;
+
+pub use combat::*;
+pub use command::*;
+pub use journal::*;
+pub use map::*;
+pub use npc::*;
+pub use save::*;
+pub use sector::*;
+pub use snapshot::*;
+pub use view::*;
+
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+use arc_swap::ArcSwap;
+use rand::{rngs::StdRng, SeedableRng};
+use tokio::{
+    sync::{mpsc, broadcast, oneshot},
+    time::{interval, MissedTickBehavior},
+};
+use tokio_util::sync::CancellationToken;
+use network::{Message, MoveRejectReason, ObjectInfo, PieceClass, PlayerId, MapError, TileMap};
+use config::WorldConfig;
+
+use super::account::WriterInterface;
 
 
 struct Player {
+    /// 로그인한 계정 id. 로그인하지 않았으면 `None`
+    account: Option<i64>,
     x: i32,
     y: i32,
+    class: PieceClass,
+    vitals: Vitals,
+}
+
+impl Player {
+    fn info(&self, id: PlayerId) -> ObjectInfo {
+        ObjectInfo::new(id, self.x, self.y, self.class)
+    }
+
+    /// 죽어있으면 다음에 스폰 타일에서 체력을 채워 시작
+    fn state(&self, max_hp: u16) -> PlayerState {
+        match self.vitals.is_alive() {
+            true => PlayerState { position: Some((self.x, self.y)), class: self.class, hp: self.vitals.hp() },
+            false => PlayerState { position: None, class: self.class, hp: max_hp },
+        }
+    }
 }
 
 
-pub type WorldPointer = usize;
+/// 접속한 클라이언트. 운영자 콘솔이 주소로 차단하거나 내보낼 때 사용
+struct Connection {
+    addr: Option<SocketAddr>,
+    kick: CancellationToken,
+}
+
+
+/// 저장해둔 플레이어의 상태. 로그아웃할 때 저장하고 로그인할 때 복원한다.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlayerState {
+    /// 죽어있었으면 `None` (스폰 타일에서 시작)
+    pub position: Option<(i32, i32)>,
+    pub class: PieceClass,
+    pub hp: u16,
+}
+
+
+/// 스냅샷 broadcast 채널에 쌓아둘 수 있는 개수.
+/// 이보다 뒤쳐진 클라이언트는 오래된 스냅샷을 건너뛴다.
+const SNAPSHOT_CHANNEL_CAPACITY: usize = 16;
+/// 모든 클라이언트에게 보내는 알림(`notify`)을 쌓아둘 수 있는 개수
+const NOTICE_CHANNEL_CAPACITY: usize = 16;
 
 
 pub struct World {
-    players: HashMap<u32, Player>,
-    sender: mpsc::Sender<String>, 
-    receiver: mpsc::Receiver<String>,
+    players: HashMap<PlayerId, Player>,
+    /// 순서대로 움직이도록 id 순으로 보관
+    npcs: BTreeMap<PlayerId, Npc>,
+    /// 다음에 생성할 NPC의 인덱스
+    next_npc: u16,
+    /// 칸마다 그 칸에 있는 플레이어 또는 NPC. 한 칸에는 하나만 있을 수 있다.
+    occupied: HashMap<(i32, i32), PlayerId>,
+    sectors: SectorGrid,
+    sender: mpsc::Sender<WorldCommand>,
+    receiver: mpsc::Receiver<WorldCommand>,
+
+    config: WorldConfig,
+    map: Arc<TileMap>,
+    /// 맵의 스폰 타일 좌표 (순서대로 돌아가며 사용)
+    spawns: Vec<(i32, i32)>,
+    next_spawn: usize,
+    /// NPC의 무작위 이동에 사용
+    rng: StdRng,
+    /// 지금까지 지난 tick 수. 공격 대기시간과 부활 시각의 기준
+    ticks: u64,
+    /// 다음 스냅샷과 함께 발행할 전투
+    events: Vec<CombatEvent>,
+    /// 마지막 스냅샷 이후 변경이 있었는지
+    dirty: bool,
+    /// 접속한 클라이언트 (로그인 중 포함)
+    connections: HashMap<PlayerId, Connection>,
+    /// 운영자가 차단한 주소 (저장하지 않음)
+    banned: HashSet<IpAddr>,
+    /// 상태를 저장하는 스레드 (`recover`로 설정)
+    saves: Option<SaveWriter>,
+    /// 다음 tick에 저장 스레드로 보낼 저널
+    journal_lines: Vec<u8>,
+    /// 마지막으로 요청한 스냅샷의 세대
+    generation: u64,
+    /// 마지막으로 스냅샷을 저장한 tick
+    last_save: u64,
+    /// 로그인한 플레이어의 상태를 저장하는 task (`set_writer`로 설정)
+    writer: Option<WriterInterface>,
+    /// `writer`에 마지막으로 보낸 플레이어별 상태
+    written: HashMap<PlayerId, PlayerState>,
+    snapshot_sender: broadcast::Sender<Arc<WorldSnapshot>>,
+    notice_sender: broadcast::Sender<Message>,
+    /// 마지막으로 발행한 스냅샷. 클라이언트들은 lock 없이 읽는다.
+    latest: Arc<ArcSwap<WorldSnapshot>>,
 }
 
 impl World {
     pub fn new() -> Self {
-        let (sender, receiver) = mpsc::channel(128);
+        Self::with_tick_rate(WorldConfig::default().tick_rate)
+    }
+
+    /// `tick_rate`: 초당 스냅샷 전송 횟수 (최소 1)
+    pub fn with_tick_rate(tick_rate: u32) -> Self {
+        // 기본 크기의 빈 맵은 항상 만들 수 있음
+        Self::with_config(WorldConfig { tick_rate, ..Default::default() })
+            .expect("default world size is valid")
+    }
+
+    /// 맵 파일 없이 `width`, `height`, `spawn`으로 만든 빈 맵을 사용한다.
+    /// 맵이 `TileMap::MAX_TILES`보다 크면 `MapError::TooLarge`.
+    pub fn with_config(config: WorldConfig) -> Result<Self, MapError> {
+        let map = open_map(&config)?;
+        Ok(Self::with_map(config, map))
+    }
+
+    /// 설정 값은 `WorldConfig::validate`를 통과했다고 가정하지만, 0인 값은 1로 보정한다.
+    /// 월드의 크기는 `map`을 따른다.
+    pub fn with_map(mut config: WorldConfig, map: TileMap) -> Self {
+        config.tick_rate = config.tick_rate.max(1);
+        config.channel_capacity = config.channel_capacity.max(1);
+        config.width = map.width();
+        config.height = map.height();
+
+        let (sender, receiver) = mpsc::channel(config.channel_capacity);
+        let (snapshot_sender, _) = broadcast::channel(SNAPSHOT_CHANNEL_CAPACITY);
+        let (notice_sender, _) = broadcast::channel(NOTICE_CHANNEL_CAPACITY);
+
         Self {
             players: HashMap::new(),
+            npcs: BTreeMap::new(),
+            next_npc: 0,
+            occupied: HashMap::new(),
+            sectors: SectorGrid::default(),
             sender,
             receiver,
+
+            config,
+            spawns: map.spawns().collect(),
+            map: Arc::new(map),
+            next_spawn: 0,
+            rng: StdRng::from_entropy(),
+            ticks: 0,
+            events: Vec::new(),
+            dirty: false,
+            connections: HashMap::new(),
+            banned: HashSet::new(),
+            saves: None,
+            journal_lines: Vec::new(),
+            generation: 0,
+            last_save: 0,
+            writer: None,
+            written: HashMap::new(),
+            snapshot_sender,
+            notice_sender,
+            latest: Arc::new(ArcSwap::from_pointee(WorldSnapshot::default())),
+        }
+    }
+
+    /// 다른 task에서 `World`에 접근하기 위한 핸들
+    pub fn interface(&self) -> WorldInterface {
+        WorldInterface {
+            sender: self.sender.clone(),
+            snapshot_sender: self.snapshot_sender.clone(),
+            notice_sender: self.notice_sender.clone(),
+            latest: self.latest.clone(),
+            map: self.map.clone(),
+            view_radius: self.config.view_radius,
         }
     }
 
+    pub fn tick_rate(&self) -> u32 {
+        self.config.tick_rate
+    }
+
+    pub fn config(&self) -> &WorldConfig {
+        &self.config
+    }
+
+    pub fn map(&self) -> &TileMap {
+        &self.map
+    }
+
+    /// 로그인한 플레이어의 상태가 바뀌면 tick마다 `writer`로 보낸다.
+    pub fn set_writer(&mut self, writer: WriterInterface) {
+        self.writer = Some(writer);
+    }
+
+    /// 채널로 들어오는 메세지를 처리하면서, 
+    /// `tick_rate`마다 변경사항이 있으면 접속중인 클라이언트들에게 스냅샷을 보낸다.
     pub async fn run_message_loop(&mut self) {
+        let mut tick = interval(Duration::from_secs(1) / self.config.tick_rate);
+        tick.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
         loop {
-            match self.receiver.recv().await {
-                Some(msg) => {
-                    // println!("channel received: {}", msg);
-
-                    let msg = msg.split_whitespace()
-                        .collect::<Vec<&str>>();
-                
-                    match msg[0] {
-                        "add" => {
-                            let id = msg[1].parse::<u32>().unwrap();
-                            self.add_player(id);
-                        },
-                        
-                        "move" => {
-                            let id = msg[1].parse::<u32>().unwrap();
-                            let x = msg[2].parse::<i32>().unwrap();
-                            let y = msg[3].parse::<i32>().unwrap();
-                            self.move_player(id, x, y);
-                        },
-                
-                        "remove" => {
-                            let id = msg[1].parse::<u32>().unwrap();
-                            self.remove_player(id);
-                        },
-                
-                        _ => {}
-                    }
+            tokio::select! {
+                command = self.receiver.recv() => match command {
+                    Some(command) => self.process_command(command),
+                    None => break,
                 },
-                None => {
-                    // println!("channel closed");
-                    break;
-                }
+
+                _ = tick.tick() => self.tick(),
             }
         }
     }
 
+    fn process_command(&mut self, command: WorldCommand) {
+        self.journal(&command);
+
+        match command {
+            WorldCommand::Add { id, reply } => {
+                let player = self.add_player(id);
+                // 요청한 쪽이 기다리지 않으면 무시
+                let _ = reply.send(player);
+            },
+
+            WorldCommand::Move { id, x, y, reply } => {
+                let result = self.move_player(id, x, y);
+                let _ = reply.send(result);
+            },
+
+            WorldCommand::SetClass { id, class } => self.set_class(id, class),
+
+            WorldCommand::Attack { id, target, reply } => {
+                let result = self.attack(id, target);
+                let _ = reply.send(result);
+            },
 
-    pub fn add_player(&mut self, id: u32) {
-        self.players.insert(id, Player { x: 3, y: 3 });
+            WorldCommand::Login { id, account, state, reply } => {
+                let player = self.login_player(id, account, state);
+                let _ = reply.send(player);
+            },
+
+            // 연결이 끝나면 항상 `Remove`를 보냄
+            WorldCommand::Remove { id } => {
+                self.connections.remove(&id);
+                self.remove_player(id);
+            },
+
+            WorldCommand::Take { id, reply } => {
+                let state = self.take_player(id);
+                let _ = reply.send(state);
+            },
+
+            WorldCommand::Connect { id, addr, kick, reply } => {
+                let _ = reply.send(self.connect(id, addr, kick));
+            },
+
+            WorldCommand::List { reply } => {
+                let _ = reply.send(self.list());
+            },
+
+            WorldCommand::Kick { id, reply } => {
+                let _ = reply.send(self.kick(id));
+            },
+
+            WorldCommand::Ban { ip, reply } => {
+                let _ = reply.send(self.ban(ip));
+            },
+
+            WorldCommand::Teleport { id, x, y, reply } => {
+                let result = self.teleport(id, x, y);
+                let _ = reply.send(result);
+            },
+
+            WorldCommand::Stats { reply } => {
+                let _ = reply.send(self.stats());
+            },
+        }
     }
 
-    pub fn move_player(&mut self, id: u32, x: i32, y: i32) {
-        // println!("Move {}: ({}, {})", id, x, y);
+    /// 부활과 NPC를 처리하고, 변경사항이 있을 때만 스냅샷을 발행한다.
+    /// 클라이언트들은 같은 스냅샷(`Arc`)을 공유하고, 각자 자기 시야(`View`)에 맞게 걸러서 보낸다.
+    fn tick(&mut self) {
+        self.ticks += 1;
+        self.update_respawns();
+        self.update_npcs();
+        self.update_saves();
+        self.update_writer();
 
-        if let Some(player) = self.players.get_mut(&id) {
-            player.x += x;
-            player.y += y;
+        if !self.dirty {
+            return;
+        }
+        self.dirty = false;
+
+        let snapshot = Arc::new(self.snapshot());
+        self.events.clear();
+        self.latest.store(snapshot.clone());
+
+        // 받는 쪽이 없으면 에러지만 무시
+        let _ = self.snapshot_sender.send(snapshot);
+    }
+
+    /// 로그인한 플레이어 중 상태가 바뀐 플레이어를 `writer`로 보낸다.
+    /// 채널이 가득 차서 보내지 못했으면 다음 tick에 다시 보낸다.
+    fn update_writer(&mut self) {
+        let Some(writer) = &self.writer else {
+            return;
+        };
+
+        let max_hp = self.config.combat.max_hp;
+        let changed = self.players.iter()
+            .filter_map(|(id, player)| Some((*id, player.account?, player.state(max_hp))))
+            .filter(|(id, _, state)| self.written.get(id) != Some(state))
+            .collect::<Vec<_>>();
+        if changed.is_empty() {
+            return;
+        }
+
+        let changes = changed.iter().map(|&(_, account, state)| (account, state)).collect();
+        if writer.update(changes) {
+            self.written.extend(changed.into_iter().map(|(id, _, state)| (id, state)));
+        }
+    }
+
+    /// 살아있는 오브젝트와 아직 발행하지 않은 전투
+    pub fn snapshot(&self) -> WorldSnapshot {
+        let players = self.players.iter()
+            .filter(|(_, player)| player.vitals.is_alive())
+            .map(|(id, player)| (*id, player.info(*id)));
+        let npcs = self.npcs.iter()
+            .filter(|(_, npc)| npc.vitals.is_alive())
+            .map(|(id, npc)| (*id, npc.info(*id)));
+
+        WorldSnapshot::new(players.chain(npcs).collect(), self.sectors.clone())
+            .with_events(self.events.clone())
+    }
+
+
+    /// 빈 스폰 타일에 `PAWN`으로 플레이어를 추가한다.
+    /// 플레이어가 생성될 빈 칸이 없으면 `None`
+    pub fn add_player(&mut self, id: PlayerId) -> Option<ObjectInfo> {
+        // 같은 id가 이미 있으면 새로 추가
+        self.remove_player(id);
+
+        let (x, y) = self.spawn_point()?;
+        Some(self.insert_player(id, None, x, y, PieceClass::PAWN, self.config.combat.max_hp))
+    }
+
+    /// `account`로 로그인한 플레이어를 마지막으로 저장된 상태로 추가한다.
+    /// 저장된 상태가 없으면(새 계정) `add_player`와 같다. 빈 칸이 없으면 `None`
+    pub fn login_player(&mut self, id: PlayerId, account: i64, state: Option<PlayerState>) -> Option<ObjectInfo> {
+        self.remove_player(id);
+
+        let Some(state) = state else {
+            let (x, y) = self.spawn_point()?;
+            return Some(self.insert_player(id, Some(account), x, y, PieceClass::PAWN, self.config.combat.max_hp));
+        };
+
+        let (x, y) = self.free_cell_near(state.position)?;
+        let hp = state.hp.clamp(1, self.config.combat.max_hp);
+
+        Some(self.insert_player(id, Some(account), x, y, state.class, hp))
+    }
+
+    fn insert_player(&mut self, id: PlayerId, account: Option<i64>, x: i32, y: i32, class: PieceClass, hp: u16) -> ObjectInfo {
+        let player = Player { account, x, y, class, vitals: Vitals::new(hp) };
+        let info = player.info(id);
+
+        self.occupied.insert((x, y), id);
+        self.sectors.insert(id, x, y);
+        self.players.insert(id, player);
+        self.dirty = true;
+
+        info
+    }
+
+    /// 비어있는 스폰 타일을 순서대로 돌아가며 고른다.
+    /// 스폰 타일이 모두 차 있으면 스폰 타일에서 가장 가까운 빈 칸.
+    fn spawn_point(&mut self) -> Option<(i32, i32)> {
+        // `TileMap`은 스폰 타일이 최소 한개 있음
+        let count = self.spawns.len();
+        let free = (0..count)
+            .map(|i| (self.next_spawn + i) % count)
+            .find(|&i| !self.occupied.contains_key(&self.spawns[i]));
+
+        if let Some(i) = free {
+            self.next_spawn = (i + 1) % count;
+            return Some(self.spawns[i]);
+        }
+
+        self.nearest_free_cell(&self.spawns)
+    }
+
+    /// 저장해둔 위치가 비어있으면 그 칸, 차 있으면 가장 가까운 빈 칸.
+    /// 위치가 없거나 벽이면(맵이 바뀐 경우) 스폰 타일
+    fn free_cell_near(&mut self, position: Option<(i32, i32)>) -> Option<(i32, i32)> {
+        match position {
+            Some((x, y)) if self.map.is_walkable(x, y) && !self.occupied.contains_key(&(x, y)) => Some((x, y)),
+            Some((x, y)) if self.map.is_walkable(x, y) => self.nearest_free_cell(&[(x, y)]),
+            _ => self.spawn_point(),
+        }
+    }
 
-            if player.x < 0 {
-                player.x = 0;
+    /// `starts`에서부터 너비 우선 탐색
+    fn nearest_free_cell(&self, starts: &[(i32, i32)]) -> Option<(i32, i32)> {
+        let mut visited = starts.iter().copied().collect::<HashSet<_>>();
+        let mut queue = starts.iter().copied().collect::<VecDeque<_>>();
+
+        while let Some((x, y)) = queue.pop_front() {
+            if !self.occupied.contains_key(&(x, y)) {
+                return Some((x, y));
             }
-            if player.y < 0 {
-                player.y = 0;
+
+            for next in [(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)] {
+                if self.map.is_walkable(next.0, next.1) && visited.insert(next) {
+                    queue.push_back(next);
+                }
             }
-            if player.x > 7 {
-                player.x = 7;
+        }
+
+        None
+    }
+
+    /// 플레이어의 말 규칙에 맞게 `(x, y)`만큼 이동한다.
+    /// 벽, 맵 밖, 다른 플레이어가 있는 칸으로는 이동할 수 없고, 
+    /// 여러칸을 이동하는 말은 지나가는 칸도 비어있어야 한다. (이동하지 않음)
+    /// 같은 tick에 들어온 요청은 먼저 들어온 순서대로 처리된다.
+    pub fn move_player(&mut self, id: PlayerId, x: i32, y: i32) -> MoveResult {
+        // 월드에 없는 플레이어
+        let Some(player) = self.players.get_mut(&id) else {
+            return Err(MoveRejectReason::NotOwner);
+        };
+        if !player.vitals.is_alive() {
+            return Err(MoveRejectReason::Dead);
+        }
+
+        let (direction, distance) = player.class.step(x, y)
+            .ok_or(MoveRejectReason::InvalidStep)?;
+
+        // 지나가는 칸과 도착하는 칸을 순서대로 확인.
+        // 맵 밖에서 멈추므로 맵 크기보다 많이 반복하지 않는다.
+        let from = (player.x, player.y);
+        let mut to = from;
+        for _ in 0..distance {
+            to = (to.0.saturating_add(direction.0), to.1.saturating_add(direction.1));
+
+            if !self.map.is_walkable(to.0, to.1) {
+                return Err(MoveRejectReason::Blocked);
             }
-            if player.y > 7 {
-                player.y = 7;
+            if self.occupied.contains_key(&to) {
+                return Err(MoveRejectReason::Occupied);
             }
         }
+
+        (player.x, player.y) = to;
+        let info = player.info(id);
+
+        self.occupied.remove(&from);
+        self.occupied.insert(to, id);
+        self.sectors.update(id, from, to);
+        self.dirty = true;
+
+        Ok(info)
+    }
+
+    /// 말의 규칙과 상관없이 플레이어를 `(x, y)` 칸으로 옮긴다. (운영자용)
+    /// 벽, 맵 밖, 다른 플레이어가 있는 칸으로는 옮길 수 없고, 죽어있는 플레이어도 옮길 수 없다.
+    pub fn teleport(&mut self, id: PlayerId, x: i32, y: i32) -> MoveResult {
+        let Some(player) = self.players.get_mut(&id) else {
+            return Err(MoveRejectReason::NotOwner);
+        };
+        if !player.vitals.is_alive() {
+            return Err(MoveRejectReason::Dead);
+        }
+
+        let from = (player.x, player.y);
+        let to = (x, y);
+        if !self.map.is_walkable(x, y) {
+            return Err(MoveRejectReason::Blocked);
+        }
+        if self.occupied.get(&to).is_some_and(|&other| other != id) {
+            return Err(MoveRejectReason::Occupied);
+        }
+
+        (player.x, player.y) = to;
+        let info = player.info(id);
+
+        self.occupied.remove(&from);
+        self.occupied.insert(to, id);
+        self.sectors.update(id, from, to);
+        self.dirty = true;
+
+        Ok(info)
+    }
+
+    pub fn set_class(&mut self, id: PlayerId, class: PieceClass) {
+        if let Some(player) = self.players.get_mut(&id) {
+            if player.class != class {
+                player.class = class;
+                self.dirty = true;
+            }
+        }
+    }
+
+    /// 플레이어를 삭제하고, 저장할 상태를 돌려준다.
+    pub fn take_player(&mut self, id: PlayerId) -> Option<PlayerState> {
+        let state = self.players.get(&id)?.state(self.config.combat.max_hp);
+
+        self.remove_player(id);
+        Some(state)
+    }
+
+    /// 모든 플레이어를 삭제하고, 로그인한 플레이어의 계정별 상태를 id 순서대로 돌려준다.
+    pub fn take_all_players(&mut self) -> Vec<(i64, PlayerState)> {
+        let mut ids = self.players.keys().copied().collect::<Vec<_>>();
+        ids.sort();
+
+        let accounts = ids.iter()
+            .filter_map(|id| self.players.get(id))
+            .filter_map(|player| Some((player.account?, player.state(self.config.combat.max_hp))))
+            .collect();
+
+        for id in ids {
+            self.remove_player(id);
+        }
+        accounts
+    }
+
+    /// 죽어있는 플레이어는 칸을 차지하지 않으므로 목록에서만 지운다.
+    pub fn remove_player(&mut self, id: PlayerId) {
+        self.written.remove(&id);
+        if let Some(player) = self.players.remove(&id) {
+            if !player.vitals.is_alive() {
+                return;
+            }
+            self.occupied.remove(&(player.x, player.y));
+            self.sectors.remove(id, player.x, player.y);
+            self.dirty = true;
+        }
+    }
+}
+
+/// 연결 관리 (운영자 콘솔)
+impl World {
+    /// 연결을 등록한다. 차단된 주소면 등록하지 않고 `false`
+    pub fn connect(&mut self, id: PlayerId, addr: Option<SocketAddr>, kick: CancellationToken) -> bool {
+        if addr.is_some_and(|addr| self.banned.contains(&addr.ip())) {
+            return false;
+        }
+
+        self.connections.insert(id, Connection { addr, kick });
+        true
+    }
+
+    /// 연결과 월드의 플레이어를 id 순서대로 돌려준다. (연결 없이 추가된 플레이어 포함)
+    pub fn list(&self) -> Vec<PlayerSummary> {
+        let max_hp = self.config.combat.max_hp;
+        let mut ids = self.connections.keys()
+            .chain(self.players.keys())
+            .copied()
+            .collect::<Vec<_>>();
+        ids.sort();
+        ids.dedup();
+
+        ids.into_iter()
+            .map(|id| {
+                let player = self.players.get(&id);
+                PlayerSummary {
+                    id,
+                    addr: self.connections.get(&id).and_then(|connection| connection.addr),
+                    account: player.and_then(|player| player.account),
+                    state: player.map(|player| player.state(max_hp)),
+                }
+            })
+            .collect()
+    }
+
+    /// 연결을 끊는다. 플레이어 삭제와 저장은 연결이 끝날 때(`Remove`) 처리된다.
+    /// 연결이 없으면 `false`
+    pub fn kick(&mut self, id: PlayerId) -> bool {
+        match self.connections.get(&id) {
+            Some(connection) => {
+                connection.kick.cancel();
+                true
+            },
+            None => false,
+        }
     }
 
-    pub fn remove_player(&mut self, id: u32) {
-        self.players.remove(&id);
+    /// `ip`를 차단하고, 그 주소에서 접속한 연결을 모두 끊는다. 끊은 연결 수를 돌려준다.
+    pub fn ban(&mut self, ip: IpAddr) -> usize {
+        self.banned.insert(ip);
+
+        self.connections.values()
+            .filter(|connection| connection.addr.is_some_and(|addr| addr.ip() == ip))
+            .inspect(|connection| connection.kick.cancel())
+            .count()
     }
 
-    pub fn update_message(&self) -> String {
-        let objects = self.players.iter()
-            .map(|(id, player)| format!("{} {} {}", id, player.x, player.y))
-            .collect::<Vec<String>>();
-        
-        format!("update {} {}", objects.len(), objects.join(" "))
+    pub fn stats(&self) -> WorldStats {
+        WorldStats {
+            ticks: self.ticks,
+            players: self.players.len(),
+            npcs: self.npc_count(),
+            connections: self.connections.len(),
+            banned: self.banned.len(),
+        }
     }
 }
 
-impl Into<WorldPointer> for &World {
-    fn into(self) -> WorldPointer {
-        self as *const World as WorldPointer
+impl Default for World {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 
 
 /// Mutex를 적용하면 read할때도 lock을 걸어야 하기 때문에 사용하지 않음.
-/// `Arc`를 사용해서 여러 스레드에서 **read**가능하도록 하고,
-/// **write**이 필요한 경우는 `WorldInterface`에서 `mpsc`를 통해 `World`로 메세지를 보내서 처리.
+/// `World`가 tick마다 바뀌지 않는 스냅샷을 만들어서 `ArcSwap`으로 교체하고,
+/// 여러 스레드에서는 lock 없이 그 스냅샷을 **read**한다.
+/// **write**이 필요한 경우는 `mpsc`를 통해 `World`로 메세지를 보내서 처리.
+/// 스냅샷은 `subscribe`한 클라이언트들에게도 보내준다.
+#[derive(Clone)]
 pub struct WorldInterface {
-    world: WorldPointer,
-    sender: mpsc::Sender<String>,
+    sender: mpsc::Sender<WorldCommand>,
+    snapshot_sender: broadcast::Sender<Arc<WorldSnapshot>>,
+    notice_sender: broadcast::Sender<Message>,
+    latest: Arc<ArcSwap<WorldSnapshot>>,
+    map: Arc<TileMap>,
+    view_radius: i32,
 }
 
 impl WorldInterface {
-    pub fn new(world: WorldPointer) -> Self {
-        let ptr = world as *const World ;
 
-        Self { 
-            world: world as WorldPointer, 
-            sender: unsafe { &*ptr }.sender.clone(),
-        }
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<WorldSnapshot>> {
+        self.snapshot_sender.subscribe()
+    }
+
+    /// `notify`로 보내는 알림을 받는다.
+    pub fn subscribe_notices(&self) -> broadcast::Receiver<Message> {
+        self.notice_sender.subscribe()
+    }
+
+    /// 구독 중인 모든 클라이언트에게 `message`를 보낸다. (종료 알림 등)
+    pub fn notify(&self, message: Message) {
+        // 받는 쪽이 없으면 에러지만 무시
+        let _ = self.notice_sender.send(message);
+    }
+
+    async fn send(&self, command: WorldCommand) -> Result<(), WorldClosed> {
+        self.sender.send(command).await.map_err(|_| WorldClosed)
+    }
+
+    /// 플레이어가 추가될 때까지 기다리고, 추가된 플레이어의 정보를 돌려준다.
+    /// 플레이어가 생성될 빈 칸이 없으면 `None`
+    pub async fn add_player(&self, id: PlayerId) -> Result<Option<ObjectInfo>, WorldClosed> {
+        let (reply, response) = oneshot::channel();
+        self.send(WorldCommand::Add { id, reply }).await?;
+
+        response.await.map_err(|_| WorldClosed)
+    }
+
+    /// 이동이 처리될 때까지 기다리고, 이동한 결과를 돌려준다.
+    pub async fn move_player(&self, id: PlayerId, x: i32, y: i32) -> Result<MoveResult, WorldClosed> {
+        let (reply, response) = oneshot::channel();
+        self.send(WorldCommand::Move { id, x, y, reply }).await?;
+
+        response.await.map_err(|_| WorldClosed)
+    }
+
+    /// 공격이 처리될 때까지 기다리고, 결과를 돌려준다.
+    /// 피해와 죽음은 스냅샷과 함께 `CombatEvent`로 전달된다.
+    pub async fn attack(&self, id: PlayerId, target: PlayerId) -> Result<AttackResult, WorldClosed> {
+        let (reply, response) = oneshot::channel();
+        self.send(WorldCommand::Attack { id, target, reply }).await?;
+
+        response.await.map_err(|_| WorldClosed)
+    }
+
+    pub async fn set_class(&self, id: PlayerId, class: PieceClass) -> Result<(), WorldClosed> {
+        self.send(WorldCommand::SetClass { id, class }).await
+    }
+
+    /// 로그인한 플레이어를 추가한다. (`World::login_player`)
+    pub async fn login_player(&self, id: PlayerId, account: i64, state: Option<PlayerState>) -> Result<Option<ObjectInfo>, WorldClosed> {
+        let (reply, response) = oneshot::channel();
+        self.send(WorldCommand::Login { id, account, state, reply }).await?;
+
+        response.await.map_err(|_| WorldClosed)
+    }
+
+    pub async fn remove_player(&self, id: PlayerId) -> Result<(), WorldClosed> {
+        self.send(WorldCommand::Remove { id }).await
+    }
+
+    /// 플레이어가 삭제될 때까지 기다리고, 삭제하기 전의 상태를 돌려준다.
+    pub async fn take_player(&self, id: PlayerId) -> Result<Option<PlayerState>, WorldClosed> {
+        let (reply, response) = oneshot::channel();
+        self.send(WorldCommand::Take { id, reply }).await?;
+
+        response.await.map_err(|_| WorldClosed)
+    }
+
+    /// 연결을 등록한다. 차단된 주소면 `false` (`World::connect`)
+    pub async fn connect(&self, id: PlayerId, addr: Option<SocketAddr>, kick: CancellationToken) -> Result<bool, WorldClosed> {
+        let (reply, response) = oneshot::channel();
+        self.send(WorldCommand::Connect { id, addr, kick, reply }).await?;
+
+        response.await.map_err(|_| WorldClosed)
+    }
+
+    pub async fn list(&self) -> Result<Vec<PlayerSummary>, WorldClosed> {
+        let (reply, response) = oneshot::channel();
+        self.send(WorldCommand::List { reply }).await?;
+
+        response.await.map_err(|_| WorldClosed)
+    }
+
+    /// 연결을 끊는다. 연결이 없었으면 `false`
+    pub async fn kick(&self, id: PlayerId) -> Result<bool, WorldClosed> {
+        let (reply, response) = oneshot::channel();
+        self.send(WorldCommand::Kick { id, reply }).await?;
+
+        response.await.map_err(|_| WorldClosed)
+    }
+
+    /// 주소를 차단하고, 끊은 연결 수를 돌려준다.
+    pub async fn ban(&self, ip: IpAddr) -> Result<usize, WorldClosed> {
+        let (reply, response) = oneshot::channel();
+        self.send(WorldCommand::Ban { ip, reply }).await?;
+
+        response.await.map_err(|_| WorldClosed)
+    }
+
+    /// 플레이어를 옮기고, 옮긴 결과를 돌려준다. (`World::teleport`)
+    pub async fn teleport(&self, id: PlayerId, x: i32, y: i32) -> Result<MoveResult, WorldClosed> {
+        let (reply, response) = oneshot::channel();
+        self.send(WorldCommand::Teleport { id, x, y, reply }).await?;
+
+        response.await.map_err(|_| WorldClosed)
+    }
+
+    pub async fn stats(&self) -> Result<WorldStats, WorldClosed> {
+        let (reply, response) = oneshot::channel();
+        self.send(WorldCommand::Stats { reply }).await?;
+
+        response.await.map_err(|_| WorldClosed)
+    }
+
+    /// 월드의 맵 (바뀌지 않음)
+    pub fn map(&self) -> &TileMap {
+        &self.map
+    }
+
+    /// 마지막으로 발행된 스냅샷 (최대 1 tick 늦을 수 있음)
+    pub fn latest(&self) -> Arc<WorldSnapshot> {
+        self.latest.load_full()
+    }
+
+    /// 클라이언트의 시야 반경 (`WorldConfig::view_radius`)
+    pub fn view_radius(&self) -> i32 {
+        self.view_radius
+    }
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use tokio::sync::broadcast::error::TryRecvError;
+    use config::AccountConfig;
+    use super::super::account::{AccountStore, Accounts, PlayerWriter};
+    use crate::server::test_util::id;
+
+    fn snapshot_ids(snapshot: Arc<WorldSnapshot>) -> Vec<u16> {
+        let mut ids = snapshot.objects().map(|o| o.id.index()).collect::<Vec<_>>();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn test_with_config_too_large() {
+        let config = WorldConfig { width: 1000, height: 1000, ..Default::default() };
+        assert!(matches!(World::with_config(config), Err(MapError::TooLarge { width: 1000, height: 1000 })));
+    }
+
+    #[test]
+    fn test_tick_sends_only_when_dirty() {
+        let mut world = World::new();
+        let mut snapshots = world.snapshot_sender.subscribe();
+
+        world.tick();
+        assert!(matches!(snapshots.try_recv(), Err(TryRecvError::Empty)));
+
+        world.add_player(id(1));
+        world.add_player(id(2));
+        world.tick();
+        assert_eq!(snapshot_ids(snapshots.try_recv().unwrap()), vec![1, 2]);
+
+        world.tick();
+        assert!(matches!(snapshots.try_recv(), Err(TryRecvError::Empty)));
+
+        world.move_player(id(1), -1, 0).unwrap();
+        world.remove_player(id(2));
+        world.tick();
+        assert_eq!(snapshot_ids(snapshots.try_recv().unwrap()), vec![1]);
+
+        // 없는 플레이어, 이동하지 못한 경우는 변경사항이 아님
+        world.remove_player(id(2));
+        assert!(world.move_player(id(2), 1, 0).is_err());
+        assert!(world.move_player(id(1), 0, -100).is_err());
+        world.tick();
+        assert!(matches!(snapshots.try_recv(), Err(TryRecvError::Empty)));
+    }
+
+    #[test]
+    fn test_latest_published_on_tick() {
+        let mut world = World::new();
+        let interface = world.interface();
+        assert!(interface.latest().is_empty());
+
+        world.add_player(id(1));
+        assert!(interface.latest().is_empty());
+
+        world.tick();
+        assert_eq!(snapshot_ids(interface.latest()), vec![1]);
+        assert_eq!(interface.latest().get(id(1)), Some(&ObjectInfo::new(id(1), 3, 3, PieceClass::PAWN)));
+    }
+
+    #[tokio::test]
+    async fn test_commands() {
+        let mut world = World::new();
+        let interface = world.interface();
+
+        let (reply, response) = oneshot::channel();
+        world.process_command(WorldCommand::Add { id: id(1), reply });
+        assert_eq!(response.await.unwrap(), Some(ObjectInfo::new(id(1), 3, 3, PieceClass::PAWN)));
+
+        let (reply, response) = oneshot::channel();
+        world.process_command(WorldCommand::Move { id: id(1), x: 0, y: 1, reply });
+        assert_eq!(response.await.unwrap(), Ok(ObjectInfo::new(id(1), 3, 4, PieceClass::PAWN)));
+
+        // 없는 플레이어에 대한 요청은 무시
+        let (reply, response) = oneshot::channel();
+        world.process_command(WorldCommand::Move { id: id(9), x: 1, y: 0, reply });
+        assert_eq!(response.await.unwrap(), Err(MoveRejectReason::NotOwner));
+        world.process_command(WorldCommand::Remove { id: id(9) });
+        assert_eq!(world.snapshot().get(id(1)), Some(&ObjectInfo::new(id(1), 3, 4, PieceClass::PAWN)));
+
+        world.process_command(WorldCommand::Remove { id: id(1) });
+        assert!(world.snapshot().is_empty());
+
+        // 응답을 기다리지 않아도 문제없음
+        let (reply, _) = oneshot::channel();
+        world.process_command(WorldCommand::Add { id: id(2), reply });
+        assert_eq!(world.snapshot().len(), 1);
+
+        drop(world);
+        assert_eq!(interface.add_player(id(3)).await, Err(WorldClosed));
+        assert_eq!(interface.move_player(id(3), 1, 0).await, Err(WorldClosed));
     }
 
-    pub async fn add_player(&self, id: u32) {
-        self.sender.send(format!("add {}", id)).await.unwrap();
+    #[test]
+    fn test_login_and_take() {
+        let mut world = World::with_map(WorldConfig::default(), "S..\n.#.\n".parse().unwrap());
+        world.add_player(id(1));
+        world.move_player(id(1), 1, 0).unwrap();
+        world.set_class(id(1), PieceClass::ROOK);
+
+        let state = world.take_player(id(1)).unwrap();
+        assert_eq!(state, PlayerState { position: Some((1, 0)), class: PieceClass::ROOK, hp: 100 });
+        assert!(world.snapshot().is_empty());
+        assert_eq!(world.take_player(id(1)), None);
+
+        // 저장된 칸이 비어있으면 그대로, 차 있으면 가장 가까운 빈 칸
+        let restored = world.login_player(id(2), 7, Some(state)).unwrap();
+        assert_eq!((restored.x, restored.y, restored.class), (1, 0, PieceClass::ROOK));
+        let restored = world.login_player(id(3), 8, Some(PlayerState { hp: 0, ..state })).unwrap();
+        assert_ne!((restored.x, restored.y), (1, 0));
+        assert_eq!(world.take_player(id(3)).map(|state| state.hp), Some(1));
+
+        // 벽이나 위치가 없으면 스폰 타일
+        let restored = world.login_player(id(4), 9, Some(PlayerState { position: Some((1, 1)), ..state })).unwrap();
+        assert_eq!((restored.x, restored.y), (0, 0));
+        let restored = world.login_player(id(5), 10, Some(PlayerState { position: None, ..state })).unwrap();
+        assert_ne!((restored.x, restored.y), (0, 0));
+        // 새 계정
+        let added = world.login_player(id(6), 11, None).unwrap();
+        assert_eq!(added.class, PieceClass::PAWN);
+
+        // 로그인한 플레이어만 계정별로
+        world.add_player(id(1));
+        let taken = world.take_all_players();
+        assert_eq!(taken.iter().map(|(account, _)| *account).collect::<Vec<_>>(), vec![7, 9, 10, 11]);
+        assert_eq!(taken[0].1, state);
+        assert!(world.snapshot().is_empty());
     }
 
-    pub async fn move_player(&self, id: u32, x: i32, y: i32) {
-        self.sender.send(format!("move {} {} {}", id, x, y)).await.unwrap();
+    #[test]
+    fn test_teleport() {
+        let mut world = World::with_map(WorldConfig::default(), "S..\n.#.\n".parse().unwrap());
+        world.add_player(id(1));
+        world.add_player(id(2));
+        let other = *world.snapshot().get(id(2)).unwrap();
+
+        // 말의 규칙과 상관없이 옮기지만, 벽, 맵 밖, 다른 플레이어가 있는 칸은 거부
+        let moved = world.teleport(id(1), 2, 1).unwrap();
+        assert_eq!((moved.x, moved.y), (2, 1));
+        assert_eq!(world.teleport(id(1), 2, 1).map(|info| (info.x, info.y)), Ok((2, 1)));
+        assert_eq!(world.teleport(id(1), 1, 1), Err(MoveRejectReason::Blocked));
+        assert_eq!(world.teleport(id(1), 3, 0), Err(MoveRejectReason::Blocked));
+        assert_eq!(world.teleport(id(1), other.x, other.y), Err(MoveRejectReason::Occupied));
+        assert_eq!(world.teleport(id(3), 0, 0), Err(MoveRejectReason::NotOwner));
+
+        // 이전 칸은 비워짐
+        world.teleport(id(1), 0, 0).unwrap();
+        world.teleport(id(2), 2, 1).unwrap();
+        assert_eq!(world.snapshot().get(id(2)).map(|info| (info.x, info.y)), Some((2, 1)));
+    }
+
+    #[test]
+    fn test_map() {
+        let map = "\
+#####
+#S.S#
+#.#.#
+#####
+".parse().unwrap();
+        let mut world = World::with_map(WorldConfig::default(), map);
+        assert_eq!((world.config().width, world.config().height), (5, 4));
+
+        // 스폰 타일을 돌아가며 사용
+        assert_eq!(world.add_player(id(1)), Some(ObjectInfo::new(id(1), 1, 1, PieceClass::PAWN)));
+        assert_eq!(world.add_player(id(2)), Some(ObjectInfo::new(id(2), 3, 1, PieceClass::PAWN)));
+        world.tick();
+
+        // 벽으로는 이동할 수 없고, 변경사항도 아님
+        assert_eq!(world.move_player(id(1), -1, 0), Err(MoveRejectReason::Blocked));
+        assert_eq!(world.move_player(id(1), 0, -1), Err(MoveRejectReason::Blocked));
+        assert!(!world.dirty);
+        assert_eq!(world.snapshot().get(id(1)), Some(&ObjectInfo::new(id(1), 1, 1, PieceClass::PAWN)));
+
+        assert_eq!(world.move_player(id(1), 1, 0), Ok(ObjectInfo::new(id(1), 2, 1, PieceClass::PAWN)));
+        assert_eq!(world.move_player(id(1), 0, 1), Err(MoveRejectReason::Blocked));
+
+        assert_eq!(world.move_player(id(2), 0, 1), Ok(ObjectInfo::new(id(2), 3, 2, PieceClass::PAWN)));
+        assert_eq!(world.move_player(id(2), 0, 1), Err(MoveRejectReason::Blocked));
     }
 
-    pub async fn remove_player(&self, id: u32) {
-        self.sender.send(format!("remove {}", id)).await.unwrap();
+    #[test]
+    fn test_spawn_on_free_cell() {
+        let map = "\
+S..#.
+#S.#.
+".parse().unwrap();
+        let mut world = World::with_map(WorldConfig::default(), map);
+
+        assert_eq!(world.add_player(id(1)), Some(ObjectInfo::new(id(1), 0, 0, PieceClass::PAWN)));
+        assert_eq!(world.add_player(id(2)), Some(ObjectInfo::new(id(2), 1, 1, PieceClass::PAWN)));
+
+        // 스폰 타일이 모두 차면 가장 가까운 빈 칸
+        assert_eq!(world.add_player(id(3)), Some(ObjectInfo::new(id(3), 1, 0, PieceClass::PAWN)));
+        assert_eq!(world.add_player(id(4)), Some(ObjectInfo::new(id(4), 2, 1, PieceClass::PAWN)));
+        assert_eq!(world.add_player(id(5)), Some(ObjectInfo::new(id(5), 2, 0, PieceClass::PAWN)));
+
+        // 벽 너머의 칸은 스폰 타일에서 갈 수 없음
+        assert_eq!(world.add_player(id(6)), None);
+        assert_eq!(world.snapshot().len(), 5);
+
+        // 빈 스폰 타일이 생기면 그 칸부터 사용
+        world.remove_player(id(2));
+        assert_eq!(world.add_player(id(6)), Some(ObjectInfo::new(id(6), 1, 1, PieceClass::PAWN)));
+
+        // 이미 있는 id는 다시 생성
+        assert_eq!(world.add_player(id(1)), Some(ObjectInfo::new(id(1), 0, 0, PieceClass::PAWN)));
+        assert_eq!(world.snapshot().len(), 5);
     }
 
-    pub fn update_message(&self) -> String {
-        unsafe { &*(self.world as *const World) }.update_message()
+    #[test]
+    fn test_piece_moves() {
+        let map = "\
+S......
+...#...
+.......
+.......
+".parse().unwrap();
+        let mut world = World::with_map(WorldConfig::default(), map);
+        world.add_player(id(1));
+        world.add_player(id(2));
+        let position = |world: &World, index| {
+            let &ObjectInfo { x, y, .. } = world.snapshot().get(id(index)).unwrap();
+            (x, y)
+        };
+        assert_eq!((position(&world, 1), position(&world, 2)), ((0, 0), (1, 0)));
+
+        // PAWN은 한칸만
+        assert_eq!(world.move_player(id(1), 0, 2), Err(MoveRejectReason::InvalidStep));
+
+        // KNIGHT는 L자, 다른 플레이어를 뛰어넘을 수 있음
+        world.set_class(id(1), PieceClass::KNIGHT);
+        assert_eq!(world.move_player(id(1), 1, 1), Err(MoveRejectReason::InvalidStep));
+        assert_eq!(world.move_player(id(1), 2, 1), Ok(ObjectInfo::new(id(1), 2, 1, PieceClass::KNIGHT)));
+        assert_eq!(world.move_player(id(1), 2, -1), Ok(ObjectInfo::new(id(1), 4, 0, PieceClass::KNIGHT)));
+
+        // ROOK은 직선으로 여러칸, 지나가는 칸이 비어있어야 함
+        world.set_class(id(2), PieceClass::ROOK);
+        assert_eq!(world.move_player(id(2), 4, 0), Err(MoveRejectReason::Occupied));
+        assert_eq!(world.move_player(id(2), 2, 0), Ok(ObjectInfo::new(id(2), 3, 0, PieceClass::ROOK)));
+        assert_eq!(world.move_player(id(2), 0, 3), Err(MoveRejectReason::Blocked));
+        assert_eq!(world.move_player(id(2), 0, -1), Err(MoveRejectReason::Blocked));
+
+        // BISHOP은 대각선
+        world.set_class(id(2), PieceClass::BISHOP);
+        assert_eq!(world.move_player(id(2), 0, 1), Err(MoveRejectReason::InvalidStep));
+        assert_eq!(world.move_player(id(2), -3, 3), Ok(ObjectInfo::new(id(2), 0, 3, PieceClass::BISHOP)));
+        assert_eq!(world.move_player(id(2), 3, -3), Ok(ObjectInfo::new(id(2), 3, 0, PieceClass::BISHOP)));
+
+        // QUEEN은 대각선과 직선, 맵 밖에서 멈춤
+        world.set_class(id(2), PieceClass::QUEEN);
+        assert_eq!(world.move_player(id(2), 0, i32::MAX), Err(MoveRejectReason::Blocked));
+        assert_eq!(world.move_player(id(2), 3, 3), Ok(ObjectInfo::new(id(2), 6, 3, PieceClass::QUEEN)));
+
+        // KING은 8방향 한칸
+        world.set_class(id(2), PieceClass::KING);
+        assert_eq!(world.move_player(id(2), -1, -1), Ok(ObjectInfo::new(id(2), 5, 2, PieceClass::KING)));
+        assert_eq!(world.move_player(id(2), -2, 0), Err(MoveRejectReason::InvalidStep));
     }
+
+    /// 같은 tick 안에 들어온 이동 요청은 들어온 순서대로 처리되고,
+    /// 한 칸에는 항상 한명만 있다.
+    #[tokio::test]
+    async fn test_contested_moves() {
+        let map = "SSSS\n....\n".parse().unwrap();
+        let mut world = World::with_map(WorldConfig::default(), map);
+        let interface = world.interface();
+        let mut snapshots = world.snapshot_sender.subscribe();
+
+        for index in 0..4 {
+            world.add_player(id(index));
+        }
+        world.remove_player(id(1));
+        world.tick();
+        snapshots.try_recv().unwrap();
+
+        // (0, 0) -> (1, 0) <- (2, 0): 먼저 요청한 0번만 이동
+        let first = interface.move_player(id(0), 1, 0);
+        let second = interface.move_player(id(2), -1, 0);
+        // 3번이 비켜준 다음 2번이 그 칸으로 이동
+        let third = interface.move_player(id(3), 0, 1);
+        let fourth = interface.move_player(id(2), 1, 0);
+        // 서로 자리를 바꿀 수는 없음
+        let fifth = interface.move_player(id(3), 0, -1);
+
+        let world_task = tokio::spawn(async move {
+            // 요청이 모두 채널에 쌓인 다음 처리
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            world.run_message_loop().await;
+        });
+
+        let (first, second, third, fourth, fifth) = tokio::join!(first, second, third, fourth, fifth);
+        assert_eq!(first.unwrap(), Ok(ObjectInfo::new(id(0), 1, 0, PieceClass::PAWN)));
+        assert_eq!(second.unwrap(), Err(MoveRejectReason::Occupied));
+        assert_eq!(third.unwrap(), Ok(ObjectInfo::new(id(3), 3, 1, PieceClass::PAWN)));
+        assert_eq!(fourth.unwrap(), Ok(ObjectInfo::new(id(2), 3, 0, PieceClass::PAWN)));
+        assert_eq!(fifth.unwrap(), Err(MoveRejectReason::Occupied));
+
+        // 같은 tick의 결과는 스냅샷 하나로 발행됨
+        let snapshot = tokio::time::timeout(Duration::from_secs(5), snapshots.recv()).await
+            .unwrap()
+            .unwrap();
+        let mut positions = snapshot.objects()
+            .map(|&ObjectInfo { id, x, y, .. }| (id.index(), x, y))
+            .collect::<Vec<_>>();
+        positions.sort();
+        assert_eq!(positions, vec![(0, 1, 0), (2, 3, 0), (3, 3, 1)]);
+
+        world_task.abort();
+    }
+
+    /// 여러 스레드에서 스냅샷을 읽는 동안 여러 task가 동시에 쓰기 요청을 보낸다.
+    /// 읽는 쪽은 항상 온전한(중간 상태가 아닌) 스냅샷을 봐야 한다.
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_readers_and_writers() {
+        const WRITERS: u16 = 16;
+        const READERS: usize = 8;
+        const MOVES: usize = 200;
+
+        let mut world = World::with_tick_rate(1000);
+        let interface = world.interface();
+        let world_task = tokio::spawn(async move { world.run_message_loop().await });
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let readers = (0..READERS)
+            .map(|_| {
+                let interface = interface.clone();
+                let stop = stop.clone();
+
+                std::thread::spawn(move || {
+                    let mut reads = 0;
+                    while !stop.load(Ordering::Relaxed) {
+                        let snapshot = interface.latest();
+
+                        for &ObjectInfo { x, y, .. } in snapshot.objects() {
+                            assert!((0..=7).contains(&x) && (0..=7).contains(&y));
+                        }
+                        // 섹터 인덱스와 오브젝트 목록이 같은 시점의 것이어야 함
+                        assert_eq!(snapshot.in_range(3, 3, 4).count(), snapshot.len());
+
+                        assert!(snapshot.len() <= WRITERS as usize);
+                        reads += 1;
+                    }
+                    reads
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let writers = (0..WRITERS)
+            .map(|index| {
+                let interface = interface.clone();
+                let id = id(index);
+
+                tokio::spawn(async move {
+                    interface.add_player(id).await.unwrap();
+                    for i in 0..MOVES {
+                        let (x, y) = match (i + index as usize) % 4 {
+                            0 => (1, 0),
+                            1 => (0, 1),
+                            2 => (-1, 0),
+                            _ => (0, -1),
+                        };
+                        // 다른 플레이어에 막힐 수 있음
+                        let _ = interface.move_player(id, x, y).await.unwrap();
+
+                        if i % 16 == 0 {
+                            tokio::task::yield_now().await;
+                        }
+                    }
+                    if index % 2 == 0 {
+                        interface.remove_player(id).await.unwrap();
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for writer in writers {
+            writer.await.unwrap();
+        }
+
+        // 모든 쓰기 요청이 반영된 스냅샷이 발행될 때까지 대기
+        let expected = (0..WRITERS).filter(|id| id % 2 == 1).collect::<Vec<_>>();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while snapshot_ids(interface.latest()) != expected {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        }).await.expect("final snapshot was not published");
+
+        stop.store(true, Ordering::Relaxed);
+        for reader in readers {
+            assert!(reader.join().unwrap() > 0);
+        }
+
+        world_task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_writer_changes() {
+        let config = AccountConfig { min_password_len: 4, ..Default::default() };
+        let accounts = Accounts::with_store(AccountStore::open_in_memory().unwrap(), &config);
+        let account = accounts.register("alice".to_string(), "pass".to_string()).await.unwrap().account();
+
+        let mut writer = PlayerWriter::new(accounts.clone(), &config);
+        let interface = writer.interface();
+        let writer_task = tokio::spawn(async move { writer.run_message_loop().await });
+        let pending = || async { interface.stats().await.unwrap().pending };
+
+        let mut world = World::new();
+        world.set_writer(interface.clone());
+        let state = PlayerState { position: Some((1, 2)), class: PieceClass::PAWN, hp: 80 };
+
+        // 로그인하지 않은 플레이어는 보내지 않음
+        world.add_player(id(2));
+        world.login_player(id(1), account, Some(state));
+        world.tick();
+        assert_eq!(pending().await, 1);
+        interface.flush().await.unwrap().unwrap();
+
+        // 바뀌지 않았으면 보내지 않음
+        world.tick();
+        assert_eq!(pending().await, 0);
+
+        world.set_class(id(1), PieceClass::BISHOP);
+        world.tick();
+        assert_eq!(pending().await, 1);
+        interface.flush().await.unwrap().unwrap();
+
+        let session = accounts.login("alice".to_string(), "pass".to_string()).await.unwrap();
+        assert_eq!(session.state(), Some(PlayerState { class: PieceClass::BISHOP, ..state }));
+
+        writer_task.abort();
+    }
+
 }
@@ -0,0 +1,429 @@
+use std::{collections::{HashMap, VecDeque}, fmt};
+use rand::seq::SliceRandom;
+use network::{ObjectInfo, PieceClass, PlayerId, TileMap};
+use config::{NpcBehavior, NpcConfig};
+
+use super::{SavedNpc, Vitals, World};
+
+
+/// NPC가 한번에 이동할 수 있는 방향 (말과 상관없이 상하좌우 한칸)
+const STEPS: [(i32, i32); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+
+/// 경로를 찾을 때 확인하는 최대 칸 수. NPC마다 tick마다 찾으므로 맵 크기와 상관없이 제한한다.
+const MAX_PATH_CELLS: usize = 1024;
+
+
+/// NPC를 생성하지 못한 이유
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum NpcError {
+    UnknownPiece(String),
+    /// 생성 위치나 경유지가 벽이거나 맵 밖
+    Unwalkable((i32, i32)),
+    /// 빈 칸이나 NPC id가 없음
+    WorldFull,
+}
+
+impl fmt::Display for NpcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownPiece(piece) => write!(f, "Unknown NPC piece '{}'", piece),
+            Self::Unwalkable((x, y)) => write!(f, "NPC cell ({}, {}) is not walkable", x, y),
+            Self::WorldFull => write!(f, "No free cell for NPC"),
+        }
+    }
+}
+
+impl std::error::Error for NpcError {}
+
+
+pub(super) struct Npc {
+    pub(super) x: i32,
+    pub(super) y: i32,
+    /// 설정의 생성 위치. 부활할 때도 사용
+    pub(super) home: Option<(i32, i32)>,
+    pub(super) vitals: Vitals,
+    class: PieceClass,
+    behavior: NpcBehavior,
+    waypoints: Vec<(i32, i32)>,
+    /// 다음에 갈 `waypoints`의 인덱스
+    next_waypoint: usize,
+    chase_range: i32,
+    move_interval: u32,
+    /// 다음 이동까지 남은 tick
+    cooldown: u32,
+}
+
+impl Npc {
+    pub(super) fn info(&self, id: PlayerId) -> ObjectInfo {
+        ObjectInfo::npc(id, self.x, self.y, self.class)
+    }
+
+    pub(super) fn save(&self, id: PlayerId) -> SavedNpc {
+        SavedNpc {
+            id: id.to_bits(),
+            x: self.x,
+            y: self.y,
+            home: self.home,
+            vitals: self.vitals,
+            class: self.class.value(),
+            behavior: self.behavior,
+            waypoints: self.waypoints.clone(),
+            next_waypoint: self.next_waypoint,
+            chase_range: self.chase_range,
+            move_interval: self.move_interval,
+            cooldown: self.cooldown,
+        }
+    }
+
+    /// 모르는 말은 `PAWN`
+    pub(super) fn load(saved: &SavedNpc) -> Self {
+        Self {
+            x: saved.x,
+            y: saved.y,
+            home: saved.home,
+            vitals: saved.vitals,
+            class: PieceClass::from_u8(saved.class).unwrap_or(PieceClass::PAWN),
+            behavior: saved.behavior,
+            waypoints: saved.waypoints.clone(),
+            next_waypoint: saved.next_waypoint,
+            chase_range: saved.chase_range,
+            move_interval: saved.move_interval.max(1),
+            cooldown: saved.cooldown.max(1),
+        }
+    }
+}
+
+
+impl World {
+    /// 설정(`WorldConfig::npcs`)의 NPC를 모두 생성하고, 생성한 수를 돌려준다.
+    pub fn spawn_npcs(&mut self) -> Result<usize, NpcError> {
+        let configs = self.config.npcs.clone();
+
+        let mut count = 0;
+        for config in &configs {
+            for _ in 0..config.count {
+                self.add_npc(config)?;
+                count += 1;
+            }
+        }
+
+        Ok(count)
+    }
+
+    pub fn npc_count(&self) -> usize {
+        self.npcs.len()
+    }
+
+    /// `config.count`와 상관없이 한 마리만 생성한다.
+    pub fn add_npc(&mut self, config: &NpcConfig) -> Result<PlayerId, NpcError> {
+        let class = config.piece.parse()
+            .map_err(|_| NpcError::UnknownPiece(config.piece.clone()))?;
+
+        if let Some(&cell) = config.waypoints.iter().find(|&&(x, y)| !self.map.is_walkable(x, y)) {
+            return Err(NpcError::Unwalkable(cell));
+        }
+
+        let (x, y) = match config.spawn {
+            Some((x, y)) if !self.map.is_walkable(x, y) => return Err(NpcError::Unwalkable((x, y))),
+            Some(spawn) if !self.occupied.contains_key(&spawn) => spawn,
+            Some(spawn) => self.nearest_free_cell(&[spawn]).ok_or(NpcError::WorldFull)?,
+            None => self.spawn_point().ok_or(NpcError::WorldFull)?,
+        };
+
+        let id = PlayerId::npc(self.next_npc);
+        self.next_npc = self.next_npc.checked_add(1).ok_or(NpcError::WorldFull)?;
+
+        self.npcs.insert(id, Npc {
+            x,
+            y,
+            home: config.spawn,
+            vitals: Vitals::new(self.config.combat.max_hp),
+            class,
+            behavior: config.behavior,
+            waypoints: config.waypoints.clone(),
+            next_waypoint: 0,
+            chase_range: config.chase_range,
+            move_interval: config.move_interval.max(1),
+            cooldown: config.move_interval.max(1),
+        });
+        self.occupied.insert((x, y), id);
+        self.sectors.insert(id, x, y);
+        self.dirty = true;
+
+        Ok(id)
+    }
+
+    /// tick마다 호출. `move_interval`이 된 NPC를 한칸씩 움직인다.
+    pub(super) fn update_npcs(&mut self) {
+        let ids = self.npcs.keys().copied().collect::<Vec<_>>();
+
+        for id in ids {
+            let Some(npc) = self.npcs.get_mut(&id) else {
+                continue;
+            };
+            if !npc.vitals.is_alive() {
+                continue;
+            }
+
+            if npc.cooldown > 1 {
+                npc.cooldown -= 1;
+                continue;
+            }
+            npc.cooldown = npc.move_interval;
+
+            if let Some(to) = self.next_npc_cell(id) {
+                self.move_npc(id, to);
+            }
+        }
+    }
+
+    /// 행동에 따라 이번에 이동할 칸. 이동하지 않으면 `None`
+    fn next_npc_cell(&mut self, id: PlayerId) -> Option<(i32, i32)> {
+        let npc = self.npcs.get_mut(&id)?;
+        let from = (npc.x, npc.y);
+
+        match npc.behavior {
+            NpcBehavior::Wander => self.wander(from),
+
+            NpcBehavior::Patrol => {
+                // 도착했으면 다음 경유지로
+                if npc.waypoints.get(npc.next_waypoint) == Some(&from) {
+                    npc.next_waypoint = (npc.next_waypoint + 1) % npc.waypoints.len();
+                }
+                let goal = *npc.waypoints.get(npc.next_waypoint)?;
+
+                step_towards(&self.map, &self.occupied, from, goal, i32::MAX)
+            },
+
+            NpcBehavior::Chase => {
+                let range = npc.chase_range;
+                match self.nearest_player(from, range) {
+                    Some(goal) => step_towards(&self.map, &self.occupied, from, goal, range),
+                    None => self.wander(from),
+                }
+            },
+        }
+    }
+
+    /// 비어있는 이웃 칸 중 무작위
+    fn wander(&mut self, (x, y): (i32, i32)) -> Option<(i32, i32)> {
+        let free = STEPS.iter()
+            .map(|&(dx, dy)| (x + dx, y + dy))
+            .filter(|&(x, y)| self.map.is_walkable(x, y) && !self.occupied.contains_key(&(x, y)))
+            .collect::<Vec<_>>();
+
+        free.choose(&mut self.rng).copied()
+    }
+
+    /// 가로, 세로 모두 `range`칸 이내의 플레이어 중 가장 가까운 (맨해튼 거리) 플레이어의 위치
+    fn nearest_player(&self, (x, y): (i32, i32), range: i32) -> Option<(i32, i32)> {
+        self.sectors.query(x, y, range)
+            .filter_map(|id| self.players.get(&id).map(|player| (id, player.x, player.y)))
+            .filter(|&(_, px, py)| (px - x).abs() <= range && (py - y).abs() <= range)
+            .min_by_key(|&(id, px, py)| ((px - x).abs() + (py - y).abs(), id))
+            .map(|(_, px, py)| (px, py))
+    }
+
+    /// 빈 칸일 때만 이동한다.
+    fn move_npc(&mut self, id: PlayerId, to: (i32, i32)) {
+        if self.occupied.contains_key(&to) || !self.map.is_walkable(to.0, to.1) {
+            return;
+        }
+        let Some(npc) = self.npcs.get_mut(&id) else {
+            return;
+        };
+
+        let from = (npc.x, npc.y);
+        (npc.x, npc.y) = to;
+
+        self.occupied.remove(&from);
+        self.occupied.insert(to, id);
+        self.sectors.update(id, from, to);
+        self.dirty = true;
+    }
+}
+
+
+/// `from`에서 `goal`까지 가장 짧은 경로의 첫 칸 (너비 우선 탐색).
+/// 가로, 세로 모두 `from`에서 `range`칸 이내의 칸만 지나가고, 다른 오브젝트가 있는 칸은 지나갈 수 없지만 `goal`은 차 있어도 된다.
+/// `MAX_PATH_CELLS`칸을 확인해도 찾지 못하면 확인한 칸 중 `goal`에 가장 가까운 칸으로 향한다.
+/// 이미 도착했거나 경로가 없으면 `None`
+fn step_towards(map: &TileMap, occupied: &HashMap<(i32, i32), PlayerId>, from: (i32, i32), goal: (i32, i32), range: i32) -> Option<(i32, i32)> {
+    if from == goal {
+        return None;
+    }
+
+    // 칸마다 처음 도착했을 때의 첫 칸
+    let mut first = HashMap::from([(from, from)]);
+    let mut queue = VecDeque::from([from]);
+
+    while let Some(cell) = queue.pop_front() {
+        if first.len() >= MAX_PATH_CELLS {
+            // 같은 거리면 좌표 순으로 골라서 저널로 복구해도 같은 칸
+            let distance = |(x, y): (i32, i32)| (x - goal.0).abs() + (y - goal.1).abs();
+            return first.iter()
+                .filter(|(&cell, _)| cell != from)
+                .min_by_key(|(&cell, _)| (distance(cell), cell))
+                .map(|(_, &step)| step);
+        }
+
+        for (dx, dy) in STEPS {
+            let next = (cell.0 + dx, cell.1 + dy);
+            if (next.0 - from.0).abs() > range || (next.1 - from.1).abs() > range {
+                continue;
+            }
+            if first.contains_key(&next) || !map.is_walkable(next.0, next.1) {
+                continue;
+            }
+
+            let step = if cell == from { next } else { first[&cell] };
+            if next == goal {
+                return Some(step);
+            }
+            if occupied.contains_key(&next) {
+                continue;
+            }
+
+            first.insert(next, step);
+            queue.push_back(next);
+        }
+    }
+
+    None
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::WorldConfig;
+    use network::EntityKind;
+
+    fn npc_position(world: &World, id: PlayerId) -> (i32, i32) {
+        let info = *world.snapshot().get(id).unwrap();
+        assert_eq!(info.kind, EntityKind::NPC);
+        (info.x, info.y)
+    }
+
+    fn world(map: &str, npcs: Vec<NpcConfig>) -> World {
+        let config = WorldConfig { npcs, ..Default::default() };
+        World::with_map(config, map.parse().unwrap())
+    }
+
+    #[test]
+    fn test_step_towards() {
+        let map = "\
+S....
+.###.
+.....
+".parse().unwrap();
+        let occupied = HashMap::from([((1, 0), PlayerId::new(1, 0))]);
+
+        // (1, 0)이 막혀있으므로 아래로 돌아감
+        assert_eq!(step_towards(&map, &occupied, (0, 0), (4, 0), i32::MAX), Some((0, 1)));
+        assert_eq!(step_towards(&map, &HashMap::new(), (0, 0), (4, 0), i32::MAX), Some((1, 0)));
+        // 차 있는 목표
+        assert_eq!(step_towards(&map, &occupied, (0, 0), (1, 0), i32::MAX), Some((1, 0)));
+        assert_eq!(step_towards(&map, &occupied, (0, 0), (0, 0), i32::MAX), None);
+        assert_eq!(step_towards(&map, &occupied, (0, 0), (2, 1), i32::MAX), None);
+
+        // 돌아가는 길이 `range` 밖이면 찾지 않음
+        assert_eq!(step_towards(&map, &occupied, (0, 0), (4, 0), 1), None);
+        assert_eq!(step_towards(&map, &occupied, (0, 0), (0, 2), 2), Some((0, 1)));
+    }
+
+    #[test]
+    fn test_step_towards_limit() {
+        // 확인할 수 있는 칸보다 넓은 맵에서도 목표 쪽으로
+        let map: TileMap = format!("S{}\n", ".".repeat(199)).repeat(200).parse().unwrap();
+        assert_eq!(step_towards(&map, &HashMap::new(), (0, 0), (199, 0), i32::MAX), Some((1, 0)));
+        assert_eq!(step_towards(&map, &HashMap::new(), (100, 100), (100, 0), i32::MAX), Some((100, 99)));
+    }
+
+    #[test]
+    fn test_patrol() {
+        let patrol = NpcConfig {
+            behavior: NpcBehavior::Patrol,
+            piece: "rook".to_string(),
+            spawn: Some((0, 0)),
+            waypoints: vec![(2, 0), (0, 0)],
+            move_interval: 2,
+            ..Default::default()
+        };
+        let mut world = world("S...\n", vec![patrol]);
+        assert_eq!(world.spawn_npcs(), Ok(1));
+
+        let id = PlayerId::npc(0);
+        assert_eq!(world.snapshot().get(id).unwrap().class, PieceClass::ROOK);
+
+        // 2 tick마다 한칸씩 경유지를 오감
+        let mut positions = Vec::new();
+        for _ in 0..10 {
+            world.tick();
+            positions.push(npc_position(&world, id).0);
+        }
+        assert_eq!(positions, [0, 1, 1, 2, 2, 1, 1, 0, 0, 1]);
+    }
+
+    #[test]
+    fn test_chase_and_wander() {
+        let chase = NpcConfig {
+            behavior: NpcBehavior::Chase,
+            spawn: Some((0, 0)),
+            chase_range: 3,
+            move_interval: 1,
+            ..Default::default()
+        };
+        let mut world = world("\
+S.......
+........
+", vec![chase]);
+        world.spawn_npcs().unwrap();
+        let id = PlayerId::npc(0);
+
+        // 플레이어는 (0, 0)이 차 있으므로 가장 가까운 빈 칸
+        world.add_player(PlayerId::new(1, 0)).unwrap();
+        for _ in 0..2 {
+            world.move_player(PlayerId::new(1, 0), 1, 0).unwrap();
+        }
+        let player = world.snapshot().get(PlayerId::new(1, 0)).map(|info| (info.x, info.y)).unwrap();
+        assert_eq!(player, (3, 0));
+
+        // 옆 칸까지 쫓아가서 멈춤
+        for _ in 0..5 {
+            world.tick();
+        }
+        assert_eq!(npc_position(&world, id), (2, 0));
+
+        // 범위 밖이면 돌아다님
+        world.remove_player(PlayerId::new(1, 0));
+        for _ in 0..20 {
+            world.tick();
+            let (x, y) = npc_position(&world, id);
+            assert!(world.map.is_walkable(x, y));
+        }
+    }
+
+    #[test]
+    fn test_spawn_errors() {
+        let npc = |piece: &str, spawn, waypoints| NpcConfig {
+            piece: piece.to_string(),
+            spawn,
+            waypoints,
+            ..Default::default()
+        };
+
+        let mut world = world("S#\n", vec![]);
+        assert_eq!(world.add_npc(&npc("dragon", None, vec![])), Err(NpcError::UnknownPiece("dragon".to_string())));
+        assert_eq!(world.add_npc(&npc("pawn", Some((1, 0)), vec![])), Err(NpcError::Unwalkable((1, 0))));
+        assert_eq!(world.add_npc(&npc("pawn", None, vec![(5, 5)])), Err(NpcError::Unwalkable((5, 5))));
+
+        assert_eq!(world.add_npc(&npc("pawn", None, vec![])), Ok(PlayerId::npc(0)));
+        assert_eq!(world.add_npc(&npc("pawn", None, vec![])), Err(NpcError::WorldFull));
+
+        // 플레이어도 NPC가 있는 칸에는 생성되지 않음
+        assert_eq!(world.add_player(PlayerId::new(0, 0)), None);
+    }
+}
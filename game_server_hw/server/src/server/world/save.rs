@@ -0,0 +1,476 @@
+use std::path::Path;
+use rand::{rngs::StdRng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use network::{PieceClass, PlayerId};
+use config::NpcBehavior;
+
+use tokio::sync::oneshot;
+
+use super::{Npc, Player, PlayerState, SaveCommand, SaveDir, SaveError, SaveWriter, Vitals, World, WorldCommand};
+
+
+/// 저장 파일 형식의 버전. 형식이 바뀌면 올린다.
+pub const SAVE_VERSION: u32 = 1;
+
+
+/// 스냅샷 파일에 저장하는 월드의 상태
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(super) struct WorldSave {
+    pub(super) version: u32,
+    /// 스냅샷을 쓸 때마다 1씩 증가. 이 스냅샷 다음의 요청은 같은 번호의 저널에 기록된다.
+    pub(super) generation: u64,
+    pub(super) ticks: u64,
+    /// 복구한 뒤에도 NPC가 같은 무작위 이동을 하도록 스냅샷마다 새로 정하는 시드
+    pub(super) seed: u64,
+    pub(super) next_spawn: usize,
+    pub(super) next_npc: u16,
+    pub(super) players: Vec<SavedPlayer>,
+    pub(super) npcs: Vec<SavedNpc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(super) struct SavedPlayer {
+    /// `PlayerId::to_bits`
+    pub(super) id: u32,
+    pub(super) account: Option<i64>,
+    pub(super) x: i32,
+    pub(super) y: i32,
+    pub(super) class: u8,
+    pub(super) vitals: Vitals,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(super) struct SavedNpc {
+    pub(super) id: u32,
+    pub(super) x: i32,
+    pub(super) y: i32,
+    pub(super) home: Option<(i32, i32)>,
+    pub(super) vitals: Vitals,
+    pub(super) class: u8,
+    pub(super) behavior: NpcBehavior,
+    pub(super) waypoints: Vec<(i32, i32)>,
+    pub(super) next_waypoint: usize,
+    pub(super) chase_range: i32,
+    pub(super) move_interval: u32,
+    pub(super) cooldown: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(super) struct SavedState {
+    pub(super) position: Option<(i32, i32)>,
+    pub(super) class: u8,
+    pub(super) hp: u16,
+}
+
+impl From<PlayerState> for SavedState {
+    fn from(state: PlayerState) -> Self {
+        Self { position: state.position, class: state.class.value(), hp: state.hp }
+    }
+}
+
+impl From<SavedState> for PlayerState {
+    fn from(state: SavedState) -> Self {
+        let class = PieceClass::from_u8(state.class).unwrap_or(PieceClass::PAWN);
+        Self { position: state.position, class, hp: state.hp }
+    }
+}
+
+
+/// 저널에 기록하는 `WorldCommand` (응답 채널 제외)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub(super) enum JournalEntry {
+    Add { id: u32 },
+    Login { id: u32, account: i64, state: Option<SavedState> },
+    Move { id: u32, x: i32, y: i32 },
+    SetClass { id: u32, class: u8 },
+    Attack { id: u32, target: u32 },
+    Remove { id: u32 },
+    Take { id: u32 },
+    Teleport { id: u32, x: i32, y: i32 },
+    /// 스냅샷을 요청하면서 rng를 새 시드로 바꿈.
+    /// 스냅샷을 쓰지 못해도 이전 스냅샷과 저널로 같은 상태를 복구할 수 있도록 이전 저널에 기록
+    Reseed { seed: u64 },
+    /// tick이 끝남. 요청이 없던 tick의 NPC 이동과 부활까지 복구하도록 tick마다 기록
+    Tick,
+}
+
+impl JournalEntry {
+    /// 월드의 상태를 바꾸지 않는 요청(연결 관리, 조회 등)은 `None`
+    fn from_command(command: &WorldCommand) -> Option<Self> {
+        let entry = match *command {
+            WorldCommand::Add { id, .. } => Self::Add { id: id.to_bits() },
+            WorldCommand::Login { id, account, state, .. } => {
+                Self::Login { id: id.to_bits(), account, state: state.map(SavedState::from) }
+            },
+            WorldCommand::Move { id, x, y, .. } => Self::Move { id: id.to_bits(), x, y },
+            WorldCommand::SetClass { id, class } => Self::SetClass { id: id.to_bits(), class: class.value() },
+            WorldCommand::Attack { id, target, .. } => Self::Attack { id: id.to_bits(), target: target.to_bits() },
+            WorldCommand::Remove { id } => Self::Remove { id: id.to_bits() },
+            WorldCommand::Take { id, .. } => Self::Take { id: id.to_bits() },
+            WorldCommand::Teleport { id, x, y, .. } => Self::Teleport { id: id.to_bits(), x, y },
+
+            WorldCommand::Connect { .. } |
+            WorldCommand::List { .. } |
+            WorldCommand::Kick { .. } |
+            WorldCommand::Ban { .. } |
+            WorldCommand::Stats { .. } => return None,
+        };
+        Some(entry)
+    }
+}
+
+/// 저널 파일의 한 줄
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(super) struct JournalRecord {
+    /// 요청을 처리한 때의 `World::ticks`
+    pub(super) tick: u64,
+    #[serde(flatten)]
+    pub(super) entry: JournalEntry,
+}
+
+impl JournalRecord {
+    /// 저널 파일의 한 줄 (줄바꿈 포함)
+    pub(super) fn to_line(&self) -> Vec<u8> {
+        let mut line = serde_json::to_vec(self).expect("journal record is always serializable");
+        line.push(b'\n');
+        line
+    }
+}
+
+
+impl World {
+    /// `dir`에 저장된 스냅샷이 있으면 복구하고, 그 뒤에 저널에 기록된 요청을 다시 처리한다.
+    /// 이후로는 `save`와 tick마다 `dir`에 저장한다. 복구했으면 `true`
+    ///
+    /// 플레이어나 NPC를 추가하기 전에 호출해야 한다.
+    /// 저널은 다음 `save`부터 기록하므로, 복구한 뒤 바로 `save`를 호출해야 한다.
+    pub fn recover(&mut self, dir: &Path) -> Result<bool, SaveError> {
+        let mut saves = SaveDir::new(dir);
+
+        let recovered = match saves.load()? {
+            Some((save, journal)) => {
+                self.generation = save.generation;
+                self.load_save(save);
+                for record in journal {
+                    self.replay(record);
+                }
+                true
+            },
+            None => false,
+        };
+
+        self.saves = Some(SaveWriter::spawn(saves));
+        Ok(recovered)
+    }
+
+    /// 지금 상태를 스냅샷으로 쓰고 새 저널을 시작한다. 다 쓸 때까지 기다린다. (`recover`를 호출하지 않았으면 무시)
+    /// 실패하면 이전 스냅샷과 저널을 계속 사용한다.
+    pub async fn save(&mut self) -> Result<(), SaveError> {
+        let (reply, response) = oneshot::channel();
+        if !self.request_snapshot(Some(reply)) {
+            return Ok(());
+        }
+
+        response.await.map_err(|_| SaveError::Stopped)?
+    }
+
+    /// 스냅샷을 직렬화해서 저장 스레드로 보낸다. 파일은 월드 루프 밖에서 쓴다.
+    /// rng는 새 시드로 바꾸고, 스냅샷을 쓰지 못해도 복구할 수 있도록 바꾼 시드를 지금 저널에 먼저 기록한다.
+    /// 세대는 저장 스레드가 스냅샷을 쓴 다음에 바꾼다. (`recover`를 호출하지 않았으면 `false`)
+    fn request_snapshot(&mut self, reply: Option<oneshot::Sender<Result<(), SaveError>>>) -> bool {
+        if self.saves.is_none() {
+            return false;
+        }
+
+        let seed = rand::random();
+        self.push_journal(JournalEntry::Reseed { seed });
+        self.rng = StdRng::seed_from_u64(seed);
+
+        // 실패한 세대의 번호는 다시 쓰지 않음
+        self.generation += 1;
+        let save = WorldSave { generation: self.generation, ..self.to_save(seed) };
+        let json = serde_json::to_vec(&save).expect("world save is always serializable");
+
+        let lines = std::mem::take(&mut self.journal_lines);
+        self.last_save = self.ticks;
+
+        let command = SaveCommand::Snapshot { lines, generation: self.generation, json, reply };
+        if let Some(saves) = &self.saves {
+            if !saves.send(command) {
+                eprintln!("Failed to save world: {}", SaveError::Stopped);
+            }
+        }
+        true
+    }
+
+    /// 요청을 처리하기 전에 저널에 기록한다.
+    pub(super) fn journal(&mut self, command: &WorldCommand) {
+        if let Some(entry) = JournalEntry::from_command(command) {
+            self.push_journal(entry);
+        }
+    }
+
+    /// 다음 tick에 저장 스레드로 보낼 줄을 추가한다.
+    fn push_journal(&mut self, entry: JournalEntry) {
+        if self.saves.is_none() {
+            return;
+        }
+
+        let record = JournalRecord { tick: self.ticks, entry };
+        self.journal_lines.extend(record.to_line());
+    }
+
+    /// tick마다 호출. `save_interval_secs`마다 스냅샷을 요청하고, 그 사이에는 tick을 기록해서 저널을 보낸다.
+    pub(super) fn update_saves(&mut self) {
+        if self.saves.is_none() {
+            return;
+        }
+
+        let interval = self.config.save_interval_secs.max(1) as u64 * self.config.tick_rate as u64;
+        if self.ticks >= self.last_save + interval {
+            self.request_snapshot(None);
+            return;
+        }
+
+        self.push_journal(JournalEntry::Tick);
+        let lines = std::mem::take(&mut self.journal_lines);
+        if let Some(saves) = &self.saves {
+            if !saves.send(SaveCommand::Journal { lines }) {
+                eprintln!("Failed to write world journal: {}", SaveError::Stopped);
+            }
+        }
+    }
+
+    /// 저장 스레드가 앞의 요청을 모두 처리할 때까지 기다린다.
+    #[cfg(test)]
+    async fn sync_saves(&self) {
+        let Some(saves) = &self.saves else {
+            return;
+        };
+
+        let (reply, response) = oneshot::channel();
+        if saves.send(SaveCommand::Sync { reply }) {
+            let _ = response.await;
+        }
+    }
+
+    fn to_save(&self, seed: u64) -> WorldSave {
+        let mut players = self.players.iter()
+            .map(|(id, player)| SavedPlayer {
+                id: id.to_bits(),
+                account: player.account,
+                x: player.x,
+                y: player.y,
+                class: player.class.value(),
+                vitals: player.vitals,
+            })
+            .collect::<Vec<_>>();
+        players.sort_by_key(|player| player.id);
+
+        WorldSave {
+            version: SAVE_VERSION,
+            generation: 0,
+            ticks: self.ticks,
+            seed,
+            next_spawn: self.next_spawn,
+            next_npc: self.next_npc,
+            players,
+            npcs: self.npcs.iter().map(|(id, npc)| npc.save(*id)).collect(),
+        }
+    }
+
+    /// 저장된 위치가 벽이거나 차 있으면(맵이 바뀐 경우) 가장 가까운 빈 칸에 놓는다.
+    fn load_save(&mut self, save: WorldSave) {
+        self.ticks = save.ticks;
+        self.rng = StdRng::seed_from_u64(save.seed);
+        self.next_npc = save.next_npc;
+
+        for saved in &save.players {
+            let id = PlayerId::from_bits(saved.id);
+            let class = PieceClass::from_u8(saved.class).unwrap_or(PieceClass::PAWN);
+            let mut player = Player { account: saved.account, x: saved.x, y: saved.y, class, vitals: saved.vitals };
+
+            if player.vitals.is_alive() {
+                let Some((x, y)) = self.free_cell_near(Some((player.x, player.y))) else {
+                    continue;
+                };
+                (player.x, player.y) = (x, y);
+                self.occupied.insert((x, y), id);
+                self.sectors.insert(id, x, y);
+            }
+            self.players.insert(id, player);
+        }
+
+        for saved in &save.npcs {
+            let id = PlayerId::from_bits(saved.id);
+            let mut npc = Npc::load(saved);
+
+            if npc.vitals.is_alive() {
+                let Some((x, y)) = self.free_cell_near(Some((npc.x, npc.y))) else {
+                    continue;
+                };
+                (npc.x, npc.y) = (x, y);
+                self.occupied.insert((x, y), id);
+                self.sectors.insert(id, x, y);
+            }
+            self.npcs.insert(id, npc);
+        }
+
+        // 빈 칸을 찾으면서 바뀌었을 수 있으므로 마지막에
+        self.next_spawn = save.next_spawn % self.spawns.len();
+        self.dirty = true;
+    }
+
+    /// 요청을 처리했던 tick까지 진행한 다음 같은 요청을 처리한다.
+    fn replay(&mut self, record: JournalRecord) {
+        while self.ticks < record.tick {
+            self.tick();
+        }
+
+        let id = |bits| PlayerId::from_bits(bits);
+        match record.entry {
+            JournalEntry::Add { id: player } => {
+                self.add_player(id(player));
+            },
+            JournalEntry::Login { id: player, account, state } => {
+                self.login_player(id(player), account, state.map(PlayerState::from));
+            },
+            JournalEntry::Move { id: player, x, y } => {
+                let _ = self.move_player(id(player), x, y);
+            },
+            JournalEntry::SetClass { id: player, class } => {
+                if let Some(class) = PieceClass::from_u8(class) {
+                    self.set_class(id(player), class);
+                }
+            },
+            JournalEntry::Attack { id: player, target } => {
+                let _ = self.attack(id(player), id(target));
+            },
+            JournalEntry::Remove { id: player } => self.remove_player(id(player)),
+            JournalEntry::Take { id: player } => {
+                self.take_player(id(player));
+            },
+            JournalEntry::Teleport { id: player, x, y } => {
+                let _ = self.teleport(id(player), x, y);
+            },
+            JournalEntry::Reseed { seed } => self.rng = StdRng::seed_from_u64(seed),
+            JournalEntry::Tick => {},
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use tokio::sync::oneshot;
+    use config::{NpcConfig, WorldConfig};
+    use crate::server::test_util::id;
+
+    fn save_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("world-save-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn world() -> World {
+        let npc = NpcConfig { spawn: Some((0, 0)), move_interval: 1, ..Default::default() };
+        let config = WorldConfig { npcs: vec![npc], ..Default::default() };
+        World::with_map(config, "S.....\n..#...\n......\n...S..\n".parse().unwrap())
+    }
+
+    fn objects(world: &World) -> Vec<(u32, i32, i32, u8)> {
+        let mut objects = world.snapshot().objects()
+            .map(|info| (info.id.to_bits(), info.x, info.y, info.class.value()))
+            .collect::<Vec<_>>();
+        objects.sort();
+        objects
+    }
+
+    #[test]
+    fn test_save_round_trip() {
+        let mut world = world();
+        world.spawn_npcs().unwrap();
+        world.add_player(id(1));
+        world.login_player(id(2), 7, None);
+        world.set_class(id(2), PieceClass::KNIGHT);
+        world.tick();
+
+        let save = world.to_save(42);
+        let json = serde_json::to_string(&save).unwrap();
+        assert_eq!(serde_json::from_str::<WorldSave>(&json).unwrap(), save);
+
+        let mut loaded = self::world();
+        loaded.load_save(save.clone());
+        assert_eq!(objects(&loaded), objects(&world));
+        assert_eq!(loaded.to_save(42), save);
+    }
+
+    #[tokio::test]
+    async fn test_recover() {
+        let dir = save_dir("recover");
+
+        let mut world = world();
+        assert!(!world.recover(&dir).unwrap());
+        world.spawn_npcs().unwrap();
+        world.save().await.unwrap();
+
+        // 스냅샷 다음의 요청과 tick은 저널로 복구
+        let (reply, _) = oneshot::channel();
+        world.process_command(WorldCommand::Login { id: id(1), account: 7, state: None, reply });
+        for _ in 0..5 {
+            let (reply, _) = oneshot::channel();
+            world.process_command(WorldCommand::Move { id: id(1), x: 1, y: 0, reply });
+            world.tick();
+        }
+        world.process_command(WorldCommand::SetClass { id: id(1), class: PieceClass::ROOK });
+        world.tick();
+        world.sync_saves().await;
+
+        let mut recovered = self::world();
+        assert!(recovered.recover(&dir).unwrap());
+        assert_eq!(recovered.ticks, world.ticks);
+        assert_eq!(objects(&recovered), objects(&world));
+        assert_eq!(recovered.take_all_players(), world.take_all_players());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_failed_snapshot() {
+        let dir = save_dir("failed");
+
+        let mut world = world();
+        world.recover(&dir).unwrap();
+        world.spawn_npcs().unwrap();
+        world.save().await.unwrap();
+        world.tick();
+
+        // 임시 파일을 만들 수 없으면 실패하고, 이전 스냅샷과 저널을 계속 사용
+        std::fs::create_dir(dir.join("world.json.tmp")).unwrap();
+        assert!(matches!(world.save().await, Err(SaveError::Io { .. })));
+        assert!(!dir.join("journal-2.jsonl").exists());
+
+        // 바뀐 rng로 움직인 NPC도 이전 저널로 복구
+        for _ in 0..10 {
+            world.tick();
+        }
+        world.sync_saves().await;
+
+        let mut recovered = self::world();
+        assert!(recovered.recover(&dir).unwrap());
+        assert_eq!(recovered.ticks, world.ticks);
+        assert_eq!(objects(&recovered), objects(&world));
+
+        // 다음 스냅샷은 새 세대로
+        std::fs::remove_dir(dir.join("world.json.tmp")).unwrap();
+        world.save().await.unwrap();
+        assert!(!dir.join("journal-1.jsonl").exists());
+        assert!(dir.join("journal-3.jsonl").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,126 @@
+use std::collections::{HashMap, HashSet};
+use network::PlayerId;
+
+
+/// 섹터 한 변의 칸 수 기본값
+pub const DEFAULT_SECTOR_SIZE: i32 = 8;
+
+
+/// 월드를 `sector_size` x `sector_size` 칸의 섹터로 나눠서
+/// 섹터별로 그 안에 있는 id를 저장하는 공간 인덱스.
+/// 범위 검색시 범위에 걸치는 섹터만 확인하면 된다.
+#[derive(Debug, Clone)]
+pub struct SectorGrid {
+    sector_size: i32,
+    sectors: HashMap<(i32, i32), HashSet<PlayerId>>,
+}
+
+impl SectorGrid {
+    pub fn new(sector_size: i32) -> Self {
+        Self {
+            sector_size: sector_size.max(1),
+            sectors: HashMap::new(),
+        }
+    }
+
+    pub fn sector_size(&self) -> i32 {
+        self.sector_size
+    }
+
+    /// 칸 좌표가 속한 섹터 좌표
+    pub fn sector_of(&self, x: i32, y: i32) -> (i32, i32) {
+        (x.div_euclid(self.sector_size), y.div_euclid(self.sector_size))
+    }
+
+    pub fn insert(&mut self, id: PlayerId, x: i32, y: i32) {
+        let sector = self.sector_of(x, y);
+        self.sectors.entry(sector).or_default().insert(id);
+    }
+
+    pub fn remove(&mut self, id: PlayerId, x: i32, y: i32) {
+        let sector = self.sector_of(x, y);
+
+        if let Some(ids) = self.sectors.get_mut(&sector) {
+            ids.remove(&id);
+            if ids.is_empty() {
+                self.sectors.remove(&sector);
+            }
+        }
+    }
+
+    /// 같은 섹터 안에서의 이동이면 아무것도 하지 않음
+    pub fn update(&mut self, id: PlayerId, from: (i32, i32), to: (i32, i32)) {
+        if self.sector_of(from.0, from.1) == self.sector_of(to.0, to.1) {
+            return;
+        }
+
+        self.remove(id, from.0, from.1);
+        self.insert(id, to.0, to.1);
+    }
+
+    /// `(x, y)`를 중심으로 `radius`칸 범위(정사각형)에 걸치는 섹터들의 id.
+    /// 섹터 단위이므로 범위 밖의 id도 포함될 수 있다. 정확한 거리 확인은 호출하는 쪽에서.
+    pub fn query(&self, x: i32, y: i32, radius: i32) -> impl Iterator<Item = PlayerId> + '_ {
+        let (min_x, min_y) = self.sector_of(x - radius, y - radius);
+        let (max_x, max_y) = self.sector_of(x + radius, y + radius);
+
+        (min_y..=max_y)
+            .flat_map(move |sy| (min_x..=max_x).map(move |sx| (sx, sy)))
+            .filter_map(|sector| self.sectors.get(&sector))
+            .flat_map(|ids| ids.iter().copied())
+    }
+}
+
+impl Default for SectorGrid {
+    fn default() -> Self {
+        Self::new(DEFAULT_SECTOR_SIZE)
+    }
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::test_util::id;
+
+    fn sorted(iter: impl Iterator<Item = PlayerId>) -> Vec<u16> {
+        let mut ids = iter.map(|id| id.index()).collect::<Vec<_>>();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn test_query() {
+        let mut grid = SectorGrid::new(4);
+        grid.insert(id(1), 0, 0);
+        grid.insert(id(2), 3, 3);
+        grid.insert(id(3), 4, 0);
+        grid.insert(id(4), 20, 20);
+        grid.insert(id(5), -1, -1);
+
+        assert_eq!(sorted(grid.query(1, 1, 1)), vec![1, 2]);
+        assert_eq!(sorted(grid.query(3, 1, 1)), vec![1, 2, 3]);
+        assert_eq!(sorted(grid.query(0, 0, 1)), vec![1, 2, 5]);
+        assert_eq!(sorted(grid.query(20, 20, 0)), vec![4]);
+        assert_eq!(sorted(grid.query(12, 12, 2)), Vec::<u16>::new());
+    }
+
+    #[test]
+    fn test_update_and_remove() {
+        let mut grid = SectorGrid::new(4);
+        grid.insert(id(1), 0, 0);
+
+        grid.update(id(1), (0, 0), (1, 1));
+        assert_eq!(sorted(grid.query(0, 0, 0)), vec![1]);
+
+        grid.update(id(1), (1, 1), (9, 9));
+        assert_eq!(sorted(grid.query(0, 0, 0)), Vec::<u16>::new());
+        assert_eq!(sorted(grid.query(9, 9, 0)), vec![1]);
+
+        grid.remove(id(1), 9, 9);
+        assert_eq!(sorted(grid.query(9, 9, 0)), Vec::<u16>::new());
+        assert!(grid.sectors.is_empty());
+    }
+}
@@ -0,0 +1,63 @@
+use std::collections::HashMap;
+use network::{ObjectInfo, PlayerId};
+
+use super::{sector::SectorGrid, CombatEvent};
+
+
+/// 특정 시점의 월드 상태. 만들어진 뒤로는 바뀌지 않는다.
+pub struct WorldSnapshot {
+    objects: HashMap<PlayerId, ObjectInfo>,
+    sectors: SectorGrid,
+    /// 이전 스냅샷 이후에 일어난 전투
+    events: Vec<CombatEvent>,
+}
+
+impl WorldSnapshot {
+    pub fn new(objects: HashMap<PlayerId, ObjectInfo>, sectors: SectorGrid) -> Self {
+        Self {
+            objects,
+            sectors,
+            events: Vec::new(),
+        }
+    }
+
+    pub fn with_events(mut self, events: Vec<CombatEvent>) -> Self {
+        self.events = events;
+        self
+    }
+
+    pub fn events(&self) -> &[CombatEvent] {
+        &self.events
+    }
+
+    pub fn get(&self, id: PlayerId) -> Option<&ObjectInfo> {
+        self.objects.get(&id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    pub fn objects(&self) -> impl Iterator<Item = &ObjectInfo> {
+        self.objects.values()
+    }
+
+    /// `(x, y)`에서 가로, 세로 모두 `radius`칸 이내에 있는 오브젝트
+    pub fn in_range(&self, x: i32, y: i32, radius: i32) -> impl Iterator<Item = &ObjectInfo> {
+        self.sectors.query(x, y, radius)
+            .filter_map(|id| self.objects.get(&id))
+            .filter(move |object| {
+                (object.x - x).abs() <= radius && (object.y - y).abs() <= radius
+            })
+    }
+}
+
+impl Default for WorldSnapshot {
+    fn default() -> Self {
+        Self::new(HashMap::new(), SectorGrid::default())
+    }
+}
@@ -0,0 +1,192 @@
+use std::collections::{HashMap, HashSet};
+use network::{Message, ObjectInfo, PlayerId};
+
+use super::{CombatEvent, WorldSnapshot};
+
+
+/// 클라이언트 한명의 시야.
+/// 스냅샷을 받을 때마다 시야 안의 오브젝트를 다시 계산해서
+/// 이전과 비교한 변경사항(들어옴/나감/이동, 말 변경)만 메세지로 만든다.
+/// 전투(`CombatEvent`)는 관련된 오브젝트가 이전이나 지금 시야 안에 있을 때만 보낸다.
+pub struct View {
+    radius: i32,
+    /// 시야 안에 있는 오브젝트의 마지막으로 보낸 정보
+    visible: HashMap<PlayerId, ObjectInfo>,
+}
+
+impl View {
+    pub fn new(radius: i32) -> Self {
+        Self {
+            radius: radius.max(0),
+            visible: HashMap::new(),
+        }
+    }
+
+    pub fn radius(&self) -> i32 {
+        self.radius
+    }
+
+    pub fn is_visible(&self, id: PlayerId) -> bool {
+        self.visible.contains_key(&id)
+    }
+
+    /// `id` 플레이어 기준으로 시야를 갱신하고, 클라이언트에게 보낼 메세지들을 돌려준다.
+    /// 스냅샷에 `id`가 없으면(아직 추가되지 않았거나 삭제됨, 죽음) 시야는 그대로 두고 전투만 보낸다.
+    pub fn update(&mut self, id: PlayerId, snapshot: &WorldSnapshot) -> Vec<Message> {
+        // 죽은 오브젝트는 스냅샷에 없으므로 이전 시야도 확인
+        let previous = self.visible.keys().copied().collect::<HashSet<_>>();
+
+        let Some(&ObjectInfo { x, y, .. }) = snapshot.get(id) else {
+            return self.events(id, snapshot, &previous);
+        };
+
+        let mut entered = Vec::new();
+        let mut moved = Vec::new();
+        let mut visible = HashMap::with_capacity(self.visible.len());
+
+        for object in snapshot.in_range(x, y, self.radius) {
+            let id = object.id;
+
+            match self.visible.remove(&id) {
+                None => entered.push(*object),
+                Some(prev) if prev != *object => moved.push(*object),
+                Some(_) => {},
+            }
+
+            visible.insert(id, *object);
+        }
+
+        // 남은것은 시야에서 벗어난 오브젝트
+        let left = self.visible.keys().copied().collect::<Vec<PlayerId>>();
+        self.visible = visible;
+
+        let mut messages = self.events(id, snapshot, &previous);
+
+        messages.extend(left.chunks(Message::MAX_LEAVE_IDS)
+            .map(|ids| Message::LeaveView { ids: ids.to_vec() }));
+        messages.extend(entered.chunks(Message::MAX_SNAPSHOT_OBJECTS)
+            .map(|objects| Message::EnterView { objects: objects.to_vec() }));
+        messages.extend(moved.chunks(Message::MAX_SNAPSHOT_OBJECTS)
+            .map(|objects| Message::UpdateSnapshot { objects: objects.to_vec() }));
+
+        messages
+    }
+
+    /// 시야 안의 오브젝트를 마지막으로 보낸 정보로 담은 `UpdateSnapshot` (`update` 요청의 응답).
+    /// 시야가 비어있어도 빈 메세지 하나를 돌려준다.
+    pub fn snapshot_messages(&self) -> Vec<Message> {
+        let mut objects = self.visible.values().copied().collect::<Vec<_>>();
+        objects.sort_by_key(|object| object.id);
+
+        if objects.is_empty() {
+            return vec![Message::UpdateSnapshot { objects }];
+        }
+        objects.chunks(Message::MAX_SNAPSHOT_OBJECTS)
+            .map(|objects| Message::UpdateSnapshot { objects: objects.to_vec() })
+            .collect()
+    }
+
+    fn events(&self, id: PlayerId, snapshot: &WorldSnapshot, previous: &HashSet<PlayerId>) -> Vec<Message> {
+        snapshot.events().iter()
+            .filter(|event| event.ids().iter().any(|other| {
+                *other == id || previous.contains(other) || self.visible.contains_key(other)
+            }))
+            .map(CombatEvent::message)
+            .collect()
+    }
+}
+
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::World;
+    use network::PieceClass;
+    use crate::server::test_util::id;
+
+    fn ids(objects: &[ObjectInfo]) -> Vec<u16> {
+        let mut ids = objects.iter().map(|o| o.id.index()).collect::<Vec<_>>();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn test_enter_move_leave() {
+        let mut world = World::new();
+        world.add_player(id(1));
+        world.add_player(id(2));
+
+        let mut view = View::new(2);
+
+        // 처음에는 자신 포함 전부 들어옴
+        let messages = view.update(id(1), &world.snapshot());
+        match messages.as_slice() {
+            [Message::EnterView { objects }] => assert_eq!(ids(objects), vec![1, 2]),
+            _ => panic!("unexpected messages {:?}", messages),
+        }
+
+        // 변경사항이 없으면 보낼것도 없음
+        assert!(view.update(id(1), &world.snapshot()).is_empty());
+
+        world.move_player(id(2), 0, 1).unwrap();
+        let messages = view.update(id(1), &world.snapshot());
+        assert_eq!(messages, vec![Message::UpdateSnapshot { objects: vec![ObjectInfo::new(id(2), 4, 4, PieceClass::PAWN)] }]);
+
+        // 2칸 밖으로 벗어남
+        world.move_player(id(2), 1, 0).unwrap();
+        world.move_player(id(2), 1, 0).unwrap();
+        let messages = view.update(id(1), &world.snapshot());
+        assert_eq!(messages, vec![Message::LeaveView { ids: vec![id(2)] }]);
+        assert!(!view.is_visible(id(2)));
+        // `update` 요청에도 시야 안의 오브젝트만
+        assert_eq!(view.snapshot_messages(), vec![Message::UpdateSnapshot { objects: vec![ObjectInfo::new(id(1), 3, 3, PieceClass::PAWN)] }]);
+
+        // 다시 들어옴
+        world.move_player(id(2), -1, 0).unwrap();
+        let messages = view.update(id(1), &world.snapshot());
+        assert_eq!(messages, vec![Message::EnterView { objects: vec![ObjectInfo::new(id(2), 5, 4, PieceClass::PAWN)] }]);
+
+        // 말이 바뀌어도 갱신
+        world.set_class(id(2), PieceClass::KNIGHT);
+        let messages = view.update(id(1), &world.snapshot());
+        assert_eq!(messages, vec![Message::UpdateSnapshot { objects: vec![ObjectInfo::new(id(2), 5, 4, PieceClass::KNIGHT)] }]);
+
+        world.remove_player(id(2));
+        let messages = view.update(id(1), &world.snapshot());
+        assert_eq!(messages, vec![Message::LeaveView { ids: vec![id(2)] }]);
+    }
+
+    #[test]
+    fn test_combat_events() {
+        let mut world = World::new();
+        for index in 1..=3 {
+            world.add_player(id(index));
+        }
+        // 1: (3, 3), 2: (4, 3), 3: (0, 3)
+        world.move_player(id(3), -1, 0).unwrap();
+        world.move_player(id(3), -1, 0).unwrap();
+
+        let mut near = View::new(1);
+        let mut far = View::new(1);
+        near.update(id(2), &world.snapshot());
+        far.update(id(3), &world.snapshot());
+
+        world.attack(id(1), id(2)).unwrap();
+        let damage = Message::Damage { attacker: id(1), target: id(2), amount: 10, hp: 90 };
+        assert_eq!(near.update(id(2), &world.snapshot()), vec![damage]);
+        assert!(far.update(id(3), &world.snapshot()).is_empty());
+    }
+
+    #[test]
+    fn test_unknown_player() {
+        let mut world = World::new();
+        world.add_player(id(1));
+
+        let mut view = View::new(5);
+        assert!(view.update(id(7), &world.snapshot()).is_empty());
+        assert_eq!(view.snapshot_messages(), vec![Message::UpdateSnapshot { objects: vec![] }]);
+    }
+}